@@ -151,6 +151,7 @@ pub async fn configure_tokens<'a>(
                 value_modifier: desc.collateral_weight,
                 max_staleness: desc.max_staleness,
                 token_features: TokenFeatures::from_bits(desc.token_features).unwrap(),
+                oracle_limits: Default::default(),
             }),
         )
         .await?;
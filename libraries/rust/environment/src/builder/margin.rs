@@ -1,7 +1,7 @@
 use glow_instructions::margin::derive_token_config;
 use solana_sdk::{instruction::Instruction, pubkey::Pubkey, system_program};
 
-use glow_margin::{TokenConfig, TokenConfigUpdate};
+use glow_margin::{OracleLimits, TokenConfig, TokenConfigUpdate};
 use glow_solana_client::rpc::SolanaRpcExtra;
 use squads_multisig::anchor_lang::AccountDeserialize;
 
@@ -101,7 +101,15 @@ pub async fn upgrade_token_config(
             admin: old_config.admin,
             token_features: Default::default(),
             version: 0,
-            reserved: [0; 64],
+            initial_value_modifier: 0,
+            oracle_limits: OracleLimits::default(),
+            value_modifier_ramp: Default::default(),
+            isolation: Default::default(),
+            max_collateral_share: 0,
+            size_haircut_threshold: 0,
+            concentration_group: 0,
+            liquidation_bonus_bps: 0,
+            reserved: [0; 23],
         }));
     }
     // Fall back
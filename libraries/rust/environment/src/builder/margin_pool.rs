@@ -319,6 +319,7 @@ pub(crate) async fn configure_for_token(
                 value_modifier: token.desc.collateral_weight,
                 max_staleness: token.desc.max_staleness,
                 token_features,
+                oracle_limits: Default::default(),
             },
         ));
     }
@@ -334,6 +335,7 @@ pub(crate) async fn configure_for_token(
                 value_modifier: token.desc.max_leverage,
                 max_staleness: token.desc.max_staleness,
                 token_features,
+                oracle_limits: Default::default(),
             },
         ));
     }
@@ -159,6 +159,46 @@ impl AirspaceIxBuilder {
         }
     }
 
+    /// Set whether any signer may liquidate margin accounts in the airspace
+    ///
+    /// # Params
+    ///
+    /// `is_permissionless` - If true, liquidators do not need a permit
+    pub fn set_liquidation_mode(&self, is_permissionless: bool) -> Instruction {
+        let accounts = glow_airspace::accounts::AirspaceSetLiquidationMode {
+            authority: self.airspace_manager.authority,
+            airspace: self.airspace_manager.address,
+        }
+        .to_account_metas(None);
+
+        Instruction {
+            accounts,
+            program_id: glow_airspace::ID,
+            data: glow_airspace::instruction::AirspaceSetLiquidationMode { is_permissionless }
+                .data(),
+        }
+    }
+
+    /// Set the share of liquidation fees that goes to the airspace's treasury
+    ///
+    /// # Params
+    ///
+    /// `protocol_share_bps` - The share of the fee for the treasury, in basis points
+    pub fn set_liquidation_fee_share(&self, protocol_share_bps: u16) -> Instruction {
+        let accounts = glow_airspace::accounts::AirspaceSetLiquidationFeeShare {
+            authority: self.airspace_manager.authority,
+            airspace: self.airspace_manager.address,
+        }
+        .to_account_metas(None);
+
+        Instruction {
+            accounts,
+            program_id: glow_airspace::ID,
+            data: glow_airspace::instruction::AirspaceSetLiquidationFeeShare { protocol_share_bps }
+                .data(),
+        }
+    }
+
     /// Propose an authority change for the airspace
     ///
     /// # Params
@@ -31,12 +31,19 @@ use anchor_lang::{system_program, InstructionData};
 
 use glow_margin::instruction as ix_data;
 use glow_margin::program::Margin;
-use glow_margin::seeds::{ADAPTER_CONFIG_SEED, PERMIT_SEED, TOKEN_CONFIG_SEED};
+use glow_margin::seeds::{
+    ADAPTER_CONFIG_SEED, CREDIT_LINE_SEED, DELEVERAGE_ORDER_SEED, LIQUIDATION_FEE_TREASURY_SEED,
+    PERMIT_SEED, TOKEN_CONFIG_SEED, TRIGGER_ORDER_SEED,
+};
 use glow_margin::{accounts as ix_account, MarginAccount};
 use glow_program_common::ADDRESS_LOOKUP_REGISTRY_ID;
 
 pub use glow_margin::ID as MARGIN_PROGRAM;
-pub use glow_margin::{TokenAdmin, TokenConfigUpdate, TokenKind};
+pub use glow_margin::{
+    AdapterOutflowLimits, AdapterPermissionsUpdate, DeleverageOrder, DeleverageOrderParams,
+    IsolationFlags, IsolationUpdate, SwapRoute, TokenAdmin, TokenConfigUpdate, TokenKind,
+    TokenRiskUpdate, TriggerCondition, TriggerOrder, TriggerOrderParams, ValueModifierRampUpdate,
+};
 
 use crate::airspace::derive_permit;
 use crate::airspace::AirspaceDetails;
@@ -66,6 +73,10 @@ pub struct MarginIxBuilder {
     /// Key that will sign to authorize changes to the margin account.
     /// - Defaults to owner.
     authority: Option<Pubkey>,
+
+    /// Adapters with an outflow window limit, whose configs have to be writable when
+    /// they are invoked through `adapter_invoke`.
+    windowed_adapters: Vec<Pubkey>,
 }
 
 impl MarginIxBuilder {
@@ -80,6 +91,7 @@ impl MarginIxBuilder {
             address,
             airspace_details: AirspaceDetails::from_address(airspace),
             authority: None,
+            windowed_adapters: vec![],
         }
     }
 
@@ -91,6 +103,7 @@ impl MarginIxBuilder {
             address,
             airspace_details: AirspaceDetails::from_address(airspace),
             authority: None,
+            windowed_adapters: vec![],
         }
     }
 
@@ -106,6 +119,18 @@ impl MarginIxBuilder {
         self
     }
 
+    /// Use if any invoked adapter has an outflow window limit, which is recorded in
+    /// its config.
+    pub fn with_windowed_adapters(mut self, adapters: Vec<Pubkey>) -> Self {
+        self.windowed_adapters = adapters;
+        self
+    }
+
+    /// The adapters whose configs are writable when invoked through `adapter_invoke`
+    pub fn windowed_adapters(&self) -> &[Pubkey] {
+        &self.windowed_adapters
+    }
+
     pub fn authority(&self) -> Pubkey {
         self.authority.unwrap_or(self.owner)
     }
@@ -167,6 +192,23 @@ impl MarginIxBuilder {
         }
     }
 
+    /// Get instruction to migrate the account's constraint ticket to support multiple holders
+    pub fn migrate_account_constraint_ticket(&self) -> Instruction {
+        let accounts = ix_account::MigrateAccountConstraintTicket {
+            payer: self.payer(),
+            owner: self.owner,
+            margin_account: self.address,
+            account_constraint_ticket: derive_account_constraint_ticket(&self.address),
+            system_program: SYSTEM_PROGRAM_ID,
+        };
+
+        Instruction {
+            program_id: Margin::id(),
+            data: ix_data::MigrateAccountConstraintTicket {}.data(),
+            accounts: accounts.to_account_metas(None),
+        }
+    }
+
     /// Get instruction to create address lookup registry account
     pub fn init_lookup_registry(&self) -> Instruction {
         let registry_account = self.lookup_table_registry_address();
@@ -384,6 +426,7 @@ impl MarginIxBuilder {
             self.airspace_details.address,
             self.address,
             adapter_ix,
+            &self.windowed_adapters,
             AdapterInvoke { owner: self.owner }
         )
     }
@@ -394,10 +437,107 @@ impl MarginIxBuilder {
             self.airspace_details.address,
             self.address,
             adapter_ixs,
+            &self.windowed_adapters,
             AdapterInvoke { owner: self.owner }
         )
     }
 
+    /// Add the token config of an isolated collateral to an instruction that changes the
+    /// balances of the margin account, e.g. an adapter invoke or a deposit transfer.
+    ///
+    /// This is required whenever the debt backed by the collateral changes, including when
+    /// the account newly holds the collateral while it has claims.
+    pub fn with_isolated_collateral(
+        &self,
+        mut invoke_ix: Instruction,
+        isolated_mint: &Pubkey,
+    ) -> Instruction {
+        invoke_ix.accounts.push(AccountMeta::new(
+            derive_token_config(&self.airspace_details.address, isolated_mint),
+            false,
+        ));
+        invoke_ix
+    }
+
+    /// Get instruction to create an order that a keeper can execute for this account
+    ///
+    /// # Params
+    ///
+    /// `params` - The trigger, swap route, size, maximum slippage and keeper fee of the order
+    pub fn create_trigger_order(&self, params: TriggerOrderParams) -> Instruction {
+        create_trigger_order(self.owner, self.payer(), self.address, params)
+    }
+
+    /// Get instruction to cancel an order of this account
+    ///
+    /// # Params
+    ///
+    /// `order_id` - The identifier of the order
+    pub fn cancel_trigger_order(&self, order_id: u64) -> Instruction {
+        cancel_trigger_order(self.owner, self.payer(), self.address, order_id)
+    }
+
+    /// Get instruction to create an order that a keeper can execute to deleverage this account
+    ///
+    /// # Params
+    ///
+    /// `params` - The threshold, repayment bound, swap route, maximum slippage and keeper fee of the order
+    pub fn create_deleverage_order(&self, params: DeleverageOrderParams) -> Instruction {
+        create_deleverage_order(self.owner, self.payer(), self.address, params)
+    }
+
+    /// Get instruction to cancel a deleverage order of this account
+    ///
+    /// # Params
+    ///
+    /// `order_id` - The identifier of the order
+    pub fn cancel_deleverage_order(&self, order_id: u64) -> Instruction {
+        cancel_deleverage_order(self.owner, self.payer(), self.address, order_id)
+    }
+
+    /// Get instruction to open a credit line from this account to a borrower account
+    ///
+    /// # Params
+    ///
+    /// `borrower_owner` - The owner of the borrower account, which must also sign
+    /// `borrower_account` - The margin account to lend to
+    /// `limit` - The value of the line in the numeraire of the accounts
+    pub fn open_credit_line(
+        &self,
+        borrower_owner: Pubkey,
+        borrower_account: Pubkey,
+        limit: u64,
+    ) -> Instruction {
+        open_credit_line(
+            self.owner,
+            borrower_owner,
+            self.payer(),
+            self.address,
+            borrower_account,
+            limit,
+        )
+    }
+
+    /// Get instruction to change the limit of a credit line from this account
+    ///
+    /// # Params
+    ///
+    /// `borrower_account` - The margin account borrowing on the line
+    /// `limit` - The new value of the line
+    pub fn set_credit_line_limit(&self, borrower_account: Pubkey, limit: u64) -> Instruction {
+        set_credit_line_limit(self.owner, self.address, borrower_account, limit)
+    }
+
+    /// Get instruction to close a credit line from this account, returning the rent
+    /// to the payer of this builder, which must have paid it
+    ///
+    /// # Params
+    ///
+    /// `borrower_account` - The margin account borrowing on the line
+    pub fn close_credit_line(&self, borrower_account: Pubkey) -> Instruction {
+        close_credit_line(self.owner, self.payer(), self.address, borrower_account)
+    }
+
     /// Get instruction to invoke through an adapter for permissionless accounting instructions
     ///
     /// # Params
@@ -421,46 +561,104 @@ impl MarginIxBuilder {
         )
     }
 
+    /// Begin liquidating a margin account without a liquidator permit, which
+    /// requires the airspace to allow any signer to liquidate.
+    pub fn liquidate_begin_permissionless(&self) -> Instruction {
+        liquidate_begin_permissionless(
+            self.airspace_details.address,
+            self.address,
+            self.authority(),
+            self.payer(),
+        )
+    }
+
     /// Invoke action as liquidator
-    pub fn liquidator_invoke(&self, adapter_ix: Instruction, fee_mint: MintInfo) -> Instruction {
+    pub fn liquidator_invoke(&self, adapter_ix: Instruction) -> Instruction {
         liquidator_invoke(
             self.airspace_details.address,
             self.authority(),
             self.address,
-            fee_mint,
             adapter_ix,
         )
     }
 
     /// Invoke actions as liquidator
-    pub fn liquidator_invoke_many(
+    pub fn liquidator_invoke_many(&self, adapter_ixs: &[Instruction]) -> Instruction {
+        liquidator_invoke_many(
+            self.airspace_details.address,
+            self.authority(),
+            self.address,
+            adapter_ixs.to_vec(),
+        )
+    }
+
+    /// Repay a debt as liquidator from the liquidator's own margin account, seizing
+    /// collateral in return
+    #[allow(clippy::too_many_arguments)]
+    pub fn liquidator_seize(
         &self,
+        liquidator_account: Pubkey,
+        repay_mint: MintInfo,
+        repay_amount: u64,
+        seize_mint: MintInfo,
+        seize_source: Pubkey,
+        seize_destination: Pubkey,
         adapter_ixs: &[Instruction],
-        fee_mint: MintInfo,
     ) -> Instruction {
-        liquidator_invoke_many(
+        liquidator_seize(
             self.airspace_details.address,
             self.authority(),
             self.address,
-            fee_mint,
+            liquidator_account,
+            repay_mint,
+            repay_amount,
+            seize_mint,
+            seize_source,
+            seize_destination,
             adapter_ixs.to_vec(),
         )
     }
 
+    /// Seize collateral from the lender of a credit line backing this account, while
+    /// it is being liquidated
+    ///
+    /// # Params
+    ///
+    /// `lender_account` - The margin account lending its collateral
+    /// `seize_mint` - The mint of the collateral to seize
+    /// `amount` - The amount of collateral tokens to seize
+    pub fn liquidator_seize_credit(
+        &self,
+        lender_account: Pubkey,
+        seize_mint: MintInfo,
+        amount: u64,
+    ) -> Instruction {
+        liquidator_seize_credit(
+            self.authority(),
+            self.address,
+            lender_account,
+            seize_mint,
+            amount,
+        )
+    }
+
+    /// Collect the liquidation fees accrued in each of the given mints
+    ///
+    /// # Params
+    ///
+    /// `fees` - The accrued fees to collect
+    /// `shares_fees` - Whether the liquidation shares its fees with the treasury
     pub fn collect_liquidation_fee(
         &self,
-        token_mint: MintInfo,
-        token_config: Pubkey,
-        price_oracle: Pubkey,
-        redemption_quote_oracle: Option<Pubkey>,
+        fees: &[LiquidationFeeCollection],
+        shares_fees: bool,
     ) -> Instruction {
         collect_liquidation_fee(
+            self.airspace_details.address,
             self.address,
             self.authority(),
-            token_mint,
-            token_config,
-            price_oracle,
-            redemption_quote_oracle,
+            fees,
+            shares_fees,
         )
     }
 
@@ -516,11 +714,14 @@ impl MarginIxBuilder {
             token_program: mint.token_program(),
         };
 
-        Instruction {
+        let ix = Instruction {
             program_id: glow_margin::ID,
             data: ix_data::TransferDeposit { amount }.data(),
             accounts: accounts.to_account_metas(None),
-        }
+        };
+
+        // Needed to check the destination if the account's destinations are restricted
+        with_account_constraint_ticket(ix, &self.address)
     }
 
     /// Verify that an account is healthy
@@ -537,6 +738,20 @@ impl MarginIxBuilder {
         }
     }
 
+    /// Compute the valuation of an account, which is returned through the return data
+    /// when simulating the instruction
+    pub fn get_valuation(&self) -> Instruction {
+        let accounts = ix_account::GetValuation {
+            margin_account: self.address,
+        };
+
+        Instruction {
+            program_id: Margin::id(),
+            accounts: accounts.to_account_metas(None),
+            data: ix_data::GetValuation.data(),
+        }
+    }
+
     /// Verify that an account is unhealthy
     ///
     pub fn verify_unhealthy(&self) -> Instruction {
@@ -658,12 +873,34 @@ pub fn liquidate_begin(
     payer: Pubkey,
 ) -> Instruction {
     let permit = derive_margin_permit(&airspace, &liquidator);
+    liquidate_begin_with_permit(airspace, margin_account, liquidator, payer, Some(permit))
+}
+
+/// Begin liquidating a margin account in an airspace that allows any signer to
+/// liquidate, which does not require a liquidator permit.
+pub fn liquidate_begin_permissionless(
+    airspace: Pubkey,
+    margin_account: Pubkey,
+    liquidator: Pubkey,
+    payer: Pubkey,
+) -> Instruction {
+    liquidate_begin_with_permit(airspace, margin_account, liquidator, payer, None)
+}
+
+fn liquidate_begin_with_permit(
+    airspace: Pubkey,
+    margin_account: Pubkey,
+    liquidator: Pubkey,
+    payer: Pubkey,
+    permit: Option<Pubkey>,
+) -> Instruction {
     let liquidation = derive_liquidation(margin_account, liquidator);
     let accounts = glow_margin::accounts::LiquidateBegin {
         margin_account,
         payer,
         liquidator,
         permit,
+        airspace,
         liquidation,
         system_program: system_program::ID,
     };
@@ -674,29 +911,54 @@ pub fn liquidate_begin(
     }
 }
 
+/// The accounts to collect a liquidation fee accrued in a mint
+#[derive(Clone, Copy, Debug)]
+pub struct LiquidationFeeCollection {
+    /// The mint of the accrued fee
+    pub mint: MintInfo,
+    /// The token config of the mint
+    pub token_config: Pubkey,
+    /// The price oracle of the token
+    pub price_oracle: Pubkey,
+    /// The oracle for the quote token, if the price is a redemption rate
+    pub redemption_quote_oracle: Option<Pubkey>,
+}
+
+/// Collect the liquidation fees accrued in each of the given mints. The treasury token
+/// accounts are only passed if the liquidation shares its fees with the treasury.
 pub fn collect_liquidation_fee(
+    airspace: Pubkey,
     margin_account: Pubkey,
     liquidator: Pubkey,
-    token_mint: MintInfo,
-    token_config: Pubkey,
-    price_oracle: Pubkey,
-    redemption_quote_oracle: Option<Pubkey>,
+    fees: &[LiquidationFeeCollection],
+    shares_fees: bool,
 ) -> Instruction {
-    let accounts = glow_margin::accounts::CollectLiquidationFee {
+    let treasury = derive_liquidation_fee_treasury(&airspace);
+    let mut accounts = glow_margin::accounts::CollectLiquidationFee {
         liquidator,
-        liquidation_fee_mint: token_mint.address,
         liquidation: derive_liquidation(margin_account, liquidator),
-        liquidator_fee_token: token_mint.associated_token_address(&liquidator),
-        liquidator_fee_token_program: token_mint.token_program(),
         margin_account,
-        token_config,
-        margin_account_fee_source: token_mint.associated_token_address(&margin_account),
-        price_oracle,
-        redemption_quote_oracle,
-    };
+    }
+    .to_account_metas(None);
+    for fee in fees {
+        accounts.extend([
+            AccountMeta::new_readonly(fee.mint.address, false),
+            AccountMeta::new_readonly(fee.token_config, false),
+            AccountMeta::new_readonly(fee.price_oracle, false),
+            AccountMeta::new_readonly(fee.redemption_quote_oracle.unwrap_or(Margin::id()), false),
+            AccountMeta::new(fee.mint.associated_token_address(&margin_account), false),
+            AccountMeta::new(fee.mint.associated_token_address(&liquidator), false),
+            match shares_fees {
+                true => AccountMeta::new(fee.mint.associated_token_address(&treasury), false),
+                false => AccountMeta::new_readonly(Margin::id(), false),
+            },
+            AccountMeta::new_readonly(fee.mint.token_program(), false),
+        ]);
+    }
+
     Instruction {
         program_id: Margin::id(),
-        accounts: accounts.to_account_metas(None),
+        accounts,
         data: glow_margin::instruction::CollectLiquidationFee {}.data(),
     }
 }
@@ -719,6 +981,34 @@ pub fn liquidate_end(
     }
 }
 
+/// Get instruction to end a batch of liquidations that have timed out
+///
+/// # Params
+///
+/// `keeper` - The signer ending the liquidations, which receives a bounty for each
+/// `liquidations` - Pairs of margin account and liquidator of the liquidations to end
+pub fn end_timed_out_liquidations(
+    keeper: Pubkey,
+    liquidations: &[(Pubkey, Pubkey)],
+) -> Instruction {
+    let mut accounts = ix_account::EndTimedOutLiquidations { keeper }.to_account_metas(None);
+
+    for (margin_account, liquidator) in liquidations {
+        accounts.push(AccountMeta::new(*margin_account, false));
+        accounts.push(AccountMeta::new(
+            derive_liquidation(*margin_account, *liquidator),
+            false,
+        ));
+        accounts.push(AccountMeta::new(*liquidator, false));
+    }
+
+    Instruction {
+        program_id: Margin::id(),
+        accounts,
+        data: ix_data::EndTimedOutLiquidations.data(),
+    }
+}
+
 /// Get instruction to refresh the price and balance value for a deposit account
 ///
 /// # Params
@@ -764,16 +1054,19 @@ pub fn refresh_deposit_position(
 /// # Params
 ///
 /// `adapter_ix` - The instruction to be invoked
+/// `windowed_adapters` - The adapters with an outflow window limit
 pub fn adapter_invoke(
     airspace: Pubkey,
     owner: Pubkey,
     margin_account: Pubkey,
     adapter_ix: Instruction,
+    windowed_adapters: &[Pubkey],
 ) -> Instruction {
     invoke_single!(
         airspace,
         margin_account,
         adapter_ix,
+        windowed_adapters,
         AdapterInvoke { owner }
     )
 }
@@ -783,37 +1076,222 @@ pub fn adapter_invoke_many(
     owner: Pubkey,
     margin_account: Pubkey,
     adapter_ixs: Vec<Instruction>,
+    windowed_adapters: &[Pubkey],
 ) -> Instruction {
     invoke_many!(
         airspace,
         margin_account,
         adapter_ixs,
+        windowed_adapters,
         AdapterInvoke { owner }
     )
 }
 
+/// Get instruction to create an order that a keeper can execute for a margin account
+pub fn create_trigger_order(
+    owner: Pubkey,
+    payer: Pubkey,
+    margin_account: Pubkey,
+    params: TriggerOrderParams,
+) -> Instruction {
+    let accounts = ix_account::CreateTriggerOrder {
+        owner,
+        payer,
+        margin_account,
+        order: derive_trigger_order(&margin_account, params.order_id),
+        system_program: SYSTEM_PROGRAM_ID,
+    };
+
+    Instruction {
+        program_id: Margin::id(),
+        accounts: accounts.to_account_metas(None),
+        data: ix_data::CreateTriggerOrder { params }.data(),
+    }
+}
+
+/// Get instruction to cancel an order of a margin account
+pub fn cancel_trigger_order(
+    owner: Pubkey,
+    receiver: Pubkey,
+    margin_account: Pubkey,
+    order_id: u64,
+) -> Instruction {
+    let accounts = ix_account::CancelTriggerOrder {
+        owner,
+        receiver,
+        margin_account,
+        order: derive_trigger_order(&margin_account, order_id),
+    };
+
+    Instruction {
+        program_id: Margin::id(),
+        accounts: accounts.to_account_metas(None),
+        data: ix_data::CancelTriggerOrder.data(),
+    }
+}
+
+/// The oracles of a token priced when executing a trigger order
+#[derive(Clone, Copy, Debug)]
+pub struct TriggerOrderOracle {
+    /// The price oracle of the token, stored in its token config
+    pub price_oracle: Pubkey,
+    /// The oracle for the quote token, if the price is a redemption rate
+    pub redemption_quote_oracle: Option<Pubkey>,
+}
+
+/// Get instruction for a keeper to execute an order, swapping through the
+/// adapter of its route
+///
+/// # Params
+///
+/// `owner` - The owner of the margin account, receiving the rent of the order
+/// `order` - The order to execute
+/// `trigger_oracle` - The oracles of the trigger token
+/// `input_oracle` - The oracles of the input token, valuing the tokens sold
+/// `output_oracle` - The oracles of the output token, valuing the tokens received
+/// `output_mint` - The output token of the order, paying the keeper fee
+/// `adapter_ixs` - The swap instructions to invoke through the adapter of the order
+/// `windowed_adapters` - The adapters with an outflow window limit
+#[allow(clippy::too_many_arguments)]
+pub fn execute_trigger_order(
+    airspace: Pubkey,
+    keeper: Pubkey,
+    owner: Pubkey,
+    order: &TriggerOrder,
+    trigger_oracle: TriggerOrderOracle,
+    input_oracle: TriggerOrderOracle,
+    output_oracle: TriggerOrderOracle,
+    output_mint: MintInfo,
+    adapter_ixs: Vec<Instruction>,
+    windowed_adapters: &[Pubkey],
+) -> Instruction {
+    let margin_account = order.margin_account;
+
+    invoke_many!(
+        airspace,
+        margin_account,
+        adapter_ixs,
+        windowed_adapters,
+        ExecuteTriggerOrder {
+            keeper,
+            owner,
+            order: derive_trigger_order(&margin_account, order.order_id),
+            trigger_config: derive_token_config(&airspace, &order.trigger_token),
+            price_oracle: trigger_oracle.price_oracle,
+            redemption_quote_oracle: trigger_oracle.redemption_quote_oracle,
+            input_config: derive_token_config(&airspace, &order.route.input_token),
+            input_price_oracle: input_oracle.price_oracle,
+            input_redemption_quote_oracle: input_oracle.redemption_quote_oracle,
+            output_config: derive_token_config(&airspace, &order.route.output_token),
+            output_price_oracle: output_oracle.price_oracle,
+            output_redemption_quote_oracle: output_oracle.redemption_quote_oracle,
+            output_mint: output_mint.address,
+            fee_source: output_mint.associated_token_address(&margin_account),
+            keeper_fee_token: output_mint.associated_token_address(&keeper),
+            token_program: output_mint.token_program(),
+        }
+    )
+}
+
+/// Get instruction to create an order that a keeper can execute to deleverage a margin account
+pub fn create_deleverage_order(
+    owner: Pubkey,
+    payer: Pubkey,
+    margin_account: Pubkey,
+    params: DeleverageOrderParams,
+) -> Instruction {
+    let accounts = ix_account::CreateDeleverageOrder {
+        owner,
+        payer,
+        margin_account,
+        order: derive_deleverage_order(&margin_account, params.order_id),
+        system_program: SYSTEM_PROGRAM_ID,
+    };
+
+    Instruction {
+        program_id: Margin::id(),
+        accounts: accounts.to_account_metas(None),
+        data: ix_data::CreateDeleverageOrder { params }.data(),
+    }
+}
+
+/// Get instruction to cancel a deleverage order of a margin account
+pub fn cancel_deleverage_order(
+    owner: Pubkey,
+    receiver: Pubkey,
+    margin_account: Pubkey,
+    order_id: u64,
+) -> Instruction {
+    let accounts = ix_account::CancelDeleverageOrder {
+        owner,
+        receiver,
+        margin_account,
+        order: derive_deleverage_order(&margin_account, order_id),
+    };
+
+    Instruction {
+        program_id: Margin::id(),
+        accounts: accounts.to_account_metas(None),
+        data: ix_data::CancelDeleverageOrder.data(),
+    }
+}
+
+/// Get instruction for a keeper to execute a deleverage order, swapping collateral
+/// to repay debt through the adapter of its route
+///
+/// # Params
+///
+/// `owner` - The owner of the margin account, receiving the rent of the order
+/// `order` - The order to execute
+/// `input_mint` - The collateral sold by the order, paying the keeper fee
+/// `adapter_ixs` - The swap and repay instructions to invoke through the adapter of the order
+/// `windowed_adapters` - The adapters with an outflow window limit
+pub fn execute_deleverage_order(
+    airspace: Pubkey,
+    keeper: Pubkey,
+    owner: Pubkey,
+    order: &DeleverageOrder,
+    input_mint: MintInfo,
+    adapter_ixs: Vec<Instruction>,
+    windowed_adapters: &[Pubkey],
+) -> Instruction {
+    let margin_account = order.margin_account;
+
+    invoke_many!(
+        airspace,
+        margin_account,
+        adapter_ixs,
+        windowed_adapters,
+        ExecuteDeleverageOrder {
+            keeper,
+            owner,
+            order: derive_deleverage_order(&margin_account, order.order_id),
+            input_mint: input_mint.address,
+            fee_source: input_mint.associated_token_address(&margin_account),
+            keeper_fee_token: input_mint.associated_token_address(&keeper),
+            token_program: input_mint.token_program(),
+        }
+    )
+}
+
 /// Invoke action as liquidator
 pub fn liquidator_invoke(
     airspace: Pubkey,
     liquidator: Pubkey,
     margin_account: Pubkey,
-    fee_mint: MintInfo,
     adapter_ix: Instruction,
 ) -> Instruction {
     let liquidation = derive_liquidation(margin_account, liquidator);
 
-    let liquidator_fee_mint = fee_mint.address;
-    let liquidator_fee_token_program = fee_mint.token_program();
-
     invoke_single!(
         airspace,
         margin_account,
         adapter_ix,
+        &[],
         LiquidatorInvoke {
             liquidator,
             liquidation,
-            liquidator_fee_mint,
-            liquidator_fee_token_program,
+            system_program: SYSTEM_PROGRAM_ID,
         }
     )
 }
@@ -823,27 +1301,196 @@ pub fn liquidator_invoke_many(
     airspace: Pubkey,
     liquidator: Pubkey,
     margin_account: Pubkey,
-    fee_mint: MintInfo,
     adapter_ixs: Vec<Instruction>,
 ) -> Instruction {
     let liquidation = derive_liquidation(margin_account, liquidator);
 
-    let liquidator_fee_mint = fee_mint.address;
-    let liquidator_fee_token_program = fee_mint.token_program();
-
     invoke_many!(
         airspace,
         margin_account,
         adapter_ixs,
+        &[],
         LiquidatorInvoke {
             liquidator,
             liquidation,
-            liquidator_fee_mint,
-            liquidator_fee_token_program,
+            system_program: SYSTEM_PROGRAM_ID,
         }
     )
 }
 
+/// Repay a debt of a margin account being liquidated from the liquidator's own margin
+/// account, and seize collateral of the same value plus a bonus.
+///
+/// The adapter instructions repay the debt from the liquidated account's deposit of the
+/// repaid token, which receives the tokens from the liquidator account's deposit. The
+/// seized collateral moves between the token accounts of the two positions, which differ
+/// for deposits and adapter positions.
+#[allow(clippy::too_many_arguments)]
+pub fn liquidator_seize(
+    airspace: Pubkey,
+    liquidator: Pubkey,
+    margin_account: Pubkey,
+    liquidator_account: Pubkey,
+    repay_mint: MintInfo,
+    repay_amount: u64,
+    seize_mint: MintInfo,
+    seize_source: Pubkey,
+    seize_destination: Pubkey,
+    adapter_ixs: Vec<Instruction>,
+) -> Instruction {
+    let liquidation = derive_liquidation(margin_account, liquidator);
+
+    invoke_many!(
+        airspace,
+        margin_account,
+        adapter_ixs,
+        &[],
+        LiquidatorSeize {
+            liquidator,
+            liquidation,
+            liquidator_account,
+            repay_mint: repay_mint.address,
+            repay_source: repay_mint.associated_token_address(&liquidator_account),
+            repay_destination: repay_mint.associated_token_address(&margin_account),
+            repay_token_program: repay_mint.token_program(),
+            seize_mint: seize_mint.address,
+            seize_token_config: derive_token_config(&airspace, &seize_mint.address),
+            seize_source,
+            seize_destination,
+            seize_token_program: seize_mint.token_program(),
+        },
+        args { repay_amount }
+    )
+}
+
+/// Get instruction to open a credit line from a lender account to a borrower account
+///
+/// # Params
+///
+/// `lender_owner` - The owner of the lender account
+/// `borrower_owner` - The owner of the borrower account
+/// `payer` - The address paying for rent
+/// `lender_account` - The margin account lending its collateral
+/// `borrower_account` - The margin account borrowing against it
+/// `limit` - The value of the line in the numeraire of the accounts
+pub fn open_credit_line(
+    lender_owner: Pubkey,
+    borrower_owner: Pubkey,
+    payer: Pubkey,
+    lender_account: Pubkey,
+    borrower_account: Pubkey,
+    limit: u64,
+) -> Instruction {
+    let accounts = ix_account::OpenCreditLine {
+        lender_owner,
+        borrower_owner,
+        payer,
+        lender_account,
+        borrower_account,
+        credit_line: derive_credit_line(&lender_account, &borrower_account),
+        system_program: system_program::ID,
+    };
+
+    Instruction {
+        program_id: Margin::id(),
+        accounts: accounts.to_account_metas(None),
+        data: ix_data::OpenCreditLine { limit }.data(),
+    }
+}
+
+/// Get instruction to change the limit of a credit line
+///
+/// # Params
+///
+/// `lender_owner` - The owner of the lender account
+/// `lender_account` - The margin account lending its collateral
+/// `borrower_account` - The margin account borrowing against it
+/// `limit` - The new value of the line
+pub fn set_credit_line_limit(
+    lender_owner: Pubkey,
+    lender_account: Pubkey,
+    borrower_account: Pubkey,
+    limit: u64,
+) -> Instruction {
+    let accounts = ix_account::SetCreditLineLimit {
+        lender_owner,
+        lender_account,
+        borrower_account,
+        credit_line: derive_credit_line(&lender_account, &borrower_account),
+    };
+
+    Instruction {
+        program_id: Margin::id(),
+        accounts: accounts.to_account_metas(None),
+        data: ix_data::SetCreditLineLimit { limit }.data(),
+    }
+}
+
+/// Get instruction to close a credit line
+///
+/// # Params
+///
+/// `authority` - The owner of the lender or the borrower account
+/// `receiver` - The account that paid the rent of the line, which receives it back
+/// `lender_account` - The margin account lending its collateral
+/// `borrower_account` - The margin account borrowing against it
+pub fn close_credit_line(
+    authority: Pubkey,
+    receiver: Pubkey,
+    lender_account: Pubkey,
+    borrower_account: Pubkey,
+) -> Instruction {
+    let accounts = ix_account::CloseCreditLine {
+        authority,
+        receiver,
+        lender_account,
+        borrower_account,
+        credit_line: derive_credit_line(&lender_account, &borrower_account),
+    };
+
+    Instruction {
+        program_id: Margin::id(),
+        accounts: accounts.to_account_metas(None),
+        data: ix_data::CloseCreditLine.data(),
+    }
+}
+
+/// Get instruction for a liquidator to seize collateral from the lender of a credit
+/// line backing the account being liquidated
+///
+/// # Params
+///
+/// `liquidator` - The liquidator of the borrower
+/// `margin_account` - The borrower being liquidated
+/// `lender_account` - The margin account lending its collateral
+/// `seize_mint` - The mint of the collateral to seize
+/// `amount` - The amount of collateral tokens to seize
+pub fn liquidator_seize_credit(
+    liquidator: Pubkey,
+    margin_account: Pubkey,
+    lender_account: Pubkey,
+    seize_mint: MintInfo,
+    amount: u64,
+) -> Instruction {
+    let accounts = ix_account::LiquidatorSeizeCredit {
+        liquidator,
+        liquidation: derive_liquidation(margin_account, liquidator),
+        margin_account,
+        lender_account,
+        credit_line: derive_credit_line(&lender_account, &margin_account),
+        seize_mint: seize_mint.address,
+        seize_source: seize_mint.associated_token_address(&lender_account),
+        seize_destination: seize_mint.associated_token_address(&margin_account),
+        seize_token_program: seize_mint.token_program(),
+    };
+
+    Instruction {
+        program_id: Margin::id(),
+        accounts: accounts.to_account_metas(None),
+        data: ix_data::LiquidatorSeizeCredit { amount }.data(),
+    }
+}
+
 /// Get instruction to invoke through an adapter for permissionless accounting instructions
 ///
 /// # Params
@@ -854,7 +1501,7 @@ pub fn accounting_invoke(
     margin_account: Pubkey,
     adapter_ix: Instruction,
 ) -> Instruction {
-    invoke_single!(airspace, margin_account, adapter_ix, AccountingInvoke)
+    invoke_single!(airspace, margin_account, adapter_ix, &[], AccountingInvoke)
 }
 
 pub fn accounting_invoke_many(
@@ -862,7 +1509,7 @@ pub fn accounting_invoke_many(
     margin_account: Pubkey,
     adapter_ixs: Vec<Instruction>,
 ) -> Instruction {
-    invoke_many!(airspace, margin_account, adapter_ixs, AccountingInvoke)
+    invoke_many!(airspace, margin_account, adapter_ixs, &[], AccountingInvoke)
 }
 
 /// Utility for creating instructions that modify configuration for the margin program within
@@ -905,6 +1552,21 @@ impl MarginConfigIxBuilder {
         }
     }
 
+    /// Set the risk parameters of a token
+    pub fn configure_token_risk(&self, token_mint: Pubkey, update: TokenRiskUpdate) -> Instruction {
+        let accounts = ix_account::ConfigureTokenRisk {
+            authority: self.airspace_details.authority,
+            airspace: self.airspace_details.address,
+            token_config: self.derive_token_config(&token_mint),
+        };
+
+        Instruction {
+            program_id: glow_margin::ID,
+            data: ix_data::ConfigureTokenRisk { update }.data(),
+            accounts: accounts.to_account_metas(None),
+        }
+    }
+
     /// Set the configuration for an adapter program
     pub fn configure_adapter(&self, program_id: Pubkey, is_adapter: bool) -> Instruction {
         let accounts = ix_account::ConfigureAdapter {
@@ -923,6 +1585,64 @@ impl MarginConfigIxBuilder {
         }
     }
 
+    /// Set the invoke permissions for an adapter program
+    pub fn configure_adapter_permissions(
+        &self,
+        program_id: Pubkey,
+        update: AdapterPermissionsUpdate,
+    ) -> Instruction {
+        let accounts = ix_account::ConfigureAdapterPermissions {
+            authority: self.airspace_details.authority,
+            airspace: self.airspace_details.address,
+            adapter_program: program_id,
+            adapter_config: self.derive_adapter_config(&program_id),
+        };
+
+        Instruction {
+            program_id: glow_margin::ID,
+            data: ix_data::ConfigureAdapterPermissions { update }.data(),
+            accounts: accounts.to_account_metas(None),
+        }
+    }
+
+    /// Set the outflow limits for an adapter program
+    pub fn configure_adapter_outflow_limits(
+        &self,
+        program_id: Pubkey,
+        limits: AdapterOutflowLimits,
+    ) -> Instruction {
+        let accounts = ix_account::ConfigureAdapterPermissions {
+            authority: self.airspace_details.authority,
+            airspace: self.airspace_details.address,
+            adapter_program: program_id,
+            adapter_config: self.derive_adapter_config(&program_id),
+        };
+
+        Instruction {
+            program_id: glow_margin::ID,
+            data: ix_data::ConfigureAdapterOutflowLimits { limits }.data(),
+            accounts: accounts.to_account_metas(None),
+        }
+    }
+
+    /// Migrate the configuration for an adapter program to the current version
+    pub fn migrate_adapter_config(&self, program_id: Pubkey) -> Instruction {
+        let accounts = ix_account::MigrateAdapterConfig {
+            authority: self.airspace_details.authority,
+            airspace: self.airspace_details.address,
+            payer: self.payer,
+            adapter_program: program_id,
+            adapter_config: self.derive_adapter_config(&program_id),
+            system_program: system_program::ID,
+        };
+
+        Instruction {
+            program_id: glow_margin::ID,
+            data: ix_data::MigrateAdapterConfig {}.data(),
+            accounts: accounts.to_account_metas(None),
+        }
+    }
+
     /// Set the configuration for a liquidator
     pub fn configure_liquidator(&self, liquidator: Pubkey, is_liquidator: bool) -> Instruction {
         Instruction {
@@ -954,6 +1674,31 @@ impl MarginConfigIxBuilder {
         }
     }
 
+    /// Claim tokens from the treasury receiving the protocol's share of liquidation fees
+    pub fn claim_liquidation_fee_treasury(
+        &self,
+        mint: MintInfo,
+        destination: Pubkey,
+        amount: u64,
+    ) -> Instruction {
+        let treasury = derive_liquidation_fee_treasury(&self.airspace_details.address);
+        let accounts = ix_account::ClaimLiquidationFeeTreasury {
+            authority: self.airspace_details.authority,
+            airspace: self.airspace_details.address,
+            treasury,
+            treasury_token: mint.associated_token_address(&treasury),
+            destination,
+            mint: mint.address,
+            token_program: mint.token_program(),
+        };
+
+        Instruction {
+            program_id: glow_margin::ID,
+            data: ix_data::ClaimLiquidationFeeTreasury { amount }.data(),
+            accounts: accounts.to_account_metas(None),
+        }
+    }
+
     /// get the accounts to configure a permit
     fn configure_permit(&self, owner: Pubkey) -> ix_account::ConfigurePermit {
         ix_account::ConfigurePermit {
@@ -1022,6 +1767,18 @@ pub fn derive_account_constraint_ticket(margin_account: &Pubkey) -> Pubkey {
     .0
 }
 
+/// Append the constraint ticket of a margin account to an instruction, which is
+/// required when tokens leave a margin account whose destinations are restricted
+/// (e.g. withdrawals from a margin pool).
+pub fn with_account_constraint_ticket(mut ix: Instruction, margin_account: &Pubkey) -> Instruction {
+    ix.accounts.push(AccountMeta::new_readonly(
+        derive_account_constraint_ticket(margin_account),
+        false,
+    ));
+
+    ix
+}
+
 /// Derive the address for a user's margin account from the data in that account
 pub fn derive_margin_account_from_state(state: &MarginAccount) -> Pubkey {
     derive_margin_account(
@@ -1068,6 +1825,16 @@ pub fn derive_margin_permit(airspace: &Pubkey, owner: &Pubkey) -> Pubkey {
     .0
 }
 
+/// Derive address for the treasury receiving the protocol's share of liquidation
+/// fees in an airspace
+pub fn derive_liquidation_fee_treasury(airspace: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &[LIQUIDATION_FEE_TREASURY_SEED, airspace.as_ref()],
+        &glow_margin::ID,
+    )
+    .0
+}
+
 pub fn derive_liquidation(margin_account: Pubkey, liquidator: Pubkey) -> Pubkey {
     Pubkey::find_program_address(
         &[b"liquidation", margin_account.as_ref(), liquidator.as_ref()],
@@ -1076,18 +1843,63 @@ pub fn derive_liquidation(margin_account: Pubkey, liquidator: Pubkey) -> Pubkey
     .0
 }
 
+/// Derive the address of an order attached to a margin account
+pub fn derive_trigger_order(margin_account: &Pubkey, order_id: u64) -> Pubkey {
+    Pubkey::find_program_address(
+        &[
+            TRIGGER_ORDER_SEED,
+            margin_account.as_ref(),
+            order_id.to_le_bytes().as_ref(),
+        ],
+        &glow_margin::id(),
+    )
+    .0
+}
+
+/// Derive the address of a deleverage order attached to a margin account
+pub fn derive_deleverage_order(margin_account: &Pubkey, order_id: u64) -> Pubkey {
+    Pubkey::find_program_address(
+        &[
+            DELEVERAGE_ORDER_SEED,
+            margin_account.as_ref(),
+            order_id.to_le_bytes().as_ref(),
+        ],
+        &glow_margin::id(),
+    )
+    .0
+}
+
+/// Derive the address of the credit line from a lender account to a borrower account
+pub fn derive_credit_line(lender_account: &Pubkey, borrower_account: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &[
+            CREDIT_LINE_SEED,
+            lender_account.as_ref(),
+            borrower_account.as_ref(),
+        ],
+        &glow_margin::id(),
+    )
+    .0
+}
+
 /// Generic invocation logic that can be applied to any margin account invoke
 /// instruction, such as adapter_invoke, liquidate_invoke, and accounting_invoke
+///
+/// The configs of the windowed adapters are passed as writable, so that the outflow
+/// of the invocation can be recorded in their window. Only `adapter_invoke` and order
+/// executions record outflows, so other invocations pass no windowed adapters.
 macro_rules! invoke_single {
     (
         $airspace:expr,
         $margin_account:expr,
         $adapter_ix:ident,
+        $windowed_adapters:expr,
         $Instruction:ident $({
             $($additional_field:ident$(: $value:expr)?),* $(,)?
         })?
     ) => {{
         let adapter_config = derive_adapter_config(&$airspace, &$adapter_ix.program_id);
+        let windowed_adapters: &[Pubkey] = $windowed_adapters;
 
         let mut accounts = ix_account::$Instruction {
             margin_account: $margin_account,
@@ -1103,7 +1915,7 @@ macro_rules! invoke_single {
         accounts.push(anchor_lang::prelude::AccountMeta {
             pubkey: adapter_config,
             is_signer: false,
-            is_writable: false,
+            is_writable: windowed_adapters.contains(&$adapter_ix.program_id),
         });
 
         let num_accounts = $adapter_ix.accounts.len() as u8;
@@ -1137,15 +1949,20 @@ macro_rules! invoke_many {
         $airspace:expr,
         $margin_account:expr,
         $adapter_ixs:ident,
+        $windowed_adapters:expr,
         $Instruction:ident $({
             $($additional_field:ident$(: $value:expr)?),* $(,)?
         })?
+        $(, args {
+            $($arg:ident$(: $arg_value:expr)?),* $(,)?
+        })?
     ) => {{
         let mut accounts = ix_account::$Instruction {
             margin_account: $margin_account,
             $($($additional_field$(: $value)?),*)?
         }
         .to_account_metas(None);
+        let windowed_adapters: &[Pubkey] = $windowed_adapters;
         let mut instructions = Vec::with_capacity($adapter_ixs.len());
         for adapter_ix in &$adapter_ixs {
             let adapter_config = derive_adapter_config(&$airspace, &adapter_ix.program_id);
@@ -1157,7 +1974,7 @@ macro_rules! invoke_many {
             accounts.push(anchor_lang::prelude::AccountMeta {
                 pubkey: adapter_config,
                 is_signer: false,
-                is_writable: false,
+                is_writable: windowed_adapters.contains(&adapter_ix.program_id),
             });
             let num_accounts = adapter_ix.accounts.len() as u8;
             for acc in &adapter_ix.accounts {
@@ -1180,6 +1997,7 @@ macro_rules! invoke_many {
         Instruction {
             program_id: Margin::id(),
             data: ix_data::$Instruction {
+                $($($arg$(: $arg_value)?,)*)?
                 instructions
             }
             .data(),
@@ -53,6 +53,7 @@
 pub mod get_state;
 /// Instruction builders for programs and adapters supported by the SDK
 pub mod ix_builder;
+pub mod liquidation_sweeper;
 /// ease of use for reading a MarginAccount
 pub mod margin_account_ext;
 /// generic code to integrate adapters with margin
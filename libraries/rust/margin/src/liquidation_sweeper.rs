@@ -0,0 +1,126 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+//
+// Copyright (C) 2024 A1 XYZ, INC.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Discover liquidations abandoned by their liquidator, and end them in
+//! batches to collect the [bounty](glow_margin::LIQUIDATION_TIMEOUT_KEEPER_BOUNTY) for each.
+
+use std::sync::Arc;
+
+use anchor_lang::Discriminator;
+use anyhow::Result;
+use glow_instructions::margin::end_timed_out_liquidations;
+use glow_margin::{LiquidationState, LIQUIDATION_TIMEOUT};
+use glow_simulation::solana_rpc_api::SolanaRpcClient;
+use glow_solana_client::rpc::AccountFilter;
+use solana_sdk::{pubkey::Pubkey, signature::Signature, signer::Signer};
+
+use crate::solana::transaction::{SendTransactionBuilder, TransactionBuilder};
+
+/// The number of liquidations ended by a single crank instruction, which
+/// keeps each transaction comfortably within the account limit.
+pub const LIQUIDATIONS_PER_CRANK: usize = 8;
+
+/// A liquidation that has passed the timeout without being ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StaleLiquidation {
+    /// The address of the liquidation state account
+    pub address: Pubkey,
+    /// The margin account being liquidated
+    pub margin_account: Pubkey,
+    /// The liquidator that abandoned the liquidation
+    pub liquidator: Pubkey,
+    /// The time the liquidation was started
+    pub start_time: i64,
+}
+
+/// Finds and ends timed out liquidations, paying the bounty to the rpc payer
+pub struct LiquidationSweeper {
+    rpc: Arc<dyn SolanaRpcClient>,
+}
+
+impl LiquidationSweeper {
+    /// Create a sweeper that sends crank transactions paid by the rpc payer
+    pub fn new(rpc: Arc<dyn SolanaRpcClient>) -> Self {
+        Self { rpc }
+    }
+
+    /// The keeper that signs the crank transactions and receives the bounty
+    pub fn keeper(&self) -> Pubkey {
+        self.rpc.payer().pubkey()
+    }
+
+    /// Find all liquidations that have passed the [`LIQUIDATION_TIMEOUT`]
+    pub async fn find_stale_liquidations(&self) -> Result<Vec<StaleLiquidation>> {
+        let now = self.rpc.get_clock().await?.unix_timestamp;
+        let accounts = self
+            .rpc
+            .get_program_accounts(
+                &glow_margin::ID,
+                vec![AccountFilter::Memcmp {
+                    offset: 0,
+                    bytes: LiquidationState::discriminator().to_vec(),
+                }],
+            )
+            .await?;
+
+        Ok(accounts
+            .into_iter()
+            .filter_map(|(address, account)| {
+                let data = account.data.get(8..LiquidationState::space(0))?;
+                let state = bytemuck::pod_read_unaligned::<LiquidationState>(data);
+
+                Some(StaleLiquidation {
+                    address,
+                    margin_account: state.margin_account,
+                    liquidator: state.liquidator,
+                    start_time: state.state.start_time(),
+                })
+            })
+            .filter(|stale| now - stale.start_time >= LIQUIDATION_TIMEOUT)
+            .collect())
+    }
+
+    /// Build the transactions to end the given liquidations, in batches of
+    /// [`LIQUIDATIONS_PER_CRANK`]
+    pub fn sweep_transactions(&self, stale: &[StaleLiquidation]) -> Vec<TransactionBuilder> {
+        let keeper = self.keeper();
+
+        stale
+            .chunks(LIQUIDATIONS_PER_CRANK)
+            .map(|batch| {
+                let pairs = batch
+                    .iter()
+                    .map(|s| (s.margin_account, s.liquidator))
+                    .collect::<Vec<_>>();
+
+                end_timed_out_liquidations(keeper, &pairs).into()
+            })
+            .collect()
+    }
+
+    /// Find and end all timed out liquidations
+    pub async fn sweep(&self) -> Result<Vec<Signature>> {
+        let stale = self.find_stale_liquidations().await?;
+        let mut signatures = vec![];
+
+        for tx in self.sweep_transactions(&stale) {
+            signatures.push(self.rpc.send_and_confirm(tx).await?);
+        }
+
+        Ok(signatures)
+    }
+}
@@ -45,8 +45,10 @@ pub async fn refresh_deposit_positions(
         // However, that requires integrating with a Hermes service, which is out of the scope
         // of this lib at this point. We can add this integration separately and later.
         let pyth_price_update = derive_pyth_price_feed_account(&feed_id, None, pyth_oracle);
+        // Stake pool oracles read the stake pool account in place of a redemption feed
         let pyth_redemption_price_update = redemption_feed_id
-            .map(|feed_id| derive_pyth_price_feed_account(&feed_id, None, pyth_oracle));
+            .map(|feed_id| derive_pyth_price_feed_account(&feed_id, None, pyth_oracle))
+            .or(oracle.stake_pool().copied());
 
         let refresh = refresh_deposit_position(
             &state.airspace,
@@ -68,7 +68,8 @@ pub async fn refresh_all_pool_positions_underlying_to_tx(
         let redemption_price_oracle = t_metadata
             .token_price_oracle
             .pyth_redemption_feed_id()
-            .map(|feed_id| derive_pyth_price_feed_account(feed_id, None, pyth_program));
+            .map(|feed_id| derive_pyth_price_feed_account(feed_id, None, pyth_program))
+            .or(t_metadata.token_price_oracle.stake_pool().copied());
         let inner =
             ix_builder.margin_refresh_position(address, price_oracle, redemption_price_oracle);
         let ix = accounting_invoke(state.airspace, address, inner);
@@ -26,7 +26,10 @@ use crate::{
     },
     solana::transaction::TransactionBuilder,
 };
-use glow_margin::{TokenAdmin, TokenConfigUpdate, TokenFeatures, TokenKind};
+use glow_margin::{
+    AdapterOutflowLimits, AdapterPermissionsUpdate, TokenAdmin, TokenConfigUpdate, TokenFeatures,
+    TokenKind, TokenRiskUpdate,
+};
 
 /// Utility for constructing transactions for administrative functions on protocol
 /// resources within an airspace.
@@ -102,6 +105,7 @@ impl AirspaceAdmin {
                 value_modifier: metadata.collateral_weight,
                 max_staleness: metadata.max_staleness,
                 token_features: TokenFeatures::from_bits(metadata.token_features).unwrap(),
+                oracle_limits: Default::default(),
             };
 
             let mut loan_note_config_update = TokenConfigUpdate {
@@ -112,6 +116,7 @@ impl AirspaceAdmin {
                 value_modifier: metadata.max_leverage,
                 max_staleness: metadata.max_staleness,
                 token_features: TokenFeatures::from_bits(metadata.token_features).unwrap(),
+                oracle_limits: Default::default(),
             };
 
             if let Some(metadata) = &config.metadata {
@@ -151,12 +156,25 @@ impl AirspaceAdmin {
                 oracle: config.oracle,
             },
             token_features: config.token_features,
+            oracle_limits: Default::default(),
         });
 
         vec![margin_config_ix.configure_token(underlying_mint.address, config_update.unwrap())]
             .into()
     }
 
+    /// Configure the risk parameters of a token
+    pub fn configure_margin_token_risk(
+        &self,
+        token_mint: Pubkey,
+        update: TokenRiskUpdate,
+    ) -> TransactionBuilder {
+        let margin_config_ix =
+            MarginConfigIxBuilder::new(self.airspace_details().clone(), self.payer);
+
+        vec![margin_config_ix.configure_token_risk(token_mint, update)].into()
+    }
+
     /// Configure an adapter that can be invoked through a margin account
     pub fn configure_margin_adapter(
         &self,
@@ -169,6 +187,30 @@ impl AirspaceAdmin {
         vec![margin_config_ix.configure_adapter(adapter_program_id, is_adapter)].into()
     }
 
+    /// Configure how an adapter may be invoked through a margin account
+    pub fn configure_margin_adapter_permissions(
+        &self,
+        adapter_program_id: Pubkey,
+        update: AdapterPermissionsUpdate,
+    ) -> TransactionBuilder {
+        let margin_config_ix =
+            MarginConfigIxBuilder::new(self.airspace_details().clone(), self.payer);
+
+        vec![margin_config_ix.configure_adapter_permissions(adapter_program_id, update)].into()
+    }
+
+    /// Configure the limits on value leaving margin accounts through an adapter
+    pub fn configure_margin_adapter_outflow_limits(
+        &self,
+        adapter_program_id: Pubkey,
+        limits: AdapterOutflowLimits,
+    ) -> TransactionBuilder {
+        let margin_config_ix =
+            MarginConfigIxBuilder::new(self.airspace_details().clone(), self.payer);
+
+        vec![margin_config_ix.configure_adapter_outflow_limits(adapter_program_id, limits)].into()
+    }
+
     /// Configure an adapter that can be invoked through a margin account
     pub fn configure_margin_liquidator(
         &self,
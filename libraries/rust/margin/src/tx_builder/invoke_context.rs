@@ -1,12 +1,9 @@
 //! This module only defines the generic code for executing margin invocations.
 //! Other modules define ways to use this context to invoke specific adapters.
 
-use glow_instructions::{
-    margin::{
-        accounting_invoke, accounting_invoke_many, adapter_invoke, adapter_invoke_many,
-        liquidator_invoke, liquidator_invoke_many,
-    },
-    MintInfo,
+use glow_instructions::margin::{
+    accounting_invoke, accounting_invoke_many, adapter_invoke, adapter_invoke_many,
+    liquidator_invoke, liquidator_invoke_many,
 };
 use glow_solana_client::{signature::NeedsSignature, transaction::TransactionBuilder};
 use solana_sdk::{instruction::Instruction, pubkey::Pubkey};
@@ -30,51 +27,55 @@ pub struct MarginInvokeContext {
     pub authority: Pubkey,
     /// Is the authority a liquidator?
     pub is_liquidator: bool,
+    /// Adapters with an outflow window limit, whose configs are written when invoked.
+    pub windowed_adapters: Vec<Pubkey>,
 }
 
 impl MarginInvokeContext {
-    fn invoke_ix(&self, inner: Instruction, fee_mint: Option<MintInfo>) -> Instruction {
+    fn invoke_ix(&self, inner: Instruction) -> Instruction {
         let MarginInvokeContext {
             airspace,
             margin_account,
             authority,
             is_liquidator,
+            windowed_adapters,
         } = self;
         if inner.needs_signature(*margin_account) {
             if *is_liquidator {
-                liquidator_invoke(
+                liquidator_invoke(*airspace, *authority, *margin_account, inner)
+            } else {
+                adapter_invoke(
                     *airspace,
                     *authority,
                     *margin_account,
-                    fee_mint.unwrap(),
                     inner,
+                    windowed_adapters,
                 )
-            } else {
-                adapter_invoke(*airspace, *authority, *margin_account, inner)
             }
         } else {
             accounting_invoke(*airspace, *margin_account, inner)
         }
     }
 
-    fn invoke_ix_many(&self, inner: Vec<Instruction>, fee_mint: Option<MintInfo>) -> Instruction {
+    fn invoke_ix_many(&self, inner: Vec<Instruction>) -> Instruction {
         let MarginInvokeContext {
             airspace,
             margin_account,
             authority,
             is_liquidator,
+            windowed_adapters,
         } = self;
         if inner.needs_signature(*margin_account) {
             if *is_liquidator {
-                liquidator_invoke_many(
+                liquidator_invoke_many(*airspace, *authority, *margin_account, inner)
+            } else {
+                adapter_invoke_many(
                     *airspace,
                     *authority,
                     *margin_account,
-                    fee_mint.unwrap(),
                     inner,
+                    windowed_adapters,
                 )
-            } else {
-                adapter_invoke_many(*airspace, *authority, *margin_account, inner)
             }
         } else {
             accounting_invoke_many(*airspace, *margin_account, inner)
@@ -85,8 +86,8 @@ impl MarginInvokeContext {
 impl MarginInvokeContext {
     /// Invoke margin adapters through a margin account using whatever wrapper
     /// is needed: adapter_invoke, accounting_invoke, or liquidator_invoke.  
-    pub fn invoke(&self, inner: Instruction, fee_mint: Option<MintInfo>) -> TransactionBuilder {
-        self.invoke_ix(inner, fee_mint).into()
+    pub fn invoke(&self, inner: Instruction) -> TransactionBuilder {
+        self.invoke_ix(inner).into()
     }
 
     // /// Applies `invoke` individually to each instruction and returns a vec of results
@@ -95,12 +96,8 @@ impl MarginInvokeContext {
     // }
 
     /// Invokes many instructions as part of a single transaction call
-    pub fn invoke_many(
-        &self,
-        inners: Vec<Instruction>,
-        fee_mint: Option<MintInfo>,
-    ) -> TransactionBuilder {
-        self.invoke_ix_many(inners, fee_mint).into()
+    pub fn invoke_many(&self, inners: Vec<Instruction>) -> TransactionBuilder {
+        self.invoke_ix_many(inners).into()
     }
 
     // /// Individually invokes each instruction and combines it into a single
@@ -146,7 +143,7 @@ pub mod invoke_into {
 
     impl InvokeEachInto for Instruction {
         fn invoke_each_into(self, ctx: &MarginInvokeContext) -> Vec<TransactionBuilder> {
-            vec![ctx.invoke(self, None)]
+            vec![ctx.invoke(self)]
         }
     }
 
@@ -158,8 +155,6 @@ pub mod invoke_into {
 
     /// Defines the way to unpack some type, invoke any containing instructions
     /// through margin, and pack it all into a single TransactionBuilder.
-    ///
-    /// NOTE: This is unsuitable for use by liquidators as the `fee_mint` is empty.
     pub trait InvokeInto: Sized {
         /// Invoke a margin adapter through a margin account using whichever wrapper
         /// is needed: adapter_invoke, accounting_invoke, or liquidator_invoke. If
@@ -174,21 +169,21 @@ pub mod invoke_into {
 
     impl InvokeInto for Instruction {
         fn invoke_into(self, ctx: &MarginInvokeContext) -> TransactionBuilder {
-            ctx.invoke(self, None)
+            ctx.invoke(self)
         }
 
         fn invoke_many_into(self, ctx: &MarginInvokeContext) -> TransactionBuilder {
-            ctx.invoke(self, None)
+            ctx.invoke(self)
         }
     }
 
     impl InvokeInto for Vec<Instruction> {
         fn invoke_into(self, ctx: &MarginInvokeContext) -> TransactionBuilder {
-            ctx.invoke_many(self, None)
+            ctx.invoke_many(self)
         }
 
         fn invoke_many_into(self, ctx: &MarginInvokeContext) -> TransactionBuilder {
-            ctx.invoke_many(self, None)
+            ctx.invoke_many(self)
         }
     }
 }
@@ -27,24 +27,17 @@ impl MarginInvokeContext {
         let pool = MarginPoolIxBuilder::new(self.airspace, underlying_mint);
         let source_authority = source_authority.unwrap_or(self.margin_account);
         let (target, mut instructions) = self.get_or_create_pool_deposit(underlying_mint, target);
-        instructions.push(self.invoke(
-            pool.deposit(
-                source_authority,
-                Some(self.margin_account),
-                source.or_ata(
-                    &source_authority,
-                    &underlying_mint.address,
-                    &underlying_mint.token_program(),
-                ),
-                target,
-                change,
+        instructions.push(self.invoke(pool.deposit(
+            source_authority,
+            Some(self.margin_account),
+            source.or_ata(
+                &source_authority,
+                &underlying_mint.address,
+                &underlying_mint.token_program(),
             ),
-            if self.is_liquidator {
-                Some(underlying_mint)
-            } else {
-                None
-            },
-        ));
+            target,
+            change,
+        )));
         instructions
     }
 
@@ -99,18 +92,11 @@ impl MarginInvokeContext {
                 payer,
                 pool.pool_deposit_mint_info(),
             ),
-            self.invoke(
-                pool.margin_refresh_position(
-                    self.margin_account,
-                    pool_oracle,
-                    pool_redemption_rate_oracle
-                ),
-                if self.is_liquidator {
-                    Some(underlying_mint)
-                } else {
-                    None
-                },
-            ),
+            self.invoke(pool.margin_refresh_position(
+                self.margin_account,
+                pool_oracle,
+                pool_redemption_rate_oracle
+            ),),
         ]
     }
 }
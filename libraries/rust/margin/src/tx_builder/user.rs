@@ -33,12 +33,16 @@ use solana_sdk::transaction::VersionedTransaction;
 
 use anchor_lang::AccountDeserialize;
 
-use glow_margin::{AccountFeatureFlags, LiquidationState, MarginAccount, TokenConfig, TokenKind};
+use glow_margin::{
+    AccountFeatureFlags, AdapterConfig, LiquidationState, MarginAccount, TokenConfig, TokenKind,
+};
 use glow_margin_pool::MarginPool;
 use glow_simulation::solana_rpc_api::SolanaRpcClient;
 
 use crate::cat;
-use crate::get_state::{get_margin_account, get_position_config, get_token_metadata};
+use crate::get_state::{
+    get_anchor_account, get_margin_account, get_position_config, get_token_metadata,
+};
 use crate::refresh::deposit::refresh_deposit_positions;
 use crate::refresh::pool::{
     refresh_all_pool_positions, refresh_all_pool_positions_underlying_to_tx,
@@ -183,6 +187,7 @@ impl MarginTxBuilder {
             margin_account: *self.address(),
             authority: MarginActionAuthority::AccountAuthority.resolve(&self.ix),
             is_liquidator: self.is_liquidator,
+            windowed_adapters: self.ix.windowed_adapters().to_vec(),
         }
     }
 
@@ -212,6 +217,29 @@ impl MarginTxBuilder {
         self
     }
 
+    /// Creates a variant of the builder that passes the configs of the given adapters
+    /// as writable, which is required when they have an outflow window limit.
+    pub fn with_windowed_adapters(mut self, adapters: Vec<Pubkey>) -> Self {
+        self.ix = self.ix.with_windowed_adapters(adapters);
+
+        self
+    }
+
+    /// Find which of the given adapters have an outflow window limit in the airspace,
+    /// so that their configs are passed as writable when they are invoked.
+    pub async fn load_windowed_adapters(self, adapters: &[Pubkey]) -> Result<Self> {
+        let mut windowed = vec![];
+        for adapter in adapters {
+            let config_address = derive_adapter_config(&self.airspace(), adapter);
+            let config: AdapterConfig = get_anchor_account(&self.rpc, &config_address).await?;
+            if config.outflow_limits.has_window() {
+                windowed.push(*adapter);
+            }
+        }
+
+        Ok(self.with_windowed_adapters(windowed))
+    }
+
     async fn create_transaction(
         &self,
         instructions: &[Instruction],
@@ -327,7 +355,7 @@ impl MarginTxBuilder {
         let instructions = vec![
             self.ix
                 .close_position(pool.pool_deposit_mint_info(), deposit_account),
-            self.adapter_invoke_ix(pool.close_loan(*self.address(), self.ix.payer()), None),
+            self.adapter_invoke_ix(pool.close_loan(*self.address(), self.ix.payer())),
         ];
         self.create_transaction(&instructions).await
     }
@@ -347,9 +375,10 @@ impl MarginTxBuilder {
                 self.ix.get_token_account_address(&pool.deposit_note_mint),
             ),
             TokenKind::Claim => {
-                self.adapter_invoke_ix(pool.close_loan(*self.address(), self.ix.payer()), None)
+                self.adapter_invoke_ix(pool.close_loan(*self.address(), self.ix.payer()))
             }
             TokenKind::AdapterCollateral => panic!("pools do not issue AdapterCollateral"),
+            TokenKind::NonCollateral => panic!("pools do not issue NonCollateral"),
         };
 
         self.create_transaction(&[ix]).await
@@ -371,7 +400,7 @@ impl MarginTxBuilder {
                         self.airspace(),
                         *loan_to_token.get(&p.token).unwrap(),
                     );
-                    self.adapter_invoke_ix(pool.close_loan(*self.address(), self.ix.payer()), None)
+                    self.adapter_invoke_ix(pool.close_loan(*self.address(), self.ix.payer()))
                 } else {
                     // Glow margin pools use token-2022, so any pool position should default to that token program
                     if p.adapter == glow_margin_pool::id() {
@@ -476,7 +505,7 @@ impl MarginTxBuilder {
                 instructions.push(self.refresh_pool_position(token_mint).await?);
             }
         }
-        instructions.push(self.smart_invoke(inner_ix, None));
+        instructions.push(self.smart_invoke(inner_ix));
 
         Ok(self.create_transaction_builder(&instructions))
     }
@@ -506,7 +535,8 @@ impl MarginTxBuilder {
             .pyth_redemption_feed_id()
             .map(|feed_id| {
                 derive_pyth_price_feed_account(feed_id, None, self.network_kind.pyth_oracle())
-            });
+            })
+            .or(token_metadata.token_price_oracle.stake_pool().copied());
 
         self.with_liquidation_fee_accounts(token_mint, &mut instructions);
 
@@ -523,7 +553,7 @@ impl MarginTxBuilder {
 
         let inner_borrow_ix = pool.margin_borrow(self.ix.address, deposit_position, change);
 
-        instructions.push(self.adapter_invoke_ix(inner_borrow_ix, Some(token_mint)));
+        instructions.push(self.adapter_invoke_ix(inner_borrow_ix));
         Ok(self.create_transaction_builder(&instructions))
     }
 
@@ -552,7 +582,7 @@ impl MarginTxBuilder {
 
         let inner_repay_ix = pool.margin_repay(self.ix.address, deposit_position, change);
 
-        instructions.push(self.adapter_invoke_ix(inner_repay_ix, Some(token_mint)));
+        instructions.push(self.adapter_invoke_ix(inner_repay_ix));
         Ok(self.create_transaction_builder(&instructions))
     }
 
@@ -576,7 +606,7 @@ impl MarginTxBuilder {
         let pool = MarginPoolIxBuilder::new(self.airspace(), token_mint);
         let loan_notes = derive_loan_account(&self.ix.address, &pool.loan_note_mint);
         let inner_ix = pool.repay(authority, source, loan_notes, change);
-        let wrapped_ix = self.smart_invoke(inner_ix, None);
+        let wrapped_ix = self.smart_invoke(inner_ix);
 
         self.create_transaction_builder(&[wrapped_ix])
     }
@@ -613,7 +643,7 @@ impl MarginTxBuilder {
         let inner_withdraw_ix =
             pool.withdraw(self.ix.address, deposit_position, *destination, change);
 
-        instructions.push(self.adapter_invoke_ix(inner_withdraw_ix, Some(token_mint)));
+        instructions.push(self.adapter_invoke_ix(inner_withdraw_ix));
         self.create_transaction(&instructions).await
     }
 
@@ -655,7 +685,8 @@ impl MarginTxBuilder {
             .pyth_redemption_feed_id()
             .map(|feed_id| {
                 derive_pyth_price_feed_account(feed_id, None, self.network_kind.pyth_oracle())
-            });
+            })
+            .or(token_metadata.token_price_oracle.stake_pool().copied());
         let inner_refresh_loan_ix =
             src_pool.margin_refresh_position(self.ix.address, oracle, redemption_price_oracle);
         let token_metadata =
@@ -670,7 +701,8 @@ impl MarginTxBuilder {
             .pyth_redemption_feed_id()
             .map(|feed_id| {
                 derive_pyth_price_feed_account(feed_id, None, self.network_kind.pyth_oracle())
-            });
+            })
+            .or(token_metadata.token_price_oracle.stake_pool().copied());
         let inner_refresh_deposit_ix =
             dst_pool.margin_refresh_position(self.ix.address, oracle, redemption_price_oracle);
 
@@ -710,17 +742,14 @@ impl MarginTxBuilder {
             loan_position,
             TokenChange::set_source(borrow_dst_balance),
         );
-        let invoke_ix = self.adapter_invoke_many_ix(
-            &[
-                inner_refresh_loan_ix,
-                inner_refresh_deposit_ix,
-                inner_borrow_ix,
-                swap_instruction.clone(),
-                inner_deposit_ix,
-                revert_bal_ix,
-            ],
-            None,
-        );
+        let invoke_ix = self.adapter_invoke_many_ix(&[
+            inner_refresh_loan_ix,
+            inner_refresh_deposit_ix,
+            inner_borrow_ix,
+            swap_instruction.clone(),
+            inner_deposit_ix,
+            revert_bal_ix,
+        ]);
         instructions.push(invoke_ix);
 
         Ok(self.create_transaction_builder(&instructions))
@@ -788,9 +817,12 @@ impl MarginTxBuilder {
                 None,
                 self.network_kind.pyth_oracle(),
             );
-            let redemption_price_oracle = oracle.pyth_redemption_feed_id().map(|feed_id| {
-                derive_pyth_price_feed_account(feed_id, None, self.network_kind.pyth_oracle())
-            });
+            let redemption_price_oracle = oracle
+                .pyth_redemption_feed_id()
+                .map(|feed_id| {
+                    derive_pyth_price_feed_account(feed_id, None, self.network_kind.pyth_oracle())
+                })
+                .or(oracle.stake_pool().copied());
             instructions.push(self.ix.refresh_deposit_position(
                 from_mint,
                 &price_oracle,
@@ -807,9 +839,12 @@ impl MarginTxBuilder {
                 None,
                 self.network_kind.pyth_oracle(),
             );
-            let redemption_price_oracle = oracle.pyth_redemption_feed_id().map(|feed_id| {
-                derive_pyth_price_feed_account(feed_id, None, self.network_kind.pyth_oracle())
-            });
+            let redemption_price_oracle = oracle
+                .pyth_redemption_feed_id()
+                .map(|feed_id| {
+                    derive_pyth_price_feed_account(feed_id, None, self.network_kind.pyth_oracle())
+                })
+                .or(oracle.stake_pool().copied());
             instructions.push(self.ix.refresh_deposit_position(
                 to_mint,
                 &price_oracle,
@@ -833,7 +868,8 @@ impl MarginTxBuilder {
             .pyth_redemption_feed_id()
             .map(|feed_id| {
                 derive_pyth_price_feed_account(feed_id, None, self.network_kind.pyth_oracle())
-            });
+            })
+            .or(token_metadata.token_price_oracle.stake_pool().copied());
         let inner_refresh_src_pool_ix =
             src_pool.margin_refresh_position(self.ix.address, oracle, redemption_price_oracle);
         let token_metadata =
@@ -848,7 +884,8 @@ impl MarginTxBuilder {
             .pyth_redemption_feed_id()
             .map(|feed_id| {
                 derive_pyth_price_feed_account(feed_id, None, self.network_kind.pyth_oracle())
-            });
+            })
+            .or(token_metadata.token_price_oracle.stake_pool().copied());
 
         let inner_refresh_dst_pool_ix =
             dst_pool.margin_refresh_position(self.ix.address, oracle, redemption_price_oracle);
@@ -893,17 +930,14 @@ impl MarginTxBuilder {
             dst_pool_deposit_position,
             TokenChange::set_source(to_ata_balance + liquidation_fee),
         );
-        let invoke_ix = self.adapter_invoke_many_ix(
-            &[
-                inner_refresh_src_pool_ix,
-                inner_refresh_dst_pool_ix,
-                inner_src_pool_withdraw_ix,
-                swap_instruction.clone(),
-                inner_dst_pool_repay_ix,
-                inner_dst_pool_deposit_ix,
-            ],
-            Some(to_mint),
-        );
+        let invoke_ix = self.adapter_invoke_many_ix(&[
+            inner_refresh_src_pool_ix,
+            inner_refresh_dst_pool_ix,
+            inner_src_pool_withdraw_ix,
+            swap_instruction.clone(),
+            inner_dst_pool_repay_ix,
+            inner_dst_pool_deposit_ix,
+        ]);
         instructions.push(invoke_ix);
 
         let swap_builder = self.create_transaction_builder(&instructions);
@@ -939,8 +973,14 @@ impl MarginTxBuilder {
 
     /// Transaction to begin liquidating user account.
     /// If `refresh_position` is provided, all the margin pools will be refreshed first.
-    pub async fn liquidate_begin(&self, refresh_positions: bool) -> Result<VersionedTransaction> {
-        let builder = self.liquidate_begin_builder(refresh_positions).await?;
+    pub async fn liquidate_begin(
+        &self,
+        refresh_positions: bool,
+        permission: LiquidatorPermission,
+    ) -> Result<VersionedTransaction> {
+        let builder = self
+            .liquidate_begin_builder(refresh_positions, permission)
+            .await?;
         self.rpc.compile(builder).await
     }
 
@@ -949,6 +989,7 @@ impl MarginTxBuilder {
     pub async fn liquidate_begin_builder(
         &self,
         refresh_positions: bool,
+        permission: LiquidatorPermission,
     ) -> Result<TransactionBuilder> {
         assert!(self.is_liquidator);
 
@@ -972,14 +1013,17 @@ impl MarginTxBuilder {
         };
 
         // Add liquidation instruction
-        txs.instructions.push(self.ix.liquidate_begin());
+        txs.instructions.push(match permission {
+            LiquidatorPermission::Permit => self.ix.liquidate_begin(),
+            LiquidatorPermission::Permissionless => self.ix.liquidate_begin_permissionless(),
+        });
         txs.signers
             .push(self.signer.as_ref().context("missing signer")?.clone());
 
         Ok(txs)
     }
 
-    /// Collect the liquidation fee in specified token
+    /// Collect the liquidation fees accrued in every mint
     pub async fn collect_liquidation_fees(&self) -> Result<Vec<TransactionBuilder>> {
         let pyth_oracle = self.network_kind.pyth_oracle();
         assert!(self.is_liquidator);
@@ -992,18 +1036,18 @@ impl MarginTxBuilder {
             .get_account(&derive_liquidation(*margin_account, liquidator))
             .await?
             .expect("Liquidation account should exist");
-        let liquidation_state =
-            bytemuck::pod_read_unaligned::<LiquidationState>(&liquidation_state.data[8..]);
+        let liquidation_fees = LiquidationState::read_fees(&liquidation_state.data)?;
+        let shares_fees = LiquidationState::read_state(&liquidation_state.data)?
+            .state
+            .protocol_fee_share_bps
+            > 0;
+        let treasury = derive_liquidation_fee_treasury(&self.airspace());
         let margin_account_state = self.get_account_state().await?;
 
-        let liquidation_fees = liquidation_state
-            .state
-            .accrued_liquidation_fees
-            .iter()
-            .filter(|f| f.mint != Pubkey::default());
         // To collect fees, the liquidator should withdraw the desired amounts from pools to the user's margin tokens
         let mut transfer_ixs = vec![];
         let mut collect_ixs = vec![];
+        let mut collections = vec![];
         for fee in liquidation_fees {
             let mint = self.rpc().get_account(&fee.mint).await?.unwrap();
             let mint = MintInfo::with_token_program(fee.mint, mint.owner);
@@ -1022,21 +1066,23 @@ impl MarginTxBuilder {
             if let Some(position) = existing_position {
                 if position.balance < fee.amount {
                     // Withdraw from margin pool
-                    transfer_ixs.push(self.ix.liquidator_invoke(
-                        pool.withdraw(
-                            *margin_account,
-                            deposit_position,
-                            mint.associated_token_address(margin_account),
-                            TokenChange::set_destination(fee.amount),
-                        ),
-                        mint,
-                    ));
+                    transfer_ixs.push(self.ix.liquidator_invoke(pool.withdraw(
+                        *margin_account,
+                        deposit_position,
+                        mint.associated_token_address(margin_account),
+                        TokenChange::set_destination(fee.amount),
+                    )));
                 }
             }
-            // Create ATAs
+            // Create ATAs, including the treasury's for the protocol's share of the fee
             collect_ixs.push(
                 mint.create_associated_token_account_idempotent(&liquidator, &self.ix.payer()),
             );
+            if shares_fees {
+                collect_ixs.push(
+                    mint.create_associated_token_account_idempotent(&treasury, &self.ix.payer()),
+                );
+            }
             // Get the token config
             let token_config = derive_token_config(&self.ix.airspace_details.address, &fee.mint);
             let token_config_data = self.rpc().get_account(&token_config).await?.unwrap();
@@ -1048,14 +1094,16 @@ impl MarginTxBuilder {
             let quote_feed_id = oracle.pyth_redemption_feed_id();
             let price_oracle = derive_pyth_price_feed_account(feed_id, None, pyth_oracle);
             let redemption_quote_oracle = quote_feed_id
-                .map(|feed_id| derive_pyth_price_feed_account(feed_id, None, pyth_oracle));
-            collect_ixs.push(self.ix.collect_liquidation_fee(
+                .map(|feed_id| derive_pyth_price_feed_account(feed_id, None, pyth_oracle))
+                .or(oracle.stake_pool().copied());
+            collections.push(LiquidationFeeCollection {
                 mint,
                 token_config,
                 price_oracle,
                 redemption_quote_oracle,
-            ))
+            });
         }
+        collect_ixs.push(self.ix.collect_liquidation_fee(&collections, shares_fees));
 
         // Add signer
         let mut transfer_builder: TransactionBuilder = transfer_ixs.into();
@@ -1100,9 +1148,12 @@ impl MarginTxBuilder {
             None,
             self.network_kind.pyth_oracle(),
         );
-        let redemption_price_oracle = pool_oracle.pyth_redemption_feed_id().map(|feed_id| {
-            derive_pyth_price_feed_account(feed_id, None, self.network_kind.pyth_oracle())
-        });
+        let redemption_price_oracle = pool_oracle
+            .pyth_redemption_feed_id()
+            .map(|feed_id| {
+                derive_pyth_price_feed_account(feed_id, None, self.network_kind.pyth_oracle())
+            })
+            .or(pool_oracle.stake_pool().copied());
 
         Ok(self
             .ix
@@ -1273,36 +1324,32 @@ impl MarginTxBuilder {
             position.address
         } else {
             let pools_ix = pool.register_loan(self.ix.address, self.ix.payer());
-            let wrapped_ix = self.adapter_invoke_ix(pools_ix, None);
+            let wrapped_ix = self.adapter_invoke_ix(pools_ix);
             instructions.push(wrapped_ix);
 
             derive_loan_account(&self.ix.address, &pool.loan_note_mint)
         })
     }
 
-    fn adapter_invoke_ix(&self, inner: Instruction, fee_mint: Option<MintInfo>) -> Instruction {
+    fn adapter_invoke_ix(&self, inner: Instruction) -> Instruction {
         match self.is_liquidator {
-            true => self.ix.liquidator_invoke(inner, fee_mint.unwrap()),
+            true => self.ix.liquidator_invoke(inner),
             false => self.ix.adapter_invoke(inner),
         }
     }
 
-    fn adapter_invoke_many_ix(
-        &self,
-        inners: &[Instruction],
-        fee_mint: Option<MintInfo>,
-    ) -> Instruction {
+    fn adapter_invoke_many_ix(&self, inners: &[Instruction]) -> Instruction {
         match self.is_liquidator {
-            true => self.ix.liquidator_invoke_many(inners, fee_mint.unwrap()),
+            true => self.ix.liquidator_invoke_many(inners),
             false => self.ix.adapter_invoke_many(inners),
         }
     }
 
     /// If the margin account needs to sign, then use adapter or liquidator
     /// invoke, otherwise use accounting invoke.
-    pub fn smart_invoke(&self, inner: Instruction, fee_mint: Option<MintInfo>) -> Instruction {
+    pub fn smart_invoke(&self, inner: Instruction) -> Instruction {
         if self.ix.needs_signature(&inner) {
-            self.adapter_invoke_ix(inner, fee_mint)
+            self.adapter_invoke_ix(inner)
         } else {
             self.ix.accounting_invoke(inner)
         }
@@ -1348,3 +1395,12 @@ impl MarginActionAuthority {
         }
     }
 }
+
+/// How a liquidator is allowed to begin liquidating a margin account
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub enum LiquidatorPermission {
+    /// The liquidator holds a permit issued by the airspace authority
+    Permit,
+    /// The airspace allows any signer to liquidate, so no permit is provided
+    Permissionless,
+}
@@ -69,13 +69,15 @@ pub const ADDRESS_LOOKUP_REGISTRY_ID: Pubkey =
 /// return data can come from any program that such safe program calls, if the program doesn't
 /// set its own data before ending the CPI call.
 #[cfg(feature = "testing")]
-pub const SAFE_RETURN_DATA_PROGRAMS: [Pubkey; 2] = [
+pub const SAFE_RETURN_DATA_PROGRAMS: [Pubkey; 3] = [
     pubkey!("CWPeEXnSpELj7tSz9W4oQAGGRbavBtdnhY2bWMyPoo1"), // glow margin pool
+    pubkey!("G8VyZJ3DhkD1j8sRo5A8e5ByUUNHwhyygvJUum1qhndB"), // glow whirlpool adapter
     pubkey!("test7JXXboKpc8hGTadvoXcFWN4xgnHLGANU92JKrwA"), // test service as it has a test swap pool
 ];
 #[cfg(not(feature = "testing"))]
-pub const SAFE_RETURN_DATA_PROGRAMS: [Pubkey; 1] = [
+pub const SAFE_RETURN_DATA_PROGRAMS: [Pubkey; 2] = [
     pubkey!("CWPeEXnSpELj7tSz9W4oQAGGRbavBtdnhY2bWMyPoo1"), // glow margin pool
+    pubkey!("G8VyZJ3DhkD1j8sRo5A8e5ByUUNHwhyygvJUum1qhndB"), // glow whirlpool adapter
 ];
 
 /// Known external programs whose side effects and event data we want to observe.
@@ -150,6 +150,48 @@ impl Number128 {
     pub fn from_i128(value: i128) -> Self {
         Self(value)
     }
+
+    /// The square root of this number, rounded down.
+    ///
+    /// Negative numbers have no square root and return zero.
+    pub fn sqrt(&self) -> Self {
+        if self.0 <= 0 {
+            return Self::ZERO;
+        }
+
+        // sqrt(x / ONE) * ONE == sqrt(x * ONE). If x * ONE overflows, x is only scaled by the
+        // largest even power of ten that fits, and the root is scaled by the square root of
+        // the remaining factor, which only loses the last digits of precision.
+        let value = self.0 as u128;
+        let half_precision = (PRECISION / 2) as u32;
+        let (scaled, shift) = (0..=half_precision)
+            .rev()
+            .find_map(|shift| {
+                value
+                    .checked_mul(10u128.pow(2 * shift))
+                    .map(|scaled| (scaled, shift))
+            })
+            .unwrap_or((value, 0));
+
+        Self((isqrt(scaled) * 10u128.pow(half_precision - shift)) as i128)
+    }
+}
+
+/// The integer square root, rounded down
+fn isqrt(n: u128) -> u128 {
+    if n < 2 {
+        return n;
+    }
+
+    // Newton's method, starting above the root
+    let mut x = 1u128 << ((128 - n.leading_zeros()) / 2 + 1);
+    loop {
+        let next = (x + n / x) / 2;
+        if next >= x {
+            return x;
+        }
+        x = next;
+    }
 }
 
 impl std::fmt::Debug for Number128 {
@@ -580,4 +622,44 @@ mod tests {
             Number128::ZERO
         );
     }
+
+    #[test]
+    fn sqrt() {
+        assert_eq!(Number128::ZERO.sqrt(), Number128::ZERO);
+        assert_eq!(Number128::from_decimal(-4, 0).sqrt(), Number128::ZERO);
+        assert_eq!(
+            Number128::from_decimal(16, 0).sqrt(),
+            Number128::from_decimal(4, 0)
+        );
+        assert_eq!(
+            Number128::from_decimal(25, -2).sqrt(),
+            Number128::from_decimal(5, -1)
+        );
+        assert_eq!(
+            Number128::from_decimal(4, 20).sqrt(),
+            Number128::from_decimal(2, 10)
+        );
+        assert_eq!(
+            Number128::from_decimal(2, 0).sqrt(),
+            Number128::from_decimal(14_142_135_623i64, -10)
+        );
+    }
+
+    #[test]
+    fn sqrt_near_overflow() {
+        // the largest numbers that can still be scaled by ONE have an exact root
+        assert_eq!(
+            Number128::from_decimal(3, 18).sqrt(),
+            Number128(17_320_508_075_688_772_935)
+        );
+
+        // beyond them, only the last digits of the root are lost
+        let root = Number128::from_decimal(5, 18).sqrt();
+        let exact = Number128(22_360_679_774_997_896_964);
+        assert!(root <= exact);
+        assert!((exact - root).0 < 10);
+
+        // the largest number has a root without overflowing
+        assert_eq!(Number128::MAX.sqrt().as_u64(0), 130_438_178_253_327);
+    }
 }
@@ -1,5 +1,15 @@
+use anchor_lang::prelude::{pubkey, Pubkey};
 use anchor_lang::{AnchorDeserialize, AnchorSerialize};
 
+/// The SPL stake pool program
+pub const STAKE_POOL_PROGRAM_ID: Pubkey = pubkey!("SPoo1Ku8WFXoNDMHPsrGSTSG1Y47rzgn41SLUNakuHy");
+
+/// The Pyth feed id of the SOL/USD price, which identifies tokens that can be priced in SOL
+pub const SOL_USD_FEED_ID: [u8; 32] = [
+    0xef, 0x0d, 0x8b, 0x6f, 0xda, 0x2c, 0xeb, 0xa4, 0x1d, 0xa1, 0x5d, 0x40, 0x95, 0xd1, 0xda, 0x39,
+    0x2a, 0x0d, 0x2f, 0x8e, 0xd0, 0xc6, 0xc7, 0xbc, 0x0f, 0x4c, 0xfa, 0xc8, 0xc2, 0x80, 0xb5, 0x6d,
+];
+
 #[derive(Default, Debug, Copy, Eq, PartialEq, Clone, AnchorSerialize, AnchorDeserialize)]
 pub enum TokenPriceOracle {
     #[default]
@@ -13,6 +23,12 @@ pub enum TokenPriceOracle {
         feed_id: [u8; 32],
         quote_feed_id: [u8; 32],
     },
+    /// The fair value of a stake pool token (LST), derived from the SOL backing each pool token
+    /// in the stake pool account, multiplied by a SOL/USD price feed.
+    StakePool {
+        stake_pool: Pubkey,
+        sol_feed_id: [u8; 32],
+    },
 }
 
 impl TokenPriceOracle {
@@ -20,6 +36,15 @@ impl TokenPriceOracle {
         match self {
             TokenPriceOracle::PythPull { feed_id } => Some(feed_id),
             TokenPriceOracle::PythPullRedemption { feed_id, .. } => Some(feed_id),
+            TokenPriceOracle::StakePool { sol_feed_id, .. } => Some(sol_feed_id),
+            _ => None,
+        }
+    }
+
+    /// The stake pool account that determines the price of the token, if any
+    pub fn stake_pool(&self) -> Option<&Pubkey> {
+        match self {
+            TokenPriceOracle::StakePool { stake_pool, .. } => Some(stake_pool),
             _ => None,
         }
     }
@@ -34,6 +59,41 @@ impl TokenPriceOracle {
             _ => None,
         }
     }
+
+    /// How the token is priced in SOL without a SOL/USD price, if it can be.
+    ///
+    /// SOL itself is recognised by its SOL/USD feed, and derivatives by a redemption
+    /// rate or stake pool that is quoted in SOL.
+    pub fn sol_quote(&self) -> Option<SolQuote> {
+        match self {
+            TokenPriceOracle::PythPull { feed_id } if *feed_id == SOL_USD_FEED_ID => {
+                Some(SolQuote::Sol)
+            }
+            TokenPriceOracle::PythPullRedemption {
+                feed_id,
+                quote_feed_id,
+            } if *quote_feed_id == SOL_USD_FEED_ID => {
+                Some(SolQuote::RedemptionRate { feed_id: *feed_id })
+            }
+            TokenPriceOracle::StakePool { stake_pool, .. } => Some(SolQuote::StakePool {
+                stake_pool: *stake_pool,
+            }),
+            _ => None,
+        }
+    }
+}
+
+/// The source of the price of a SOL based token when it is quoted in SOL
+#[derive(Debug, Copy, Eq, PartialEq, Clone)]
+pub enum SolQuote {
+    /// The token is SOL
+    Sol,
+
+    /// The token is priced by a redemption rate to SOL (e.g. sSOL:SOL)
+    RedemptionRate { feed_id: [u8; 32] },
+
+    /// The token is priced by the SOL backing each pool token of a stake pool
+    StakePool { stake_pool: Pubkey },
 }
 
 impl serde::Serialize for TokenPriceOracle {
@@ -58,9 +118,69 @@ impl serde::Serialize for TokenPriceOracle {
                 );
                 serializer.serialize_str(&hex)
             }
+            TokenPriceOracle::StakePool {
+                stake_pool,
+                sol_feed_id,
+            } => {
+                let hex = format!("StakePool:{}:0x{}", stake_pool, hex::encode(sol_feed_id));
+                serializer.serialize_str(&hex)
+            }
         }
     }
 }
+
+/// The SOL backing of the tokens of an SPL stake pool, as recorded in the stake pool account
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct StakePoolRate {
+    /// The mint of the pool tokens
+    pub pool_mint: Pubkey,
+
+    /// The total lamports staked by the pool, including the reserve
+    pub total_lamports: u64,
+
+    /// The supply of pool tokens
+    pub pool_token_supply: u64,
+
+    /// The epoch in which the pool's balances were last updated.
+    ///
+    /// The stake pool records no slot or time of its update, and its balances only change
+    /// once per epoch, so a rate is treated as current for the whole epoch it was updated in.
+    pub last_update_epoch: u64,
+}
+
+impl StakePoolRate {
+    /// The discriminator of a stake pool account in the SPL stake pool program
+    const ACCOUNT_TYPE_STAKE_POOL: u8 = 1;
+
+    /// The offset of `pool_mint` in the stake pool account
+    const POOL_MINT_OFFSET: usize = 162;
+
+    /// The offset of `total_lamports` in the stake pool account
+    const TOTAL_LAMPORTS_OFFSET: usize = 258;
+
+    /// Read the rate from the data of an SPL stake pool account.
+    ///
+    /// Only the fields that are needed are read, so that the stake pool crate does not need
+    /// to be imported by programs.
+    pub fn try_from_account_data(data: &[u8]) -> Option<Self> {
+        if data.first() != Some(&Self::ACCOUNT_TYPE_STAKE_POOL) {
+            return None;
+        }
+        let read_u64 = |offset: usize| -> Option<u64> {
+            let bytes = data.get(offset..offset + 8)?;
+            Some(u64::from_le_bytes(bytes.try_into().ok()?))
+        };
+
+        let pool_mint = data.get(Self::POOL_MINT_OFFSET..Self::POOL_MINT_OFFSET + 32)?;
+
+        Some(Self {
+            pool_mint: Pubkey::try_from(pool_mint).ok()?,
+            total_lamports: read_u64(Self::TOTAL_LAMPORTS_OFFSET)?,
+            pool_token_supply: read_u64(Self::TOTAL_LAMPORTS_OFFSET + 8)?,
+            last_update_epoch: read_u64(Self::TOTAL_LAMPORTS_OFFSET + 16)?,
+        })
+    }
+}
 pub mod pyth_feed_ids {
     use super::get_feed_id_from_hex;
 
@@ -122,3 +242,69 @@ pub fn get_feed_id_from_hex(input: &str) -> [u8; 32] {
     }
     feed_id
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stake_pool_rate_from_account_data() {
+        let mut data = vec![0u8; 300];
+        assert_eq!(StakePoolRate::try_from_account_data(&data), None);
+
+        let pool_mint = Pubkey::new_unique();
+        data[0] = 1;
+        data[162..194].copy_from_slice(pool_mint.as_ref());
+        data[258..266].copy_from_slice(&1_100u64.to_le_bytes());
+        data[266..274].copy_from_slice(&1_000u64.to_le_bytes());
+        data[274..282].copy_from_slice(&42u64.to_le_bytes());
+        assert_eq!(
+            StakePoolRate::try_from_account_data(&data),
+            Some(StakePoolRate {
+                pool_mint,
+                total_lamports: 1_100,
+                pool_token_supply: 1_000,
+                last_update_epoch: 42,
+            })
+        );
+
+        assert_eq!(StakePoolRate::try_from_account_data(&data[..280]), None);
+    }
+
+    #[test]
+    fn sol_quote() {
+        assert_eq!(SOL_USD_FEED_ID, pyth_feed_ids::sol_usd());
+        assert_eq!(
+            TokenPriceOracle::PythPull {
+                feed_id: pyth_feed_ids::sol_usd()
+            }
+            .sol_quote(),
+            Some(SolQuote::Sol)
+        );
+        assert_eq!(
+            TokenPriceOracle::PythPull {
+                feed_id: pyth_feed_ids::usdc_usd()
+            }
+            .sol_quote(),
+            None
+        );
+        assert_eq!(
+            TokenPriceOracle::PythPullRedemption {
+                feed_id: pyth_feed_ids::ssol_sol_rr(),
+                quote_feed_id: pyth_feed_ids::sol_usd(),
+            }
+            .sol_quote(),
+            Some(SolQuote::RedemptionRate {
+                feed_id: pyth_feed_ids::ssol_sol_rr()
+            })
+        );
+        assert_eq!(
+            TokenPriceOracle::PythPullRedemption {
+                feed_id: pyth_feed_ids::ssol_sol_rr(),
+                quote_feed_id: pyth_feed_ids::usdc_usd(),
+            }
+            .sol_quote(),
+            None
+        );
+    }
+}
@@ -29,6 +29,8 @@ pub struct MarginAccountValuation {
     pub available_collateral: f64,
     pub available_setup_collateral: f64,
     pub assets: f64,
+    /// The weighted collateral that is not counted due to concentration limits
+    pub concentration_excess: f64,
     pub total_positions: u32,
     pub unvalued_positions: u32,
 }
@@ -92,40 +94,77 @@ impl MarginAccountValuation {
             available_collateral: 0.0,
             required_setup_collateral: 0.0,
             available_setup_collateral: 0.0,
+            concentration_excess: 0.0,
             total_positions: updated.len() as u32,
             unvalued_positions: 0,
         };
+        // The size haircut applies to the value of each token, which may be held in several
+        // positions
+        let mut token_values: HashMap<&str, f64> = HashMap::new();
         for position in updated.values() {
+            if let (PositionKind::Deposit | PositionKind::AdapterCollateral, Some(price)) =
+                (position.kind(), prices.get(&position.token))
+            {
+                *token_values.entry(position.token.as_str()).or_default() +=
+                    position.value(price.price);
+            }
+        }
+
+        // Weighted collateral counted towards concentration limits, per group or position,
+        // with the strictest max share of its positions
+        let mut concentrated: HashMap<(u8, &str), (f64, f64)> = HashMap::new();
+        for position in updated.values() {
+            // NonCollateral positions count for nothing and need no price
+            if position.kind() == PositionKind::NonCollateral {
+                continue;
+            }
             let price = prices.get(&position.token);
             if let Some(price) = price {
-                let value =
-                    position.balance as f64 * 10.0_f64.powi(position.exponent) * price.price;
-                match &position.position_kind {
-                    // 0 = NoValue
-                    // 1 = Deposit
-                    // 3 = AdapterCollateral
-                    1 => {
+                let value = position.value(price.price);
+                match position.kind() {
+                    PositionKind::Deposit | PositionKind::AdapterCollateral => {
+                        let weighted =
+                            position.weighted_value(value, token_values[position.token.as_str()]);
                         valuation.assets += value;
-                        valuation.weighted_collateral += value * position.value_modifier;
+                        valuation.weighted_collateral += weighted;
+                        if position.max_collateral_share > 0.0 || position.concentration_group > 0 {
+                            let key = match position.concentration_group {
+                                0 => (0, position.address.as_str()),
+                                group => (group, ""),
+                            };
+                            let (collateral, max_share) =
+                                concentrated.entry(key).or_insert((0.0, 0.0));
+                            *collateral += weighted;
+                            if position.max_collateral_share > 0.0
+                                && (*max_share == 0.0 || position.max_collateral_share < *max_share)
+                            {
+                                *max_share = position.max_collateral_share;
+                            }
+                        }
                     }
-                    3 => {
-                        valuation.assets += value;
-                        valuation.weighted_collateral += value * position.value_modifier;
-                    }
-                    // 2 = Claim
-                    2 => {
+                    PositionKind::Claim => {
                         valuation.liabilities += value;
                         valuation.required_collateral += value / position.value_modifier;
                         valuation.required_setup_collateral += value
                             / (position.value_modifier * MARGIN_ACCOUNT_SETUP_LEVERAGE_FRACTION);
                     }
-                    _ => {}
+                    PositionKind::NoValue | PositionKind::NonCollateral => {}
                 }
             } else {
                 valuation.unvalued_positions += 1;
             }
         }
 
+        // A token or group only counts up to its max share of the weighted collateral
+        for (collateral, max_share) in concentrated.into_values() {
+            if max_share == 0.0 {
+                continue;
+            }
+            valuation.concentration_excess +=
+                (collateral - valuation.weighted_collateral * max_share).max(0.0);
+        }
+        valuation.weighted_collateral -= valuation.concentration_excess;
+
         valuation.effective_collateral = valuation.weighted_collateral - valuation.liabilities;
         valuation.available_collateral =
             valuation.weighted_collateral - valuation.liabilities - valuation.required_collateral;
@@ -143,6 +182,29 @@ pub struct WOraclePrice {
     pub price: f64,
 }
 
+/// The kind of a position, numbered like the token kinds of the margin program
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum PositionKind {
+    NoValue = 0,
+    Deposit = 1,
+    Claim = 2,
+    AdapterCollateral = 3,
+    NonCollateral = 4,
+}
+
+impl From<u8> for PositionKind {
+    fn from(kind: u8) -> Self {
+        match kind {
+            1 => Self::Deposit,
+            2 => Self::Claim,
+            3 => Self::AdapterCollateral,
+            4 => Self::NonCollateral,
+            _ => Self::NoValue,
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct WMarginPosition {
     pub address: String,
@@ -152,6 +214,15 @@ pub struct WMarginPosition {
     pub exponent: i32,
     pub position_kind: u8,
     pub value_modifier: f64,
+    /// The max share of weighted collateral counted from the position, or 0 for no limit
+    #[serde(default)]
+    pub max_collateral_share: f64,
+    /// The collateral value above which the weight of the position decays, or 0 for none
+    #[serde(default)]
+    pub size_haircut_threshold: f64,
+    /// The concentration group whose positions share a concentration limit, or 0 for none
+    #[serde(default)]
+    pub concentration_group: u8,
 }
 
 impl WMarginPosition {
@@ -170,8 +241,51 @@ impl WMarginPosition {
             exponent,
             position_kind,
             value_modifier,
+            max_collateral_share: 0.0,
+            size_haircut_threshold: 0.0,
+            concentration_group: 0,
         }
     }
+
+    /// The kind of the position
+    pub fn kind(&self) -> PositionKind {
+        self.position_kind.into()
+    }
+
+    /// Apply a size haircut, which decays the weight of the position above a value
+    pub fn with_size_haircut_threshold(mut self, size_haircut_threshold: f64) -> Self {
+        self.size_haircut_threshold = size_haircut_threshold;
+        self
+    }
+
+    /// The value of the position at a price
+    fn value(&self, price: f64) -> f64 {
+        self.balance as f64 * 10.0_f64.powi(self.exponent) * price
+    }
+
+    /// The value of the position weighted by its value modifier, with the weight decaying
+    /// by `sqrt(threshold / token_value)` above the size haircut threshold, where
+    /// `token_value` is the value of all positions in the token
+    fn weighted_value(&self, value: f64, token_value: f64) -> f64 {
+        let weighted = value * self.value_modifier;
+        if self.size_haircut_threshold > 0.0 && token_value > self.size_haircut_threshold {
+            weighted * (self.size_haircut_threshold / token_value).sqrt()
+        } else {
+            weighted
+        }
+    }
+
+    /// Limit the share of weighted collateral counted from this position
+    pub fn with_max_collateral_share(mut self, max_collateral_share: f64) -> Self {
+        self.max_collateral_share = max_collateral_share;
+        self
+    }
+
+    /// Count the position towards the concentration limit of a group
+    pub fn with_concentration_group(mut self, concentration_group: u8) -> Self {
+        self.concentration_group = concentration_group;
+        self
+    }
 }
 
 #[cfg(test)]
@@ -261,4 +375,126 @@ mod tests {
         assert_eq!(valuation.total_positions, 3);
         assert!(valuation.health_level() > 0.05);
     }
+
+    #[test]
+    fn test_concentration_limit() {
+        let positions = HashMap::from_iter([
+            (
+                "USDC-deposit".to_string(),
+                WMarginPosition::new(
+                    "USDC-deposit".to_string(),
+                    "USDC".to_string(),
+                    10_000_000,
+                    -6,
+                    1,
+                    1.0,
+                ),
+            ),
+            (
+                "BONK-deposit".to_string(),
+                WMarginPosition::new(
+                    "BONK-deposit".to_string(),
+                    "BONK".to_string(),
+                    30_000_000,
+                    -6,
+                    1,
+                    1.0,
+                )
+                .with_max_collateral_share(0.25),
+            ),
+        ]);
+        let prices = HashMap::from_iter([
+            ("USDC".to_string(), WOraclePrice { price: 1.0 }),
+            ("BONK".to_string(), WOraclePrice { price: 1.0 }),
+        ]);
+        let valuation = MarginAccountValuation::value(positions, vec![], &prices);
+
+        // BONK only counts up to a quarter of the weighted collateral of 40
+        assert_eq!(valuation.assets, 40.0);
+        assert_eq!(valuation.concentration_excess, 20.0);
+        assert_eq!(valuation.weighted_collateral, 20.0);
+        assert_eq!(valuation.available_collateral, 20.0);
+    }
+
+    #[test]
+    fn test_concentration_group() {
+        let position = |name: &str, token: &str, balance| {
+            WMarginPosition::new(name.to_string(), token.to_string(), balance, -6, 1, 1.0)
+        };
+        let grouped = |name: &str| {
+            position(name, "BONK", 10_000_000)
+                .with_max_collateral_share(0.25)
+                .with_concentration_group(1)
+        };
+        let positions = HashMap::from_iter([
+            (
+                "USDC-deposit".to_string(),
+                position("USDC-deposit", "USDC", 20_000_000),
+            ),
+            ("BONK-deposit".to_string(), grouped("BONK-deposit")),
+            (
+                "BONK-pool-deposit".to_string(),
+                grouped("BONK-pool-deposit"),
+            ),
+        ]);
+        let prices = HashMap::from_iter([
+            ("USDC".to_string(), WOraclePrice { price: 1.0 }),
+            ("BONK".to_string(), WOraclePrice { price: 1.0 }),
+        ]);
+        let valuation = MarginAccountValuation::value(positions, vec![], &prices);
+
+        // Both BONK positions together count up to a quarter of the weighted collateral of 40
+        assert_eq!(valuation.concentration_excess, 10.0);
+        assert_eq!(valuation.weighted_collateral, 30.0);
+    }
+
+    #[test]
+    fn test_size_haircut() {
+        let position = WMarginPosition::new(
+            "SMALLCAP-deposit".to_string(),
+            "SMALLCAP".to_string(),
+            4_000_000_000_000,
+            -6,
+            1,
+            0.5,
+        );
+        let prices = HashMap::from_iter([("SMALLCAP".to_string(), WOraclePrice { price: 1.0 })]);
+
+        let valuation = MarginAccountValuation::value(
+            HashMap::from_iter([(position.address.clone(), position.clone())]),
+            vec![],
+            &prices,
+        );
+        assert_eq!(valuation.weighted_collateral, 2_000_000.0);
+
+        // A position 4x the threshold has half the weight
+        let position = position.with_size_haircut_threshold(1_000_000.0);
+        let valuation = MarginAccountValuation::value(
+            HashMap::from_iter([(position.address.clone(), position.clone())]),
+            vec![],
+            &prices,
+        );
+        assert_eq!(valuation.assets, 4_000_000.0);
+        assert_eq!(valuation.weighted_collateral, 1_000_000.0);
+
+        // Splitting the token across positions does not avoid the haircut
+        let half = WMarginPosition {
+            balance: 2_000_000_000_000,
+            ..position
+        };
+        let other_half = WMarginPosition {
+            address: "SMALLCAP-deposit-2".to_string(),
+            ..half.clone()
+        };
+        let valuation = MarginAccountValuation::value(
+            HashMap::from_iter([
+                (half.address.clone(), half),
+                (other_half.address.clone(), other_half),
+            ]),
+            vec![],
+            &prices,
+        );
+        assert_eq!(valuation.assets, 4_000_000.0);
+        assert_eq!(valuation.weighted_collateral, 1_000_000.0);
+    }
 }
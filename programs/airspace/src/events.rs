@@ -45,6 +45,18 @@ pub struct AirspaceAuthoritySet {
     pub authority: Pubkey,
 }
 
+#[event]
+pub struct AirspaceLiquidationModeSet {
+    pub airspace: Pubkey,
+    pub is_liquidation_permissionless: bool,
+}
+
+#[event]
+pub struct AirspaceLiquidationFeeShareSet {
+    pub airspace: Pubkey,
+    pub liquidation_fee_protocol_share_bps: u16,
+}
+
 #[event]
 pub struct GovernorAuthorityTransferRequest {
     pub governor_id: Pubkey,
@@ -0,0 +1,50 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+//
+// Copyright (C) 2024 A1 XYZ, INC.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use anchor_lang::prelude::*;
+
+use crate::{events::AirspaceLiquidationFeeShareSet, state::Airspace, AirspaceErrorCode};
+
+#[derive(Accounts)]
+pub struct AirspaceSetLiquidationFeeShare<'info> {
+    /// The airspace authority
+    authority: Signer<'info>,
+
+    /// The airspace to be modified
+    #[account(mut, has_one = authority)]
+    airspace: Account<'info, Airspace>,
+}
+
+pub fn airspace_set_liquidation_fee_share_handler(
+    ctx: Context<AirspaceSetLiquidationFeeShare>,
+    protocol_share_bps: u16,
+) -> Result<()> {
+    require!(
+        protocol_share_bps <= 10_000,
+        AirspaceErrorCode::InvalidShare
+    );
+
+    let airspace = &mut ctx.accounts.airspace;
+    airspace.liquidation_fee_protocol_share_bps = protocol_share_bps;
+
+    emit!(AirspaceLiquidationFeeShareSet {
+        airspace: airspace.key(),
+        liquidation_fee_protocol_share_bps: protocol_share_bps,
+    });
+
+    Ok(())
+}
@@ -0,0 +1,45 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+//
+// Copyright (C) 2024 A1 XYZ, INC.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use anchor_lang::prelude::*;
+
+use crate::{events::AirspaceLiquidationModeSet, state::Airspace};
+
+#[derive(Accounts)]
+pub struct AirspaceSetLiquidationMode<'info> {
+    /// The airspace authority
+    authority: Signer<'info>,
+
+    /// The airspace to be modified
+    #[account(mut, has_one = authority)]
+    airspace: Account<'info, Airspace>,
+}
+
+pub fn airspace_set_liquidation_mode_handler(
+    ctx: Context<AirspaceSetLiquidationMode>,
+    is_permissionless: bool,
+) -> Result<()> {
+    let airspace = &mut ctx.accounts.airspace;
+    airspace.is_liquidation_permissionless = is_permissionless;
+
+    emit!(AirspaceLiquidationModeSet {
+        airspace: airspace.key(),
+        is_liquidation_permissionless: is_permissionless,
+    });
+
+    Ok(())
+}
@@ -3,6 +3,8 @@ mod set_governor;
 
 mod airspace_create;
 mod airspace_set_authority;
+mod airspace_set_liquidation_fee_share;
+mod airspace_set_liquidation_mode;
 
 mod airspace_permit_issuer_create;
 mod airspace_permit_issuer_revoke;
@@ -15,6 +17,8 @@ pub use set_governor::*;
 
 pub use airspace_create::*;
 pub use airspace_set_authority::*;
+pub use airspace_set_liquidation_fee_share::*;
+pub use airspace_set_liquidation_mode::*;
 
 pub use airspace_permit_issuer_create::*;
 pub use airspace_permit_issuer_revoke::*;
@@ -121,6 +121,35 @@ pub mod airspace {
         instructions::airspace_authority_finalize(ctx)
     }
 
+    /// Set whether any signer may liquidate margin accounts within an airspace
+    ///
+    /// By default, only liquidators given a permit by the airspace authority may liquidate.
+    ///
+    /// # Parameters
+    ///
+    /// * `is_permissionless` - If true, then any signer may liquidate unhealthy accounts.
+    pub fn airspace_set_liquidation_mode(
+        ctx: Context<AirspaceSetLiquidationMode>,
+        is_permissionless: bool,
+    ) -> Result<()> {
+        instructions::airspace_set_liquidation_mode_handler(ctx, is_permissionless)
+    }
+
+    /// Set the share of liquidation fees within an airspace that goes to its treasury
+    ///
+    /// The rest of a liquidation fee goes to the liquidator. By default, the liquidator
+    /// receives the whole fee.
+    ///
+    /// # Parameters
+    ///
+    /// * `protocol_share_bps` - The share of the fee for the treasury, in basis points.
+    pub fn airspace_set_liquidation_fee_share(
+        ctx: Context<AirspaceSetLiquidationFeeShare>,
+        protocol_share_bps: u16,
+    ) -> Result<()> {
+        instructions::airspace_set_liquidation_fee_share_handler(ctx, protocol_share_bps)
+    }
+
     /// Create a new license for an address to serve as an airspace regulator.
     ///
     /// Addresses with regulator licenses in an airspace are allowed to issue new permits
@@ -163,4 +192,8 @@ pub enum AirspaceErrorCode {
     /// 707000 - No permissions to do an action
     #[msg("The signer does not have the required permissions to do this")]
     PermissionDenied = 701_000,
+
+    /// 701001 - A share in basis points exceeds 100%
+    #[msg("The share cannot exceed 10000 basis points")]
+    InvalidShare,
 }
@@ -43,6 +43,14 @@ pub struct Airspace {
     /// permission from an authorized regulator. If false, any user may request a permit without
     /// the need for any authorization.
     pub is_restricted: bool,
+
+    /// If true, any signer may liquidate unhealthy margin accounts within the airspace. If false,
+    /// only liquidators with a permit from the airspace authority may liquidate.
+    pub is_liquidation_permissionless: bool,
+
+    /// The share of liquidation fees, in basis points, that is routed to the liquidation
+    /// fee treasury of the airspace instead of the liquidator.
+    pub liquidation_fee_protocol_share_bps: u16,
 }
 
 declare_account_size!(Airspace, 304);
@@ -0,0 +1,52 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+//
+// Copyright (C) 2024 A1 XYZ, INC.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use anchor_lang::prelude::*;
+
+#[event]
+pub struct BackstopDeposited {
+    pub pool: Pubkey,
+    pub owner: Pubkey,
+    pub amount: u64,
+    pub total_deposits: u64,
+}
+
+#[event]
+pub struct BackstopWithdrawn {
+    pub pool: Pubkey,
+    pub owner: Pubkey,
+    pub amount: u64,
+    pub total_deposits: u64,
+}
+
+#[event]
+pub struct BackstopGainsClaimed {
+    pub pool: Pubkey,
+    pub owner: Pubkey,
+    pub collateral_mint: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+pub struct BackstopLiquidation {
+    pub pool: Pubkey,
+    pub margin_account: Pubkey,
+    pub repaid_tokens: u64,
+    pub seize_mint: Pubkey,
+    pub seized_tokens: u64,
+    pub total_deposits: u64,
+}
@@ -0,0 +1,103 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+//
+// Copyright (C) 2024 A1 XYZ, INC.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
+
+use glow_margin::{program::Margin, MarginAccount};
+
+use crate::{
+    events::BackstopGainsClaimed,
+    seeds,
+    state::{BackstopDeposit, BackstopPool},
+};
+
+#[derive(Accounts)]
+pub struct ClaimGains<'info> {
+    /// The owner of the deposit
+    pub depositor: Signer<'info>,
+
+    /// The pool the deposit is in
+    #[account(has_one = margin_account)]
+    pub pool: Box<Account<'info, BackstopPool>>,
+
+    /// The margin account of the pool
+    #[account(mut)]
+    pub margin_account: AccountLoader<'info, MarginAccount>,
+
+    /// The deposit of the depositor in the pool
+    #[account(mut,
+              seeds = [
+                seeds::BACKSTOP_DEPOSIT,
+                pool.key().as_ref(),
+                depositor.key().as_ref()
+              ],
+              bump,
+    )]
+    pub deposit: Box<Account<'info, BackstopDeposit>>,
+
+    /// The mint of the collateral being claimed
+    pub collateral_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    /// The collateral position of the pool's margin account
+    #[account(mut)]
+    pub collateral_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// The token account to withdraw the collateral to
+    #[account(mut,
+              token::mint = collateral_mint,
+              token::token_program = token_program,
+    )]
+    pub destination: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub margin_program: Program<'info, Margin>,
+}
+
+pub fn claim_gains_handler(ctx: Context<ClaimGains>) -> Result<()> {
+    let pool = &ctx.accounts.pool;
+    let index = pool.collateral_index(&ctx.accounts.collateral_mint.key())?;
+    let amount = pool.claim_gains(&mut ctx.accounts.deposit, index)?;
+
+    if amount > 0 {
+        glow_margin::cpi::transfer_deposit(
+            CpiContext::new_with_signer(
+                ctx.accounts.margin_program.to_account_info(),
+                glow_margin::cpi::accounts::TransferDeposit {
+                    owner: pool.to_account_info(),
+                    margin_account: ctx.accounts.margin_account.to_account_info(),
+                    source_owner: ctx.accounts.margin_account.to_account_info(),
+                    source: ctx.accounts.collateral_account.to_account_info(),
+                    destination: ctx.accounts.destination.to_account_info(),
+                    mint: ctx.accounts.collateral_mint.to_account_info(),
+                    token_program: ctx.accounts.token_program.to_account_info(),
+                },
+                &[&pool.signer_seeds()],
+            ),
+            amount,
+        )?;
+    }
+
+    emit!(BackstopGainsClaimed {
+        pool: pool.key(),
+        owner: ctx.accounts.depositor.key(),
+        collateral_mint: ctx.accounts.collateral_mint.key(),
+        amount,
+    });
+
+    Ok(())
+}
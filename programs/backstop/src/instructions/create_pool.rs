@@ -0,0 +1,189 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+//
+// Copyright (C) 2024 A1 XYZ, INC.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token_interface::{Mint, TokenAccount, TokenInterface},
+};
+
+use glow_airspace::{program::Airspace as AirspaceProgram, state::Airspace};
+use glow_margin::{program::Margin, TokenConfig, TokenKind};
+use glow_program_common::Number;
+
+use crate::{seeds, state::BackstopPool, ErrorCode, POOL_MARGIN_ACCOUNT_SEED};
+
+#[derive(Accounts)]
+pub struct CreatePool<'info> {
+    /// The authority to create pools, which must be the airspace authority
+    pub authority: Signer<'info>,
+
+    /// The airspace that the pool liquidates accounts in
+    #[account(
+      constraint = airspace.authority == authority.key(),
+    )]
+    pub airspace: Box<Account<'info, Airspace>>,
+
+    /// The payer of rent for new accounts
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    /// The mint of the token deposited in the pool
+    pub token_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    /// The margin token config of the pool token, which must be collateral
+    /// held directly by margin accounts
+    #[account(
+      constraint = token_config.mint == token_mint.key() @ ErrorCode::InvalidTokenConfig,
+      constraint = token_config.airspace == airspace.key() @ ErrorCode::InvalidTokenConfig,
+      constraint = token_config.token_kind == TokenKind::Collateral @ ErrorCode::InvalidTokenConfig,
+      constraint = token_config.oracle().is_some() @ ErrorCode::InvalidTokenConfig,
+    )]
+    pub token_config: Box<Account<'info, TokenConfig>>,
+
+    /// The pool to be created
+    #[account(init,
+              seeds = [
+                seeds::BACKSTOP_POOL,
+                airspace.key().as_ref(),
+                token_mint.key().as_ref()
+              ],
+              bump,
+              space = BackstopPool::SPACE,
+              payer = payer
+    )]
+    pub pool: Box<Account<'info, BackstopPool>>,
+
+    /// The airspace permit of the pool, created by the airspace program
+    ///
+    /// CHECK: The account is created and checked by the airspace program
+    #[account(mut)]
+    pub airspace_permit: UncheckedAccount<'info>,
+
+    /// The permit issuer identity of the airspace authority
+    ///
+    /// CHECK: The account is checked by the airspace program
+    pub issuer_id: UncheckedAccount<'info>,
+
+    /// The margin account of the pool, created by the margin program
+    ///
+    /// CHECK: The account is created and checked by the margin program
+    #[account(mut)]
+    pub margin_account: UncheckedAccount<'info>,
+
+    /// The liquidator permit of the pool, created by the margin program
+    ///
+    /// CHECK: The account is created and checked by the margin program
+    #[account(mut)]
+    pub liquidator_permit: UncheckedAccount<'info>,
+
+    /// The position of the pool's margin account holding the deposits
+    #[account(init,
+              payer = payer,
+              associated_token::mint = token_mint,
+              associated_token::authority = margin_account,
+              associated_token::token_program = token_program,
+    )]
+    pub deposit_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub airspace_program: Program<'info, AirspaceProgram>,
+    pub margin_program: Program<'info, Margin>,
+    pub rent: Sysvar<'info, Rent>,
+    pub system_program: Program<'info, System>,
+}
+
+pub fn create_pool_handler(ctx: Context<CreatePool>) -> Result<()> {
+    let pool = &mut ctx.accounts.pool;
+
+    pool.bump = [ctx.bumps.pool];
+    pool.airspace = ctx.accounts.airspace.key();
+    pool.token_mint = ctx.accounts.token_mint.key();
+    pool.margin_account = ctx.accounts.margin_account.key();
+    pool.deposit_account = ctx.accounts.deposit_account.key();
+    pool.total_deposits = 0;
+    pool.product = Number::ONE.into_bits();
+
+    let pool_info = pool.to_account_info();
+    let pool_seeds = pool.signer_seeds();
+
+    glow_airspace::cpi::airspace_permit_create(
+        CpiContext::new(
+            ctx.accounts.airspace_program.to_account_info(),
+            glow_airspace::cpi::accounts::AirspacePermitCreate {
+                payer: ctx.accounts.payer.to_account_info(),
+                authority: ctx.accounts.authority.to_account_info(),
+                airspace: ctx.accounts.airspace.to_account_info(),
+                permit: ctx.accounts.airspace_permit.to_account_info(),
+                issuer_id: ctx.accounts.issuer_id.to_account_info(),
+                system_program: ctx.accounts.system_program.to_account_info(),
+            },
+        ),
+        pool_info.key(),
+    )?;
+
+    glow_margin::cpi::create_account(
+        CpiContext::new_with_signer(
+            ctx.accounts.margin_program.to_account_info(),
+            glow_margin::cpi::accounts::CreateAccount {
+                owner: pool_info.clone(),
+                permit: ctx.accounts.airspace_permit.to_account_info(),
+                payer: ctx.accounts.payer.to_account_info(),
+                margin_account: ctx.accounts.margin_account.to_account_info(),
+                system_program: ctx.accounts.system_program.to_account_info(),
+            },
+            &[&pool_seeds],
+        ),
+        POOL_MARGIN_ACCOUNT_SEED,
+        0,
+    )?;
+
+    glow_margin::cpi::configure_liquidator(
+        CpiContext::new(
+            ctx.accounts.margin_program.to_account_info(),
+            glow_margin::cpi::accounts::ConfigurePermit {
+                authority: ctx.accounts.authority.to_account_info(),
+                airspace: ctx.accounts.airspace.to_account_info(),
+                payer: ctx.accounts.payer.to_account_info(),
+                owner: pool_info.clone(),
+                permit: ctx.accounts.liquidator_permit.to_account_info(),
+                system_program: ctx.accounts.system_program.to_account_info(),
+            },
+        ),
+        true,
+    )?;
+
+    glow_margin::cpi::create_deposit_position(CpiContext::new_with_signer(
+        ctx.accounts.margin_program.to_account_info(),
+        glow_margin::cpi::accounts::CreateDepositPosition {
+            authority: pool_info,
+            payer: ctx.accounts.payer.to_account_info(),
+            margin_account: ctx.accounts.margin_account.to_account_info(),
+            mint: ctx.accounts.token_mint.to_account_info(),
+            config: ctx.accounts.token_config.to_account_info(),
+            token_account: ctx.accounts.deposit_account.to_account_info(),
+            associated_token_program: ctx.accounts.associated_token_program.to_account_info(),
+            mint_token_program: ctx.accounts.token_program.to_account_info(),
+            rent: ctx.accounts.rent.to_account_info(),
+            system_program: ctx.accounts.system_program.to_account_info(),
+        },
+        &[&pool_seeds],
+    ))?;
+
+    Ok(())
+}
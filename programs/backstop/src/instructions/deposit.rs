@@ -0,0 +1,121 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+//
+// Copyright (C) 2024 A1 XYZ, INC.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{self, Mint, TokenAccount, TokenInterface, TransferChecked};
+
+use glow_margin::{program::Margin, MarginAccount};
+
+use crate::{
+    events::BackstopDeposited,
+    seeds,
+    state::{BackstopDeposit, BackstopPool},
+};
+
+#[derive(Accounts)]
+pub struct Deposit<'info> {
+    /// The owner of the deposit
+    pub depositor: Signer<'info>,
+
+    /// The payer of rent for new accounts
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    /// The pool to deposit into
+    #[account(mut,
+              has_one = token_mint,
+              has_one = margin_account,
+              has_one = deposit_account,
+    )]
+    pub pool: Box<Account<'info, BackstopPool>>,
+
+    /// The margin account of the pool
+    #[account(mut)]
+    pub margin_account: AccountLoader<'info, MarginAccount>,
+
+    /// The deposit of the depositor in the pool
+    #[account(init_if_needed,
+              seeds = [
+                seeds::BACKSTOP_DEPOSIT,
+                pool.key().as_ref(),
+                depositor.key().as_ref()
+              ],
+              bump,
+              space = BackstopDeposit::SPACE,
+              payer = payer
+    )]
+    pub deposit: Box<Account<'info, BackstopDeposit>>,
+
+    /// The mint of the pool token
+    pub token_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    /// The token account to deposit tokens from
+    #[account(mut,
+              token::mint = token_mint,
+              token::authority = depositor,
+              token::token_program = token_program,
+    )]
+    pub source: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// The position of the pool's margin account holding the deposits
+    #[account(mut)]
+    pub deposit_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub margin_program: Program<'info, Margin>,
+    pub system_program: Program<'info, System>,
+}
+
+pub fn deposit_handler(ctx: Context<Deposit>, amount: u64) -> Result<()> {
+    let pool = &mut ctx.accounts.pool;
+    let deposit = &mut ctx.accounts.deposit;
+
+    deposit.owner = ctx.accounts.depositor.key();
+    deposit.pool = pool.key();
+    pool.deposit(deposit, amount)?;
+
+    token_interface::transfer_checked(
+        CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            TransferChecked {
+                from: ctx.accounts.source.to_account_info(),
+                to: ctx.accounts.deposit_account.to_account_info(),
+                authority: ctx.accounts.depositor.to_account_info(),
+                mint: ctx.accounts.token_mint.to_account_info(),
+            },
+        ),
+        amount,
+        ctx.accounts.token_mint.decimals,
+    )?;
+
+    glow_margin::cpi::update_position_balance(CpiContext::new(
+        ctx.accounts.margin_program.to_account_info(),
+        glow_margin::cpi::accounts::UpdatePositionBalance {
+            margin_account: ctx.accounts.margin_account.to_account_info(),
+            token_account: ctx.accounts.deposit_account.to_account_info(),
+        },
+    ))?;
+
+    emit!(BackstopDeposited {
+        pool: pool.key(),
+        owner: deposit.owner,
+        amount,
+        total_deposits: pool.total_deposits,
+    });
+
+    Ok(())
+}
@@ -0,0 +1,283 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+//
+// Copyright (C) 2024 A1 XYZ, INC.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use anchor_lang::{prelude::*, InstructionData};
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
+
+use glow_margin::{program::Margin, IxData};
+use glow_margin_pool::{program::MarginPool as MarginPoolProgram, MarginPool};
+use glow_program_common::token_change::ChangeKind;
+
+use crate::{events::BackstopLiquidation, state::BackstopPool, ErrorCode};
+
+#[derive(Accounts)]
+pub struct Liquidate<'info> {
+    /// The payer of rent for the liquidation state, which is returned at the end
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    /// The pool that liquidates the account
+    #[account(mut,
+              constraint = pool.margin_account == pool_margin_account.key(),
+              constraint = pool.deposit_account == repay_source.key(),
+              constraint = pool.token_mint == repay_mint.key(),
+    )]
+    pub pool: Box<Account<'info, BackstopPool>>,
+
+    /// The margin account of the pool
+    ///
+    /// CHECK: The account is checked by the margin program
+    #[account(mut)]
+    pub pool_margin_account: UncheckedAccount<'info>,
+
+    /// The liquidator permit of the pool
+    ///
+    /// CHECK: The account is checked by the margin program
+    pub liquidator_permit: UncheckedAccount<'info>,
+
+    /// The airspace of the pool
+    ///
+    /// CHECK: The account is checked by the margin program
+    #[account(address = pool.airspace)]
+    pub airspace: UncheckedAccount<'info>,
+
+    /// The margin account being liquidated
+    ///
+    /// CHECK: The account is checked by the margin program
+    #[account(mut)]
+    pub margin_account: UncheckedAccount<'info>,
+
+    /// The liquidation state, which is created and closed during the instruction
+    ///
+    /// CHECK: The account is created and checked by the margin program
+    #[account(mut)]
+    pub liquidation: UncheckedAccount<'info>,
+
+    /// The mint of the pool token
+    pub repay_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    /// The position of the pool's margin account holding the deposits
+    #[account(mut)]
+    pub repay_source: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// The deposit of the liquidated account that the debt is repaid from
+    #[account(mut)]
+    pub repay_destination: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    pub repay_token_program: Interface<'info, TokenInterface>,
+
+    /// The margin pool that the liquidated account borrowed the pool token from
+    #[account(mut,
+              has_one = loan_note_mint,
+              has_one = vault,
+              constraint = margin_pool.token_mint == repay_mint.key(),
+              constraint = margin_pool.airspace == pool.airspace,
+    )]
+    pub margin_pool: Box<Account<'info, MarginPool>>,
+
+    /// The adapter config of the margin pool program
+    ///
+    /// CHECK: The account is checked by the margin program
+    pub margin_pool_adapter_config: UncheckedAccount<'info>,
+
+    /// The mint of the loan notes of the margin pool
+    ///
+    /// CHECK: The account is checked by the margin pool
+    #[account(mut)]
+    pub loan_note_mint: UncheckedAccount<'info>,
+
+    /// The vault of the margin pool
+    ///
+    /// CHECK: The account is checked by the margin pool
+    #[account(mut)]
+    pub vault: UncheckedAccount<'info>,
+
+    /// The loan of the liquidated account that is repaid
+    ///
+    /// CHECK: The address is derived from the liquidated account
+    #[account(mut,
+              seeds = [margin_account.key().as_ref(), loan_note_mint.key().as_ref()],
+              seeds::program = margin_pool_program.key(),
+              bump,
+    )]
+    pub loan_account: UncheckedAccount<'info>,
+
+    pub loan_token_program: Interface<'info, TokenInterface>,
+
+    /// The mint of the seized collateral
+    pub seize_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    /// The margin token config of the seized collateral
+    ///
+    /// CHECK: The account is checked by the margin program
+    pub seize_token_config: UncheckedAccount<'info>,
+
+    /// The collateral position of the liquidated account
+    ///
+    /// CHECK: The account is checked by the margin program
+    #[account(mut)]
+    pub seize_source: UncheckedAccount<'info>,
+
+    /// The collateral position of the pool's margin account
+    #[account(mut)]
+    pub seize_destination: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    pub seize_token_program: Interface<'info, TokenInterface>,
+    pub margin_pool_program: Program<'info, MarginPoolProgram>,
+    pub margin_program: Program<'info, Margin>,
+    pub system_program: Program<'info, System>,
+}
+
+pub fn liquidate_handler<'a, 'b, 'c: 'info, 'info>(
+    ctx: Context<'a, 'b, 'c, 'info, Liquidate<'info>>,
+    repay_amount: u64,
+) -> Result<()> {
+    let repay_source_before = ctx.accounts.repay_source.amount;
+    let repay_destination_before = ctx.accounts.repay_destination.amount;
+    let seize_destination_before = ctx.accounts.seize_destination.amount;
+
+    let pool_info = ctx.accounts.pool.to_account_info();
+    let lamports_before = pool_info.lamports();
+
+    // The pool only repays the loan of the liquidated account in the margin pool of its
+    // token, so the caller cannot direct the deposits anywhere else
+    let (repay_ix, mut repay_accounts) = margin_pool_repay(&ctx.accounts, repay_amount);
+    repay_accounts.extend_from_slice(ctx.remaining_accounts);
+
+    {
+        let pool_seeds = ctx.accounts.pool.signer_seeds();
+        let signer = [&pool_seeds[..]];
+
+        glow_margin::cpi::liquidate_begin(CpiContext::new_with_signer(
+            ctx.accounts.margin_program.to_account_info(),
+            glow_margin::cpi::accounts::LiquidateBegin {
+                margin_account: ctx.accounts.margin_account.to_account_info(),
+                payer: ctx.accounts.payer.to_account_info(),
+                liquidator: pool_info.clone(),
+                permit: Some(ctx.accounts.liquidator_permit.to_account_info()),
+                airspace: ctx.accounts.airspace.to_account_info(),
+                liquidation: ctx.accounts.liquidation.to_account_info(),
+                system_program: ctx.accounts.system_program.to_account_info(),
+            },
+            &signer,
+        ))?;
+
+        glow_margin::cpi::liquidator_seize(
+            CpiContext::new_with_signer(
+                ctx.accounts.margin_program.to_account_info(),
+                glow_margin::cpi::accounts::LiquidatorSeize {
+                    liquidator: pool_info.clone(),
+                    liquidation: ctx.accounts.liquidation.to_account_info(),
+                    margin_account: ctx.accounts.margin_account.to_account_info(),
+                    liquidator_account: ctx.accounts.pool_margin_account.to_account_info(),
+                    repay_mint: ctx.accounts.repay_mint.to_account_info(),
+                    repay_source: ctx.accounts.repay_source.to_account_info(),
+                    repay_destination: ctx.accounts.repay_destination.to_account_info(),
+                    repay_token_program: ctx.accounts.repay_token_program.to_account_info(),
+                    seize_mint: ctx.accounts.seize_mint.to_account_info(),
+                    seize_token_config: ctx.accounts.seize_token_config.to_account_info(),
+                    seize_source: ctx.accounts.seize_source.to_account_info(),
+                    seize_destination: ctx.accounts.seize_destination.to_account_info(),
+                    seize_token_program: ctx.accounts.seize_token_program.to_account_info(),
+                },
+                &signer,
+            )
+            .with_remaining_accounts(repay_accounts),
+            repay_amount,
+            vec![repay_ix],
+        )?;
+
+        glow_margin::cpi::liquidate_end(CpiContext::new_with_signer(
+            ctx.accounts.margin_program.to_account_info(),
+            glow_margin::cpi::accounts::LiquidateEnd {
+                authority: pool_info.clone(),
+                margin_account: ctx.accounts.margin_account.to_account_info(),
+                liquidation: ctx.accounts.liquidation.to_account_info(),
+            },
+            &signer,
+        ))?;
+    }
+
+    // The liquidation state was closed to the pool, so its rent is returned to the payer
+    let refund = pool_info.lamports().saturating_sub(lamports_before);
+    **pool_info.try_borrow_mut_lamports()? -= refund;
+    **ctx.accounts.payer.try_borrow_mut_lamports()? += refund;
+
+    ctx.accounts.repay_source.reload()?;
+    ctx.accounts.repay_destination.reload()?;
+    ctx.accounts.seize_destination.reload()?;
+
+    // Any tokens sent to the liquidated account that were not used to repay its debt
+    // would be lost by the depositors.
+    require!(
+        ctx.accounts.repay_destination.amount <= repay_destination_before,
+        ErrorCode::IncompleteRepayment
+    );
+
+    let repaid_tokens = repay_source_before - ctx.accounts.repay_source.amount;
+    let seized_tokens = ctx.accounts.seize_destination.amount - seize_destination_before;
+
+    let pool = &mut ctx.accounts.pool;
+    pool.record_liquidation(&ctx.accounts.seize_mint.key(), repaid_tokens, seized_tokens)?;
+
+    emit!(BackstopLiquidation {
+        pool: pool.key(),
+        margin_account: ctx.accounts.margin_account.key(),
+        repaid_tokens,
+        seize_mint: ctx.accounts.seize_mint.key(),
+        seized_tokens,
+        total_deposits: pool.total_deposits,
+    });
+
+    Ok(())
+}
+
+/// The margin pool instruction that repays the loan of the liquidated account from its
+/// deposit, with the accounts for the margin program to invoke it
+fn margin_pool_repay<'info>(
+    accounts: &Liquidate<'info>,
+    repay_amount: u64,
+) -> (IxData, Vec<AccountInfo<'info>>) {
+    let data = glow_margin_pool::instruction::Repay {
+        change_kind: ChangeKind::ShiftBy,
+        amount: repay_amount,
+    }
+    .data();
+    let repay_accounts = vec![
+        accounts.margin_pool.to_account_info(),
+        accounts.loan_note_mint.to_account_info(),
+        accounts.repay_mint.to_account_info(),
+        accounts.vault.to_account_info(),
+        accounts.loan_account.to_account_info(),
+        accounts.repay_destination.to_account_info(),
+        accounts.margin_account.to_account_info(),
+        accounts.repay_token_program.to_account_info(),
+        accounts.loan_token_program.to_account_info(),
+    ];
+    let ix = IxData {
+        num_accounts: repay_accounts.len() as u8,
+        data,
+    };
+
+    let mut invoke_accounts = vec![
+        accounts.margin_pool_program.to_account_info(),
+        accounts.margin_pool_adapter_config.to_account_info(),
+    ];
+    invoke_accounts.extend(repay_accounts);
+
+    (ix, invoke_accounts)
+}
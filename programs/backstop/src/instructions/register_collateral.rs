@@ -0,0 +1,136 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+//
+// Copyright (C) 2024 A1 XYZ, INC.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::{self, AssociatedToken},
+    token_interface::{Mint, TokenInterface},
+};
+
+use glow_airspace::state::Airspace;
+use glow_margin::{program::Margin, MarginAccount, TokenConfig, TokenKind};
+
+use crate::{state::BackstopPool, ErrorCode};
+
+#[derive(Accounts)]
+pub struct RegisterCollateral<'info> {
+    /// The authority to register collaterals, which must be the airspace authority
+    pub authority: Signer<'info>,
+
+    /// The airspace of the pool
+    #[account(
+      constraint = airspace.authority == authority.key(),
+    )]
+    pub airspace: Box<Account<'info, Airspace>>,
+
+    /// The payer of rent for new accounts
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    /// The pool receiving the collateral
+    #[account(mut,
+              has_one = airspace,
+              has_one = margin_account,
+              constraint = pool.token_mint != collateral_mint.key() @ ErrorCode::InvalidTokenConfig,
+    )]
+    pub pool: Box<Account<'info, BackstopPool>>,
+
+    /// The margin account of the pool
+    #[account(mut)]
+    pub margin_account: AccountLoader<'info, MarginAccount>,
+
+    /// The mint of the collateral
+    pub collateral_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    /// The margin token config of the collateral
+    #[account(
+      constraint = collateral_config.mint == collateral_mint.key() @ ErrorCode::InvalidTokenConfig,
+      constraint = collateral_config.airspace == airspace.key() @ ErrorCode::InvalidTokenConfig,
+      constraint = collateral_config.token_kind == TokenKind::Collateral @ ErrorCode::InvalidTokenConfig,
+    )]
+    pub collateral_config: Box<Account<'info, TokenConfig>>,
+
+    /// The collateral position of the pool's margin account
+    ///
+    /// CHECK: The account is created and checked by the token or margin program
+    #[account(mut)]
+    pub collateral_account: UncheckedAccount<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub margin_program: Program<'info, Margin>,
+    pub rent: Sysvar<'info, Rent>,
+    pub system_program: Program<'info, System>,
+}
+
+pub fn register_collateral_handler(ctx: Context<RegisterCollateral>) -> Result<()> {
+    let pool = &mut ctx.accounts.pool;
+    pool.register_collateral(ctx.accounts.collateral_mint.key())?;
+
+    let pool_info = pool.to_account_info();
+    let pool_seeds = pool.signer_seeds();
+
+    if ctx.accounts.collateral_config.adapter_program().is_some() {
+        // Tokens minted by adapters are held in a position PDA created by margin
+        glow_margin::cpi::register_position(CpiContext::new_with_signer(
+            ctx.accounts.margin_program.to_account_info(),
+            glow_margin::cpi::accounts::RegisterPosition {
+                authority: pool_info,
+                payer: ctx.accounts.payer.to_account_info(),
+                margin_account: ctx.accounts.margin_account.to_account_info(),
+                position_token_mint: ctx.accounts.collateral_mint.to_account_info(),
+                config: ctx.accounts.collateral_config.to_account_info(),
+                token_account: ctx.accounts.collateral_account.to_account_info(),
+                token_program: ctx.accounts.token_program.to_account_info(),
+                rent: ctx.accounts.rent.to_account_info(),
+                system_program: ctx.accounts.system_program.to_account_info(),
+            },
+            &[&pool_seeds],
+        ))?;
+    } else {
+        associated_token::create(CpiContext::new(
+            ctx.accounts.associated_token_program.to_account_info(),
+            associated_token::Create {
+                payer: ctx.accounts.payer.to_account_info(),
+                associated_token: ctx.accounts.collateral_account.to_account_info(),
+                authority: ctx.accounts.margin_account.to_account_info(),
+                mint: ctx.accounts.collateral_mint.to_account_info(),
+                system_program: ctx.accounts.system_program.to_account_info(),
+                token_program: ctx.accounts.token_program.to_account_info(),
+            },
+        ))?;
+
+        glow_margin::cpi::create_deposit_position(CpiContext::new_with_signer(
+            ctx.accounts.margin_program.to_account_info(),
+            glow_margin::cpi::accounts::CreateDepositPosition {
+                authority: pool_info,
+                payer: ctx.accounts.payer.to_account_info(),
+                margin_account: ctx.accounts.margin_account.to_account_info(),
+                mint: ctx.accounts.collateral_mint.to_account_info(),
+                config: ctx.accounts.collateral_config.to_account_info(),
+                token_account: ctx.accounts.collateral_account.to_account_info(),
+                associated_token_program: ctx.accounts.associated_token_program.to_account_info(),
+                mint_token_program: ctx.accounts.token_program.to_account_info(),
+                rent: ctx.accounts.rent.to_account_info(),
+                system_program: ctx.accounts.system_program.to_account_info(),
+            },
+            &[&pool_seeds],
+        ))?;
+    }
+
+    Ok(())
+}
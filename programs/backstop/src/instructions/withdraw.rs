@@ -0,0 +1,104 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+//
+// Copyright (C) 2024 A1 XYZ, INC.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
+
+use glow_margin::{program::Margin, MarginAccount};
+
+use crate::{
+    events::BackstopWithdrawn,
+    seeds,
+    state::{BackstopDeposit, BackstopPool},
+};
+
+#[derive(Accounts)]
+pub struct Withdraw<'info> {
+    /// The owner of the deposit
+    pub depositor: Signer<'info>,
+
+    /// The pool to withdraw from
+    #[account(mut,
+              has_one = token_mint,
+              has_one = margin_account,
+              has_one = deposit_account,
+    )]
+    pub pool: Box<Account<'info, BackstopPool>>,
+
+    /// The margin account of the pool
+    #[account(mut)]
+    pub margin_account: AccountLoader<'info, MarginAccount>,
+
+    /// The deposit of the depositor in the pool
+    #[account(mut,
+              seeds = [
+                seeds::BACKSTOP_DEPOSIT,
+                pool.key().as_ref(),
+                depositor.key().as_ref()
+              ],
+              bump,
+    )]
+    pub deposit: Box<Account<'info, BackstopDeposit>>,
+
+    /// The mint of the pool token
+    pub token_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    /// The position of the pool's margin account holding the deposits
+    #[account(mut)]
+    pub deposit_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// The token account to withdraw tokens to
+    #[account(mut,
+              token::mint = token_mint,
+              token::token_program = token_program,
+    )]
+    pub destination: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub margin_program: Program<'info, Margin>,
+}
+
+pub fn withdraw_handler(ctx: Context<Withdraw>, amount: u64) -> Result<()> {
+    let pool = &mut ctx.accounts.pool;
+    pool.withdraw(&mut ctx.accounts.deposit, amount)?;
+
+    glow_margin::cpi::transfer_deposit(
+        CpiContext::new_with_signer(
+            ctx.accounts.margin_program.to_account_info(),
+            glow_margin::cpi::accounts::TransferDeposit {
+                owner: pool.to_account_info(),
+                margin_account: ctx.accounts.margin_account.to_account_info(),
+                source_owner: ctx.accounts.margin_account.to_account_info(),
+                source: ctx.accounts.deposit_account.to_account_info(),
+                destination: ctx.accounts.destination.to_account_info(),
+                mint: ctx.accounts.token_mint.to_account_info(),
+                token_program: ctx.accounts.token_program.to_account_info(),
+            },
+            &[&pool.signer_seeds()],
+        ),
+        amount,
+    )?;
+
+    emit!(BackstopWithdrawn {
+        pool: pool.key(),
+        owner: ctx.accounts.depositor.key(),
+        amount,
+        total_deposits: pool.total_deposits,
+    });
+
+    Ok(())
+}
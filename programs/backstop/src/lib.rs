@@ -0,0 +1,228 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+//
+// Copyright (C) 2024 A1 XYZ, INC.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+// Allow this until fixed upstream
+#![allow(clippy::result_large_err)]
+
+use anchor_lang::prelude::*;
+
+pub mod events;
+mod instructions;
+pub mod state;
+use instructions::*;
+
+pub use state::{BackstopDeposit, BackstopPool, CollateralGain};
+
+declare_id!("Ec5i6wUpJboqQeeFTQucuckQ3scdLHFiSgsxYVcXqAp5");
+
+/// The maximum number of collateral tokens that a backstop pool can receive
+/// from liquidations.
+pub const MAX_BACKSTOP_COLLATERALS: usize = 8;
+
+/// The seed of the margin account owned by a backstop pool.
+pub const POOL_MARGIN_ACCOUNT_SEED: u16 = 0;
+
+pub mod seeds {
+    use super::constant;
+
+    #[constant]
+    pub const BACKSTOP_POOL: &[u8] = b"backstop-pool";
+
+    #[constant]
+    pub const BACKSTOP_DEPOSIT: &[u8] = b"backstop-deposit";
+}
+
+#[program]
+mod backstop {
+    use super::*;
+
+    /// Create a backstop pool for a token in an airspace.
+    ///
+    /// The pool owns a margin account in the airspace, which holds the deposits of the
+    /// pool and the collateral received from liquidations. This creates the airspace permit
+    /// and the margin account of the pool, registers the deposit position of the pool token,
+    /// and configures the pool as a liquidator in the airspace.
+    ///
+    /// # [Accounts](backstop::accounts::CreatePool)
+    ///
+    /// |     |     |     |
+    /// | --- | --- | --- |
+    /// | **Name** | **Type** | **Description** |
+    /// | `authority` | `Signer` | The airspace authority. |
+    /// | `airspace` | `read_only` | The airspace the pool liquidates accounts in. |
+    /// | `payer` | `Signer` | The payer of rent for new accounts. |
+    /// | `token_mint` | `read_only` | The mint of the token deposited in the pool. |
+    /// | `token_config` | `read_only` | The margin token config of the pool token. |
+    /// | `pool` | `writable` | The pool to be created. |
+    /// | `airspace_permit` | `writable` | The airspace permit of the pool to be created. |
+    /// | `issuer_id` | `read_only` | The permit issuer identity of the airspace authority. |
+    /// | `margin_account` | `writable` | The margin account of the pool to be created. |
+    /// | `liquidator_permit` | `writable` | The liquidator permit of the pool to be created. |
+    /// | `deposit_account` | `writable` | The deposit position of the pool to be created. |
+    /// | `token_program` | `read_only` | The token program of the pool token. |
+    /// | `associated_token_program` | `read_only` | The associated token program. |
+    /// | `airspace_program` | `read_only` | The airspace program. |
+    /// | `margin_program` | `read_only` | The margin program. |
+    /// | `rent` | `read_only` | The rent sysvar. |
+    /// | `system_program` | `read_only` | The [system native program](https://docs.solana.com/developing/runtime-facilities/programs#system-program). |
+    pub fn create_pool(ctx: Context<CreatePool>) -> Result<()> {
+        instructions::create_pool_handler(ctx)
+    }
+
+    /// Register a collateral token that the pool can receive from liquidations.
+    ///
+    /// The pool can receive at most [MAX_BACKSTOP_COLLATERALS] collaterals, which are
+    /// chosen by the airspace authority.
+    ///
+    /// This creates the position of the collateral in the margin account of the pool.
+    /// Tokens administered by the margin program are held in an associated token account,
+    /// while tokens administered by an adapter are held in a position PDA.
+    ///
+    /// # [Accounts](backstop::accounts::RegisterCollateral)
+    ///
+    /// |     |     |     |
+    /// | --- | --- | --- |
+    /// | **Name** | **Type** | **Description** |
+    /// | `authority` | `Signer` | The airspace authority. |
+    /// | `airspace` | `read_only` | The airspace of the pool. |
+    /// | `payer` | `Signer` | The payer of rent for new accounts. |
+    /// | `pool` | `writable` | The pool receiving the collateral. |
+    /// | `margin_account` | `writable` | The margin account of the pool. |
+    /// | `collateral_mint` | `read_only` | The mint of the collateral. |
+    /// | `collateral_config` | `read_only` | The margin token config of the collateral. |
+    /// | `collateral_account` | `writable` | The collateral position to be created. |
+    /// | `token_program` | `read_only` | The token program of the collateral. |
+    /// | `associated_token_program` | `read_only` | The associated token program. |
+    /// | `margin_program` | `read_only` | The margin program. |
+    /// | `rent` | `read_only` | The rent sysvar. |
+    /// | `system_program` | `read_only` | The [system native program](https://docs.solana.com/developing/runtime-facilities/programs#system-program). |
+    pub fn register_collateral(ctx: Context<RegisterCollateral>) -> Result<()> {
+        instructions::register_collateral_handler(ctx)
+    }
+
+    /// Deposit tokens into a pool, creating the deposit account of the depositor if needed.
+    ///
+    /// Any collateral gained by an existing deposit is kept aside to be claimed.
+    pub fn deposit(ctx: Context<Deposit>, amount: u64) -> Result<()> {
+        instructions::deposit_handler(ctx, amount)
+    }
+
+    /// Withdraw tokens from a pool, up to the amount remaining of the deposit after
+    /// the liquidations since it was made.
+    pub fn withdraw(ctx: Context<Withdraw>, amount: u64) -> Result<()> {
+        instructions::withdraw_handler(ctx, amount)
+    }
+
+    /// Withdraw the collateral gained by a deposit from liquidations.
+    pub fn claim_gains(ctx: Context<ClaimGains>) -> Result<()> {
+        instructions::claim_gains_handler(ctx)
+    }
+
+    /// Liquidate a margin account using the deposits of a pool.
+    ///
+    /// Anyone may run this instruction, which begins a liquidation with the pool as the
+    /// liquidator, repays `repay_amount` of the account's debt from the pool deposits,
+    /// seizes collateral at the liquidation discount of the margin program, then ends
+    /// the liquidation. The depositors share the repaid debt and the seized collateral
+    /// in proportion to their deposits.
+    ///
+    /// The debt is repaid from the account's loan in the margin pool of the pool token,
+    /// with an instruction built by the backstop, so that the deposits cannot be spent on
+    /// anything else. The remaining accounts are the token configs of any isolated
+    /// collateral, which the margin program needs to update the isolated debt.
+    ///
+    /// The rent of the liquidation state is returned to the payer.
+    ///
+    /// # [Accounts](backstop::accounts::Liquidate)
+    ///
+    /// |     |     |     |
+    /// | --- | --- | --- |
+    /// | **Name** | **Type** | **Description** |
+    /// | `payer` | `Signer` | The payer of rent for the liquidation state. |
+    /// | `pool` | `writable` | The pool that liquidates the account. |
+    /// | `pool_margin_account` | `writable` | The margin account of the pool. |
+    /// | `liquidator_permit` | `read_only` | The liquidator permit of the pool. |
+    /// | `airspace` | `read_only` | The airspace of the pool. |
+    /// | `margin_account` | `writable` | The margin account being liquidated. |
+    /// | `liquidation` | `writable` | The liquidation state, which is created and closed. |
+    /// | `repay_mint` | `read_only` | The mint of the pool token. |
+    /// | `repay_source` | `writable` | The deposit position of the pool. |
+    /// | `repay_destination` | `writable` | The deposit of the liquidated account the debt is repaid from. |
+    /// | `repay_token_program` | `read_only` | The token program of the pool token. |
+    /// | `margin_pool` | `writable` | The margin pool of the pool token. |
+    /// | `margin_pool_adapter_config` | `read_only` | The adapter config of the margin pool program. |
+    /// | `loan_note_mint` | `writable` | The loan note mint of the margin pool. |
+    /// | `vault` | `writable` | The vault of the margin pool. |
+    /// | `loan_account` | `writable` | The loan of the liquidated account. |
+    /// | `loan_token_program` | `read_only` | The token program of the loan notes. |
+    /// | `seize_mint` | `read_only` | The mint of the seized collateral. |
+    /// | `seize_token_config` | `read_only` | The margin token config of the seized collateral. |
+    /// | `seize_source` | `writable` | The collateral position of the liquidated account. |
+    /// | `seize_destination` | `writable` | The collateral position of the pool. |
+    /// | `seize_token_program` | `read_only` | The token program of the seized collateral. |
+    /// | `margin_pool_program` | `read_only` | The margin pool program. |
+    /// | `margin_program` | `read_only` | The margin program. |
+    /// | `system_program` | `read_only` | The [system native program](https://docs.solana.com/developing/runtime-facilities/programs#system-program). |
+    ///
+    /// # Events
+    ///
+    /// |     |     |
+    /// | --- | --- |
+    /// | **Event Name** | **Description** |
+    /// | [`events::BackstopLiquidation`] | Marks the debt repaid and the collateral received by the pool. |
+    pub fn liquidate<'a, 'b, 'c: 'info, 'info>(
+        ctx: Context<'a, 'b, 'c, 'info, Liquidate<'info>>,
+        repay_amount: u64,
+    ) -> Result<()> {
+        instructions::liquidate_handler(ctx, repay_amount)
+    }
+}
+
+#[error_code]
+pub enum ErrorCode {
+    /// 141300 - The token config is not valid for the pool
+    #[msg("The token config is not valid for the pool")]
+    InvalidTokenConfig = 135_300,
+
+    /// 141301 - The collateral is not registered with the pool
+    #[msg("The collateral is not registered with the pool")]
+    CollateralNotRegistered,
+
+    /// 141302 - The collateral is already registered with the pool
+    #[msg("The collateral is already registered with the pool")]
+    CollateralAlreadyRegistered,
+
+    /// 141303 - The maximum number of collaterals has been registered
+    #[msg("The maximum number of collaterals has been registered")]
+    MaxCollateralsReached,
+
+    /// 141304 - The deposit is less than the amount requested
+    #[msg("The deposit is less than the amount requested")]
+    InsufficientDeposit,
+
+    /// 141305 - The pool does not have enough deposits for the liquidation
+    #[msg("The pool does not have enough deposits for the liquidation")]
+    BackstopDepleted,
+
+    /// 141306 - The tokens sent to the liquidated account were not all repaid
+    #[msg("The tokens sent to the liquidated account were not all repaid")]
+    IncompleteRepayment,
+
+    /// 141307 - A calculation overflowed
+    #[msg("A calculation overflowed")]
+    MathOverflow,
+}
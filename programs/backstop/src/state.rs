@@ -0,0 +1,346 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+//
+// Copyright (C) 2024 A1 XYZ, INC.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use anchor_lang::prelude::*;
+
+use glow_program_common::Number;
+
+use crate::{seeds, ErrorCode, MAX_BACKSTOP_COLLATERALS};
+
+/// The smallest value the product of a pool may reach after a liquidation.
+///
+/// Each liquidation scales the product down by the fraction of deposits that remain,
+/// so a liquidation that would use up (almost) all of the deposits is rejected to keep
+/// the compounded deposits precise.
+const MIN_PRODUCT_EXPONENT: i32 = -9;
+
+/// A pool of deposits in a single token, which is used to repay the debt of
+/// liquidated margin accounts in exchange for their collateral.
+///
+/// The deposits and collateral gains of each depositor are tracked with a running
+/// product and sums, so that each liquidation only updates the pool:
+///
+/// * The `product` is the fraction of an initial deposit that remains after all the
+///   liquidations since the start of the pool.
+/// * The `sum` of each collateral is the amount of collateral gained per deposited token,
+///   scaled by the product at the time of each liquidation.
+///
+/// A deposit made when the product was `P0` and the sum was `S0` is then worth
+/// `deposit * P / P0`, with a gain of `deposit * (S - S0) / P0` of the collateral.
+#[account]
+#[derive(Default, Debug)]
+pub struct BackstopPool {
+    /// The bump seed used to create the pool address
+    pub bump: [u8; 1],
+
+    /// The airspace the pool liquidates accounts in
+    pub airspace: Pubkey,
+
+    /// The mint of the token deposited in the pool
+    pub token_mint: Pubkey,
+
+    /// The margin account owned by the pool, which holds the deposits and the
+    /// seized collateral
+    pub margin_account: Pubkey,
+
+    /// The deposit position of the pool's margin account
+    pub deposit_account: Pubkey,
+
+    /// The total amount of tokens deposited, after all liquidations
+    pub total_deposits: u64,
+
+    /// The running product of the fraction of deposits remaining after each liquidation
+    pub product: [u8; 24],
+
+    /// The collateral tokens that the pool can receive from liquidations
+    pub collaterals: [CollateralGain; MAX_BACKSTOP_COLLATERALS],
+}
+
+/// The gains of a collateral token received by a backstop pool
+#[derive(AnchorSerialize, AnchorDeserialize, Default, Debug, Clone, Copy)]
+pub struct CollateralGain {
+    /// The mint of the collateral, or the default address if the slot is unused
+    pub mint: Pubkey,
+
+    /// The running sum of the collateral gained per deposited token
+    pub sum: [u8; 24],
+}
+
+impl BackstopPool {
+    pub const SPACE: usize = 8 + std::mem::size_of::<Self>();
+
+    pub fn signer_seeds(&self) -> [&[u8]; 4] {
+        [
+            seeds::BACKSTOP_POOL,
+            self.airspace.as_ref(),
+            self.token_mint.as_ref(),
+            self.bump.as_ref(),
+        ]
+    }
+
+    pub fn product(&self) -> Number {
+        Number::from_bits(self.product)
+    }
+
+    /// Find the slot of a collateral token registered with the pool
+    pub fn collateral_index(&self, mint: &Pubkey) -> Result<usize> {
+        self.collaterals
+            .iter()
+            .position(|c| c.mint == *mint && *mint != Pubkey::default())
+            .ok_or_else(|| error!(ErrorCode::CollateralNotRegistered))
+    }
+
+    /// Register a collateral token that the pool can receive
+    pub fn register_collateral(&mut self, mint: Pubkey) -> Result<()> {
+        require!(
+            self.collateral_index(&mint).is_err(),
+            ErrorCode::CollateralAlreadyRegistered
+        );
+        let slot = self
+            .collaterals
+            .iter_mut()
+            .find(|c| c.mint == Pubkey::default())
+            .ok_or(ErrorCode::MaxCollateralsReached)?;
+        *slot = CollateralGain {
+            mint,
+            sum: Number::ZERO.into_bits(),
+        };
+
+        Ok(())
+    }
+
+    /// Bring a deposit up to date with the liquidations since it was last updated,
+    /// moving its collateral gains to the pending gains.
+    pub fn settle(&self, deposit: &mut BackstopDeposit) -> Result<()> {
+        let product = self.product();
+
+        if deposit.amount > 0 {
+            let initial = Number::from(deposit.amount);
+            let snapshot = Number::from_bits(deposit.product);
+
+            for (i, collateral) in self.collaterals.iter().enumerate() {
+                let sum = Number::from_bits(collateral.sum);
+                let gain = (sum - Number::from_bits(deposit.sums[i])) * initial / snapshot;
+                deposit.pending_gains[i] = deposit.pending_gains[i]
+                    .checked_add(token_amount(gain)?)
+                    .ok_or(ErrorCode::MathOverflow)?;
+            }
+
+            deposit.amount = token_amount(initial * product / snapshot)?;
+        }
+
+        deposit.product = product.into_bits();
+        for (i, collateral) in self.collaterals.iter().enumerate() {
+            deposit.sums[i] = collateral.sum;
+        }
+
+        Ok(())
+    }
+
+    /// Add tokens to a deposit
+    pub fn deposit(&mut self, deposit: &mut BackstopDeposit, amount: u64) -> Result<()> {
+        self.settle(deposit)?;
+
+        deposit.amount = deposit
+            .amount
+            .checked_add(amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+        self.total_deposits = self
+            .total_deposits
+            .checked_add(amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        Ok(())
+    }
+
+    /// Remove tokens from a deposit
+    pub fn withdraw(&mut self, deposit: &mut BackstopDeposit, amount: u64) -> Result<()> {
+        self.settle(deposit)?;
+
+        require!(deposit.amount >= amount, ErrorCode::InsufficientDeposit);
+        deposit.amount -= amount;
+        self.total_deposits = self
+            .total_deposits
+            .checked_sub(amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        Ok(())
+    }
+
+    /// Take the pending gains of a collateral from a deposit
+    pub fn claim_gains(&self, deposit: &mut BackstopDeposit, index: usize) -> Result<u64> {
+        self.settle(deposit)?;
+
+        Ok(std::mem::take(&mut deposit.pending_gains[index]))
+    }
+
+    /// Account for a liquidation, where `debt` tokens of the deposits were used to
+    /// repay a margin account in exchange for `collateral` tokens of the given mint.
+    ///
+    /// The collateral is distributed to depositors in proportion to their deposits,
+    /// which are reduced by their share of the debt.
+    pub fn record_liquidation(&mut self, mint: &Pubkey, debt: u64, collateral: u64) -> Result<()> {
+        let index = self.collateral_index(mint)?;
+        require!(debt < self.total_deposits, ErrorCode::BackstopDepleted);
+
+        let total = Number::from(self.total_deposits);
+        let product = self.product();
+        let new_product = product * Number::from(self.total_deposits - debt) / total;
+        require!(
+            new_product >= Number::from_decimal(1, MIN_PRODUCT_EXPONENT),
+            ErrorCode::BackstopDepleted
+        );
+
+        let gain = &mut self.collaterals[index];
+        let sum = Number::from_bits(gain.sum) + Number::from(collateral) * product / total;
+        gain.sum = sum.into_bits();
+
+        self.product = new_product.into_bits();
+        self.total_deposits -= debt;
+
+        Ok(())
+    }
+}
+
+/// An amount of tokens, which has to fit in a u64
+fn token_amount(value: Number) -> Result<u64> {
+    require!(value <= Number::from(u64::MAX), ErrorCode::MathOverflow);
+
+    Ok(value.as_u64(0))
+}
+
+/// The deposit of a user in a backstop pool
+#[account]
+#[derive(Default, Debug)]
+pub struct BackstopDeposit {
+    /// The owner of the deposit
+    pub owner: Pubkey,
+
+    /// The pool the deposit is in
+    pub pool: Pubkey,
+
+    /// The amount deposited, as of the last update of the deposit
+    pub amount: u64,
+
+    /// The product of the pool as of the last update of the deposit
+    pub product: [u8; 24],
+
+    /// The sums of the pool collaterals as of the last update of the deposit
+    pub sums: [[u8; 24]; MAX_BACKSTOP_COLLATERALS],
+
+    /// The collateral gains that have not been claimed yet
+    pub pending_gains: [u64; MAX_BACKSTOP_COLLATERALS],
+}
+
+impl BackstopDeposit {
+    pub const SPACE: usize = 8 + std::mem::size_of::<Self>();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pool_with_collateral(mint: Pubkey) -> BackstopPool {
+        let mut pool = BackstopPool {
+            product: Number::ONE.into_bits(),
+            ..Default::default()
+        };
+        pool.register_collateral(mint).unwrap();
+        pool
+    }
+
+    #[test]
+    fn collaterals_are_registered_up_to_the_max() {
+        let mut pool = pool_with_collateral(Pubkey::new_unique());
+        for _ in 1..MAX_BACKSTOP_COLLATERALS {
+            pool.register_collateral(Pubkey::new_unique()).unwrap();
+        }
+        assert!(pool.register_collateral(Pubkey::new_unique()).is_err());
+        assert!(pool.collateral_index(&Pubkey::default()).is_err());
+    }
+
+    #[test]
+    fn liquidations_are_shared_pro_rata() {
+        let mint = Pubkey::new_unique();
+        let mut pool = pool_with_collateral(mint);
+        let mut alice = BackstopDeposit::default();
+        let mut bob = BackstopDeposit::default();
+
+        pool.deposit(&mut alice, 3_000).unwrap();
+        pool.deposit(&mut bob, 1_000).unwrap();
+        pool.record_liquidation(&mint, 2_000, 400).unwrap();
+
+        assert_eq!(2_000, pool.total_deposits);
+        assert_eq!(300, pool.claim_gains(&mut alice, 0).unwrap());
+        assert_eq!(1_500, alice.amount);
+        assert_eq!(100, pool.claim_gains(&mut bob, 0).unwrap());
+        assert_eq!(500, bob.amount);
+
+        // Gains are only claimed once
+        assert_eq!(0, pool.claim_gains(&mut alice, 0).unwrap());
+    }
+
+    #[test]
+    fn later_deposits_do_not_share_earlier_liquidations() {
+        let mint = Pubkey::new_unique();
+        let mut pool = pool_with_collateral(mint);
+        let mut alice = BackstopDeposit::default();
+        let mut bob = BackstopDeposit::default();
+
+        pool.deposit(&mut alice, 1_000).unwrap();
+        pool.record_liquidation(&mint, 500, 100).unwrap();
+        pool.deposit(&mut bob, 500).unwrap();
+        pool.record_liquidation(&mint, 500, 200).unwrap();
+
+        assert_eq!(200, pool.claim_gains(&mut alice, 0).unwrap());
+        assert_eq!(250, alice.amount);
+        assert_eq!(100, pool.claim_gains(&mut bob, 0).unwrap());
+        assert_eq!(250, bob.amount);
+
+        pool.withdraw(&mut alice, 250).unwrap();
+        assert!(pool.withdraw(&mut bob, 251).is_err());
+        pool.withdraw(&mut bob, 250).unwrap();
+        assert_eq!(0, pool.total_deposits);
+    }
+
+    #[test]
+    fn liquidations_cannot_deplete_the_pool() {
+        let mint = Pubkey::new_unique();
+        let mut pool = pool_with_collateral(mint);
+        let mut deposit = BackstopDeposit::default();
+
+        pool.deposit(&mut deposit, 1_000).unwrap();
+        assert!(pool.record_liquidation(&mint, 1_000, 100).is_err());
+        assert!(pool
+            .record_liquidation(&Pubkey::new_unique(), 100, 10)
+            .is_err());
+        pool.record_liquidation(&mint, 999, 100).unwrap();
+        assert_eq!(1, pool.total_deposits);
+    }
+
+    #[test]
+    fn overflowing_deposits_are_rejected() {
+        let mut pool = pool_with_collateral(Pubkey::new_unique());
+        let mut alice = BackstopDeposit::default();
+        let mut bob = BackstopDeposit::default();
+
+        pool.deposit(&mut alice, u64::MAX).unwrap();
+        assert!(pool.deposit(&mut alice, 1).is_err());
+        assert!(pool.deposit(&mut bob, 1).is_err());
+        assert!(pool.withdraw(&mut bob, 1).is_err());
+    }
+}
@@ -21,7 +21,8 @@ use anchor_lang::prelude::*;
 use anchor_spl::token_interface::{self, Mint, MintTo, TokenAccount, TokenInterface};
 
 use glow_margin::{
-    AdapterResult, MarginAccount, PositionChange, TokenBalanceChange, TokenBalanceChangeCause,
+    AccountConstraints, AdapterResult, MarginAccount, PositionChange, TokenBalanceChange,
+    TokenBalanceChangeCause,
 };
 use glow_program_common::token_change::{ChangeKind, TokenChange};
 
@@ -92,6 +93,16 @@ pub fn margin_borrow_handler(
     change_kind: ChangeKind,
     amount: u64,
 ) -> Result<()> {
+    // Margin accounts constrained from taking on debt cannot borrow
+    require!(
+        !ctx.accounts
+            .margin_account
+            .load()?
+            .constraints
+            .contains(AccountConstraints::DENY_BORROWS),
+        ErrorCode::BorrowsDenied
+    );
+
     let change = TokenChange {
         kind: change_kind,
         tokens: amount,
@@ -24,7 +24,8 @@ use anchor_spl::{
 };
 
 use glow_margin::{
-    AdapterResult, MarginAccount, PositionChange, TokenBalanceChange, TokenBalanceChangeCause,
+    AccountConstraints, AdapterResult, MarginAccount, PositionChange, TokenBalanceChange,
+    TokenBalanceChangeCause,
 };
 use glow_program_common::token_change::{ChangeKind, TokenChange};
 
@@ -97,6 +98,16 @@ impl<'info> MarginBorrowV2<'info> {
 
 #[inline(never)]
 pub fn margin_borrow_v2_handler(ctx: Context<MarginBorrowV2>, amount: u64) -> Result<()> {
+    // Margin accounts constrained from taking on debt cannot borrow
+    require!(
+        !ctx.accounts
+            .margin_account
+            .load()?
+            .constraints
+            .contains(AccountConstraints::DENY_BORROWS),
+        ErrorCode::BorrowsDenied
+    );
+
     let change = TokenChange {
         kind: ChangeKind::ShiftBy,
         tokens: amount,
@@ -18,7 +18,7 @@
 use anchor_lang::prelude::*;
 
 use glow_margin::{
-    AdapterResult, MarginAccount, PositionChange, PriceChangeInfo, MAX_ORACLE_STALENESS,
+    AdapterResult, MarginAccount, Numeraire, PositionChange, PriceChangeInfo, MAX_ORACLE_STALENESS,
 };
 use pyth_solana_receiver_sdk::price_update::PriceUpdateV2;
 
@@ -37,8 +37,9 @@ pub struct MarginRefreshPosition<'info> {
     /// CHECK: We verify this account against the pyth pull receiver program
     pub price_oracle: AccountInfo<'info>,
 
-    /// An optional oracle price account for the quote token, if the position uses a redemption rate.
-    /// CHECK: We verify this account against the pyth pull receiver program
+    /// An optional oracle price account for the quote token, if the position uses a redemption rate,
+    /// or the stake pool account, if the position uses a stake pool oracle.
+    /// CHECK: We verify this account against the pyth pull receiver or stake pool program
     pub redemption_quote_oracle: Option<AccountInfo<'info>>,
 }
 
@@ -57,7 +58,15 @@ pub fn margin_refresh_position_handler(ctx: Context<MarginRefreshPosition>) -> R
             ctx.accounts.price_oracle.owner == &pyth_solana_receiver_sdk::id(),
             crate::ErrorCode::InvalidPoolOracle
         );
-        if let Some(oracle) = &ctx.accounts.redemption_quote_oracle {
+        // A stake pool account in place of the quote oracle is verified when its rate is read
+        let quote_is_stake_pool = ctx
+            .accounts
+            .margin_pool
+            .token_price_oracle
+            .stake_pool()
+            .is_some();
+        if let (Some(oracle), false) = (&ctx.accounts.redemption_quote_oracle, quote_is_stake_pool)
+        {
             // The account must be owned by the Pyth receiver or our test program (devnet) if not testing.
             // Anchor has a quirk where this validation is still applied against an empty optional account,
             // so we don't fail the check if the account is owned by the system program.
@@ -75,18 +84,32 @@ pub fn margin_refresh_position_handler(ctx: Context<MarginRefreshPosition>) -> R
             );
         }
     }
+    let pool = &ctx.accounts.margin_pool;
+
+    // Prices may be as old as the most lenient oracle limit of the pool's registered positions,
+    // the margin program then applies the limit of each position when accepting its price.
+    let (max_staleness, numeraire) = {
+        let margin_account = ctx.accounts.margin_account.load()?;
+        let max_staleness = [pool.deposit_note_mint, pool.loan_note_mint]
+            .iter()
+            .filter_map(|mint| margin_account.get_position(mint))
+            .map(|position| position.oracle_limits().max_oracle_staleness())
+            .max()
+            .unwrap_or(MAX_ORACLE_STALENESS);
+        (max_staleness, margin_account.numeraire())
+    };
+
     let clock = Clock::get()?;
-    let min_oracle_freshness = clock.unix_timestamp - MAX_ORACLE_STALENESS as i64;
+    let min_oracle_freshness = clock.unix_timestamp - max_staleness as i64;
     let oracle_data = ctx.accounts.price_oracle.try_borrow_data()?;
     let oracle_update = PriceUpdateV2::try_deserialize(&mut &oracle_data[..])?;
     if oracle_update.price_message.publish_time < min_oracle_freshness {
         msg!("stale oracle: {}", oracle_update.price_message.publish_time);
     }
 
-    let pool = &ctx.accounts.margin_pool;
-
     let quote_oracle_update = {
-        if !pool.token_price_oracle.is_redemption_rate() {
+        // Accounts valued in SOL don't use the SOL/USD quote of a redemption rate
+        if !pool.token_price_oracle.is_redemption_rate() || numeraire == Numeraire::Sol {
             None
         } else {
             let oracle_data = ctx
@@ -103,7 +126,24 @@ pub fn margin_refresh_position_handler(ctx: Context<MarginRefreshPosition>) -> R
         }
     };
 
-    let prices = pool.calculate_prices(&oracle_update, quote_oracle_update.as_ref(), &clock)?;
+    let stake_pool_rate =
+        pool.load_stake_pool_rate(ctx.accounts.redemption_quote_oracle.as_ref())?;
+
+    let prices = match numeraire {
+        Numeraire::Usd => pool.calculate_prices(
+            &oracle_update,
+            quote_oracle_update.as_ref(),
+            stake_pool_rate.as_ref(),
+            &clock,
+            max_staleness,
+        )?,
+        Numeraire::Sol => pool.calculate_prices_in_sol(
+            &oracle_update,
+            stake_pool_rate.as_ref(),
+            &clock,
+            max_staleness,
+        )?,
+    };
 
     // Tell the margin program what the current prices are
     glow_margin::write_adapter_result(
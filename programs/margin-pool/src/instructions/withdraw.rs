@@ -21,7 +21,7 @@ use anchor_lang::{prelude::*, Discriminator};
 use anchor_spl::token_2022::{Burn, TransferChecked};
 use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
 use anchor_spl::{token, token_interface};
-use glow_margin::{AccountConstraints, MarginAccount};
+use glow_margin::{AccountConstraintTicket, AccountConstraints, MarginAccount};
 use glow_program_common::token_change::{ChangeKind, TokenChange};
 
 use crate::{events, state::*, ErrorCode};
@@ -61,6 +61,11 @@ pub struct Withdraw<'info> {
     /// (`AccountConstraints::DENY_WITHDRAWALS`), the destination must be the
     /// margin account's associated token account (ATA) for `token_mint`.
     ///
+    /// If the signer is a margin account with restricted destinations
+    /// (`AccountConstraints::RESTRICT_DESTINATIONS`), a destination not owned by
+    /// the margin account must be allowlisted on its constraint ticket, which is
+    /// passed as the first remaining account.
+    ///
     /// Otherwise, if the signer is a margin account, the destination can be
     /// owned by either the margin account itself or the margin account owner.
     /// If the signer is not a margin account, the destination must be owned by
@@ -140,6 +145,20 @@ pub fn withdraw_handler(
                     ctx.accounts.destination.key() == ctx.accounts.destination_ata.key(),
                     crate::ErrorCode::InvalidWithdrawalAuthority
                 );
+            } else if margin_account
+                .constraints
+                .contains(AccountConstraints::RESTRICT_DESTINATIONS)
+                && destination_authority != depositor_address
+            {
+                // Tokens leaving the margin account must go to an allowlisted destination
+                let ticket = AccountConstraintTicket::load_checked(
+                    ctx.remaining_accounts.first(),
+                    &depositor_address,
+                )?;
+                require!(
+                    ticket.allows_destination(&ctx.accounts.destination.key()),
+                    crate::ErrorCode::InvalidWithdrawalAuthority
+                );
             } else {
                 // Regular margin accounts may withdraw to either their owner wallet's ATA or their own ATA
                 require!(
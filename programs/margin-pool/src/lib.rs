@@ -487,4 +487,8 @@ pub enum ErrorCode {
 
     #[msg("Pool permissions do not allow this action")]
     PoolPermissionDenied,
+
+    /// 141117 - The margin account is constrained from borrowing
+    #[msg("The margin account is not allowed to borrow")]
+    BorrowsDenied,
 }
@@ -20,7 +20,9 @@ use std::convert::TryFrom;
 
 use anchor_lang::{prelude::*, solana_program::clock::UnixTimestamp};
 use glow_margin::MAX_ORACLE_STALENESS;
-use glow_program_common::oracle::TokenPriceOracle;
+use glow_program_common::oracle::{
+    SolQuote, StakePoolRate, TokenPriceOracle, STAKE_POOL_PROGRAM_ID,
+};
 use glow_program_common::token_change::{ChangeKind, TokenChange};
 use glow_program_common::{Number, BPS_EXPONENT};
 
@@ -33,6 +35,9 @@ use serde::{
 
 use crate::{util, Amount, AmountKind, ErrorCode, MAX_POOL_UTIL_RATIO_AFTER_BORROW_BPS};
 
+/// The exponent of note prices when the price of SOL is fixed at 1
+const SOL_PRICE_EXPONENT: i32 = -8;
+
 /// Account containing information about a margin pool, which
 /// services lending/borrowing operations.
 #[account]
@@ -378,17 +383,105 @@ impl MarginPool {
     ///
     /// We pass in the clock as a variable as we also use this function off-chain,
     /// where we don't have access to the sysvars.
+    ///
+    /// The `max_staleness` is the maximum age of the oracle prices in seconds, which is
+    /// usually [MAX_ORACLE_STALENESS] unless overridden for the pool's tokens.
+    ///
+    /// The `stake_pool` rate is required if the token is priced by a stake pool.
     pub fn calculate_prices(
         &self,
         update: &PriceUpdateV2,
         quote_update: Option<&PriceUpdateV2>,
+        stake_pool: Option<&StakePoolRate>,
         clock: &Clock,
+        max_staleness: u64,
+    ) -> Result<PriceResult> {
+        let (price_value, conf_value, ema_value, exponent, publish_time) = match self
+            .token_price_oracle
+        {
+            TokenPriceOracle::PythPull { feed_id } => {
+                let price = update.get_price_no_older_than(clock, max_staleness, &feed_id)?;
+                (
+                    Number::from_decimal(price.price, price.exponent),
+                    Number::from_decimal(price.conf, price.exponent),
+                    Number::from_decimal(update.price_message.ema_price, price.exponent),
+                    price.exponent,
+                    price.publish_time,
+                )
+            }
+            TokenPriceOracle::PythPullRedemption {
+                feed_id,
+                quote_feed_id,
+            } => {
+                // The quote mint should match the oracle price mint
+                let quote_update = quote_update.ok_or(ErrorCode::InvalidPoolOracle)?;
+                let price = update.get_price_no_older_than(clock, max_staleness, &feed_id)?;
+                let quote =
+                    quote_update.get_price_no_older_than(clock, max_staleness, &quote_feed_id)?;
+
+                // SECURITY: If we were to incorrectly configure the oracle feed chain, we could significantly misprice tokens.
+                // E.g. SUSD redemption * BTC underlying.
+                let quote_price = Number::from_decimal(quote.price, quote.exponent);
+                let quote_ema =
+                    Number::from_decimal(quote_update.price_message.ema_price, quote.exponent);
+                let quote_conf = Number::from_decimal(quote.conf, quote.exponent);
+                let publish_time = price.publish_time.min(quote.publish_time);
+
+                // The confidence of the price is the sum of the two confidence values in USD.
+                // (quote.conf * price) + price.conf
+                (
+                    Number::from_decimal(price.price, price.exponent) * quote_price,
+                    Number::from_decimal(price.conf, price.exponent) * quote_price + quote_conf,
+                    Number::from_decimal(update.price_message.ema_price, price.exponent)
+                        * quote_ema,
+                    price.exponent,
+                    publish_time,
+                )
+            }
+            TokenPriceOracle::NoOracle => {
+                return err!(ErrorCode::InvalidPoolOracle);
+            }
+            TokenPriceOracle::StakePool { sol_feed_id, .. } => {
+                // The SOL backing each pool token, priced in USD
+                let exchange_rate = stake_pool_exchange_rate(stake_pool, clock)?;
+                let price = update.get_price_no_older_than(clock, max_staleness, &sol_feed_id)?;
+                (
+                    Number::from_decimal(price.price, price.exponent) * exchange_rate,
+                    Number::from_decimal(price.conf, price.exponent) * exchange_rate,
+                    Number::from_decimal(update.price_message.ema_price, price.exponent)
+                        * exchange_rate,
+                    price.exponent,
+                    price.publish_time,
+                )
+            }
+        };
+
+        Ok(self.note_prices(price_value, conf_value, ema_value, exponent, publish_time))
+    }
+
+    /// Calculate the prices for the deposit and loan notes in SOL, for margin accounts
+    /// that are valued in SOL.
+    ///
+    /// SOL has a price of 1, and SOL derivatives are priced by their redemption rate or
+    /// stake pool exchange rate to SOL, so that the SOL/USD price is not used.
+    pub fn calculate_prices_in_sol(
+        &self,
+        update: &PriceUpdateV2,
+        stake_pool: Option<&StakePoolRate>,
+        clock: &Clock,
+        max_staleness: u64,
     ) -> Result<PriceResult> {
         let (price_value, conf_value, ema_value, exponent, publish_time) =
-            match self.token_price_oracle {
-                TokenPriceOracle::PythPull { feed_id } => {
-                    let price =
-                        update.get_price_no_older_than(clock, MAX_ORACLE_STALENESS, &feed_id)?;
+            match self.token_price_oracle.sol_quote() {
+                Some(SolQuote::Sol) => (
+                    Number::ONE,
+                    Number::ZERO,
+                    Number::ONE,
+                    SOL_PRICE_EXPONENT,
+                    clock.unix_timestamp,
+                ),
+                Some(SolQuote::RedemptionRate { feed_id }) => {
+                    let price = update.get_price_no_older_than(clock, max_staleness, &feed_id)?;
                     (
                         Number::from_decimal(price.price, price.exponent),
                         Number::from_decimal(price.conf, price.exponent),
@@ -397,44 +490,67 @@ impl MarginPool {
                         price.publish_time,
                     )
                 }
-                TokenPriceOracle::PythPullRedemption {
-                    feed_id,
-                    quote_feed_id,
-                } => {
-                    // The quote mint should match the oracle price mint
-                    let quote_update = quote_update.ok_or(ErrorCode::InvalidPoolOracle)?;
-                    let price =
-                        update.get_price_no_older_than(clock, MAX_ORACLE_STALENESS, &feed_id)?;
-                    let quote = quote_update.get_price_no_older_than(
-                        clock,
-                        MAX_ORACLE_STALENESS,
-                        &quote_feed_id,
-                    )?;
-
-                    // SECURITY: If we were to incorrectly configure the oracle feed chain, we could significantly misprice tokens.
-                    // E.g. SUSD redemption * BTC underlying.
-                    let quote_price = Number::from_decimal(quote.price, quote.exponent);
-                    let quote_ema =
-                        Number::from_decimal(quote_update.price_message.ema_price, quote.exponent);
-                    let quote_conf = Number::from_decimal(quote.conf, quote.exponent);
-                    let publish_time = price.publish_time.min(quote.publish_time);
-
-                    // The confidence of the price is the sum of the two confidence values in USD.
-                    // (quote.conf * price) + price.conf
+                // The stake pool rate holds for the whole epoch it was updated in
+                Some(SolQuote::StakePool { .. }) => {
+                    let exchange_rate = stake_pool_exchange_rate(stake_pool, clock)?;
                     (
-                        Number::from_decimal(price.price, price.exponent) * quote_price,
-                        Number::from_decimal(price.conf, price.exponent) * quote_price + quote_conf,
-                        Number::from_decimal(update.price_message.ema_price, price.exponent)
-                            * quote_ema,
-                        price.exponent,
-                        publish_time,
+                        exchange_rate,
+                        Number::ZERO,
+                        exchange_rate,
+                        SOL_PRICE_EXPONENT,
+                        clock.unix_timestamp,
                     )
                 }
-                TokenPriceOracle::NoOracle => {
+                None => {
+                    msg!("the pool token cannot be priced in SOL");
                     return err!(ErrorCode::InvalidPoolOracle);
                 }
             };
 
+        Ok(self.note_prices(price_value, conf_value, ema_value, exponent, publish_time))
+    }
+
+    /// Read the rate of the stake pool that prices the pool token, if the pool has a
+    /// stake pool oracle. The stake pool account is passed in place of the quote oracle.
+    pub fn load_stake_pool_rate(
+        &self,
+        stake_pool_info: Option<&AccountInfo>,
+    ) -> Result<Option<StakePoolRate>> {
+        let Some(stake_pool) = self.token_price_oracle.stake_pool() else {
+            return Ok(None);
+        };
+        let stake_pool_info = stake_pool_info.ok_or(ErrorCode::MissingQuoteOracleAccount)?;
+        require_keys_eq!(
+            stake_pool_info.key(),
+            *stake_pool,
+            ErrorCode::InvalidPoolOracle
+        );
+        require_keys_eq!(
+            *stake_pool_info.owner,
+            STAKE_POOL_PROGRAM_ID,
+            ErrorCode::InvalidPoolOracle
+        );
+
+        let rate = StakePoolRate::try_from_account_data(&stake_pool_info.try_borrow_data()?)
+            .ok_or(ErrorCode::InvalidPoolOracle)?;
+        require_keys_eq!(
+            rate.pool_mint,
+            self.token_mint,
+            ErrorCode::InvalidPoolOracle
+        );
+
+        Ok(Some(rate))
+    }
+
+    /// Convert a token price into the prices of the deposit and loan notes
+    fn note_prices(
+        &self,
+        price_value: Number,
+        conf_value: Number,
+        ema_value: Number,
+        exponent: i32,
+        publish_time: UnixTimestamp,
+    ) -> PriceResult {
         let deposit_note_exchange_rate = self.deposit_note_exchange_rate();
         let loan_note_exchange_rate = self.loan_note_exchange_rate();
 
@@ -452,7 +568,7 @@ impl MarginPool {
         let loan_note_twap =
             i64::try_from((ema_value * loan_note_exchange_rate).as_u64_rounded(exponent)).unwrap();
 
-        Ok(PriceResult {
+        PriceResult {
             deposit_note_price,
             deposit_note_conf,
             deposit_note_twap,
@@ -461,7 +577,7 @@ impl MarginPool {
             loan_note_twap,
             publish_time,
             exponent,
-        })
+        }
     }
 
     pub fn calculate_full_amount(
@@ -770,6 +886,26 @@ impl RoundingDirection {
     }
 }
 
+/// The SOL backing each pool token of a stake pool, which has to be updated in the
+/// current epoch.
+fn stake_pool_exchange_rate(rate: Option<&StakePoolRate>, clock: &Clock) -> Result<Number> {
+    let rate = rate.ok_or(ErrorCode::InvalidPoolOracle)?;
+    if rate.last_update_epoch != clock.epoch {
+        msg!(
+            "stake pool was last updated in epoch {}, current epoch is {}",
+            rate.last_update_epoch,
+            clock.epoch
+        );
+        return err!(ErrorCode::InvalidPoolOracle);
+    }
+    if rate.pool_token_supply == 0 {
+        msg!("stake pool has no supply");
+        return err!(ErrorCode::InvalidPoolOracle);
+    }
+
+    Ok(Number::from(rate.total_lamports) / Number::from(rate.pool_token_supply))
+}
+
 pub struct PriceResult {
     pub deposit_note_price: i64,
     pub deposit_note_conf: u64,
@@ -23,8 +23,8 @@ use anchor_lang::{
     solana_program::{instruction::Instruction, program},
 };
 use glow_program_common::{
-    oracle::TokenPriceOracle, Number128, JUPITER_V6, KNOWN_EXTERNAL_PROGRAMS,
-    SAFE_RETURN_DATA_PROGRAMS,
+    oracle::{SolQuote, StakePoolRate, TokenPriceOracle, STAKE_POOL_PROGRAM_ID},
+    Number128, JUPITER_V6, KNOWN_EXTERNAL_PROGRAMS, SAFE_RETURN_DATA_PROGRAMS,
 };
 use pyth_solana_receiver_sdk::price_update::PriceUpdateV2;
 use solana_program::clock::UnixTimestamp;
@@ -32,9 +32,9 @@ use solana_program::clock::UnixTimestamp;
 use crate::{
     syscall::{sys, Sys},
     util::Require,
-    AccountPositionKey, AdapterConfig, AdapterPositionFlags, Approver, ErrorCode, MarginAccount,
-    PositionConfigUpdate, PriceInfo, SignerSeeds, TokenConfig, MAX_ORACLE_CONFIDENCE,
-    MAX_ORACLE_STALENESS,
+    AccountPosition, AccountPositionKey, AdapterConfig, AdapterInvokeFlags, AdapterPositionFlags,
+    Approver, ErrorCode, MarginAccount, Numeraire, OracleLimits, PositionConfigUpdate, PriceInfo,
+    SignerSeeds, TokenConfig, TokenKind,
 };
 pub struct InvokeAdapter<'b, 'c: 'info, 'info> {
     /// The margin account to proxy an action for
@@ -164,10 +164,21 @@ impl PriceChangeInfo {
         }
     }
 
-    /// Convert into [PriceInfo], checking that EMA and confidence are valid.
+    /// The confidence interval of the price
+    pub fn confidence(&self) -> Number128 {
+        Number128::from_decimal(self.confidence, self.exponent)
+    }
+
+    /// The time that the price was published at
+    pub fn publish_time(&self) -> i64 {
+        self.publish_time
+    }
+
+    /// Convert into [PriceInfo], checking that EMA and confidence are valid within
+    /// the oracle limits of the token.
     /// The returned price info should be checked for validity if used directly.
-    pub fn to_price_info(self, unix_timestamp: UnixTimestamp) -> PriceInfo {
-        let max_confidence = Number128::from_bps(MAX_ORACLE_CONFIDENCE);
+    pub fn to_price_info(self, unix_timestamp: UnixTimestamp, limits: &OracleLimits) -> PriceInfo {
+        let max_confidence = Number128::from_bps(limits.max_confidence());
 
         let ema = Number128::from_decimal(self.ema, self.exponent);
         let confidence = Number128::from_decimal(self.confidence, self.exponent);
@@ -182,7 +193,9 @@ impl PriceChangeInfo {
                 msg!("price confidence exceeding max");
                 PriceInfo::new_invalid()
             }
-            (_, publish_time) if (unix_timestamp - publish_time) > MAX_ORACLE_STALENESS as i64 => {
+            (_, publish_time)
+                if (unix_timestamp - publish_time) > limits.max_oracle_staleness() as i64 =>
+            {
                 msg!(
                     "price timestamp is too old/stale. published: {}, now: {}",
                     publish_time,
@@ -198,8 +211,9 @@ impl PriceChangeInfo {
         price: &PriceUpdateV2,
         feed_id: &[u8; 32],
         clock: &Clock,
+        max_staleness: u64,
     ) -> Result<Self> {
-        let price_obj = price.get_price_no_older_than(clock, MAX_ORACLE_STALENESS, feed_id)?;
+        let price_obj = price.get_price_no_older_than(clock, max_staleness, feed_id)?;
         Ok(Self {
             value: price_obj.price,
             confidence: price_obj.conf,
@@ -215,10 +229,10 @@ impl PriceChangeInfo {
         feed_id: &[u8; 32],
         quote_feed_id: &[u8; 32],
         clock: &Clock,
+        max_staleness: u64,
     ) -> Result<Self> {
-        let price_obj = price.get_price_no_older_than(clock, MAX_ORACLE_STALENESS, feed_id)?;
-        let quote_obj =
-            quote.get_price_no_older_than(clock, MAX_ORACLE_STALENESS, quote_feed_id)?;
+        let price_obj = price.get_price_no_older_than(clock, max_staleness, feed_id)?;
+        let quote_obj = quote.get_price_no_older_than(clock, max_staleness, quote_feed_id)?;
 
         let price_value = Number128::from_decimal(price_obj.price, price_obj.exponent);
         let price_ema = Number128::from_decimal(price.price_message.ema_price, price_obj.exponent);
@@ -249,14 +263,58 @@ impl PriceChangeInfo {
         })
     }
 
+    /// Derive the fair value of a stake pool token from the SOL backing each pool token,
+    /// priced with a SOL/USD feed.
+    ///
+    /// The stake pool must have been updated in the current epoch, so that its rate
+    /// reflects the rewards earned by the pool. The rate holds for the whole epoch, so the
+    /// publish time of the price is that of the SOL/USD feed.
+    pub fn try_from_pyth_pull_stake_pool(
+        price: &PriceUpdateV2,
+        rate: &StakePoolRate,
+        sol_feed_id: &[u8; 32],
+        clock: &Clock,
+        max_staleness: u64,
+    ) -> Result<Self> {
+        let exchange_rate = stake_pool_exchange_rate(rate, clock)?;
+        let price_obj = price.get_price_no_older_than(clock, max_staleness, sol_feed_id)?;
+
+        let price_value = Number128::from_decimal(price_obj.price, price_obj.exponent);
+        let price_ema = Number128::from_decimal(price.price_message.ema_price, price_obj.exponent);
+        let price_conf = Number128::from_decimal(price_obj.conf, price_obj.exponent);
+
+        let value = (price_value * exchange_rate)
+            .as_u64(price_obj.exponent)
+            .try_into()
+            .map_err(|_| error!(crate::ErrorCode::MathOpFailed))?;
+        let confidence = (price_conf * exchange_rate).as_u64(price_obj.exponent);
+        let ema = (price_ema * exchange_rate)
+            .as_u64(price_obj.exponent)
+            .try_into()
+            .map_err(|_| error!(crate::ErrorCode::MathOpFailed))?;
+
+        Ok(Self {
+            value,
+            confidence,
+            ema,
+            publish_time: price_obj.publish_time,
+            exponent: price_obj.exponent,
+        })
+    }
+
     /// Construct from Pyth oracles, validating the type of oracle in the process.
+    ///
+    /// `token_mint` is the mint of the priced token, which a stake pool has to issue.
     pub fn try_from_oracle_accounts(
         price: &AccountInfo,
         quote: &Option<AccountInfo>,
         price_oracle: &TokenPriceOracle,
+        token_mint: &Pubkey,
         clock: &Clock,
+        limits: &OracleLimits,
     ) -> Result<Self> {
-        let min_oracle_freshness = clock.unix_timestamp - MAX_ORACLE_STALENESS as i64;
+        let max_staleness = limits.max_oracle_staleness();
+        let min_oracle_freshness = clock.unix_timestamp - max_staleness as i64;
         // check account ownership
         match price_oracle {
             TokenPriceOracle::NoOracle => err!(crate::ErrorCode::InvalidOracle),
@@ -267,7 +325,7 @@ impl PriceChangeInfo {
                 if update.price_message.publish_time < min_oracle_freshness {
                     msg!("stale oracle: {}", update.price_message.publish_time);
                 }
-                Self::try_from_pyth_pull(&update, feed_id, clock)
+                Self::try_from_pyth_pull(&update, feed_id, clock, max_staleness)
             }
             TokenPriceOracle::PythPullRedemption {
                 feed_id,
@@ -288,12 +346,161 @@ impl PriceChangeInfo {
                 if quote.price_message.publish_time < min_oracle_freshness {
                     msg!("stale quote oracle: {}", quote.price_message.publish_time);
                 }
-                Self::try_from_pyth_pull_redemption(&price, &quote, feed_id, quote_feed_id, clock)
+                Self::try_from_pyth_pull_redemption(
+                    &price,
+                    &quote,
+                    feed_id,
+                    quote_feed_id,
+                    clock,
+                    max_staleness,
+                )
+            }
+            TokenPriceOracle::StakePool {
+                stake_pool,
+                sol_feed_id,
+            } => {
+                // The stake pool account is passed in place of the quote oracle
+                let rate = load_stake_pool_rate(quote, stake_pool, token_mint)?;
+                verify_oracle_ownership(price)?;
+
+                let oracle_data = price.try_borrow_data()?;
+                let update = PriceUpdateV2::try_deserialize(&mut &oracle_data[..])?;
+                if update.price_message.publish_time < min_oracle_freshness {
+                    msg!("stale oracle: {}", update.price_message.publish_time);
+                }
+                Self::try_from_pyth_pull_stake_pool(
+                    &update,
+                    &rate,
+                    sol_feed_id,
+                    clock,
+                    max_staleness,
+                )
+            }
+        }
+    }
+
+    /// Construct from oracle accounts, expressing the price in the given numeraire.
+    ///
+    /// In the SOL numeraire, SOL has a fixed price of 1, and derivatives are priced by their
+    /// redemption rate or stake pool exchange rate, so that no SOL/USD price is read.
+    /// Tokens that cannot be quoted in SOL have no valid price. Prices that are not read
+    /// from an oracle are published at the current time, as the stake pool rate holds for
+    /// the whole epoch it was updated in.
+    pub fn try_from_oracle_accounts_in(
+        numeraire: Numeraire,
+        price: &AccountInfo,
+        quote: &Option<AccountInfo>,
+        price_oracle: &TokenPriceOracle,
+        token_mint: &Pubkey,
+        clock: &Clock,
+        limits: &OracleLimits,
+    ) -> Result<Self> {
+        if numeraire == Numeraire::Usd {
+            return Self::try_from_oracle_accounts(
+                price,
+                quote,
+                price_oracle,
+                token_mint,
+                clock,
+                limits,
+            );
+        }
+        let max_staleness = limits.max_oracle_staleness();
+        match price_oracle.sol_quote() {
+            None => {
+                msg!("the token cannot be priced in SOL");
+                err!(crate::ErrorCode::InvalidOracle)
+            }
+            Some(SolQuote::Sol) => Ok(Self::new(
+                SOL_NUMERAIRE_ONE,
+                0,
+                SOL_NUMERAIRE_ONE,
+                clock.unix_timestamp,
+                SOL_NUMERAIRE_EXPONENT,
+            )),
+            Some(SolQuote::RedemptionRate { feed_id }) => {
+                // The redemption rate oracle is already quoted in SOL
+                verify_oracle_ownership(price)?;
+                let oracle_data = price.try_borrow_data()?;
+                let update = PriceUpdateV2::try_deserialize(&mut &oracle_data[..])?;
+                Self::try_from_pyth_pull(&update, &feed_id, clock, max_staleness)
+            }
+            Some(SolQuote::StakePool { stake_pool }) => {
+                let rate = load_stake_pool_rate(quote, &stake_pool, token_mint)?;
+                let exchange_rate = stake_pool_exchange_rate(&rate, clock)?;
+                let value = exchange_rate
+                    .as_u64(SOL_NUMERAIRE_EXPONENT)
+                    .try_into()
+                    .map_err(|_| error!(crate::ErrorCode::MathOpFailed))?;
+
+                Ok(Self::new(
+                    value,
+                    0,
+                    value,
+                    clock.unix_timestamp,
+                    SOL_NUMERAIRE_EXPONENT,
+                ))
             }
         }
     }
 }
 
+/// The exponent of prices that are derived without an oracle in the SOL numeraire
+const SOL_NUMERAIRE_EXPONENT: i32 = -8;
+
+/// The price of SOL in the SOL numeraire, at [SOL_NUMERAIRE_EXPONENT]
+const SOL_NUMERAIRE_ONE: i64 = 100_000_000;
+
+/// Read the rate of a stake pool account that is passed in place of the quote oracle,
+/// checking that the pool issues the priced token.
+fn load_stake_pool_rate(
+    stake_pool_info: &Option<AccountInfo>,
+    stake_pool: &Pubkey,
+    token_mint: &Pubkey,
+) -> Result<StakePoolRate> {
+    let stake_pool_info = stake_pool_info
+        .as_ref()
+        .ok_or(crate::ErrorCode::InvalidOracle)?;
+    require_keys_eq!(
+        stake_pool_info.key(),
+        *stake_pool,
+        crate::ErrorCode::InvalidOracle
+    );
+    require_keys_eq!(
+        *stake_pool_info.owner,
+        STAKE_POOL_PROGRAM_ID,
+        crate::ErrorCode::InvalidOracle
+    );
+
+    let rate = StakePoolRate::try_from_account_data(&stake_pool_info.try_borrow_data()?)
+        .ok_or_else(|| error!(crate::ErrorCode::InvalidOracle))?;
+    require_keys_eq!(rate.pool_mint, *token_mint, crate::ErrorCode::InvalidOracle);
+
+    Ok(rate)
+}
+
+/// The SOL backing each pool token of a stake pool.
+///
+/// The stake pool must have been updated in the current epoch, so that its rate
+/// reflects the rewards earned by the pool.
+fn stake_pool_exchange_rate(rate: &StakePoolRate, clock: &Clock) -> Result<Number128> {
+    if rate.last_update_epoch != clock.epoch {
+        msg!(
+            "stake pool was last updated in epoch {}, current epoch is {}",
+            rate.last_update_epoch,
+            clock.epoch
+        );
+        return err!(crate::ErrorCode::InvalidOracle);
+    }
+    if rate.pool_token_supply == 0 {
+        msg!("stake pool has no supply");
+        return err!(crate::ErrorCode::InvalidOracle);
+    }
+
+    Ok(Number128::from_decimal(rate.total_lamports, 0)
+        / Number128::from_decimal(rate.pool_token_supply, 0))
+}
+
 /// Verify oracle ownership based on program comppile feature flags.
 ///
 /// * On mainnet, accounts must be owned by the Pyth receiver program.
@@ -332,8 +539,65 @@ pub struct AdapterAccounts<'info> {
     adapter_config: Account<'info, AdapterConfig>,
 }
 
+/// The margin instruction through which adapters are being invoked
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InvokeKind {
+    /// `adapter_invoke`, authorized by the margin account owner
+    Adapter,
+
+    /// `liquidator_invoke`, authorized by the liquidator of the margin account
+    Liquidator,
+
+    /// `accounting_invoke`, which is permissionless
+    Accounting,
+
+    /// `execute_trigger_order`, authorized by a trigger order of the margin account owner
+    Order,
+}
+
+impl InvokeKind {
+    /// Whether the margin account signs for the invocation
+    pub fn signed(&self) -> bool {
+        match self {
+            InvokeKind::Adapter | InvokeKind::Liquidator | InvokeKind::Order => true,
+            InvokeKind::Accounting => false,
+        }
+    }
+
+    /// Check that the adapter config permits invoking the instruction through this kind
+    /// of invocation.
+    pub fn verify_permitted(&self, config: &AdapterConfig, data: &[u8]) -> Result<()> {
+        let required_flag = match self {
+            InvokeKind::Adapter => AdapterInvokeFlags::empty(),
+            InvokeKind::Liquidator => AdapterInvokeFlags::LIQUIDATOR_INVOKE,
+            InvokeKind::Accounting => AdapterInvokeFlags::ACCOUNTING_INVOKE,
+            InvokeKind::Order => AdapterInvokeFlags::ORDER_INVOKE,
+        };
+
+        if !config.invoke_flags.contains(required_flag) {
+            msg!(
+                "adapter {} cannot be invoked through {:?}",
+                config.adapter_program,
+                self
+            );
+            return err!(ErrorCode::AdapterInvokeNotPermitted);
+        }
+
+        if !config.is_instruction_allowed(data) {
+            msg!(
+                "instruction is not allowed for adapter {}",
+                config.adapter_program
+            );
+            return err!(ErrorCode::AdapterInstructionNotAllowed);
+        }
+
+        Ok(())
+    }
+}
+
 /// Invoke a margin adapter with the requested data
-/// * `signed` - sign with the margin account
+/// * `kind` - the instruction invoking the adapter, which determines if the
+///   margin account signs and which adapter permissions are required
 ///
 /// accounts structure:
 ///
@@ -352,7 +616,7 @@ pub fn invoke_many<'info>(
     margin_account: &AccountLoader<'info, MarginAccount>,
     accounts: &'info [AccountInfo<'info>],
     data: Vec<IxData>,
-    signed: bool,
+    kind: InvokeKind,
 ) -> Result<Vec<TokenBalanceChange>> {
     let mut token_changes = vec![];
     let mut account_ix = 0;
@@ -366,7 +630,7 @@ pub fn invoke_many<'info>(
         let mut reallocs = Default::default();
         let adapter_accounts = &accounts[account_ix..(account_ix + 2)];
         account_ix += 2;
-        let adapter_accounts = AdapterAccounts::try_accounts(
+        let mut adapter_accounts = AdapterAccounts::try_accounts(
             &crate::ID,
             &mut &adapter_accounts[..],
             &[],
@@ -378,18 +642,32 @@ pub fn invoke_many<'info>(
             adapter_accounts.adapter_config.airspace == margin_account.load()?.airspace,
             ErrorCode::WrongAirspace
         );
+        // Check the adapter may be invoked this way
+        kind.verify_permitted(&adapter_accounts.adapter_config, &data)?;
+
         let remaining_accounts = &accounts[account_ix..(account_ix + num_accounts as usize)];
         account_ix += num_accounts as usize;
 
-        token_changes.extend_from_slice(&invoke(
+        let outflow_snapshot = OutflowSnapshot::new(&*margin_account.load()?);
+        let invoke_changes = invoke(
             &InvokeAdapter {
                 margin_account,
                 adapter_program: &adapter_accounts.adapter_program,
                 accounts: remaining_accounts,
-                signed,
+                signed: kind.signed(),
             },
             data,
-        )?);
+        )?;
+
+        if matches!(kind, InvokeKind::Adapter | InvokeKind::Order) {
+            verify_outflow_limits(
+                &mut adapter_accounts.adapter_config,
+                &*margin_account.load()?,
+                &outflow_snapshot,
+            )?;
+        }
+
+        token_changes.extend_from_slice(&invoke_changes);
 
         if adapter_accounts.adapter_program.key() == JUPITER_V6 {
             emit!(events::JupiterSwap {
@@ -402,6 +680,286 @@ pub fn invoke_many<'info>(
     Ok(token_changes)
 }
 
+/// The adapter program, accounts and data of each instruction of an invocation
+pub fn invoked_instructions<'a, T: Key>(
+    accounts: &'a [T],
+    data: &'a [IxData],
+) -> Vec<(Pubkey, &'a [T], &'a IxData)> {
+    let mut account_ix = 0;
+    data.iter()
+        .filter_map(|ix| {
+            let start = account_ix;
+            account_ix += ix.num_accounts as usize + 2;
+            let adapter = accounts.get(start)?.key();
+            let ix_accounts = accounts.get(start + 2..account_ix)?;
+            Some((adapter, ix_accounts, ix))
+        })
+        .collect()
+}
+
+/// Split the accounts of an invocation from any trailing accounts that are not passed
+/// to the adapters, such as the token config of an isolated collateral.
+pub fn split_invoke_accounts<'a, T>(accounts: &'a [T], data: &[IxData]) -> (&'a [T], &'a [T]) {
+    let invoke_accounts: usize = data.iter().map(|v| v.num_accounts as usize + 2).sum();
+    accounts.split_at(invoke_accounts.min(accounts.len()))
+}
+
+/// The claims and isolated collateral of a margin account before its balances change.
+///
+/// Changes in debt are measured at the prices in the snapshot, so that a price refreshed
+/// while the balances change is not recorded as debt being taken on or repaid.
+pub struct DebtSnapshot {
+    isolated_mint: Option<Pubkey>,
+    claims: Vec<ClaimSnapshot>,
+}
+
+struct ClaimSnapshot {
+    token: Pubkey,
+    balance: u64,
+    exponent: i16,
+    price: PriceInfo,
+}
+
+impl ClaimSnapshot {
+    fn value_of(&self, balance: u64) -> Number128 {
+        Number128::from_decimal(balance, self.exponent)
+            * Number128::from_decimal(self.price.value, self.price.exponent)
+    }
+}
+
+impl DebtSnapshot {
+    pub fn new(account: &MarginAccount) -> Self {
+        Self {
+            isolated_mint: account.isolated_position().map(|p| p.token),
+            claims: account
+                .positions()
+                .filter(|p| p.kind() == TokenKind::Claim)
+                .map(|p| ClaimSnapshot {
+                    token: p.token,
+                    balance: p.balance,
+                    exponent: p.exponent,
+                    price: p.price,
+                })
+                .collect(),
+        }
+    }
+
+    /// The value of the debt borrowed and repaid by the account since the snapshot.
+    ///
+    /// An account that newly holds an isolated collateral borrows all of its claims
+    /// against it.
+    pub(crate) fn debt_change(
+        &self,
+        account: &MarginAccount,
+        newly_isolated: bool,
+    ) -> (Number128, Number128) {
+        let mut borrowed = Number128::ZERO;
+        let mut repaid = Number128::ZERO;
+
+        for position in account.positions().filter(|p| p.kind() == TokenKind::Claim) {
+            match self.claims.iter().find(|c| c.token == position.token) {
+                Some(claim) => {
+                    let start_balance = if newly_isolated { 0 } else { claim.balance };
+                    if position.balance > start_balance {
+                        borrowed += claim.value_of(position.balance - start_balance);
+                    } else {
+                        repaid += claim.value_of(start_balance - position.balance);
+                    }
+                }
+                // A claim registered since the snapshot has no price in it
+                None => borrowed += position.value(),
+            }
+        }
+
+        // Claims that were closed since the snapshot have been repaid
+        if !newly_isolated {
+            for claim in &self.claims {
+                if account.get_position(&claim.token).is_none() {
+                    repaid += claim.value_of(claim.balance);
+                }
+            }
+        }
+
+        (borrowed, repaid)
+    }
+}
+
+/// Record the change in debt of a margin account against its isolated collateral, if any.
+///
+/// The token config of the isolated collateral has to be provided whenever the debt
+/// changes, so that borrowing is checked against the debt ceiling and repayments free it
+/// up again. This includes the existing claims of an account that newly holds the isolated
+/// collateral.
+pub fn record_isolated_debt<'info>(
+    margin_account: &AccountLoader<'info, MarginAccount>,
+    snapshot: &DebtSnapshot,
+    accounts: &'info [AccountInfo<'info>],
+) -> Result<()> {
+    let account = margin_account.load()?;
+    let end_isolated_mint = account.isolated_position().map(|p| p.token);
+    let mint = match end_isolated_mint.or(snapshot.isolated_mint) {
+        Some(mint) => mint,
+        None => return Ok(()),
+    };
+    let newly_isolated = end_isolated_mint.is_some() && end_isolated_mint != snapshot.isolated_mint;
+    let (borrowed, repaid) = snapshot.debt_change(&account, newly_isolated);
+    if borrowed == repaid {
+        return Ok(());
+    }
+    // Debt ceilings are in USD, so other numeraires never record isolated debt
+    if account.numeraire() != Numeraire::Usd {
+        if borrowed > repaid {
+            msg!("isolated debt in USD cannot be borrowed in another numeraire");
+            return err!(ErrorCode::UsdLimitInNumeraire);
+        }
+        return Ok(());
+    }
+
+    let config = accounts.iter().find_map(|info| {
+        Account::<TokenConfig>::try_from(info)
+            .ok()
+            .filter(|config| config.mint == mint && config.airspace == account.airspace)
+    });
+    let mut config = match config {
+        Some(config) => config,
+        None => {
+            msg!(
+                "the token config of isolated collateral {} is required",
+                mint
+            );
+            return err!(ErrorCode::IsolationConfigMissing);
+        }
+    };
+
+    config.isolation.record_debt_change(borrowed, repaid)?;
+    config.exit(&crate::ID)?;
+
+    emit!(events::IsolatedDebtUpdated {
+        margin_account: margin_account.key(),
+        mint,
+        isolated_debt: config.isolation.isolated_debt,
+    });
+
+    Ok(())
+}
+
+/// The positions of a margin account before an adapter is invoked, to value the tokens
+/// that left the account during the invocation.
+///
+/// The balances of the positions are compared after the invocation, so the outflow is
+/// measured for any adapter, whether or not it reports its token changes.
+struct OutflowSnapshot {
+    positions: Vec<OutflowPosition>,
+}
+
+struct OutflowPosition {
+    token: Pubkey,
+    kind: TokenKind,
+    balance: u64,
+    exponent: i16,
+    price: PriceInfo,
+}
+
+impl OutflowSnapshot {
+    fn new(account: &MarginAccount) -> Self {
+        Self {
+            positions: account.positions().map(OutflowPosition::new).collect(),
+        }
+    }
+}
+
+impl OutflowPosition {
+    fn new(position: &AccountPosition) -> Self {
+        Self {
+            token: position.token,
+            kind: position.kind(),
+            balance: position.balance,
+            exponent: position.exponent,
+            price: position.price,
+        }
+    }
+}
+
+/// Check the value that left the margin account during an adapter invocation against
+/// the adapter's outflow limits, recording it in the adapter's current outflow window.
+fn verify_outflow_limits(
+    adapter_config: &mut Account<AdapterConfig>,
+    margin_account: &MarginAccount,
+    snapshot: &OutflowSnapshot,
+) -> Result<()> {
+    if !adapter_config.outflow_limits.is_enabled() {
+        return Ok(());
+    }
+    if margin_account.numeraire() != Numeraire::Usd {
+        msg!("outflow limits in USD cannot be applied in another numeraire");
+        return err!(ErrorCode::UsdLimitInNumeraire);
+    }
+
+    let outflow = outflow_value(margin_account, snapshot)?;
+    adapter_config.verify_invoke_outflow(outflow)?;
+
+    if adapter_config.outflow_limits.has_window() {
+        // The window is tracked on the adapter config, so it has to be saved
+        require!(
+            adapter_config.to_account_info().is_writable,
+            anchor_lang::error::ErrorCode::ConstraintMut
+        );
+        adapter_config.record_window_outflow(outflow, sys().unix_timestamp())?;
+        adapter_config.exit(&crate::ID)?;
+    }
+
+    Ok(())
+}
+
+/// The net USD value that left a margin account since a snapshot of its positions,
+/// measured at the prices in the snapshot.
+///
+/// Value leaves the account when its collateral decreases or its claims increase. Such
+/// changes of positions without a valid price can't be valued and are rejected, while
+/// changes that add value to the account are not counted if they can't be valued.
+fn outflow_value(margin_account: &MarginAccount, snapshot: &OutflowSnapshot) -> Result<Number128> {
+    let mut outflow = Number128::ZERO;
+
+    // Positions registered during the invocation had no balance before it
+    let registered = margin_account
+        .positions()
+        .filter(|p| !snapshot.positions.iter().any(|s| s.token == p.token))
+        .map(|p| OutflowPosition {
+            balance: 0,
+            ..OutflowPosition::new(p)
+        })
+        .collect::<Vec<_>>();
+
+    for start in snapshot.positions.iter().chain(&registered) {
+        let end_balance = margin_account
+            .get_position(&start.token)
+            .map(|p| p.balance)
+            .unwrap_or_default();
+        let (tokens, decreased) = match end_balance.cmp(&start.balance) {
+            std::cmp::Ordering::Equal => continue,
+            std::cmp::Ordering::Less => (start.balance - end_balance, true),
+            std::cmp::Ordering::Greater => (end_balance - start.balance, false),
+        };
+        let is_outflow = decreased != (start.kind == TokenKind::Claim);
+
+        match (is_outflow, start.price.to_number128()) {
+            (true, Ok(price)) => {
+                outflow += Number128::from_decimal(tokens, start.exponent) * price;
+            }
+            (true, Err(_)) => {
+                msg!("cannot value the outflow of token {}", start.token);
+                return err!(ErrorCode::AdapterOutflowLimitExceeded);
+            }
+            (false, Ok(price)) => {
+                outflow -= Number128::from_decimal(tokens, start.exponent) * price;
+            }
+            (false, Err(_)) => (),
+        }
+    }
+
+    Ok(outflow.max(Number128::ZERO))
+}
+
 /// Invoke a margin adapter with the requested data
 /// * `signed` - sign with the margin account
 pub fn invoke<'b, 'c: 'info, 'info>(
@@ -625,7 +1183,10 @@ fn apply_changes<'b, 'c: 'info, 'info>(
         match change {
             PositionChange::Price(px) => {
                 if let Some(pos) = position {
-                    pos.set_price(&px.to_price_info(sys().unix_timestamp() as UnixTimestamp))?;
+                    let limits = pos.oracle_limits();
+                    pos.set_price(
+                        &px.to_price_info(sys().unix_timestamp() as UnixTimestamp, &limits),
+                    )?;
                 }
             }
             PositionChange::Flags(flags, true) => position.require_mut()?.flags |= flags,
@@ -850,6 +1411,13 @@ mod test {
                     value_modifier: 100,
                     max_staleness: 40,
                     token_features: TokenFeatures::empty(),
+                    initial_value_modifier: 0,
+                    oracle_limits: Default::default(),
+                    value_modifier_ramp: Default::default(),
+                    isolation: Default::default(),
+                    max_collateral_share: 0,
+                    concentration_group: 0,
+                    size_haircut_threshold: 0,
                 },
                 &[
                     Approver::MarginAccountAuthority,
@@ -874,6 +1442,13 @@ mod test {
                     value_modifier: 100,
                     max_staleness: 40,
                     token_features: TokenFeatures::empty(),
+                    initial_value_modifier: 0,
+                    oracle_limits: Default::default(),
+                    value_modifier_ramp: Default::default(),
+                    isolation: Default::default(),
+                    max_collateral_share: 0,
+                    concentration_group: 0,
+                    size_haircut_threshold: 0,
                 },
                 &[
                     Approver::MarginAccountAuthority,
@@ -969,6 +1544,13 @@ mod test {
                     value_modifier: 100,
                     max_staleness: 40,
                     token_features: TokenFeatures::empty(),
+                    initial_value_modifier: 0,
+                    oracle_limits: Default::default(),
+                    value_modifier_ramp: Default::default(),
+                    isolation: Default::default(),
+                    max_collateral_share: 0,
+                    concentration_group: 0,
+                    size_haircut_threshold: 0,
                 },
                 &[
                     Approver::MarginAccountAuthority,
@@ -1,4 +1,8 @@
-use crate::{AccountFeatureFlags, Liquidation, Permissions, TokenConfigUpdate, Valuation};
+use crate::{
+    AccountFeatureFlags, AdapterOutflowLimits, AdapterPermissionsUpdate, DeleverageOrder,
+    IsolationConfig, Liquidation, LiquidationFee, Permissions, TokenConfigUpdate, TriggerOrder,
+    Valuation, ValueModifierRamp,
+};
 use anchor_lang::prelude::*;
 
 #[event]
@@ -60,8 +64,30 @@ pub struct LiquidatorInvokeBegin {
 pub struct LiquidatorInvokeEnd {
     pub liquidation_data: Liquidation,
     pub valuation_summary: ValuationSummary,
-    pub accrued_liquidation_fee_amount: u64,
-    pub liquidation_fee_mint: Pubkey,
+    /// The fees accrued by this invoke in each repaid mint, including the
+    /// protocol's share of each fee
+    pub liquidation_fees: Vec<LiquidationFee>,
+}
+
+#[event]
+pub struct LiquidationFeeTreasuryClaimed {
+    pub airspace: Pubkey,
+    pub mint: Pubkey,
+    pub destination: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+pub struct LiquidatorSeize {
+    pub margin_account: Pubkey,
+    pub liquidator: Pubkey,
+    pub liquidator_account: Pubkey,
+    pub repay_mint: Pubkey,
+    pub repaid_tokens: u64,
+    pub seize_mint: Pubkey,
+    pub seized_tokens: u64,
+    pub liquidation_data: Liquidation,
+    pub valuation_summary: ValuationSummary,
 }
 
 #[event]
@@ -87,6 +113,27 @@ pub struct TokenConfigured {
     pub mint: Pubkey,
 }
 
+#[event]
+pub struct TokenRiskConfigured {
+    pub airspace: Pubkey,
+    pub mint: Pubkey,
+    pub value_modifier: u16,
+    pub initial_value_modifier: u16,
+    pub value_modifier_ramp: ValueModifierRamp,
+    pub isolation: IsolationConfig,
+    pub max_collateral_share: u8,
+    pub concentration_group: u8,
+    pub size_haircut_threshold: u16,
+    pub liquidation_bonus_bps: u16,
+}
+
+#[event]
+pub struct IsolatedDebtUpdated {
+    pub margin_account: Pubkey,
+    pub mint: Pubkey,
+    pub isolated_debt: u64,
+}
+
 #[event]
 pub struct AdapterConfigured {
     pub airspace: Pubkey,
@@ -94,6 +141,20 @@ pub struct AdapterConfigured {
     pub is_adapter: bool,
 }
 
+#[event]
+pub struct AdapterPermissionsConfigured {
+    pub airspace: Pubkey,
+    pub adapter_program: Pubkey,
+    pub update: AdapterPermissionsUpdate,
+}
+
+#[event]
+pub struct AdapterOutflowLimitsConfigured {
+    pub airspace: Pubkey,
+    pub adapter_program: Pubkey,
+    pub limits: AdapterOutflowLimits,
+}
+
 #[event]
 pub struct PermitConfigured {
     pub airspace: Pubkey,
@@ -107,6 +168,83 @@ pub struct JupiterSwap {
     pub adapter_program: Pubkey,
 }
 
+#[event]
+pub struct TriggerOrderCreated {
+    pub margin_account: Pubkey,
+    pub order: Pubkey,
+    pub order_state: TriggerOrder,
+}
+
+#[event]
+pub struct TriggerOrderCancelled {
+    pub margin_account: Pubkey,
+    pub order: Pubkey,
+}
+
+#[event]
+pub struct TriggerOrderExecuted {
+    pub margin_account: Pubkey,
+    pub order: Pubkey,
+    pub keeper: Pubkey,
+    pub price: i128,
+    pub sold: u64,
+    pub received: u64,
+    pub keeper_fee: u64,
+}
+
+#[event]
+pub struct DeleverageOrderCreated {
+    pub margin_account: Pubkey,
+    pub order: Pubkey,
+    pub order_state: DeleverageOrder,
+}
+
+#[event]
+pub struct DeleverageOrderCancelled {
+    pub margin_account: Pubkey,
+    pub order: Pubkey,
+}
+
+#[event]
+pub struct DeleverageOrderExecuted {
+    pub margin_account: Pubkey,
+    pub order: Pubkey,
+    pub keeper: Pubkey,
+    pub start_c_ratio: i128,
+    pub end_c_ratio: i128,
+    pub repaid: i128,
+    pub sold: u64,
+    pub keeper_fee: u64,
+}
+
+#[event]
+pub struct CreditLineOpened {
+    pub credit_line: Pubkey,
+    pub lender: Pubkey,
+    pub borrower: Pubkey,
+    pub limit: u64,
+}
+
+#[event]
+pub struct CreditLineLimitSet {
+    pub credit_line: Pubkey,
+    pub limit: u64,
+}
+
+#[event]
+pub struct CreditLineClosed {
+    pub credit_line: Pubkey,
+}
+
+#[event]
+pub struct CreditLineSeized {
+    pub credit_line: Pubkey,
+    pub liquidator: Pubkey,
+    pub seize_mint: Pubkey,
+    pub seized_tokens: u64,
+    pub limit: u64,
+}
+
 #[derive(AnchorDeserialize, AnchorSerialize)]
 pub struct ValuationSummary {
     pub equity: i128,
@@ -17,13 +17,23 @@
 
 mod accounting_invoke;
 mod adapter_invoke;
+mod cancel_deleverage_order;
+mod cancel_trigger_order;
+mod claim_liquidation_fee_treasury;
 mod close_account;
 mod close_position;
 mod collect_liquidation_fee;
 mod create_account;
+mod create_deleverage_order;
+mod create_trigger_order;
+mod end_timed_out_liquidations;
+mod execute_deleverage_order;
+mod execute_trigger_order;
+mod get_valuation;
 mod liquidate_begin;
 mod liquidate_end;
 mod liquidator_invoke;
+mod liquidator_seize;
 mod register_position;
 mod update_position_balance;
 mod verify_healthy;
@@ -31,18 +41,29 @@ mod verify_unhealthy;
 
 mod admin;
 mod configure;
+mod credit_line;
 mod lookup_tables;
 mod positions;
 
 pub use accounting_invoke::*;
 pub use adapter_invoke::*;
+pub use cancel_deleverage_order::*;
+pub use cancel_trigger_order::*;
+pub use claim_liquidation_fee_treasury::*;
 pub use close_account::*;
 pub use close_position::*;
 pub use collect_liquidation_fee::*;
 pub use create_account::*;
+pub use create_deleverage_order::*;
+pub use create_trigger_order::*;
+pub use end_timed_out_liquidations::*;
+pub use execute_deleverage_order::*;
+pub use execute_trigger_order::*;
+pub use get_valuation::*;
 pub use liquidate_begin::*;
 pub use liquidate_end::*;
 pub use liquidator_invoke::*;
+pub use liquidator_seize::*;
 pub use register_position::*;
 pub use update_position_balance::*;
 pub use verify_healthy::*;
@@ -50,6 +71,7 @@ pub use verify_unhealthy::*;
 
 pub use admin::*;
 pub use configure::*;
+pub use credit_line::*;
 // removed re-export: configure_delegate::*
 pub use lookup_tables::*;
 pub use positions::*;
@@ -17,7 +17,7 @@
 
 use anchor_lang::prelude::*;
 
-use crate::adapter::{self, IxData};
+use crate::adapter::{self, InvokeKind, IxData};
 use crate::{events, MarginAccount};
 
 #[derive(Accounts)]
@@ -40,7 +40,7 @@ pub fn accounting_invoke_handler<'a, 'b, 'c: 'info, 'info>(
         &ctx.accounts.margin_account,
         ctx.remaining_accounts,
         instructions,
-        false,
+        InvokeKind::Accounting,
     )?;
 
     emit!(events::AccountingInvokeEnd {});
@@ -17,7 +17,7 @@
 
 use anchor_lang::prelude::*;
 
-use crate::adapter::{self, IxData};
+use crate::adapter::{self, InvokeKind, IxData};
 use crate::syscall::{sys, Sys};
 use crate::{events, ErrorCode, MarginAccount};
 
@@ -40,26 +40,63 @@ pub fn adapter_invoke_handler<'a, 'b, 'c: 'info, 'info>(
         return Err(ErrorCode::Liquidating.into());
     }
 
+    // The initial margin only has to be met if the invocation increases the account's risk,
+    // so that an account below its initial margin can still reduce its exposure.
+    let start_valuation = ctx
+        .accounts
+        .margin_account
+        .load()?
+        .valuation(sys().unix_timestamp())
+        .ok();
+    let start_shortfall = start_valuation
+        .as_ref()
+        .map(|valuation| valuation.initial_margin_shortfall());
+
+    // Debt backed by an isolated collateral is tracked against its debt ceiling
+    let debt_snapshot = adapter::DebtSnapshot::new(&*ctx.accounts.margin_account.load()?);
+    let (invoke_accounts, isolation_accounts) =
+        adapter::split_invoke_accounts(ctx.remaining_accounts, &instructions);
+
     emit!(events::AdapterInvokeBegin {
         margin_account: ctx.accounts.margin_account.key(),
     });
 
     adapter::invoke_many(
         &ctx.accounts.margin_account,
-        ctx.remaining_accounts,
+        invoke_accounts,
         instructions,
-        true,
+        InvokeKind::Adapter,
     )?;
 
     emit!(events::AdapterInvokeEnd {});
 
+    adapter::record_isolated_debt(
+        &ctx.accounts.margin_account,
+        &debt_snapshot,
+        isolation_accounts,
+    )?;
+
     let margin_account = &mut ctx.accounts.margin_account.load_mut()?;
 
-    margin_account
-        .valuation(sys().unix_timestamp())?
-        .verify_healthy()?;
+    let valuation = margin_account.valuation(sys().unix_timestamp())?;
+
+    match start_valuation.filter(|start| start.is_undercollateralized()) {
+        // The owner of an unhealthy account may deleverage it without a liquidator, as long
+        // as it loses no more equity for the repaid debt than a liquidation could, and
+        // without paying a fee.
+        Some(start) => valuation.verify_deleveraged(&start)?,
+        None => {
+            valuation.verify_healthy()?;
+
+            let end_shortfall = valuation.initial_margin_shortfall();
+            if !matches!(start_shortfall, Some(start) if end_shortfall <= start) {
+                valuation.verify_initial_margin()?;
+            }
+        }
+    }
 
     margin_account.assert_position_feature_violation()?;
+    margin_account.assert_isolation_mode()?;
 
     Ok(())
 }
@@ -0,0 +1,48 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+//
+// Copyright (C) 2024 A1 XYZ, INC.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use anchor_lang::prelude::*;
+
+use crate::{events, DeleverageOrder, MarginAccount};
+
+#[derive(Accounts)]
+pub struct CancelDeleverageOrder<'info> {
+    /// The owner of the margin account
+    pub owner: Signer<'info>,
+
+    /// The account to receive the rent of the order
+    /// CHECK:
+    #[account(mut)]
+    pub receiver: AccountInfo<'info>,
+
+    /// The margin account the order is executed for
+    #[account(has_one = owner)]
+    pub margin_account: AccountLoader<'info, MarginAccount>,
+
+    /// The order to cancel
+    #[account(mut, has_one = margin_account, close = receiver)]
+    pub order: Account<'info, DeleverageOrder>,
+}
+
+pub fn cancel_deleverage_order_handler(ctx: Context<CancelDeleverageOrder>) -> Result<()> {
+    emit!(events::DeleverageOrderCancelled {
+        margin_account: ctx.accounts.margin_account.key(),
+        order: ctx.accounts.order.key(),
+    });
+
+    Ok(())
+}
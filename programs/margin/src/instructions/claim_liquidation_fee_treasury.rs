@@ -0,0 +1,97 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+//
+// Copyright (C) 2024 A1 XYZ, INC.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use anchor_lang::prelude::*;
+
+use anchor_spl::token_interface::{self, Mint, TokenAccount, TokenInterface};
+use glow_airspace::state::Airspace;
+
+use crate::{events, seeds::LIQUIDATION_FEE_TREASURY_SEED};
+
+#[derive(Accounts)]
+pub struct ClaimLiquidationFeeTreasury<'info> {
+    /// The airspace authority
+    pub authority: Signer<'info>,
+
+    /// The airspace whose treasury is claimed
+    #[account(has_one = authority)]
+    pub airspace: Account<'info, Airspace>,
+
+    /// The treasury receiving the protocol's share of liquidation fees in the airspace
+    ///
+    /// CHECK: The account is the PDA owning the treasury's token accounts
+    #[account(
+        seeds = [
+            LIQUIDATION_FEE_TREASURY_SEED,
+            airspace.key().as_ref()
+        ],
+        bump,
+    )]
+    pub treasury: AccountInfo<'info>,
+
+    /// The treasury's token account to claim from
+    #[account(
+        mut,
+        token::mint = mint,
+        token::authority = treasury,
+        token::token_program = token_program,
+    )]
+    pub treasury_token: InterfaceAccount<'info, TokenAccount>,
+
+    /// The token account receiving the claimed tokens
+    #[account(mut, token::mint = mint, token::token_program = token_program)]
+    pub destination: InterfaceAccount<'info, TokenAccount>,
+
+    pub mint: InterfaceAccount<'info, Mint>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+pub fn claim_liquidation_fee_treasury_handler(
+    ctx: Context<ClaimLiquidationFeeTreasury>,
+    amount: u64,
+) -> Result<()> {
+    let airspace = ctx.accounts.airspace.key();
+
+    token_interface::transfer_checked(
+        CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            token_interface::TransferChecked {
+                from: ctx.accounts.treasury_token.to_account_info(),
+                mint: ctx.accounts.mint.to_account_info(),
+                to: ctx.accounts.destination.to_account_info(),
+                authority: ctx.accounts.treasury.to_account_info(),
+            },
+        )
+        .with_signer(&[&[
+            LIQUIDATION_FEE_TREASURY_SEED,
+            airspace.as_ref(),
+            &[ctx.bumps.treasury],
+        ]]),
+        amount,
+        ctx.accounts.mint.decimals,
+    )?;
+
+    emit!(events::LiquidationFeeTreasuryClaimed {
+        airspace,
+        mint: ctx.accounts.mint.key(),
+        destination: ctx.accounts.destination.key(),
+        amount,
+    });
+
+    Ok(())
+}
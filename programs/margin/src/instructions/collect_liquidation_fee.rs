@@ -21,10 +21,16 @@ use anchor_spl::token_interface::{self, Mint, TokenAccount, TokenInterface};
 use glow_program_common::{traits::SafeSub, Number128};
 
 use crate::{
+    seeds::LIQUIDATION_FEE_TREASURY_SEED,
     syscall::{sys, Sys},
-    LiquidationState, MarginAccount, PriceChangeInfo, SignerSeeds, TokenAdmin, TokenConfig,
+    ErrorCode, Liquidation, LiquidationFee, LiquidationState, MarginAccount, PriceChangeInfo,
+    SignerSeeds, TokenAdmin, TokenConfig,
 };
 
+/// The number of remaining accounts to pass for each collected fee, in the order
+/// of the fields of [FeeCollectionAccounts]
+pub const FEE_COLLECTION_ACCOUNTS: usize = 8;
+
 #[derive(Accounts)]
 pub struct CollectLiquidationFee<'info> {
     /// The account in need of liquidation
@@ -46,82 +52,220 @@ pub struct CollectLiquidationFee<'info> {
         bump,
     )]
     pub liquidation: AccountLoader<'info, LiquidationState>,
+    // The accounts of each collected fee are passed as remaining accounts,
+    // see [FeeCollectionAccounts]
+}
 
-    /// The liquidator's token account
-    #[account(
-        mut,
-        token::mint = liquidation_fee_mint,
-        token::authority = liquidator,
-        token::token_program = liquidator_fee_token_program,
-    )]
-    pub liquidator_fee_token: Box<InterfaceAccount<'info, TokenAccount>>,
-
-    #[account(
-        mut,
-        token::mint = liquidation_fee_mint,
-        token::authority = margin_account,
-        token::token_program = liquidator_fee_token_program,
-    )]
-    pub margin_account_fee_source: Box<InterfaceAccount<'info, TokenAccount>>,
-
-    pub liquidation_fee_mint: Box<InterfaceAccount<'info, Mint>>,
-
-    pub liquidator_fee_token_program: Interface<'info, TokenInterface>,
+/// The accounts needed to collect the fee accrued in one mint
+struct FeeCollectionAccounts<'info> {
+    liquidation_fee_mint: InterfaceAccount<'info, Mint>,
 
-    #[account(
-        constraint = token_config.airspace == margin_account.load()?.airspace,
-        constraint = token_config.mint == liquidation_fee_mint.key(),
-    )]
-    pub token_config: Box<Account<'info, TokenConfig>>,
+    /// The token config of the mint in the airspace of the margin account
+    token_config: Account<'info, TokenConfig>,
 
     /// The oracle for the token. If the oracle is a redemption rate, it should be the redemption oracle.
     /// If the oracle is not a redemption rate, it should be the price oracle.
-    /// CHECK: We verify this account against the pyth pull receiver program
-    pub price_oracle: AccountInfo<'info>,
+    price_oracle: AccountInfo<'info>,
 
     /// An optional oracle price account for the quote token, if the position uses a redemption rate.
-    /// CHECK: We verify this account against the pyth pull receiver program
-    pub redemption_quote_oracle: Option<AccountInfo<'info>>,
+    /// The margin program id is passed in its place if there is none.
+    redemption_quote_oracle: Option<AccountInfo<'info>>,
+
+    /// The margin account's token account to take the fee from
+    margin_account_fee_source: InterfaceAccount<'info, TokenAccount>,
+
+    /// The liquidator's token account
+    liquidator_fee_token: InterfaceAccount<'info, TokenAccount>,
+
+    /// The token account of the airspace's liquidation fee treasury, receiving the
+    /// protocol's share of the fee. The margin program id is passed in its place if
+    /// the liquidation does not share its fees with the protocol.
+    treasury_fee_token: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    liquidator_fee_token_program: Interface<'info, TokenInterface>,
+}
+
+impl<'info> FeeCollectionAccounts<'info> {
+    fn try_from_accounts(
+        accounts: &'info [AccountInfo<'info>],
+        margin_account: &AccountLoader<'info, MarginAccount>,
+        liquidator: &Signer<'info>,
+        treasury: &Pubkey,
+    ) -> Result<Self> {
+        let [mint, config, oracle, quote_oracle, source, destination, treasury_token, program] =
+            accounts
+        else {
+            return err!(anchor_lang::error::ErrorCode::AccountNotEnoughKeys);
+        };
+        let liquidation_fee_mint = InterfaceAccount::<Mint>::try_from(mint)?;
+        let token_config = Account::<TokenConfig>::try_from(config)?;
+        let margin_account_fee_source = InterfaceAccount::<TokenAccount>::try_from(source)?;
+        let liquidator_fee_token = InterfaceAccount::<TokenAccount>::try_from(destination)?;
+        let treasury_fee_token = (treasury_token.key() != crate::ID)
+            .then(|| InterfaceAccount::<TokenAccount>::try_from(treasury_token))
+            .transpose()?;
+        let liquidator_fee_token_program = Interface::<TokenInterface>::try_from(program)?;
+
+        require_keys_eq!(
+            token_config.airspace,
+            margin_account.load()?.airspace,
+            ErrorCode::WrongAirspace
+        );
+        require_keys_eq!(
+            token_config.mint,
+            mint.key(),
+            ErrorCode::InvalidLiquidationFeeMint
+        );
+        require_keys_eq!(
+            *mint.owner,
+            program.key(),
+            anchor_lang::error::ErrorCode::ConstraintMintTokenProgram
+        );
+        require_keys_eq!(
+            margin_account_fee_source.mint,
+            mint.key(),
+            anchor_lang::error::ErrorCode::ConstraintTokenMint
+        );
+        require_keys_eq!(
+            margin_account_fee_source.owner,
+            margin_account.key(),
+            anchor_lang::error::ErrorCode::ConstraintTokenOwner
+        );
+        require_keys_eq!(
+            liquidator_fee_token.mint,
+            mint.key(),
+            anchor_lang::error::ErrorCode::ConstraintTokenMint
+        );
+        require_keys_eq!(
+            liquidator_fee_token.owner,
+            liquidator.key(),
+            anchor_lang::error::ErrorCode::ConstraintTokenOwner
+        );
+        if let Some(treasury_fee_token) = &treasury_fee_token {
+            require_keys_eq!(
+                treasury_fee_token.mint,
+                mint.key(),
+                anchor_lang::error::ErrorCode::ConstraintTokenMint
+            );
+            require_keys_eq!(
+                treasury_fee_token.owner,
+                *treasury,
+                anchor_lang::error::ErrorCode::ConstraintTokenOwner
+            );
+        }
+
+        Ok(Self {
+            liquidation_fee_mint,
+            token_config,
+            price_oracle: oracle.clone(),
+            redemption_quote_oracle: (quote_oracle.key() != crate::ID)
+                .then(|| quote_oracle.clone()),
+            margin_account_fee_source,
+            liquidator_fee_token,
+            treasury_fee_token,
+            liquidator_fee_token_program,
+        })
+    }
 }
 
-pub fn collect_liquidation_fee_handler(ctx: Context<CollectLiquidationFee>) -> Result<()> {
+pub fn collect_liquidation_fee_handler<'a, 'b, 'c: 'info, 'info>(
+    ctx: Context<'a, 'b, 'c, 'info, CollectLiquidationFee<'info>>,
+) -> Result<()> {
     // SECURITY: Oracle ownership is validated with [verify_oracle_ownership] when constructing the price.
 
     let margin_account = &ctx.accounts.margin_account;
+    let remaining_accounts: &'c [AccountInfo<'info>] = ctx.remaining_accounts;
+    if remaining_accounts.len() % FEE_COLLECTION_ACCOUNTS != 0 {
+        return err!(anchor_lang::error::ErrorCode::AccountNotEnoughKeys);
+    }
+
+    let (treasury, _) = Pubkey::find_program_address(
+        &[
+            LIQUIDATION_FEE_TREASURY_SEED,
+            margin_account.load()?.airspace.as_ref(),
+        ],
+        &crate::ID,
+    );
+
+    let mut fees = LiquidationState::read_fees(
+        &ctx.accounts
+            .liquidation
+            .to_account_info()
+            .try_borrow_data()?,
+    )?;
+    {
+        let liquidation = &mut ctx.accounts.liquidation.load_mut()?.state;
+        // Update liquidation state
+        liquidation.is_collecting_fees = 1;
+
+        for accounts in remaining_accounts.chunks_exact(FEE_COLLECTION_ACCOUNTS) {
+            let accounts = FeeCollectionAccounts::try_from_accounts(
+                accounts,
+                margin_account,
+                &ctx.accounts.liquidator,
+                &treasury,
+            )?;
+            // Taking the fee out of the list resets its slot
+            let fee_mint = accounts.liquidation_fee_mint.key();
+            let fee_index = fees
+                .iter()
+                .position(|fee| fee.mint == fee_mint)
+                .ok_or(ErrorCode::InvalidLiquidationFeeMint)?;
+            let fee = fees.remove(fee_index);
+
+            collect_fee(margin_account, liquidation, accounts, fee)?;
+        }
+    }
+    LiquidationState::write_fees(
+        &mut ctx
+            .accounts
+            .liquidation
+            .to_account_info()
+            .try_borrow_mut_data()?,
+        &fees,
+    )?;
+
+    margin_account
+        .load()?
+        .valuation(sys().unix_timestamp())?
+        .verify_healthy()?;
 
-    let fee_mint = ctx.accounts.liquidation_fee_mint.key();
-    let liquidation = &mut ctx.accounts.liquidation.load_mut()?.state;
-    // Update liquidation state
-    liquidation.is_collecting_fees = 1;
-    let liquidation_slot = liquidation
-        .accrued_liquidation_fees
-        .iter()
-        .find(|p| p.mint == fee_mint)
-        .ok_or(crate::ErrorCode::InvalidLiquidationFeeMint)?;
+    Ok(())
+}
 
+/// Transfer an accrued fee to the liquidator and the treasury, less the equity lost
+/// by the liquidation that has not been offset by other fees yet.
+fn collect_fee<'info>(
+    margin_account: &AccountLoader<'info, MarginAccount>,
+    liquidation: &mut Liquidation,
+    mut accounts: FeeCollectionAccounts<'info>,
+    fee: LiquidationFee,
+) -> Result<()> {
     let timestamp = sys().unix_timestamp();
 
     // Need oracle to validate fee
-    let TokenAdmin::Margin { oracle } = ctx.accounts.token_config.admin else {
-        return err!(crate::ErrorCode::InvalidOracle);
+    let TokenAdmin::Margin { oracle } = accounts.token_config.admin else {
+        return err!(ErrorCode::InvalidOracle);
     };
 
     let clock = Clock::get()?;
-    let price_info = PriceChangeInfo::try_from_oracle_accounts(
-        &ctx.accounts.price_oracle,
-        &ctx.accounts.redemption_quote_oracle,
+    let price_info = PriceChangeInfo::try_from_oracle_accounts_in(
+        margin_account.load()?.numeraire(),
+        &accounts.price_oracle,
+        &accounts.redemption_quote_oracle,
         &oracle,
+        &accounts.token_config.mint,
         &clock,
+        &accounts.token_config.oracle_limits,
     )?
-    .to_price_info(clock.unix_timestamp);
+    .to_price_info(clock.unix_timestamp, &accounts.token_config.oracle_limits);
 
-    let decimals: i32 = -(ctx.accounts.liquidation_fee_mint.decimals as i32);
+    let decimals: i32 = -(accounts.liquidation_fee_mint.decimals as i32);
 
     // Get the price if it's valid
     let price_as_num128 = price_info.to_number128()?;
     // The fee is offset against the equity loss incurred.
-    let mut liquidation_fee =
-        Number128::from_decimal(liquidation_slot.amount, decimals) * price_as_num128;
+    let mut liquidation_fee = Number128::from_decimal(fee.amount, decimals) * price_as_num128;
     // If value lost > liquidation fee, absorb all the fee
     if liquidation.equity_loss() <= &Number128::ZERO {
         // Congratulate the liquidator for making the user better, they can take their whole fee
@@ -136,24 +280,42 @@ pub fn collect_liquidation_fee_handler(ctx: Context<CollectLiquidationFee>) -> R
     // Convert liquidation fee back to tokens, and take the tokens
     let fee_tokens = (liquidation_fee / price_as_num128).as_u64(decimals);
 
+    // The protocol's share is taken from what is left of the fee
+    let protocol_tokens = liquidation.protocol_share_of(fee_tokens);
+    let liquidator_tokens = fee_tokens - protocol_tokens;
+
     if fee_tokens > 0 {
-        token_interface::transfer_checked(
-            CpiContext::new(
-                ctx.accounts.liquidator_fee_token_program.to_account_info(),
-                token_interface::TransferChecked {
-                    from: ctx.accounts.margin_account_fee_source.to_account_info(),
-                    mint: ctx.accounts.liquidation_fee_mint.to_account_info(),
-                    to: ctx.accounts.liquidator_fee_token.to_account_info(),
-                    authority: ctx.accounts.margin_account.to_account_info(),
-                },
-            )
-            .with_signer(&[&margin_account.load()?.signer_seeds()]),
-            fee_tokens,
-            ctx.accounts.liquidation_fee_mint.decimals,
-        )?;
+        let treasury_fee_token = accounts.treasury_fee_token.as_ref();
+        require!(
+            protocol_tokens == 0 || treasury_fee_token.is_some(),
+            ErrorCode::LiquidationFeeTreasuryRequired
+        );
+        let transfers = [
+            Some((&accounts.liquidator_fee_token, liquidator_tokens)),
+            treasury_fee_token.map(|treasury_fee_token| (treasury_fee_token, protocol_tokens)),
+        ];
+        for (destination, amount) in transfers.into_iter().flatten() {
+            if amount == 0 {
+                continue;
+            }
+            token_interface::transfer_checked(
+                CpiContext::new(
+                    accounts.liquidator_fee_token_program.to_account_info(),
+                    token_interface::TransferChecked {
+                        from: accounts.margin_account_fee_source.to_account_info(),
+                        mint: accounts.liquidation_fee_mint.to_account_info(),
+                        to: destination.to_account_info(),
+                        authority: margin_account.to_account_info(),
+                    },
+                )
+                .with_signer(&[&margin_account.load()?.signer_seeds()]),
+                amount,
+                accounts.liquidation_fee_mint.decimals,
+            )?;
+        }
 
-        ctx.accounts.margin_account_fee_source.reload()?;
-        let token_account = &ctx.accounts.margin_account_fee_source;
+        accounts.margin_account_fee_source.reload()?;
+        let token_account = &accounts.margin_account_fee_source;
         let balance = anchor_spl::token::accessor::amount(&token_account.to_account_info())?;
 
         // Update the margin account after taking fee
@@ -165,13 +327,5 @@ pub fn collect_liquidation_fee_handler(ctx: Context<CollectLiquidationFee>) -> R
         )?;
     }
 
-    // Reset the fee slot
-    liquidation.clear_liquidation_fee(fee_mint);
-
-    margin_account
-        .load()?
-        .valuation(timestamp)?
-        .verify_healthy()?;
-
     Ok(())
 }
@@ -40,7 +40,8 @@ pub struct ConfigureAccountConstraints<'info> {
 
     #[account(
         mut,
-        has_one = airspace
+        has_one = airspace,
+        has_one = owner
     )]
     pub margin_account: AccountLoader<'info, MarginAccount>,
 
@@ -71,6 +72,11 @@ pub struct ConfigureAccountConstraints<'info> {
     pub account_constraint_ticket: Account<'info, AccountConstraintTicket>,
 
     pub system_program: Program<'info, System>,
+
+    /// The owner of the margin account, who has to sign when an adapter starts holding
+    /// constraints on the account.
+    /// CHECK: The address is checked by the margin account, and the signature by the handler
+    pub owner: AccountInfo<'info>,
 }
 
 /// Configure margin account constraints.
@@ -81,11 +87,19 @@ pub struct ConfigureAccountConstraints<'info> {
 /// vault funds to the margin account. To prevent the margin account owner from withdrawing
 /// funds, the vault adapter sets a restriction on the margin account preventing withdrawals.
 ///
+/// Several adapters can constrain the same margin account. The constraints each adapter
+/// sets replace the ones it held before, and empty constraints release its hold. The margin
+/// account is constrained by the combination of the constraints of all holders.
+///
 /// It was not practical to require the airspace permit holder to sign the transaction, as
 /// the owner of the margin account should have autonomy in requiring constraints to be added
 /// or lifted from their margin account. We thus chose to enforce that any adapter sign this
 /// instruction to prove that it is being called via CPI, and that the owner is not lifting
 /// constraints without the adapter's awareness.
+///
+/// An adapter can only start holding constraints on an account if the owner signs as well,
+/// so that no adapter can constrain an account without the owner's consent. Holders can
+/// change or release their own constraints without the owner.
 pub fn configure_account_constraints_handler(
     ctx: Context<ConfigureAccountConstraints>,
     account_constraints: AccountConstraints,
@@ -105,39 +119,31 @@ pub fn configure_account_constraints_handler(
         .any(|p| p.address != Pubkey::default());
     require!(
         !open_positions,
-        crate::ErrorCode::UnknownTokenProgram, // TODO: use the correct code
+        crate::ErrorCode::ConstraintsWithOpenPositions
     );
 
-    // If there are no constraints, we should remove the ticket
-    if account_constraints.is_empty() {
-        // The constraint ticket should already exist
+    // A new holder needs the consent of the owner
+    let adapter = ctx.accounts.adapter_program.key();
+    if !ticket.holders.iter().any(|h| h.adapter == adapter) && !account_constraints.is_empty() {
         require!(
-            ticket.adapter == ctx.accounts.adapter_program.key(),
-            crate::ErrorCode::UnknownTokenProgram, // TODO: use the correct code
+            ctx.accounts.owner.is_signer,
+            anchor_lang::error::ErrorCode::AccountNotSigner
         );
-        require!(
-            ticket.margin_account == ctx.accounts.margin_account.key(),
-            crate::ErrorCode::UnknownTokenProgram, // TODO: use the correct code
-        );
-
-        // Close the ticket
-        ticket.close(ctx.accounts.payer.to_account_info())?;
-    } else {
-        // Ensure that the margin account has no constraints, and has no open positions (i.e. it's newly created)
-        require!(
-            margin_account.constraints.is_empty(),
-            crate::ErrorCode::UnknownFeatureFlags
-        ); // TODO error code
-
-        ticket.set_inner(AccountConstraintTicket {
-            adapter: ctx.accounts.adapter_program.key(),
-            margin_account: ctx.accounts.margin_account.key(),
-            constraints: account_constraints,
-        });
     }
 
+    // The ticket address is derived from the margin account
+    ticket.margin_account = ctx.accounts.margin_account.key();
+
+    // Each adapter may only change the constraints it holds itself
+    ticket.set_holder_constraints(adapter, account_constraints)?;
+
     // Update the constraint
-    margin_account.constraints = account_constraints;
+    margin_account.constraints = ticket.combined_constraints();
+
+    // The ticket is no longer needed once every holder has released the account
+    if ticket.is_released() {
+        ticket.close(ctx.accounts.payer.to_account_info())?;
+    }
 
     Ok(())
 }
@@ -20,7 +20,10 @@ use anchor_lang::{prelude::*, AccountsClose};
 use glow_airspace::state::Airspace;
 use glow_program_common::serialization::StorageSpace;
 
-use crate::{events::AdapterConfigured, seeds::ADAPTER_CONFIG_SEED, AdapterConfig};
+use crate::{
+    events::AdapterConfigured, seeds::ADAPTER_CONFIG_SEED, AdapterConfig, AdapterInvokeFlags,
+    ADAPTER_CONFIG_VERSION,
+};
 
 #[derive(Accounts)]
 pub struct ConfigureAdapter<'info> {
@@ -67,6 +70,13 @@ pub fn configure_adapter_handler(ctx: Context<ConfigureAdapter>, is_adapter: boo
         return config.close(ctx.accounts.payer.to_account_info());
     };
 
+    if config.adapter_program == Pubkey::default() {
+        // Newly registered adapters keep the behaviour they had before invoke
+        // permissions existed, until the authority restricts them.
+        config.version = ADAPTER_CONFIG_VERSION;
+        config.invoke_flags = AdapterInvokeFlags::all();
+    }
+
     config.adapter_program = ctx.accounts.adapter_program.key();
     config.airspace = ctx.accounts.airspace.key();
 
@@ -0,0 +1,41 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+//
+// Copyright (C) 2024 A1 XYZ, INC.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use anchor_lang::prelude::*;
+
+use crate::{events::AdapterOutflowLimitsConfigured, AdapterOutflowLimits};
+
+use super::ConfigureAdapterPermissions;
+
+pub fn configure_adapter_outflow_limits_handler(
+    ctx: Context<ConfigureAdapterPermissions>,
+    limits: AdapterOutflowLimits,
+) -> Result<()> {
+    limits.check_valid_configuration()?;
+
+    let config = &mut ctx.accounts.adapter_config;
+
+    config.outflow_limits = limits;
+
+    emit!(AdapterOutflowLimitsConfigured {
+        airspace: ctx.accounts.airspace.key(),
+        adapter_program: ctx.accounts.adapter_program.key(),
+        limits,
+    });
+
+    Ok(())
+}
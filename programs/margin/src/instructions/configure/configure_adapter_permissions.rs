@@ -0,0 +1,84 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+//
+// Copyright (C) 2024 A1 XYZ, INC.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use anchor_lang::prelude::*;
+
+use glow_airspace::state::Airspace;
+
+use crate::{
+    events::AdapterPermissionsConfigured, seeds::ADAPTER_CONFIG_SEED, AdapterConfig,
+    AdapterInvokeFlags, ErrorCode,
+};
+
+#[derive(AnchorDeserialize, AnchorSerialize, Debug, Default, Eq, PartialEq, Clone)]
+pub struct AdapterPermissionsUpdate {
+    /// The margin instructions through which the adapter may be invoked
+    pub invoke_flags: AdapterInvokeFlags,
+
+    /// The instruction discriminators that may be invoked on the adapter.
+    ///
+    /// An empty list allows any instruction to be invoked.
+    pub allowed_instructions: Vec<[u8; 8]>,
+}
+
+#[derive(Accounts)]
+pub struct ConfigureAdapterPermissions<'info> {
+    /// The authority allowed to make changes to configuration
+    pub authority: Signer<'info>,
+
+    /// The airspace being modified
+    #[account(has_one = authority)]
+    pub airspace: Account<'info, Airspace>,
+
+    /// The adapter being configured
+    pub adapter_program: AccountInfo<'info>,
+
+    /// The config account to be modified
+    #[account(mut,
+              seeds = [
+                ADAPTER_CONFIG_SEED,
+                airspace.key().as_ref(),
+                adapter_program.key().as_ref()
+              ],
+              bump,
+              has_one = adapter_program,
+    )]
+    pub adapter_config: Account<'info, AdapterConfig>,
+}
+
+pub fn configure_adapter_permissions_handler(
+    ctx: Context<ConfigureAdapterPermissions>,
+    update: AdapterPermissionsUpdate,
+) -> Result<()> {
+    require!(
+        AdapterInvokeFlags::from_bits(update.invoke_flags.bits()).is_some(),
+        ErrorCode::InvalidConfigAdapterInvokeFlags
+    );
+
+    let config = &mut ctx.accounts.adapter_config;
+
+    config.invoke_flags = update.invoke_flags;
+    config.set_allowed_instructions(&update.allowed_instructions)?;
+
+    emit!(AdapterPermissionsConfigured {
+        airspace: ctx.accounts.airspace.key(),
+        adapter_program: ctx.accounts.adapter_program.key(),
+        update,
+    });
+
+    Ok(())
+}
@@ -0,0 +1,81 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+//
+// Copyright (C) 2024 A1 XYZ, INC.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use anchor_lang::prelude::*;
+
+use glow_airspace::state::Airspace;
+
+use crate::{seeds::*, AccountConstraintTicket, AdapterConfig, MarginAccount};
+
+#[derive(Accounts)]
+pub struct ConfigureConstraintDestinations<'info> {
+    /// A PDA of the adapter that has signed to prove that the adapter is calling this instruction
+    #[account(
+        owner = adapter_program.key()
+    )]
+    pub adapter_signer: Signer<'info>,
+
+    /// The airspace of the margin account
+    pub airspace: Account<'info, Airspace>,
+
+    #[account(has_one = airspace)]
+    pub margin_account: AccountLoader<'info, MarginAccount>,
+
+    /// The adapter for which the destinations are configured
+    pub adapter_program: AccountInfo<'info>,
+
+    /// The config of the adapter
+    #[account(
+        seeds = [
+            ADAPTER_CONFIG_SEED,
+            airspace.key().as_ref(),
+            adapter_program.key().as_ref()
+        ],
+        bump,
+    )]
+    pub adapter_config: Account<'info, AdapterConfig>,
+
+    /// The ticket of the margin account, which is created when the adapter configures
+    /// its constraints
+    #[account(
+        mut,
+        seeds = [
+            MARGIN_ACCOUNT_CONSTRAINT_SEED,
+            margin_account.key().as_ref(),
+        ],
+        bump,
+        has_one = margin_account,
+    )]
+    pub account_constraint_ticket: Account<'info, AccountConstraintTicket>,
+}
+
+/// Configure the destinations a margin account may send tokens to.
+///
+/// The destinations only apply while the signing adapter holds the
+/// `RESTRICT_DESTINATIONS` constraint on the margin account, and replace any
+/// destinations the adapter allowed before. The adapter has to hold constraints
+/// through `configure_account_constraints` first.
+pub fn configure_constraint_destinations_handler(
+    ctx: Context<ConfigureConstraintDestinations>,
+    destinations: Vec<Pubkey>,
+) -> Result<()> {
+    let ticket = &mut ctx.accounts.account_constraint_ticket;
+
+    ticket.set_holder_destinations(ctx.accounts.adapter_program.key(), &destinations)?;
+
+    Ok(())
+}
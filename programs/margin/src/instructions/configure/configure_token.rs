@@ -21,9 +21,8 @@ use anchor_spl::{token::ID as TOKEN_ID, token_2022::ID as TOKEN_2022_ID, token_i
 use glow_airspace::state::Airspace;
 
 use crate::{
-    events::TokenConfigured, seeds::TOKEN_CONFIG_SEED, ErrorCode, TokenAdmin, TokenConfig,
-    TokenFeatures, TokenKind, MAX_CLAIM_VALUE_MODIFIER, MAX_COLLATERAL_VALUE_MODIFIER,
-    MAX_TOKEN_STALENESS,
+    events::TokenConfigured, seeds::TOKEN_CONFIG_SEED, ErrorCode, OracleLimits, TokenAdmin,
+    TokenConfig, TokenFeatures, TokenKind, ValueModifierRamp, MAX_TOKEN_STALENESS,
 };
 
 #[derive(AnchorDeserialize, AnchorSerialize, Debug, Eq, PartialEq, Clone)]
@@ -63,36 +62,16 @@ pub struct TokenConfigUpdate {
     /// This featureset contains token restrictions, which might be applied to margin accounts
     /// whose featureset is incompatible with the token's featureset.
     pub token_features: TokenFeatures,
+
+    /// Overrides of the global oracle confidence and staleness limits for this token.
+    /// Zero values use the global defaults.
+    pub oracle_limits: OracleLimits,
 }
 
 impl TokenConfigUpdate {
     pub fn check_modifier_limits(&self) -> Result<()> {
-        // Ensure value modifier cannot exceed limit for all token kinds.
-        // Use match in case we extend the token kinds which forces us to
-        // update the match arms here.
-        let val_modifier = self.value_modifier;
-        match self.token_kind {
-            TokenKind::Collateral | TokenKind::AdapterCollateral => {
-                if val_modifier > MAX_COLLATERAL_VALUE_MODIFIER {
-                    msg!(
-                        "collateral value modifier cannot exceed limit, got: {}",
-                        val_modifier
-                    );
-                    return err!(ErrorCode::InvalidConfigCollateralValueModifierLimit);
-                }
-            }
-            TokenKind::Claim => {
-                if val_modifier > MAX_CLAIM_VALUE_MODIFIER {
-                    msg!(
-                        "claim value modifier cannot exceed limit, got: {}",
-                        val_modifier
-                    );
-                    return err!(ErrorCode::InvalidConfigClaimValueModifierLimit);
-                }
-            }
-        }
-
-        Ok(())
+        self.token_kind
+            .check_value_modifier_limits(self.value_modifier)
     }
 
     pub fn check_max_staleness(&self) -> Result<()> {
@@ -122,8 +101,8 @@ impl TokenConfigUpdate {
         err!(ErrorCode::InvalidConfigTokenProgramUnsupported)
     }
 
-    /// There are 3 token kinds and 2 admins:
-    /// - Collateral, AdapterCollateral, Claim
+    /// There are 4 token kinds and 2 admins:
+    /// - Collateral, AdapterCollateral, Claim, NonCollateral
     /// - Margin, Adapter
     ///
     /// Validations:
@@ -131,13 +110,17 @@ impl TokenConfigUpdate {
     ///     * Claim: registered through the adapter program via CPI (e.g. margin_pool::register_loan)
     ///     * Collateral: registered directly through the margin program (e.g. margin::register_position)
     ///     * AdapterCollateral: registered through the adapter program via CPI (e.g. test_service::register_adapter_position)
-    /// - Margin can only register Collateral
+    ///     * NonCollateral: registered directly through the margin program, and never valued
+    /// - Margin can only register Collateral and NonCollateral
     pub fn check_token_kind(&self) -> Result<()> {
         match self.admin {
             TokenAdmin::Margin { .. } => {
-                msg!("Margin admin cannot own any token that is not Collateral");
+                msg!("Margin admin cannot own any token that is not Collateral or NonCollateral");
                 require!(
-                    self.token_kind == TokenKind::Collateral,
+                    matches!(
+                        self.token_kind,
+                        TokenKind::Collateral | TokenKind::NonCollateral
+                    ),
                     ErrorCode::InvalidConfigTokenKind
                 );
             }
@@ -197,6 +180,7 @@ pub fn configure_token_handler(
 
     updated_config.check_max_staleness()?;
     updated_config.token_features.check_valid_configuration()?;
+    updated_config.oracle_limits.check_valid_configuration()?;
     updated_config.check_token_kind()?;
     updated_config.check_modifier_limits()?;
     updated_config.check_token_program()?;
@@ -214,6 +198,9 @@ pub fn configure_token_handler(
         ctx.accounts.airspace.key(),
     )?;
 
+    // The value modifier may have been relaxed below a previously configured initial margin
+    config.check_initial_value_modifier(config.initial_value_modifier)?;
+
     Ok(())
 }
 
@@ -242,9 +229,14 @@ fn apply_config_update(
     config.underlying_mint_token_program = updated_config.underlying_mint_token_program;
     config.admin = updated_config.admin;
     config.token_kind = updated_config.token_kind;
+    if config.value_modifier != updated_config.value_modifier {
+        // A directly configured value modifier applies immediately, replacing any ramp
+        config.value_modifier_ramp = ValueModifierRamp::default();
+    }
     config.value_modifier = updated_config.value_modifier;
     config.max_staleness = updated_config.max_staleness;
     config.token_features = updated_config.token_features;
+    config.oracle_limits = updated_config.oracle_limits;
 
     Ok(())
 }
@@ -0,0 +1,173 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+//
+// Copyright (C) 2024 A1 XYZ, INC.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use anchor_lang::prelude::*;
+
+use glow_airspace::state::Airspace;
+
+use crate::{
+    events::TokenRiskConfigured,
+    seeds::TOKEN_CONFIG_SEED,
+    syscall::{sys, Sys},
+    ErrorCode, IsolationFlags, TokenConfig, ValueModifierRamp,
+};
+
+/// Changes to the risk parameters of a token. Parameters that are `None` are left unchanged.
+#[derive(AnchorSerialize, AnchorDeserialize, Debug, Default, Eq, PartialEq, Clone)]
+pub struct TokenRiskUpdate {
+    /// A stricter value modifier for the initial margin requirement, which must be at least
+    /// as strict as the value modifier. A value of 0 disables the initial margin for the token.
+    pub initial_value_modifier: Option<u16>,
+
+    /// Move the value modifier towards a target over time
+    pub value_modifier_ramp: Option<ValueModifierRampUpdate>,
+
+    /// The isolation mode of the token, and the debt ceiling if it is isolated
+    pub isolation: Option<IsolationUpdate>,
+
+    /// The max share (percent) of an account's weighted collateral counted from the token
+    /// and the other tokens of its concentration group. A value of 0 removes the limit.
+    pub max_collateral_share: Option<u8>,
+
+    /// The concentration group of the token, or 0 to remove it from its group
+    pub concentration_group: Option<u8>,
+
+    /// The collateral value (thousands of USD) above which the weight of a position decays
+    /// with the square root of its value. A value of 0 removes the haircut.
+    pub size_haircut_threshold: Option<u16>,
+
+    /// The bonus (bps) a liquidator receives when seizing the token, up to the max equity
+    /// loss of a liquidation. A value of 0 restores the default bonus.
+    pub liquidation_bonus_bps: Option<u16>,
+}
+
+/// A ramp of the value modifier from its currently effective value to a target
+#[derive(AnchorSerialize, AnchorDeserialize, Debug, Eq, PartialEq, Clone, Copy)]
+pub struct ValueModifierRampUpdate {
+    /// The value modifier at the end of the ramp
+    pub target_value_modifier: u16,
+
+    /// The time the ramp ends
+    pub end_timestamp: u64,
+}
+
+/// The isolation mode settings of a token that can be configured
+#[derive(AnchorSerialize, AnchorDeserialize, Debug, Eq, PartialEq, Clone, Copy)]
+pub struct IsolationUpdate {
+    /// Whether the token is isolated, or borrowable by accounts holding an isolated token
+    pub flags: IsolationFlags,
+
+    /// The max value of debt that may be backed by the token if it is isolated
+    pub debt_ceiling: u64,
+}
+
+#[derive(Accounts)]
+pub struct ConfigureTokenRisk<'info> {
+    /// The authority allowed to make changes to configuration
+    pub authority: Signer<'info>,
+
+    /// The airspace being modified
+    #[account(has_one = authority)]
+    pub airspace: Account<'info, Airspace>,
+
+    /// The config account to be modified
+    #[account(mut,
+              seeds = [
+                TOKEN_CONFIG_SEED,
+                airspace.key().as_ref(),
+                token_config.mint.as_ref()
+              ],
+              bump,
+              has_one = airspace @ ErrorCode::WrongAirspace,
+    )]
+    pub token_config: Account<'info, TokenConfig>,
+}
+
+pub fn configure_token_risk_handler(
+    ctx: Context<ConfigureTokenRisk>,
+    update: TokenRiskUpdate,
+) -> Result<()> {
+    let config = &mut ctx.accounts.token_config;
+
+    if let Some(ramp) = update.value_modifier_ramp {
+        let now = sys().unix_timestamp();
+
+        // The target has to satisfy the same limits as a directly configured value modifier
+        config
+            .token_kind
+            .check_value_modifier_limits(ramp.target_value_modifier)?;
+
+        // Start from the effective value, so that a running ramp continues without a jump
+        config.value_modifier_ramp = ValueModifierRamp::new(
+            config.effective_value_modifier(now),
+            now,
+            ramp.end_timestamp,
+        )?;
+        config.value_modifier = ramp.target_value_modifier;
+    }
+
+    if let Some(initial_value_modifier) = update.initial_value_modifier {
+        config.initial_value_modifier = initial_value_modifier;
+    }
+
+    // The ramp target may be below a previously configured initial margin
+    config.check_initial_value_modifier(config.initial_value_modifier)?;
+
+    if let Some(isolation) = update.isolation {
+        // The debt already backed by the token is kept, a ceiling below it only prevents new debt
+        config.isolation.flags = isolation.flags;
+        config.isolation.debt_ceiling = isolation.debt_ceiling;
+        config
+            .isolation
+            .check_valid_configuration(config.token_kind)?;
+    }
+
+    if let Some(max_collateral_share) = update.max_collateral_share {
+        config.check_max_collateral_share(max_collateral_share)?;
+        config.max_collateral_share = max_collateral_share;
+    }
+
+    if let Some(concentration_group) = update.concentration_group {
+        config.check_concentration_group(concentration_group)?;
+        config.concentration_group = concentration_group;
+    }
+
+    if let Some(size_haircut_threshold) = update.size_haircut_threshold {
+        config.check_size_haircut_threshold(size_haircut_threshold)?;
+        config.size_haircut_threshold = size_haircut_threshold;
+    }
+
+    if let Some(liquidation_bonus_bps) = update.liquidation_bonus_bps {
+        config.check_liquidation_bonus(liquidation_bonus_bps)?;
+        config.liquidation_bonus_bps = liquidation_bonus_bps;
+    }
+
+    emit!(TokenRiskConfigured {
+        airspace: ctx.accounts.airspace.key(),
+        mint: config.mint,
+        value_modifier: config.value_modifier,
+        initial_value_modifier: config.initial_value_modifier,
+        value_modifier_ramp: config.value_modifier_ramp,
+        isolation: config.isolation,
+        max_collateral_share: config.max_collateral_share,
+        concentration_group: config.concentration_group,
+        size_haircut_threshold: config.size_haircut_threshold,
+        liquidation_bonus_bps: config.liquidation_bonus_bps,
+    });
+
+    Ok(())
+}
@@ -0,0 +1,129 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+//
+// Copyright (C) 2024 A1 XYZ, INC.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use anchor_lang::prelude::*;
+
+use crate::migrate::AccountConstraintTicket as OldAccountConstraintTicket;
+use crate::{
+    seeds::MARGIN_ACCOUNT_CONSTRAINT_SEED, AccountConstraintTicket as NewAccountConstraintTicket,
+    ConstraintHolder, MarginAccount, MAX_CONSTRAINT_HOLDERS,
+};
+
+#[derive(Accounts)]
+pub struct MigrateAccountConstraintTicket<'info> {
+    /// The payer for any rent costs, if required
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    /// The owner of the margin account
+    pub owner: Signer<'info>,
+
+    /// The margin account the ticket belongs to
+    #[account(has_one = owner)]
+    pub margin_account: AccountLoader<'info, MarginAccount>,
+
+    /// The ticket to be migrated
+    #[account(
+        mut,
+        seeds = [
+            MARGIN_ACCOUNT_CONSTRAINT_SEED,
+            margin_account.key().as_ref(),
+        ],
+        bump,
+    )]
+    pub account_constraint_ticket: AccountInfo<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Migrate a ticket with a single adapter into a ticket with constraint holders.
+///
+/// The migration keeps the existing holder and its constraints, so it does not need
+/// to be authorized by the adapter, only by the owner of the margin account.
+pub fn migrate_account_constraint_ticket_handler(
+    ctx: Context<MigrateAccountConstraintTicket>,
+) -> Result<()> {
+    require!(
+        ctx.accounts.account_constraint_ticket.owner == &crate::ID,
+        anchor_lang::error::ErrorCode::ConstraintOwner
+    );
+
+    let existing_size = ctx.accounts.account_constraint_ticket.data_len();
+    let old_size = 8 + std::mem::size_of::<OldAccountConstraintTicket>();
+    let new_size = 8 + std::mem::size_of::<NewAccountConstraintTicket>();
+
+    if existing_size == new_size {
+        msg!("Account constraint ticket is already migrated");
+        return Ok(());
+    }
+
+    require!(
+        existing_size == old_size,
+        anchor_lang::error::ErrorCode::AccountDidNotDeserialize
+    );
+
+    let ticket = {
+        let data = ctx.accounts.account_constraint_ticket.data.borrow();
+        OldAccountConstraintTicket::try_deserialize(&mut &data[..])?
+    };
+
+    let mut holders = [ConstraintHolder::default(); MAX_CONSTRAINT_HOLDERS];
+    holders[0] = ConstraintHolder {
+        adapter: ticket.adapter,
+        constraints: ticket.constraints,
+        ..Default::default()
+    };
+    let new_ticket = NewAccountConstraintTicket {
+        margin_account: ticket.margin_account,
+        holders,
+    };
+
+    // Reallocate the account to the new size
+    let existing_balance = ctx.accounts.account_constraint_ticket.lamports();
+    let rent = Rent::get()?;
+
+    let required_rent = rent.minimum_balance(new_size);
+
+    if existing_balance < required_rent {
+        let shortfall = required_rent.saturating_sub(existing_balance);
+        msg!("Transferring shortfall of {} to ticket", shortfall);
+        anchor_lang::system_program::transfer(
+            CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                anchor_lang::system_program::Transfer {
+                    from: ctx.accounts.payer.to_account_info(),
+                    to: ctx.accounts.account_constraint_ticket.to_account_info(),
+                },
+            ),
+            shortfall,
+        )?;
+    }
+
+    msg!("Reallocating ticket from {} to {}", existing_size, new_size);
+    ctx.accounts
+        .account_constraint_ticket
+        .realloc(new_size, true)?;
+
+    // Save the new ticket
+    let mut ticket_data = ctx
+        .accounts
+        .account_constraint_ticket
+        .try_borrow_mut_data()?;
+    new_ticket.serialize(&mut &mut ticket_data[8..])?;
+
+    Ok(())
+}
@@ -0,0 +1,130 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+//
+// Copyright (C) 2024 A1 XYZ, INC.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use anchor_lang::prelude::*;
+
+use glow_airspace::state::Airspace;
+
+use crate::migrate::AdapterConfig as OldAdapterConfig;
+use crate::{
+    seeds::ADAPTER_CONFIG_SEED, AdapterConfig as NewAdapterConfig, AdapterInvokeFlags,
+    ADAPTER_CONFIG_VERSION,
+};
+
+#[derive(Accounts)]
+pub struct MigrateAdapterConfig<'info> {
+    /// The authority allowed to make changes to configuration
+    pub authority: Signer<'info>,
+
+    /// The airspace being modified
+    #[account(has_one = authority)]
+    pub airspace: Account<'info, Airspace>,
+
+    /// The payer for any rent costs, if required
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    /// The adapter being configured
+    pub adapter_program: AccountInfo<'info>,
+
+    /// The config account to be modified
+    #[account(
+        mut,
+        seeds = [
+            ADAPTER_CONFIG_SEED,
+            airspace.key().as_ref(),
+            adapter_program.key().as_ref()
+        ],
+        bump,
+    )]
+    pub adapter_config: AccountInfo<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+pub fn migrate_adapter_config_handler(ctx: Context<MigrateAdapterConfig>) -> Result<()> {
+    require!(
+        ctx.accounts.adapter_config.owner == &crate::ID,
+        anchor_lang::error::ErrorCode::ConstraintOwner
+    );
+
+    let existing_size = ctx.accounts.adapter_config.data_len();
+    let old_size = 8 + std::mem::size_of::<OldAdapterConfig>();
+    let new_size = 8 + std::mem::size_of::<NewAdapterConfig>();
+
+    // Serialize into current version
+    let config = {
+        let data = ctx.accounts.adapter_config.data.borrow();
+
+        // The old layout is a prefix of the new one, which stores its version right
+        // after the addresses of the old layout.
+        if existing_size > old_size && data[old_size] == ADAPTER_CONFIG_VERSION {
+            msg!("Adapter config is already migrated");
+            return Ok(());
+        }
+        require!(
+            existing_size == old_size,
+            anchor_lang::error::ErrorCode::AccountDidNotDeserialize
+        );
+
+        OldAdapterConfig::try_deserialize(&mut &data[..])?
+    };
+
+    // Existing adapters could be used through all invoke instructions, so they
+    // retain that ability after the migration.
+    let new_config = NewAdapterConfig {
+        airspace: config.airspace,
+        adapter_program: config.adapter_program,
+        version: ADAPTER_CONFIG_VERSION,
+        invoke_flags: AdapterInvokeFlags::all(),
+        allowed_instructions_len: 0,
+        allowed_instructions: Default::default(),
+        outflow_limits: Default::default(),
+        window_start: 0,
+        window_outflow: [0; 16],
+    };
+
+    // Reallocate the account to the new size
+    let existing_balance = ctx.accounts.adapter_config.lamports();
+    let rent = Rent::get()?;
+
+    let required_rent = rent.minimum_balance(new_size);
+
+    if existing_balance < required_rent {
+        let shortfall = required_rent.saturating_sub(existing_balance);
+        msg!("Transferring shortfall of {} to config", shortfall);
+        anchor_lang::system_program::transfer(
+            CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                anchor_lang::system_program::Transfer {
+                    from: ctx.accounts.payer.to_account_info(),
+                    to: ctx.accounts.adapter_config.to_account_info(),
+                },
+            ),
+            shortfall,
+        )?;
+    }
+
+    msg!("Reallocating config from {} to {}", existing_size, new_size);
+    ctx.accounts.adapter_config.realloc(new_size, true)?;
+
+    // Save the new adapter config
+    let mut config_data = ctx.accounts.adapter_config.try_borrow_mut_data()?;
+    new_config.serialize(&mut &mut config_data[8..])?;
+
+    Ok(())
+}
@@ -20,7 +20,10 @@ use anchor_lang::prelude::*;
 use glow_airspace::state::Airspace;
 
 use crate::migrate::TokenConfig as OldTokenConfig;
-use crate::{seeds::TOKEN_CONFIG_SEED, TokenConfig as NewTokenConfig, TOKEN_CONFIG_VERSION};
+use crate::{
+    seeds::TOKEN_CONFIG_SEED, IsolationConfig, OracleLimits, TokenConfig as NewTokenConfig,
+    ValueModifierRamp, TOKEN_CONFIG_VERSION,
+};
 
 #[derive(Accounts)]
 pub struct MigrateTokenConfig<'info> {
@@ -76,7 +79,15 @@ pub fn migrate_token_config_handler(ctx: Context<MigrateTokenConfig>) -> Result<
         admin: config.admin,
         token_features: Default::default(),
         version: TOKEN_CONFIG_VERSION,
-        reserved: [0; 64],
+        initial_value_modifier: 0,
+        oracle_limits: OracleLimits::default(),
+        value_modifier_ramp: ValueModifierRamp::default(),
+        isolation: IsolationConfig::default(),
+        max_collateral_share: 0,
+        size_haircut_threshold: 0,
+        concentration_group: 0,
+        liquidation_bonus_bps: 0,
+        reserved: [0; 23],
     };
 
     // Reallocate the account to the new size
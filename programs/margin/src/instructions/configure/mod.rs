@@ -1,11 +1,23 @@
 mod configure_account_constraints;
 mod configure_adapter;
+mod configure_adapter_outflow_limits;
+mod configure_adapter_permissions;
+mod configure_constraint_destinations;
 mod configure_permit;
 mod configure_token;
+mod configure_token_risk;
+mod migrate_account_constraint_ticket;
+mod migrate_adapter_config;
 mod migrate_token_config;
 
 pub use configure_account_constraints::*;
 pub use configure_adapter::*;
+pub use configure_adapter_outflow_limits::*;
+pub use configure_adapter_permissions::*;
+pub use configure_constraint_destinations::*;
 pub use configure_permit::*;
 pub use configure_token::*;
+pub use configure_token_risk::*;
+pub use migrate_account_constraint_ticket::*;
+pub use migrate_adapter_config::*;
 pub use migrate_token_config::*;
@@ -53,19 +53,7 @@ pub fn create_account_handler(
     feature_flags: AccountFeatureFlags,
 ) -> Result<()> {
     // Only one restriction can be set, and it should not be VIOLATION
-    require!(
-        !feature_flags.contains_unknown_bits(),
-        crate::ErrorCode::UnknownFeatureFlags
-    );
-    require!(
-        !feature_flags.contains(AccountFeatureFlags::VIOLATION),
-        crate::ErrorCode::InvalidFeatureFlags
-    );
-    // The set bits should be <= 1
-    require!(
-        feature_flags.bits().count_ones() <= 1,
-        crate::ErrorCode::InvalidFeatureFlags
-    );
+    feature_flags.check_valid_for_new_account()?;
 
     let mut account = ctx.accounts.margin_account.load_init()?;
 
@@ -0,0 +1,94 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+//
+// Copyright (C) 2024 A1 XYZ, INC.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use anchor_lang::prelude::*;
+
+use crate::{
+    events, seeds::DELEVERAGE_ORDER_SEED, DeleverageOrder, ErrorCode, MarginAccount, SwapRoute,
+};
+
+/// The parameters of a new [DeleverageOrder]
+#[derive(AnchorSerialize, AnchorDeserialize, Debug, Clone, Copy)]
+pub struct DeleverageOrderParams {
+    pub order_id: u64,
+    pub trigger_c_ratio_bps: u32,
+    pub max_repay_bps: u16,
+    pub route: SwapRoute,
+    pub max_slippage_bps: u16,
+    pub keeper_fee_bps: u16,
+}
+
+#[derive(Accounts)]
+#[instruction(params: DeleverageOrderParams)]
+pub struct CreateDeleverageOrder<'info> {
+    /// The owner of the margin account
+    pub owner: Signer<'info>,
+
+    /// The address paying for rent
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    /// The margin account the order is executed for
+    #[account(has_one = owner)]
+    pub margin_account: AccountLoader<'info, MarginAccount>,
+
+    /// The account storing the order
+    #[account(
+        init,
+        seeds = [
+            DELEVERAGE_ORDER_SEED,
+            margin_account.key().as_ref(),
+            params.order_id.to_le_bytes().as_ref(),
+        ],
+        bump,
+        payer = payer,
+        space = 8 + std::mem::size_of::<DeleverageOrder>(),
+    )]
+    pub order: Account<'info, DeleverageOrder>,
+
+    pub system_program: Program<'info, System>,
+}
+
+pub fn create_deleverage_order_handler(
+    ctx: Context<CreateDeleverageOrder>,
+    params: DeleverageOrderParams,
+) -> Result<()> {
+    if ctx.accounts.margin_account.load()?.liquidator != Pubkey::default() {
+        msg!("account is being liquidated");
+        return Err(ErrorCode::Liquidating.into());
+    }
+
+    let order = &mut ctx.accounts.order;
+    order.set_inner(DeleverageOrder {
+        margin_account: ctx.accounts.margin_account.key(),
+        order_id: params.order_id,
+        trigger_c_ratio_bps: params.trigger_c_ratio_bps,
+        max_repay_bps: params.max_repay_bps,
+        route: params.route,
+        max_slippage_bps: params.max_slippage_bps,
+        keeper_fee_bps: params.keeper_fee_bps,
+    });
+    order.validate()?;
+
+    emit!(events::DeleverageOrderCreated {
+        margin_account: order.margin_account,
+        order: order.key(),
+        order_state: (**order).clone(),
+    });
+
+    Ok(())
+}
@@ -0,0 +1,101 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+//
+// Copyright (C) 2024 A1 XYZ, INC.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use anchor_lang::prelude::*;
+
+use crate::{
+    events, seeds::TRIGGER_ORDER_SEED, ErrorCode, MarginAccount, SwapRoute, TriggerCondition,
+    TriggerOrder,
+};
+
+/// The parameters of a new [TriggerOrder]
+#[derive(AnchorSerialize, AnchorDeserialize, Debug, Clone, Copy)]
+pub struct TriggerOrderParams {
+    pub order_id: u64,
+    pub trigger_token: Pubkey,
+    pub condition: TriggerCondition,
+    pub trigger_price: i64,
+    pub trigger_price_exponent: i32,
+    pub route: SwapRoute,
+    pub max_input_amount: u64,
+    pub max_slippage_bps: u16,
+    pub keeper_fee_bps: u16,
+}
+
+#[derive(Accounts)]
+#[instruction(params: TriggerOrderParams)]
+pub struct CreateTriggerOrder<'info> {
+    /// The owner of the margin account
+    pub owner: Signer<'info>,
+
+    /// The address paying for rent
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    /// The margin account the order is executed for
+    #[account(has_one = owner)]
+    pub margin_account: AccountLoader<'info, MarginAccount>,
+
+    /// The account storing the order
+    #[account(
+        init,
+        seeds = [
+            TRIGGER_ORDER_SEED,
+            margin_account.key().as_ref(),
+            params.order_id.to_le_bytes().as_ref(),
+        ],
+        bump,
+        payer = payer,
+        space = 8 + std::mem::size_of::<TriggerOrder>(),
+    )]
+    pub order: Account<'info, TriggerOrder>,
+
+    pub system_program: Program<'info, System>,
+}
+
+pub fn create_trigger_order_handler(
+    ctx: Context<CreateTriggerOrder>,
+    params: TriggerOrderParams,
+) -> Result<()> {
+    if ctx.accounts.margin_account.load()?.liquidator != Pubkey::default() {
+        msg!("account is being liquidated");
+        return Err(ErrorCode::Liquidating.into());
+    }
+
+    let order = &mut ctx.accounts.order;
+    order.set_inner(TriggerOrder {
+        margin_account: ctx.accounts.margin_account.key(),
+        order_id: params.order_id,
+        trigger_token: params.trigger_token,
+        condition: params.condition,
+        trigger_price: params.trigger_price,
+        trigger_price_exponent: params.trigger_price_exponent,
+        route: params.route,
+        max_input_amount: params.max_input_amount,
+        max_slippage_bps: params.max_slippage_bps,
+        keeper_fee_bps: params.keeper_fee_bps,
+    });
+    order.validate()?;
+
+    emit!(events::TriggerOrderCreated {
+        margin_account: order.margin_account,
+        order: order.key(),
+        order_state: (**order).clone(),
+    });
+
+    Ok(())
+}
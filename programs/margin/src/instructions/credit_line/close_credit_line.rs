@@ -0,0 +1,85 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+//
+// Copyright (C) 2024 A1 XYZ, INC.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use anchor_lang::prelude::*;
+
+use crate::{
+    events,
+    syscall::{sys, Sys},
+    Approver, CreditLine, ErrorCode, MarginAccount,
+};
+
+#[derive(Accounts)]
+pub struct CloseCreditLine<'info> {
+    /// The owner of either the lender or the borrower account
+    pub authority: Signer<'info>,
+
+    /// The account that paid the rent of the line, which receives it back
+    /// CHECK: The address is constrained to the payer stored in the line, and the
+    /// account only receives the lamports of the closed line
+    #[account(mut, address = credit_line.payer @ ErrorCode::InvalidCreditLine)]
+    pub receiver: AccountInfo<'info>,
+
+    /// The margin account lending its collateral
+    #[account(mut)]
+    pub lender_account: AccountLoader<'info, MarginAccount>,
+
+    /// The margin account borrowing against the collateral of the lender
+    #[account(mut)]
+    pub borrower_account: AccountLoader<'info, MarginAccount>,
+
+    /// The line to close
+    #[account(mut,
+        close = receiver,
+        constraint = credit_line.lender == lender_account.key() @ ErrorCode::InvalidCreditLine,
+        constraint = credit_line.borrower == borrower_account.key() @ ErrorCode::InvalidCreditLine,
+    )]
+    pub credit_line: Account<'info, CreditLine>,
+}
+
+/// Close a credit line, removing its position from both accounts. The borrower must
+/// remain healthy without the line.
+pub fn close_credit_line_handler(ctx: Context<CloseCreditLine>) -> Result<()> {
+    let address = ctx.accounts.credit_line.key();
+    let lender = &mut ctx.accounts.lender_account.load_mut()?;
+    let borrower = &mut ctx.accounts.borrower_account.load_mut()?;
+
+    let authority = ctx.accounts.authority.key();
+    if authority != lender.owner && authority != borrower.owner {
+        return err!(ErrorCode::UnauthorizedInvocation);
+    }
+    lender.verify_not_liquidating()?;
+    borrower.verify_not_liquidating()?;
+
+    let approvals = &[
+        Approver::MarginAccountAuthority,
+        Approver::Adapter(crate::ID),
+    ];
+    let timestamp = sys().unix_timestamp();
+    for account in [&mut **lender, &mut **borrower] {
+        account.set_position_balance(&address, &address, 0, timestamp)?;
+        account.unregister_position(&address, &address, approvals)?;
+    }
+
+    borrower.valuation(timestamp)?.verify_healthy()?;
+
+    emit!(events::CreditLineClosed {
+        credit_line: address,
+    });
+
+    Ok(())
+}
@@ -0,0 +1,190 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+//
+// Copyright (C) 2024 A1 XYZ, INC.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    token_2022::TransferChecked,
+    token_interface::{self, Mint, TokenAccount, TokenInterface},
+};
+use glow_program_common::Number128;
+
+use crate::instructions::liquidator_seize::{current_price, update_position_balance};
+use crate::syscall::{sys, Sys};
+use crate::{events, CreditLine, ErrorCode, LiquidationState, MarginAccount, TokenKind};
+
+#[derive(Accounts)]
+pub struct LiquidatorSeizeCredit<'info> {
+    /// The liquidator processing the borrower
+    pub liquidator: Signer<'info>,
+
+    /// Account to persist the state of the liquidation
+    #[account(
+        has_one = liquidator,
+        has_one = margin_account,
+        constraint = liquidation.load()?.state.is_collecting_fees == 0,
+    )]
+    pub liquidation: AccountLoader<'info, LiquidationState>,
+
+    /// The borrower of the line, which is being liquidated
+    #[account(mut)]
+    pub margin_account: AccountLoader<'info, MarginAccount>,
+
+    /// The lender of the line, whose collateral is seized
+    #[account(mut)]
+    pub lender_account: AccountLoader<'info, MarginAccount>,
+
+    /// The line backing the borrower
+    #[account(mut,
+        constraint = credit_line.lender == lender_account.key() @ ErrorCode::InvalidCreditLine,
+        constraint = credit_line.borrower == margin_account.key() @ ErrorCode::InvalidCreditLine,
+    )]
+    pub credit_line: Account<'info, CreditLine>,
+
+    /// The mint of the collateral being seized
+    pub seize_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    /// The collateral position of the lender
+    #[account(mut,
+        token::mint = seize_mint,
+        token::authority = lender_account,
+        token::token_program = seize_token_program,
+    )]
+    pub seize_source: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// The position of the borrower that receives the collateral
+    #[account(mut,
+        token::mint = seize_mint,
+        token::authority = margin_account,
+        token::token_program = seize_token_program,
+    )]
+    pub seize_destination: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    pub seize_token_program: Interface<'info, TokenInterface>,
+}
+
+/// Move collateral from the lender of a credit line into the borrower being liquidated,
+/// reducing the line by the value moved. The borrower's collateral is unchanged in value,
+/// but it now holds tokens that its liquidator can use to repay its debt.
+///
+/// The value moved is limited to the collateral the borrower lacks before the seizure,
+/// so that no more of the lender's collateral is put at risk than the liquidation needs.
+pub fn liquidator_seize_credit_handler(
+    ctx: Context<LiquidatorSeizeCredit>,
+    amount: u64,
+) -> Result<()> {
+    let lender_account = &ctx.accounts.lender_account;
+    let timestamp = sys().unix_timestamp();
+
+    let shortfall = {
+        let valuation = ctx.accounts.margin_account.load()?.valuation(timestamp)?;
+        valuation.required_collateral - valuation.effective_collateral
+    };
+
+    let (units, price, exponent, lender_seeds) = {
+        let lender = lender_account.load()?;
+        lender.verify_not_liquidating()?;
+
+        let position = lender
+            .get_position(&ctx.accounts.seize_mint.key())
+            .ok_or(ErrorCode::PositionNotRegistered)?;
+        // Adapter collateral is held by its adapter, so it can't be transferred from here
+        if position.kind() != TokenKind::Collateral {
+            msg!("only collateral held by the lender can be seized");
+            return err!(ErrorCode::SeizedTokenNotCollateral);
+        }
+
+        let price = current_price(position, timestamp)?;
+        let value = Number128::from_decimal(amount, position.exponent) * price;
+        let units = CreditLine::seized_amount(value);
+        if units > ctx.accounts.credit_line.limit {
+            msg!(
+                "cannot seize {} from a line with a limit of {}",
+                units,
+                ctx.accounts.credit_line.limit
+            );
+            return err!(ErrorCode::CreditLineLimitExceeded);
+        }
+
+        (units, price, position.exponent, lender.signer_seeds_owned())
+    };
+
+    let source_start = ctx.accounts.seize_source.amount;
+
+    token_interface::transfer_checked(
+        CpiContext::new_with_signer(
+            ctx.accounts.seize_token_program.to_account_info(),
+            TransferChecked {
+                from: ctx.accounts.seize_source.to_account_info(),
+                to: ctx.accounts.seize_destination.to_account_info(),
+                authority: lender_account.to_account_info(),
+                mint: ctx.accounts.seize_mint.to_account_info(),
+            },
+            &[&lender_seeds.signer_seeds()],
+        ),
+        amount,
+        ctx.accounts.seize_mint.decimals,
+    )?;
+    update_position_balance(lender_account, &mut ctx.accounts.seize_source)?;
+    update_position_balance(
+        &ctx.accounts.margin_account,
+        &mut ctx.accounts.seize_destination,
+    )?;
+
+    let address = ctx.accounts.credit_line.key();
+    // The equity of the borrower is preserved, but not its weighted collateral, since the
+    // seized tokens are weighted by their collateral weight while the line counted in full
+    let credit_line = &mut ctx.accounts.credit_line;
+    credit_line.limit -= units;
+    credit_line.seized += units;
+    credit_line.update_positions(
+        &address,
+        &mut lender_account.load_mut()?,
+        &mut ctx.accounts.margin_account.load_mut()?,
+        timestamp,
+    )?;
+
+    // The tokens that actually left the lender must fit within the shortfall of the
+    // borrower, and be covered by the reduction of the line
+    let seized_value =
+        Number128::from_decimal(source_start - ctx.accounts.seize_source.amount, exponent) * price;
+    if seized_value > shortfall {
+        msg!(
+            "seized {} for a borrower lacking {} of collateral",
+            seized_value,
+            shortfall
+        );
+        return err!(ErrorCode::CreditLineSeizureExceedsShortfall);
+    }
+    if CreditLine::seized_amount(seized_value) > units {
+        msg!(
+            "seized {} but only reduced the line by {}",
+            seized_value,
+            units
+        );
+        return err!(ErrorCode::CreditLineLimitExceeded);
+    }
+
+    emit!(events::CreditLineSeized {
+        credit_line: address,
+        liquidator: ctx.accounts.liquidator.key(),
+        seize_mint: ctx.accounts.seize_mint.key(),
+        seized_tokens: amount,
+        limit: credit_line.limit,
+    });
+
+    Ok(())
+}
@@ -0,0 +1,9 @@
+mod close_credit_line;
+mod liquidator_seize_credit;
+mod open_credit_line;
+mod set_credit_line_limit;
+
+pub use close_credit_line::*;
+pub use liquidator_seize_credit::*;
+pub use open_credit_line::*;
+pub use set_credit_line_limit::*;
@@ -0,0 +1,121 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+//
+// Copyright (C) 2024 A1 XYZ, INC.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use anchor_lang::prelude::*;
+
+use crate::{
+    events,
+    seeds::CREDIT_LINE_SEED,
+    syscall::{sys, Sys},
+    AccountConstraints, Approver, CreditLine, ErrorCode, MarginAccount, TokenKind,
+};
+
+#[derive(Accounts)]
+pub struct OpenCreditLine<'info> {
+    /// The owner of the lender account
+    pub lender_owner: Signer<'info>,
+
+    /// The owner of the borrower account, which accepts the line
+    pub borrower_owner: Signer<'info>,
+
+    /// The address paying for rent
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    /// The margin account lending its collateral
+    #[account(mut,
+        constraint = lender_account.load()?.owner == lender_owner.key() @ ErrorCode::UnauthorizedInvocation,
+    )]
+    pub lender_account: AccountLoader<'info, MarginAccount>,
+
+    /// The margin account borrowing against the collateral of the lender
+    #[account(mut,
+        constraint = borrower_account.load()?.owner == borrower_owner.key() @ ErrorCode::UnauthorizedInvocation,
+        constraint = borrower_account.key() != lender_account.key() @ ErrorCode::InvalidCreditLine,
+    )]
+    pub borrower_account: AccountLoader<'info, MarginAccount>,
+
+    /// The account storing the line
+    #[account(
+        init,
+        seeds = [
+            CREDIT_LINE_SEED,
+            lender_account.key().as_ref(),
+            borrower_account.key().as_ref(),
+        ],
+        bump,
+        payer = payer,
+        space = 8 + std::mem::size_of::<CreditLine>(),
+    )]
+    pub credit_line: Account<'info, CreditLine>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Open a credit line from the lender to the borrower, registering its position in both
+/// accounts. The lender must meet its initial margin with the limit reserved.
+pub fn open_credit_line_handler(ctx: Context<OpenCreditLine>, limit: u64) -> Result<()> {
+    let address = ctx.accounts.credit_line.key();
+    let lender = &mut ctx.accounts.lender_account.load_mut()?;
+    let borrower = &mut ctx.accounts.borrower_account.load_mut()?;
+
+    lender.verify_not_liquidating()?;
+    borrower.verify_not_liquidating()?;
+    CreditLine::verify_accounts(lender, borrower)?;
+    CreditLine::verify_not_relending(lender, borrower)?;
+
+    // The lender takes on a claim, and its collateral may be moved to the borrower
+    if lender.constraints.intersects(
+        AccountConstraints::DENY_BORROWS
+            | AccountConstraints::DENY_TRANSFERS
+            | AccountConstraints::RESTRICT_DESTINATIONS,
+    ) {
+        return err!(ErrorCode::AccountConstraintWithdrawal);
+    }
+
+    let approvals = &[
+        Approver::MarginAccountAuthority,
+        Approver::Adapter(crate::ID),
+    ];
+    let lender_config = CreditLine::position_config(address, lender, TokenKind::Claim);
+    let borrower_config =
+        CreditLine::position_config(address, borrower, TokenKind::AdapterCollateral);
+    lender.register_position(lender_config, approvals)?;
+    borrower.register_position(borrower_config, approvals)?;
+
+    let credit_line = &mut ctx.accounts.credit_line;
+    credit_line.lender = ctx.accounts.lender_account.key();
+    credit_line.borrower = ctx.accounts.borrower_account.key();
+    credit_line.limit = limit;
+    credit_line.payer = ctx.accounts.payer.key();
+    credit_line.update_positions(&address, lender, borrower, sys().unix_timestamp())?;
+
+    lender.assert_isolation_mode()?;
+    borrower.assert_isolation_mode()?;
+    lender
+        .valuation(sys().unix_timestamp())?
+        .verify_initial_margin()?;
+
+    emit!(events::CreditLineOpened {
+        credit_line: address,
+        lender: credit_line.lender,
+        borrower: credit_line.borrower,
+        limit,
+    });
+
+    Ok(())
+}
@@ -0,0 +1,81 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+//
+// Copyright (C) 2024 A1 XYZ, INC.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use anchor_lang::prelude::*;
+
+use crate::{
+    events,
+    syscall::{sys, Sys},
+    CreditLine, ErrorCode, MarginAccount,
+};
+
+#[derive(Accounts)]
+pub struct SetCreditLineLimit<'info> {
+    /// The owner of the lender account
+    pub lender_owner: Signer<'info>,
+
+    /// The margin account lending its collateral
+    #[account(mut,
+        constraint = lender_account.load()?.owner == lender_owner.key() @ ErrorCode::UnauthorizedInvocation,
+    )]
+    pub lender_account: AccountLoader<'info, MarginAccount>,
+
+    /// The margin account borrowing against the collateral of the lender
+    #[account(mut)]
+    pub borrower_account: AccountLoader<'info, MarginAccount>,
+
+    /// The line to change
+    #[account(mut,
+        constraint = credit_line.lender == lender_account.key() @ ErrorCode::InvalidCreditLine,
+        constraint = credit_line.borrower == borrower_account.key() @ ErrorCode::InvalidCreditLine,
+    )]
+    pub credit_line: Account<'info, CreditLine>,
+}
+
+/// Change the limit of a credit line. Raising it requires the lender to meet its
+/// initial margin, and lowering it requires the borrower to remain healthy.
+pub fn set_credit_line_limit_handler(ctx: Context<SetCreditLineLimit>, limit: u64) -> Result<()> {
+    let address = ctx.accounts.credit_line.key();
+    let lender = &mut ctx.accounts.lender_account.load_mut()?;
+    let borrower = &mut ctx.accounts.borrower_account.load_mut()?;
+
+    lender.verify_not_liquidating()?;
+    borrower.verify_not_liquidating()?;
+
+    let credit_line = &mut ctx.accounts.credit_line;
+    let previous_limit = credit_line.limit;
+    credit_line.limit = limit;
+    credit_line.update_positions(&address, lender, borrower, sys().unix_timestamp())?;
+
+    if limit > previous_limit {
+        CreditLine::verify_not_relending(lender, borrower)?;
+        lender
+            .valuation(sys().unix_timestamp())?
+            .verify_initial_margin()?;
+    } else {
+        borrower
+            .valuation(sys().unix_timestamp())?
+            .verify_healthy()?;
+    }
+
+    emit!(events::CreditLineLimitSet {
+        credit_line: address,
+        limit,
+    });
+
+    Ok(())
+}
@@ -0,0 +1,116 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+//
+// Copyright (C) 2024 A1 XYZ, INC.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use anchor_lang::{prelude::*, AccountsClose};
+
+use crate::events;
+use crate::{
+    ErrorCode, LiquidationState, MarginAccount, LIQUIDATION_TIMEOUT,
+    LIQUIDATION_TIMEOUT_KEEPER_BOUNTY,
+};
+
+/// The number of remaining accounts to pass for each liquidation to end
+pub const TIMED_OUT_LIQUIDATION_ACCOUNTS: usize = 3;
+
+#[derive(Accounts)]
+pub struct EndTimedOutLiquidations<'info> {
+    /// The keeper ending the liquidations, which receives the
+    /// [bounty](crate::LIQUIDATION_TIMEOUT_KEEPER_BOUNTY) for each liquidation it ends
+    #[account(mut)]
+    pub keeper: Signer<'info>,
+    //
+    // remaining_accounts: triples of [margin_account, liquidation, liquidator], all writable
+}
+
+pub fn end_timed_out_liquidations_handler<'a, 'b, 'c: 'info, 'info>(
+    ctx: Context<'a, 'b, 'c, 'info, EndTimedOutLiquidations<'info>>,
+) -> Result<()> {
+    let remaining = ctx.remaining_accounts;
+    if remaining.len() % TIMED_OUT_LIQUIDATION_ACCOUNTS != 0 {
+        msg!("expected a margin account, liquidation state and liquidator for each liquidation");
+        return err!(anchor_lang::error::ErrorCode::AccountNotEnoughKeys);
+    }
+
+    let now = Clock::get()?.unix_timestamp;
+    let keeper = ctx.accounts.keeper.to_account_info();
+
+    for accounts in remaining.chunks_exact(TIMED_OUT_LIQUIDATION_ACCOUNTS) {
+        let [margin_account, liquidation, liquidator] = accounts else {
+            return err!(anchor_lang::error::ErrorCode::AccountNotEnoughKeys);
+        };
+
+        // another keeper, or an earlier liquidation in this batch, may have already
+        // ended this liquidation
+        if liquidation.owner != &crate::ID || liquidation.data_is_empty() {
+            msg!("liquidation {} has already ended", liquidation.key);
+            continue;
+        }
+
+        let margin_account = AccountLoader::<MarginAccount>::try_from(margin_account)?;
+        let liquidation = AccountLoader::<LiquidationState>::try_from(liquidation)?;
+
+        {
+            let state = liquidation.load()?;
+            let mut account = margin_account.load_mut()?;
+
+            require_keys_eq!(
+                state.margin_account,
+                margin_account.key(),
+                ErrorCode::WrongLiquidationState
+            );
+            require_keys_eq!(
+                account.liquidator,
+                state.liquidator,
+                ErrorCode::UnauthorizedLiquidator
+            );
+            require_keys_eq!(
+                liquidator.key(),
+                state.liquidator,
+                ErrorCode::UnauthorizedLiquidator
+            );
+
+            // the liquidator may still be active, or have just begun again after
+            // the keeper found the liquidation, which should not fail the batch
+            if now - state.state.start_time() < LIQUIDATION_TIMEOUT {
+                msg!(
+                    "liquidation {} has not passed the timeout of {} seconds",
+                    liquidation.key(),
+                    LIQUIDATION_TIMEOUT
+                );
+                continue;
+            }
+
+            account.end_liquidation();
+        }
+
+        // The keeper is paid from the rent of the liquidation state, and the rest
+        // of the rent is returned to the liquidator
+        let liquidation_info = liquidation.to_account_info();
+        let bounty = LIQUIDATION_TIMEOUT_KEEPER_BOUNTY.min(liquidation_info.lamports());
+        **liquidation_info.try_borrow_mut_lamports()? -= bounty;
+        **keeper.try_borrow_mut_lamports()? += bounty;
+        liquidation.close(liquidator.clone())?;
+
+        emit!(events::LiquidationEnded {
+            margin_account: margin_account.key(),
+            authority: keeper.key(),
+            timed_out: true,
+        });
+    }
+
+    Ok(())
+}
@@ -0,0 +1,162 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+//
+// Copyright (C) 2024 A1 XYZ, INC.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
+
+use super::execute_trigger_order::{net_change, pay_keeper_fee, verify_route};
+use crate::adapter::{self, InvokeKind, IxData};
+use crate::syscall::{sys, Sys};
+use crate::{events, DeleverageOrder, ErrorCode, MarginAccount};
+
+#[derive(Accounts)]
+pub struct ExecuteDeleverageOrder<'info> {
+    /// The keeper executing the order, which receives the keeper fee
+    pub keeper: Signer<'info>,
+
+    /// The owner of the margin account, which receives the rent of the order
+    /// CHECK: The address is constrained to the owner of the margin account, and the
+    /// account only receives the lamports of the closed order
+    #[account(mut, address = margin_account.load()?.owner)]
+    pub owner: AccountInfo<'info>,
+
+    /// The margin account to execute the order for
+    #[account(mut)]
+    pub margin_account: AccountLoader<'info, MarginAccount>,
+
+    /// The order to execute
+    #[account(mut, has_one = margin_account, close = owner)]
+    pub order: Box<Account<'info, DeleverageOrder>>,
+
+    /// The mint of the collateral sold by the order, which pays the keeper fee
+    #[account(address = order.route.input_token @ ErrorCode::TriggerOrderRouteViolated)]
+    pub input_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    /// The margin account's collateral position, which pays the keeper fee
+    #[account(mut, token::mint = input_mint, token::authority = margin_account)]
+    pub fee_source: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// The keeper's token account receiving the fee
+    #[account(mut, token::mint = input_mint)]
+    pub keeper_fee_token: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    // remaining_accounts: the accounts of the invoked adapters, followed by the token
+    // config of the isolated collateral, if any
+}
+
+/// Execute a deleverage order by swapping collateral to repay debt through the adapter
+/// of its route, on behalf of the owner.
+///
+/// The keeper can only execute the order while the effective c-ratio of the account,
+/// valued with fresh prices, is below the order's threshold. The execution must repay
+/// no more than the order's share of the liabilities, must improve the effective
+/// c-ratio, and must not lose more equity than the order's maximum slippage. The
+/// keeper is paid its fee out of the sold collateral, and the order is closed.
+pub fn execute_deleverage_order_handler<'a, 'b, 'c: 'info, 'info>(
+    ctx: Context<'a, 'b, 'c, 'info, ExecuteDeleverageOrder<'info>>,
+    instructions: Vec<IxData>,
+) -> Result<()> {
+    let order = &ctx.accounts.order;
+    let margin_account = &ctx.accounts.margin_account;
+
+    if margin_account.load()?.liquidator != Pubkey::default() {
+        msg!("account is being liquidated");
+        return Err(ErrorCode::Liquidating.into());
+    }
+
+    let start_valuation = margin_account.load()?.valuation(sys().unix_timestamp())?;
+    // Excluding stale collateral would understate the c-ratio of the account
+    if !start_valuation.stale_collateral_list().is_empty() {
+        for (position_token, error) in start_valuation.stale_collateral_list() {
+            msg!("stale position {}: {}", position_token, error)
+        }
+        return err!(ErrorCode::StalePositions);
+    }
+    if !order.is_triggered(start_valuation.effective_c_ratio()) {
+        msg!(
+            "effective c-ratio {} is not below {} bps",
+            start_valuation.effective_c_ratio(),
+            order.trigger_c_ratio_bps
+        );
+        return err!(ErrorCode::TriggerOrderNotTriggered);
+    }
+
+    let (invoke_accounts, isolation_accounts) =
+        adapter::split_invoke_accounts(ctx.remaining_accounts, &instructions);
+    verify_route(
+        &order.route,
+        &*margin_account.load()?,
+        invoke_accounts,
+        &instructions,
+        true,
+    )?;
+
+    let debt_snapshot = adapter::DebtSnapshot::new(&*margin_account.load()?);
+
+    let token_changes = adapter::invoke_many(
+        margin_account,
+        invoke_accounts,
+        instructions,
+        InvokeKind::Order,
+    )?;
+
+    adapter::record_isolated_debt(margin_account, &debt_snapshot, isolation_accounts)?;
+
+    let sold = net_change(&token_changes, order.route.input_token).saturating_neg();
+    let sold = u64::try_from(sold.max(0)).map_err(|_| error!(ErrorCode::MathOpFailed))?;
+
+    let end_valuation = margin_account.load()?.valuation(sys().unix_timestamp())?;
+    order.verify_repayment(&start_valuation, &end_valuation)?;
+
+    let keeper_fee = order.keeper_fee(sold);
+    pay_keeper_fee(
+        margin_account,
+        &ctx.accounts.input_mint,
+        &ctx.accounts.fee_source,
+        &ctx.accounts.keeper_fee_token,
+        &ctx.accounts.token_program,
+        keeper_fee,
+    )?;
+
+    let account = &mut margin_account.load_mut()?;
+    let valuation = account.valuation(sys().unix_timestamp())?;
+    if start_valuation.is_undercollateralized() {
+        // The fee must not undo the improvement of an unhealthy account
+        if valuation.effective_c_ratio() <= start_valuation.effective_c_ratio() {
+            msg!("the keeper fee undid the improvement of the account");
+            return err!(ErrorCode::DeleverageOrderRepaymentInvalid);
+        }
+    } else {
+        valuation.verify_healthy()?;
+    }
+    account.assert_position_feature_violation()?;
+    account.assert_isolation_mode()?;
+
+    emit!(events::DeleverageOrderExecuted {
+        margin_account: margin_account.key(),
+        order: order.key(),
+        keeper: ctx.accounts.keeper.key(),
+        start_c_ratio: start_valuation.effective_c_ratio().to_i128(),
+        end_c_ratio: end_valuation.effective_c_ratio().to_i128(),
+        repaid: (start_valuation.liabilities - end_valuation.liabilities).to_i128(),
+        sold,
+        keeper_fee,
+    });
+
+    Ok(())
+}
@@ -0,0 +1,406 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+//
+// Copyright (C) 2024 A1 XYZ, INC.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{self, Mint, TokenAccount, TokenInterface};
+use glow_program_common::Number128;
+
+use crate::adapter::{self, InvokeKind, IxData};
+use crate::syscall::{sys, Sys};
+use crate::{
+    events, ErrorCode, MarginAccount, Numeraire, PriceChangeInfo, SignerSeeds, SwapRoute,
+    TokenBalanceChange, TokenBalanceChangeCause, TokenConfig, TokenKind, TriggerOrder,
+};
+
+#[derive(Accounts)]
+pub struct ExecuteTriggerOrder<'info> {
+    /// The keeper executing the order, which receives the keeper fee
+    pub keeper: Signer<'info>,
+
+    /// The owner of the margin account, which receives the rent of the order
+    /// CHECK: The address is constrained to the owner of the margin account, and the
+    /// account only receives the lamports of the closed order
+    #[account(mut, address = margin_account.load()?.owner)]
+    pub owner: AccountInfo<'info>,
+
+    /// The margin account to execute the order for
+    #[account(mut)]
+    pub margin_account: AccountLoader<'info, MarginAccount>,
+
+    /// The order to execute
+    #[account(mut, has_one = margin_account, close = owner)]
+    pub order: Box<Account<'info, TriggerOrder>>,
+
+    /// The margin config of the trigger token
+    #[account(
+        constraint = trigger_config.mint == order.trigger_token @ ErrorCode::InvalidTriggerOrder,
+        constraint = trigger_config.airspace == margin_account.load()?.airspace @ ErrorCode::WrongAirspace
+    )]
+    pub trigger_config: Box<Account<'info, TokenConfig>>,
+
+    /// The oracle for the trigger token. If the oracle is a redemption rate, it should be the redemption oracle.
+    /// If the oracle is not a redemption rate, it should be the price oracle.
+    /// CHECK: We verify this account against the pyth pull receiver program
+    pub price_oracle: AccountInfo<'info>,
+
+    /// An optional oracle price account for the quote token, if the trigger token uses a redemption rate.
+    /// CHECK: We verify this account against the pyth pull receiver program
+    pub redemption_quote_oracle: Option<AccountInfo<'info>>,
+
+    /// The margin config of the input token, which prices the tokens sold
+    #[account(
+        constraint = input_config.mint == order.route.input_token @ ErrorCode::TriggerOrderRouteViolated,
+        constraint = input_config.airspace == margin_account.load()?.airspace @ ErrorCode::WrongAirspace
+    )]
+    pub input_config: Box<Account<'info, TokenConfig>>,
+
+    /// The oracle for the input token, following the same rules as the `price_oracle`
+    /// CHECK: We verify this account against the pyth pull receiver program
+    pub input_price_oracle: AccountInfo<'info>,
+
+    /// An optional oracle price account for the quote token, if the input token uses a redemption rate.
+    /// CHECK: We verify this account against the pyth pull receiver program
+    pub input_redemption_quote_oracle: Option<AccountInfo<'info>>,
+
+    /// The margin config of the output token, which prices the tokens received
+    #[account(
+        constraint = output_config.mint == order.route.output_token @ ErrorCode::TriggerOrderRouteViolated,
+        constraint = output_config.airspace == margin_account.load()?.airspace @ ErrorCode::WrongAirspace
+    )]
+    pub output_config: Box<Account<'info, TokenConfig>>,
+
+    /// The oracle for the output token, following the same rules as the `price_oracle`
+    /// CHECK: We verify this account against the pyth pull receiver program
+    pub output_price_oracle: AccountInfo<'info>,
+
+    /// An optional oracle price account for the quote token, if the output token uses a redemption rate.
+    /// CHECK: We verify this account against the pyth pull receiver program
+    pub output_redemption_quote_oracle: Option<AccountInfo<'info>>,
+
+    /// The mint of the output token, which pays the keeper fee
+    #[account(address = order.route.output_token @ ErrorCode::TriggerOrderRouteViolated)]
+    pub output_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    /// The margin account's output token position, which pays the keeper fee
+    #[account(mut, token::mint = output_mint, token::authority = margin_account)]
+    pub fee_source: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// The keeper's token account receiving the fee
+    #[account(mut, token::mint = output_mint)]
+    pub keeper_fee_token: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    // remaining_accounts: the accounts of the invoked adapters, followed by the token
+    // config of the isolated collateral, if any
+}
+
+/// Execute a trigger order by swapping through its adapter on behalf of the owner.
+///
+/// The keeper can only execute the order while the oracle price of the trigger token
+/// meets the order's condition. The swap may only invoke the adapter and instruction of
+/// the order's route, with no positions of the account other than those of the route.
+/// It must sell no more than the order's size of its input token for its output token,
+/// and must not lose more value, at the current oracle prices, than the order's maximum
+/// slippage. The keeper is paid its fee out of the received tokens, and the order is
+/// closed.
+pub fn execute_trigger_order_handler<'a, 'b, 'c: 'info, 'info>(
+    ctx: Context<'a, 'b, 'c, 'info, ExecuteTriggerOrder<'info>>,
+    instructions: Vec<IxData>,
+) -> Result<()> {
+    // SECURITY: Oracle ownership is validated with [verify_oracle_ownership] when constructing the price.
+
+    let order = &ctx.accounts.order;
+    let margin_account = &ctx.accounts.margin_account;
+
+    if margin_account.load()?.liquidator != Pubkey::default() {
+        msg!("account is being liquidated");
+        return Err(ErrorCode::Liquidating.into());
+    }
+
+    let numeraire = margin_account.load()?.numeraire();
+    let clock = Clock::get()?;
+    let price = oracle_price(
+        numeraire,
+        &ctx.accounts.trigger_config,
+        &ctx.accounts.price_oracle,
+        &ctx.accounts.redemption_quote_oracle,
+        &clock,
+    )?;
+
+    if !order.is_triggered(price) {
+        msg!(
+            "price {} does not meet the trigger price {}e{}",
+            price,
+            order.trigger_price,
+            order.trigger_price_exponent
+        );
+        return err!(ErrorCode::TriggerOrderNotTriggered);
+    }
+
+    let (invoke_accounts, isolation_accounts) =
+        adapter::split_invoke_accounts(ctx.remaining_accounts, &instructions);
+
+    verify_route(
+        &order.route,
+        &*margin_account.load()?,
+        invoke_accounts,
+        &instructions,
+        false,
+    )?;
+
+    let start_valuation = margin_account
+        .load()?
+        .valuation(sys().unix_timestamp())
+        .ok();
+    let debt_snapshot = adapter::DebtSnapshot::new(&*margin_account.load()?);
+
+    let token_changes = adapter::invoke_many(
+        margin_account,
+        invoke_accounts,
+        instructions,
+        InvokeKind::Order,
+    )?;
+
+    adapter::record_isolated_debt(margin_account, &debt_snapshot, isolation_accounts)?;
+
+    let sold = net_change(&token_changes, order.route.input_token).saturating_neg();
+    let received = net_change(&token_changes, order.route.output_token);
+    if sold <= 0 || received <= 0 {
+        msg!(
+            "the order must sell {} for {}",
+            order.route.input_token,
+            order.route.output_token
+        );
+        return err!(ErrorCode::TriggerOrderRouteViolated);
+    }
+    let sold = u64::try_from(sold).map_err(|_| error!(ErrorCode::MathOpFailed))?;
+    let received = u64::try_from(received).map_err(|_| error!(ErrorCode::MathOpFailed))?;
+    order.verify_size(sold)?;
+
+    // The swap is valued at the current oracle prices, rather than the prices of the
+    // positions, which may not have been refreshed recently
+    let input_price = oracle_price(
+        numeraire,
+        &ctx.accounts.input_config,
+        &ctx.accounts.input_price_oracle,
+        &ctx.accounts.input_redemption_quote_oracle,
+        &clock,
+    )?;
+    let output_price = oracle_price(
+        numeraire,
+        &ctx.accounts.output_config,
+        &ctx.accounts.output_price_oracle,
+        &ctx.accounts.output_redemption_quote_oracle,
+        &clock,
+    )?;
+    let (sold_value, received_value) = {
+        let account = margin_account.load()?;
+        (
+            position_value(&account, &order.route.input_token, sold, input_price)?,
+            position_value(&account, &order.route.output_token, received, output_price)?,
+        )
+    };
+    order.verify_slippage(sold_value, received_value)?;
+
+    let keeper_fee = order.keeper_fee(received);
+    pay_keeper_fee(
+        margin_account,
+        &ctx.accounts.output_mint,
+        &ctx.accounts.fee_source,
+        &ctx.accounts.keeper_fee_token,
+        &ctx.accounts.token_program,
+        keeper_fee,
+    )?;
+
+    let account = &mut margin_account.load_mut()?;
+    let valuation = account.valuation(sys().unix_timestamp())?;
+
+    match start_valuation.filter(|start| start.is_undercollateralized()) {
+        // An order may deleverage an unhealthy account just like its owner could
+        Some(start) => valuation.verify_deleveraged(&start)?,
+        None => {
+            valuation.verify_healthy()?;
+
+            let start_shortfall = start_valuation.map(|start| start.initial_margin_shortfall());
+            let end_shortfall = valuation.initial_margin_shortfall();
+            if !matches!(start_shortfall, Some(start) if end_shortfall <= start) {
+                valuation.verify_initial_margin()?;
+            }
+        }
+    }
+
+    account.assert_position_feature_violation()?;
+    account.assert_isolation_mode()?;
+
+    emit!(events::TriggerOrderExecuted {
+        margin_account: margin_account.key(),
+        order: order.key(),
+        keeper: ctx.accounts.keeper.key(),
+        price: price.to_i128(),
+        sold,
+        received,
+        keeper_fee,
+    });
+
+    Ok(())
+}
+
+/// Check that an invocation only invokes the adapter and instruction of an order's route,
+/// and only passes the margin account's positions of the route's tokens.
+///
+/// * `may_repay` - Whether the claims of the account may also be passed, so that the
+///   invocation can repay debt
+pub(crate) fn verify_route(
+    route: &SwapRoute,
+    margin_account: &MarginAccount,
+    accounts: &[AccountInfo],
+    instructions: &[IxData],
+    may_repay: bool,
+) -> Result<()> {
+    for (program, ix_accounts, ix) in adapter::invoked_instructions(accounts, instructions) {
+        if program != route.adapter_program {
+            msg!("adapter {} is not the route of the order", program);
+            return err!(ErrorCode::TriggerOrderRouteViolated);
+        }
+
+        if !route.is_instruction_allowed(&ix.data) {
+            msg!("instruction is not the route of the order");
+            return err!(ErrorCode::TriggerOrderRouteViolated);
+        }
+
+        for account in ix_accounts {
+            let Some(position) = margin_account
+                .positions()
+                .find(|p| p.address == account.key())
+            else {
+                continue;
+            };
+
+            let is_route_token =
+                position.token == route.input_token || position.token == route.output_token;
+            if !is_route_token && !(may_repay && position.kind() == TokenKind::Claim) {
+                msg!(
+                    "position {} is not part of the route of the order",
+                    position.token
+                );
+                return err!(ErrorCode::TriggerOrderRouteViolated);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// The net change of the margin account's balance of a token caused by adapters
+pub(crate) fn net_change(token_changes: &[TokenBalanceChange], mint: Pubkey) -> i128 {
+    token_changes
+        .iter()
+        .filter(|c| c.mint == mint)
+        .map(|change| match change.change_cause {
+            TokenBalanceChangeCause::ExternalIncrease => change.tokens as i128,
+            TokenBalanceChangeCause::ExternalDecrease => -(change.tokens as i128),
+            _ => 0,
+        })
+        .sum()
+}
+
+/// The current oracle price of a token, in the numeraire of the margin account
+fn oracle_price(
+    numeraire: Numeraire,
+    config: &TokenConfig,
+    price_oracle: &AccountInfo,
+    redemption_quote_oracle: &Option<AccountInfo>,
+    clock: &Clock,
+) -> Result<Number128> {
+    let token_oracle = config.oracle().ok_or(ErrorCode::InvalidOracle)?;
+
+    PriceChangeInfo::try_from_oracle_accounts_in(
+        numeraire,
+        price_oracle,
+        redemption_quote_oracle,
+        &token_oracle,
+        &config.mint,
+        clock,
+        &config.oracle_limits,
+    )?
+    .to_price_info(clock.unix_timestamp, &config.oracle_limits)
+    .to_number128()
+}
+
+/// The value of an amount of tokens of a margin account's position at the given price
+fn position_value(
+    account: &MarginAccount,
+    mint: &Pubkey,
+    tokens: u64,
+    price: Number128,
+) -> Result<Number128> {
+    let position = account
+        .get_position(mint)
+        .ok_or(ErrorCode::PositionNotRegistered)?;
+
+    Ok(Number128::from_decimal(tokens, position.exponent) * price)
+}
+
+/// Transfer the keeper fee out of a position of the margin account
+pub(crate) fn pay_keeper_fee<'info>(
+    margin_account: &AccountLoader<'info, MarginAccount>,
+    mint: &InterfaceAccount<'info, Mint>,
+    fee_source: &InterfaceAccount<'info, TokenAccount>,
+    keeper_fee_token: &InterfaceAccount<'info, TokenAccount>,
+    token_program: &Interface<'info, TokenInterface>,
+    keeper_fee: u64,
+) -> Result<()> {
+    if keeper_fee == 0 {
+        return Ok(());
+    }
+
+    let position_address = margin_account
+        .load()?
+        .get_position(&mint.key())
+        .ok_or(ErrorCode::PositionNotRegistered)?
+        .address;
+    require_keys_eq!(
+        fee_source.key(),
+        position_address,
+        ErrorCode::PositionNotRegistered
+    );
+
+    token_interface::transfer_checked(
+        CpiContext::new(
+            token_program.to_account_info(),
+            token_interface::TransferChecked {
+                from: fee_source.to_account_info(),
+                mint: mint.to_account_info(),
+                to: keeper_fee_token.to_account_info(),
+                authority: margin_account.to_account_info(),
+            },
+        )
+        .with_signer(&[&margin_account.load()?.signer_seeds()]),
+        keeper_fee,
+        mint.decimals,
+    )?;
+
+    let balance = anchor_spl::token::accessor::amount(&fee_source.to_account_info())?;
+    margin_account.load_mut()?.set_position_balance(
+        &mint.key(),
+        &position_address,
+        balance,
+        sys().unix_timestamp(),
+    )?;
+
+    Ok(())
+}
@@ -0,0 +1,206 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+//
+// Copyright (C) 2024 A1 XYZ, INC.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use anchor_lang::prelude::*;
+
+use glow_program_common::Number128;
+
+use crate::{
+    syscall::{sys, Sys},
+    MarginAccount, Valuation,
+};
+
+/// The most stale positions listed by a [ValuationSummary], which keeps the summary
+/// within the size limit of return data.
+pub const MAX_SUMMARY_STALE_POSITIONS: usize = 16;
+
+#[derive(Accounts)]
+pub struct GetValuation<'info> {
+    /// The account to compute the valuation for
+    pub margin_account: AccountLoader<'info, MarginAccount>,
+}
+
+/// The valuation of a margin account, as computed by the margin program
+#[derive(AnchorSerialize, AnchorDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ValuationSummary {
+    /// The time the valuation was computed for
+    pub timestamp: u64,
+
+    /// The net asset value of the account, ignoring collateral weights and max leverage
+    pub equity: Number128,
+
+    /// The total liability value for all claims, ignoring max leverage
+    pub liabilities: Number128,
+
+    /// The amount of collateral required to cover the claims (maintenance margin)
+    pub required_collateral: Number128,
+
+    /// The total value counted towards collateral from all deposits
+    pub weighted_collateral: Number128,
+
+    /// The weighted collateral minus liabilities
+    pub effective_collateral: Number128,
+
+    /// The amount of collateral required to cover the claims under the initial margin
+    pub initial_required_collateral: Number128,
+
+    /// The effective collateral under the initial margin
+    pub initial_effective_collateral: Number128,
+
+    /// The weighted collateral that is not counted due to concentration limits
+    pub concentration_excess: Number128,
+
+    /// The number of collateral positions excluded from the valuation
+    pub stale_position_count: u32,
+
+    /// The first collateral positions excluded from the valuation, with the error code
+    /// explaining why, up to [MAX_SUMMARY_STALE_POSITIONS]
+    pub stale_positions: Vec<StalePosition>,
+
+    /// At least one claim is past due and must be repaid immediately
+    pub past_due: bool,
+}
+
+/// A collateral position that was excluded from a valuation
+#[derive(AnchorSerialize, AnchorDeserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StalePosition {
+    /// The token of the position
+    pub token: Pubkey,
+
+    /// The error code of the reason the position is stale
+    pub error: u32,
+}
+
+impl ValuationSummary {
+    pub fn new(timestamp: u64, valuation: &Valuation) -> Self {
+        let stale_collateral = valuation.stale_collateral_list();
+
+        Self {
+            timestamp,
+            equity: valuation.equity,
+            liabilities: valuation.liabilities,
+            required_collateral: valuation.required_collateral,
+            weighted_collateral: valuation.weighted_collateral,
+            effective_collateral: valuation.effective_collateral,
+            initial_required_collateral: valuation.initial_required_collateral,
+            initial_effective_collateral: valuation.initial_effective_collateral,
+            concentration_excess: valuation.concentration_excess,
+            stale_position_count: stale_collateral.len() as u32,
+            stale_positions: stale_collateral
+                .iter()
+                .take(MAX_SUMMARY_STALE_POSITIONS)
+                .map(|(token, error)| StalePosition {
+                    token: *token,
+                    error: (*error).into(),
+                })
+                .collect(),
+            past_due: valuation.past_due(),
+        }
+    }
+}
+
+pub fn get_valuation_handler(ctx: Context<GetValuation>) -> Result<ValuationSummary> {
+    let account = ctx.accounts.margin_account.load()?;
+    let timestamp = sys().unix_timestamp();
+    let valuation = account.valuation(timestamp)?;
+
+    Ok(ValuationSummary::new(timestamp, &valuation))
+}
+
+#[cfg(test)]
+mod tests {
+    use anchor_lang::solana_program::program::MAX_RETURN_DATA;
+
+    use crate::{
+        util::Invocation, AccountConstraints, AccountFeatureFlags, Approver, PositionConfigUpdate,
+        TokenFeatures, TokenKind, MAX_USER_POSITIONS,
+    };
+
+    use super::*;
+
+    /// An account holding the most positions a user can register, none of which has a price
+    fn account_with_stale_positions() -> MarginAccount {
+        let mut account = MarginAccount {
+            version: 1,
+            bump_seed: [0],
+            user_seed: [0; 2],
+            features: AccountFeatureFlags::default(),
+            constraints: AccountConstraints::default(),
+            owner: Pubkey::default(),
+            airspace: Pubkey::default(),
+            liquidator: Pubkey::default(),
+            invocation: Invocation::default(),
+            positions: [0; 7432].into(),
+        };
+
+        for _ in 0..MAX_USER_POSITIONS {
+            let token = Pubkey::new_unique();
+            account
+                .register_position(
+                    PositionConfigUpdate {
+                        mint: token,
+                        decimals: 6,
+                        address: token,
+                        airspace: Default::default(),
+                        adapter: token,
+                        kind: TokenKind::Collateral,
+                        value_modifier: 10000,
+                        max_staleness: 0,
+                        token_program: anchor_spl::token::ID,
+                        token_features: TokenFeatures::empty(),
+                        initial_value_modifier: 0,
+                        oracle_limits: Default::default(),
+                        value_modifier_ramp: Default::default(),
+                        isolation: Default::default(),
+                        max_collateral_share: 0,
+                        concentration_group: 0,
+                        size_haircut_threshold: 0,
+                    },
+                    &[Approver::MarginAccountAuthority],
+                )
+                .unwrap();
+            account
+                .set_position_balance(&token, &token, 1_000_000, 1_000)
+                .unwrap();
+        }
+
+        account
+    }
+
+    #[test]
+    fn summary_of_stale_positions_fits_in_return_data() {
+        let account = account_with_stale_positions();
+        let valuation = account.valuation(1_000).unwrap();
+        assert_eq!(
+            MAX_USER_POSITIONS as usize,
+            valuation.stale_collateral_list().len()
+        );
+
+        let summary = ValuationSummary::new(1_000, &valuation);
+        let data = summary.try_to_vec().unwrap();
+        assert!(data.len() <= MAX_RETURN_DATA, "{} bytes", data.len());
+
+        let decoded = ValuationSummary::try_from_slice(&data).unwrap();
+        assert_eq!(summary, decoded);
+        assert_eq!(MAX_USER_POSITIONS as u32, decoded.stale_position_count);
+        assert_eq!(MAX_SUMMARY_STALE_POSITIONS, decoded.stale_positions.len());
+        assert_eq!(
+            valuation.stale_collateral_list()[0].0,
+            decoded.stale_positions[0].token
+        );
+    }
+}
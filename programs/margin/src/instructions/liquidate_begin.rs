@@ -17,6 +17,7 @@
 
 use anchor_lang::prelude::*;
 
+use glow_airspace::state::Airspace;
 use glow_program_common::Number128;
 
 use crate::{
@@ -40,13 +41,19 @@ pub struct LiquidateBegin<'info> {
     /// The liquidator account performing the liquidation actions
     pub liquidator: Signer<'info>,
 
-    /// The permit allowing the liquidator to do this
+    /// The permit allowing the liquidator to do this, which may be omitted if the
+    /// airspace allows anyone to liquidate
     #[account(
         constraint = permit.owner == liquidator.key() @ ErrorCode::UnauthorizedLiquidator,
         constraint = permit.permissions.contains(Permissions::LIQUIDATE) @ ErrorCode::UnauthorizedLiquidator,
         constraint = permit.airspace == margin_account.load()?.airspace @ ErrorCode::WrongAirspace
     )]
-    pub permit: Account<'info, Permit>,
+    pub permit: Option<Account<'info, Permit>>,
+
+    /// The airspace of the margin account, which sets whether a permit is required
+    /// and the protocol's share of the liquidation fees
+    #[account(address = margin_account.load()?.airspace @ ErrorCode::WrongAirspace)]
+    pub airspace: Box<Account<'info, Airspace>>,
 
     /// Account to persist the state of the liquidation
     #[account(
@@ -58,7 +65,7 @@ pub struct LiquidateBegin<'info> {
         ],
         bump,
         payer = payer,
-        space = 8 + std::mem::size_of::<LiquidationState>(),
+        space = LiquidationState::space(0),
     )]
     pub liquidation: AccountLoader<'info, LiquidationState>,
 
@@ -70,6 +77,12 @@ pub fn liquidate_begin_handler(ctx: Context<LiquidateBegin>) -> Result<()> {
     let account = &mut ctx.accounts.margin_account.load_mut()?;
     let timestamp = sys().unix_timestamp();
 
+    // without a permit, the airspace has to allow anyone to liquidate
+    require!(
+        ctx.accounts.permit.is_some() || ctx.accounts.airspace.is_liquidation_permissionless,
+        ErrorCode::UnauthorizedLiquidator
+    );
+
     // verify the account is subject to liquidation
     let valuation = account.valuation(timestamp)?;
     valuation.verify_unhealthy()?;
@@ -103,6 +116,7 @@ pub fn liquidate_begin_handler(ctx: Context<LiquidateBegin>) -> Result<()> {
             Clock::get()?.unix_timestamp,
             max_equity_loss,
             max_available_collateral_limit,
+            ctx.accounts.airspace.liquidation_fee_protocol_share_bps,
         ),
     };
     *ctx.accounts.liquidation.load_init()? = liquidation_state;
@@ -15,20 +15,21 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
-use anchor_lang::prelude::*;
-use anchor_spl::token_interface::{Mint, TokenInterface};
+use anchor_lang::{prelude::*, system_program};
 use glow_program_common::Number128;
 
-use crate::adapter::{self, IxData};
+use crate::adapter::{self, InvokeKind, IxData, TokenBalanceChange};
 use crate::syscall::{sys, Sys};
 use crate::{
-    events, ErrorCode, Liquidation, LiquidationState, MarginAccount, TokenBalanceChangeCause,
-    Valuation, LIQUIDATION_FEE_BPS,
+    events, ErrorCode, Liquidation, LiquidationFee, LiquidationState, MarginAccount,
+    TokenBalanceChangeCause, Valuation, LIQUIDATION_FEE_BPS,
 };
 
 #[derive(Accounts)]
 pub struct LiquidatorInvoke<'info> {
-    /// The liquidator processing the margin account
+    /// The liquidator processing the margin account, which pays for any space
+    /// needed to track fees in more mints
+    #[account(mut)]
     pub liquidator: Signer<'info>,
 
     /// Account to persist the state of the liquidation
@@ -43,11 +44,7 @@ pub struct LiquidatorInvoke<'info> {
     #[account(mut)]
     pub margin_account: AccountLoader<'info, MarginAccount>,
 
-    /// The mint in which the liquidator will accrue its fee.
-    /// This is normally the destination token, e.g. when repaying a loan.
-    pub liquidator_fee_mint: Box<InterfaceAccount<'info, Mint>>,
-
-    pub liquidator_fee_token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
 }
 
 pub fn liquidator_invoke_handler<'a, 'b, 'c: 'info, 'info>(
@@ -64,74 +61,56 @@ pub fn liquidator_invoke_handler<'a, 'b, 'c: 'info, 'info>(
         liquidator: ctx.accounts.liquidator.key(),
     });
 
-    let token_changes =
-        adapter::invoke_many(margin_account, remaining_accounts, instructions, true)?;
-
-    // Determine the token changes of the relevant token to take a fee in.
-    let fee_relevant_changes = token_changes
-        .iter()
-        .filter(|c| {
-            // Relevant changes are only the changes in the tokens of the fee mint,
-            // and the changes that a liquidator is expected to make while liquidating.
-            c.mint == ctx.accounts.liquidator_fee_mint.key()
-                && [
-                    TokenBalanceChangeCause::Borrow,
-                    TokenBalanceChangeCause::Repay,
-                    TokenBalanceChangeCause::ExternalDecrease,
-                    TokenBalanceChangeCause::ExternalIncrease,
-                ]
-                .contains(&c.change_cause)
-        })
-        .collect::<Vec<_>>();
-    // The fee for swaps is based on the lower of the increase in the token and the repaid amount
-    let increases: i128 = fee_relevant_changes
-        .iter()
-        .map(|c| {
-            match c.change_cause {
-                TokenBalanceChangeCause::ExternalIncrease => c.tokens as i128,
-                // Offset increases
-                TokenBalanceChangeCause::ExternalDecrease => -(c.tokens as i128),
-                _ => 0,
-            }
-        })
-        .sum();
-
-    let repayments: i128 = fee_relevant_changes
-        .iter()
-        .map(|c| match c.change_cause {
-            TokenBalanceChangeCause::Borrow => -(c.tokens as i128),
-            TokenBalanceChangeCause::Repay => c.tokens as i128,
-            _ => 0,
-        })
-        .sum();
+    let debt_snapshot = adapter::DebtSnapshot::new(&*margin_account.load()?);
+    let (invoke_accounts, isolation_accounts) =
+        adapter::split_invoke_accounts(remaining_accounts, &instructions);
 
-    if repayments < 0 {
-        msg!("Liquidator has a net borrow of {}", repayments);
-        return err!(crate::ErrorCode::LiquidationLostValue);
-    }
+    let token_changes = adapter::invoke_many(
+        margin_account,
+        invoke_accounts,
+        instructions,
+        InvokeKind::Liquidator,
+    )?;
 
-    let fee_eligible_tokens: u64 = increases
-        .min(repayments)
-        // max 0 to remove any negative values
-        .max(0)
-        .try_into()
-        .map_err(|_| crate::ErrorCode::MathOpFailed)?;
+    // Repayments by the liquidator free up the debt ceiling of an isolated collateral
+    adapter::record_isolated_debt(margin_account, &debt_snapshot, isolation_accounts)?;
 
-    // Accrue a liquidator fee if any.
+    // Accrue a liquidator fee in every mint that was repaid.
     // The liquidation fee is calculated as:
     //  * x/(100 + x) of the eligible amount
-    //  * minus any value lost during liquidation (e.g. slippage from swapping)
-    let decimals = ctx.accounts.liquidator_fee_mint.decimals;
-    let liquidation_fee = Number128::from_bps(LIQUIDATION_FEE_BPS)
-        / (Number128::ONE + Number128::from_bps(LIQUIDATION_FEE_BPS))
-        * Number128::from_decimal(fee_eligible_tokens, decimals);
-    let liquidation_fee = liquidation_fee.as_u64(decimals);
-
-    {
-        let liquidation = &mut ctx.accounts.liquidation.load_mut()?.state;
-        liquidation
-            .accrue_liquidation_fee(ctx.accounts.liquidator_fee_mint.key(), liquidation_fee)?;
+    //  * minus any value lost during liquidation (e.g. slippage from swapping),
+    //    which is offset when the fee is collected
+    let fee_rate = Number128::from_bps(LIQUIDATION_FEE_BPS)
+        / (Number128::ONE + Number128::from_bps(LIQUIDATION_FEE_BPS));
+    let mut repaid_mints = vec![];
+    for change in &token_changes {
+        if change.change_cause == TokenBalanceChangeCause::Repay
+            && !repaid_mints.contains(&change.mint)
+        {
+            repaid_mints.push(change.mint);
+        }
+    }
+
+    let mut accrued_fees = LiquidationState::read_fees(
+        &ctx.accounts
+            .liquidation
+            .to_account_info()
+            .try_borrow_data()?,
+    )?;
+    let mut liquidation_fees = vec![];
+    for mint in repaid_mints {
+        let fee_eligible_tokens = fee_eligible_tokens(&token_changes, mint)?;
+        let amount = (fee_rate * Number128::from_decimal(fee_eligible_tokens, 0)).as_u64(0);
+
+        LiquidationFee::accrue(&mut accrued_fees, mint, amount)?;
+        liquidation_fees.push(LiquidationFee { mint, amount });
     }
+    store_liquidation_fees(
+        &ctx.accounts.liquidation,
+        &ctx.accounts.liquidator,
+        &ctx.accounts.system_program,
+        &accrued_fees,
+    )?;
 
     let liquidation = &mut ctx.accounts.liquidation.load_mut()?.state;
     let end_value = update_and_verify_liquidation(
@@ -139,18 +118,82 @@ pub fn liquidator_invoke_handler<'a, 'b, 'c: 'info, 'info>(
         liquidation,
         start_value,
     )?;
+    margin_account.load()?.assert_isolation_mode()?;
 
     emit!(events::LiquidatorInvokeEnd {
         liquidation_data: *liquidation,
         valuation_summary: end_value.into(),
-        accrued_liquidation_fee_amount: liquidation_fee,
-        liquidation_fee_mint: ctx.accounts.liquidator_fee_mint.key(),
+        liquidation_fees,
     });
 
     Ok(())
 }
 
-fn update_and_verify_liquidation(
+/// The tokens of a repaid mint that a liquidator can take a fee on.
+///
+/// The fee for swaps is based on the lower of the increase in the token and the repaid amount
+fn fee_eligible_tokens(token_changes: &[TokenBalanceChange], mint: Pubkey) -> Result<u64> {
+    // Relevant changes are only the changes that a liquidator is expected to make while liquidating.
+    let mut increases: i128 = 0;
+    let mut repayments: i128 = 0;
+    for change in token_changes.iter().filter(|c| c.mint == mint) {
+        match change.change_cause {
+            TokenBalanceChangeCause::ExternalIncrease => increases += change.tokens as i128,
+            // Offset increases
+            TokenBalanceChangeCause::ExternalDecrease => increases -= change.tokens as i128,
+            TokenBalanceChangeCause::Borrow => repayments -= change.tokens as i128,
+            TokenBalanceChangeCause::Repay => repayments += change.tokens as i128,
+            _ => (),
+        }
+    }
+
+    if repayments < 0 {
+        msg!("Liquidator has a net borrow of {} in {}", repayments, mint);
+        return err!(crate::ErrorCode::LiquidationLostValue);
+    }
+
+    increases
+        .min(repayments)
+        // max 0 to remove any negative values
+        .max(0)
+        .try_into()
+        .map_err(|_| error!(crate::ErrorCode::MathOpFailed))
+}
+
+/// Store the fees accrued by a liquidation, growing its account if the fees need
+/// more space than it has.
+pub(crate) fn store_liquidation_fees<'info>(
+    liquidation: &AccountLoader<'info, LiquidationState>,
+    payer: &Signer<'info>,
+    system_program: &Program<'info, System>,
+    fees: &[LiquidationFee],
+) -> Result<()> {
+    let liquidation = liquidation.to_account_info();
+    let space = LiquidationState::space(fees.len());
+
+    if space > liquidation.data_len() {
+        let rent = Rent::get()?
+            .minimum_balance(space)
+            .saturating_sub(liquidation.lamports());
+        if rent > 0 {
+            system_program::transfer(
+                CpiContext::new(
+                    system_program.to_account_info(),
+                    system_program::Transfer {
+                        from: payer.to_account_info(),
+                        to: liquidation.clone(),
+                    },
+                ),
+                rent,
+            )?;
+        }
+        liquidation.realloc(space, false)?;
+    }
+
+    LiquidationState::write_fees(&mut liquidation.try_borrow_mut_data()?, fees)
+}
+
+pub(crate) fn update_and_verify_liquidation(
     margin_account: &MarginAccount,
     liquidation: &mut Liquidation,
     start_value: Valuation,
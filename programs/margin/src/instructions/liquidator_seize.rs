@@ -0,0 +1,294 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+//
+// Copyright (C) 2024 A1 XYZ, INC.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    token_2022::TransferChecked,
+    token_interface::{self, Mint, TokenAccount, TokenInterface},
+};
+use glow_program_common::Number128;
+
+use super::liquidator_invoke::update_and_verify_liquidation;
+use crate::adapter::{self, InvokeKind, IxData};
+use crate::syscall::{sys, Sys};
+use crate::{
+    events, AccountConstraints, AccountPosition, ErrorCode, LiquidationState, MarginAccount,
+    SignerSeeds, TokenBalanceChangeCause, TokenConfig, TokenKind,
+};
+
+#[derive(Accounts)]
+pub struct LiquidatorSeize<'info> {
+    /// The liquidator processing the margin account
+    pub liquidator: Signer<'info>,
+
+    /// Account to persist the state of the liquidation
+    #[account(mut,
+        has_one = liquidator,
+        has_one = margin_account,
+        constraint = liquidation.load()?.state.is_collecting_fees == 0,
+    )]
+    pub liquidation: AccountLoader<'info, LiquidationState>,
+
+    /// The margin account being liquidated
+    #[account(mut)]
+    pub margin_account: AccountLoader<'info, MarginAccount>,
+
+    /// The liquidator's own margin account, which repays the debt and receives the collateral
+    #[account(mut,
+        constraint = liquidator_account.key() != margin_account.key() @ ErrorCode::UnauthorizedLiquidator,
+        constraint = liquidator_account.load()?.owner == liquidator.key() @ ErrorCode::UnauthorizedLiquidator,
+        constraint = liquidator_account.load()?.airspace == margin_account.load()?.airspace @ ErrorCode::WrongAirspace,
+    )]
+    pub liquidator_account: AccountLoader<'info, MarginAccount>,
+
+    /// The mint of the debt being repaid
+    pub repay_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    /// The deposit of the liquidator's account that funds the repayment
+    #[account(mut,
+        token::mint = repay_mint,
+        token::authority = liquidator_account,
+        token::token_program = repay_token_program,
+    )]
+    pub repay_source: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// The deposit of the liquidated account that the debt is repaid from
+    #[account(mut,
+        token::mint = repay_mint,
+        token::authority = margin_account,
+        token::token_program = repay_token_program,
+    )]
+    pub repay_destination: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    pub repay_token_program: Interface<'info, TokenInterface>,
+
+    /// The mint of the collateral being seized, which can be a token or deposit notes
+    pub seize_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    /// The config of the collateral being seized, which sets the liquidation bonus
+    #[account(
+        constraint = seize_token_config.mint == seize_mint.key(),
+        constraint = seize_token_config.airspace == margin_account.load()?.airspace @ ErrorCode::WrongAirspace,
+    )]
+    pub seize_token_config: Box<Account<'info, TokenConfig>>,
+
+    /// The collateral position of the liquidated account
+    #[account(mut,
+        token::mint = seize_mint,
+        token::authority = margin_account,
+        token::token_program = seize_token_program,
+    )]
+    pub seize_source: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// The position of the liquidator's account that receives the collateral
+    #[account(mut,
+        token::mint = seize_mint,
+        token::authority = liquidator_account,
+        token::token_program = seize_token_program,
+    )]
+    pub seize_destination: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    pub seize_token_program: Interface<'info, TokenInterface>,
+}
+
+pub fn liquidator_seize_handler<'a, 'b, 'c: 'info, 'info>(
+    ctx: Context<'a, 'b, 'c, 'info, LiquidatorSeize<'info>>,
+    repay_amount: u64,
+    instructions: Vec<IxData>,
+) -> Result<()> {
+    let margin_account = &ctx.accounts.margin_account;
+    let liquidator_account = &ctx.accounts.liquidator_account;
+    let remaining_accounts: &'c [AccountInfo<'info>] = ctx.remaining_accounts;
+    let start_value = margin_account.load()?.valuation(sys().unix_timestamp())?;
+
+    let liquidator_seeds = {
+        let account = liquidator_account.load()?;
+        account.verify_not_liquidating()?;
+        // The repayment leaves the liquidator's account for another margin account
+        if account.constraints.intersects(
+            AccountConstraints::DENY_TRANSFERS | AccountConstraints::RESTRICT_DESTINATIONS,
+        ) {
+            return err!(ErrorCode::AccountConstraintWithdrawal);
+        }
+        account.signer_seeds_owned()
+    };
+    let liquidator_debt_snapshot = adapter::DebtSnapshot::new(&*liquidator_account.load()?);
+
+    // Move the repayment into the liquidated account, so that its debt can be repaid
+    // without swapping its collateral
+    token_interface::transfer_checked(
+        CpiContext::new_with_signer(
+            ctx.accounts.repay_token_program.to_account_info(),
+            TransferChecked {
+                from: ctx.accounts.repay_source.to_account_info(),
+                to: ctx.accounts.repay_destination.to_account_info(),
+                authority: liquidator_account.to_account_info(),
+                mint: ctx.accounts.repay_mint.to_account_info(),
+            },
+            &[&liquidator_seeds.signer_seeds()],
+        ),
+        repay_amount,
+        ctx.accounts.repay_mint.decimals,
+    )?;
+    update_position_balance(liquidator_account, &mut ctx.accounts.repay_source)?;
+    update_position_balance(margin_account, &mut ctx.accounts.repay_destination)?;
+
+    let debt_snapshot = adapter::DebtSnapshot::new(&*margin_account.load()?);
+    let (invoke_accounts, isolation_accounts) =
+        adapter::split_invoke_accounts(remaining_accounts, &instructions);
+
+    let token_changes = adapter::invoke_many(
+        margin_account,
+        invoke_accounts,
+        instructions,
+        InvokeKind::Liquidator,
+    )?;
+
+    adapter::record_isolated_debt(margin_account, &debt_snapshot, isolation_accounts)?;
+
+    // Only the debt repaid with the liquidator's tokens is rewarded with collateral
+    let repaid: i128 = token_changes
+        .iter()
+        .filter(|c| c.mint == ctx.accounts.repay_mint.key())
+        .map(|c| match c.change_cause {
+            TokenBalanceChangeCause::Repay => c.tokens as i128,
+            TokenBalanceChangeCause::Borrow => -(c.tokens as i128),
+            _ => 0,
+        })
+        .sum();
+    let repaid = u64::try_from(repaid.max(0))
+        .map_err(|_| ErrorCode::MathOpFailed)?
+        .min(repay_amount);
+    if repaid == 0 {
+        msg!("no debt was repaid with the liquidator's tokens");
+        return err!(ErrorCode::InvalidLiquidationSeizure);
+    }
+
+    let (seized, liquidated_seeds) = {
+        let account = margin_account.load()?;
+        let timestamp = sys().unix_timestamp();
+        let repay_position = account
+            .get_position(&ctx.accounts.repay_mint.key())
+            .ok_or(ErrorCode::PositionNotRegistered)?;
+        let seize_position = account
+            .get_position(&ctx.accounts.seize_mint.key())
+            .ok_or(ErrorCode::PositionNotRegistered)?;
+        // Adapter collateral is held by its adapter, so it can't be transferred from here
+        if seize_position.kind() != TokenKind::Collateral {
+            msg!("only collateral held by the margin account can be seized");
+            return err!(ErrorCode::SeizedTokenNotCollateral);
+        }
+
+        // The liquidator receives the value it repaid, plus the bonus of the collateral
+        let bonus = ctx.accounts.seize_token_config.liquidation_bonus();
+        let repaid_value = Number128::from_decimal(repaid, repay_position.exponent)
+            * current_price(repay_position, timestamp)?;
+        let seized_value = repaid_value * (Number128::ONE + Number128::from_bps(bonus));
+        let seized = (seized_value / current_price(seize_position, timestamp)?)
+            .as_u64(seize_position.exponent);
+        if seized > seize_position.balance {
+            msg!(
+                "cannot seize {} tokens from a balance of {}",
+                seized,
+                seize_position.balance
+            );
+            return err!(ErrorCode::InvalidLiquidationSeizure);
+        }
+
+        (seized, account.signer_seeds_owned())
+    };
+
+    token_interface::transfer_checked(
+        CpiContext::new_with_signer(
+            ctx.accounts.seize_token_program.to_account_info(),
+            TransferChecked {
+                from: ctx.accounts.seize_source.to_account_info(),
+                to: ctx.accounts.seize_destination.to_account_info(),
+                authority: margin_account.to_account_info(),
+                mint: ctx.accounts.seize_mint.to_account_info(),
+            },
+            &[&liquidated_seeds.signer_seeds()],
+        ),
+        seized,
+        ctx.accounts.seize_mint.decimals,
+    )?;
+    update_position_balance(margin_account, &mut ctx.accounts.seize_source)?;
+    update_position_balance(liquidator_account, &mut ctx.accounts.seize_destination)?;
+
+    // Seizing an isolated collateral puts the liquidator's existing claims against its ceiling
+    adapter::record_isolated_debt(
+        liquidator_account,
+        &liquidator_debt_snapshot,
+        isolation_accounts,
+    )?;
+
+    // The bonus counts towards the equity loss of the liquidation
+    let liquidation = &mut ctx.accounts.liquidation.load_mut()?.state;
+    let end_value = update_and_verify_liquidation(
+        &*ctx.accounts.margin_account.load()?,
+        liquidation,
+        start_value,
+    )?;
+
+    // The liquidator's account has taken on the collateral, and must remain healthy
+    {
+        let account = liquidator_account.load()?;
+        account.assert_isolation_mode()?;
+        account
+            .valuation(sys().unix_timestamp())?
+            .verify_healthy()?;
+    }
+
+    emit!(events::LiquidatorSeize {
+        margin_account: ctx.accounts.margin_account.key(),
+        liquidator: ctx.accounts.liquidator.key(),
+        liquidator_account: ctx.accounts.liquidator_account.key(),
+        repay_mint: ctx.accounts.repay_mint.key(),
+        repaid_tokens: repaid,
+        seize_mint: ctx.accounts.seize_mint.key(),
+        seized_tokens: seized,
+        liquidation_data: *liquidation,
+        valuation_summary: end_value.into(),
+    });
+
+    Ok(())
+}
+
+/// The price of a position, which has to be recent enough to count in a valuation
+pub(crate) fn current_price(position: &AccountPosition, timestamp: u64) -> Result<Number128> {
+    let price = position.price.to_number128()?;
+    if timestamp - position.price.timestamp > position.oracle_limits().max_price_quote_age() {
+        msg!("the price of {} is outdated", position.token);
+        return err!(ErrorCode::OutdatedPrice);
+    }
+
+    Ok(price)
+}
+
+pub(crate) fn update_position_balance(
+    margin_account: &AccountLoader<MarginAccount>,
+    token_account: &mut InterfaceAccount<TokenAccount>,
+) -> Result<()> {
+    token_account.reload()?;
+    margin_account.load_mut()?.set_position_balance(
+        &token_account.mint,
+        &token_account.key(),
+        token_account.amount,
+        sys().unix_timestamp(),
+    )?;
+
+    Ok(())
+}
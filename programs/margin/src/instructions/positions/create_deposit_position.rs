@@ -71,12 +71,16 @@ pub struct CreateDepositPosition<'info> {
 /// can store different tokens in their margin account, and choose which ones they opt in or out of being
 /// treated as collateral.
 pub fn create_deposit_position_handler(ctx: Context<CreateDepositPosition>) -> Result<()> {
-    // Only TokenKind::Collateral positions can be registered with this instruction.
+    // Only TokenKind::Collateral and TokenKind::NonCollateral positions can be registered
+    // with this instruction.
 
     let config = &ctx.accounts.config;
 
-    if config.token_kind != crate::TokenKind::Collateral {
-        msg!("create_deposit_position only supports TokenKind::Collateral");
+    if !matches!(
+        config.token_kind,
+        crate::TokenKind::Collateral | crate::TokenKind::NonCollateral
+    ) {
+        msg!("create_deposit_position only supports TokenKind::Collateral and TokenKind::NonCollateral");
         return err!(crate::ErrorCode::InvalidConfigRegisterPosition);
     }
 
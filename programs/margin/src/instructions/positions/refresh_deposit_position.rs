@@ -20,6 +20,7 @@ use anchor_spl::token;
 use solana_program::clock::UnixTimestamp;
 
 use crate::{
+    adapter,
     syscall::{sys, Sys},
     ErrorCode, MarginAccount, PriceChangeInfo, TokenConfig,
 };
@@ -44,19 +45,45 @@ pub struct RefreshDepositPosition<'info> {
     pub redemption_quote_oracle: Option<AccountInfo<'info>>,
     // Optional account (remaining accounts)
     // pub position_token_account: XAccount<'info, TokenAccount>,
+    // Optional account (remaining accounts), if the balance gives the account isolated collateral
+    // pub isolated_token_config: Account<'info, TokenConfig>,
 }
 
-pub fn refresh_deposit_position_handler(ctx: Context<RefreshDepositPosition>) -> Result<()> {
+pub fn refresh_deposit_position_handler<'a, 'b, 'c: 'info, 'info>(
+    ctx: Context<'a, 'b, 'c, 'info, RefreshDepositPosition<'info>>,
+) -> Result<()> {
+    let debt_snapshot = adapter::DebtSnapshot::new(&*ctx.accounts.margin_account.load()?);
+    refresh_deposit(&ctx)?;
+
+    // An isolated collateral received outside of margin puts the account's claims against
+    // its ceiling
+    adapter::record_isolated_debt(
+        &ctx.accounts.margin_account,
+        &debt_snapshot,
+        ctx.remaining_accounts,
+    )?;
+    ctx.accounts
+        .margin_account
+        .load()?
+        .assert_isolation_mode()?;
+
+    Ok(())
+}
+
+fn refresh_deposit(ctx: &Context<RefreshDepositPosition>) -> Result<()> {
     let margin_account = &mut ctx.accounts.margin_account.load_mut()?;
     let config = &ctx.accounts.config;
     let token_oracle = config.oracle().ok_or(ErrorCode::InvalidOracle)?;
 
     let clock = Clock::get()?;
-    let price_info = PriceChangeInfo::try_from_oracle_accounts(
+    let price_info = PriceChangeInfo::try_from_oracle_accounts_in(
+        margin_account.numeraire(),
         &ctx.accounts.price_oracle,
         &ctx.accounts.redemption_quote_oracle,
         &token_oracle,
+        &config.mint,
         &clock,
+        &config.oracle_limits,
     )?;
 
     if let Some(position_token_account) = ctx.remaining_accounts.first() {
@@ -72,7 +99,10 @@ pub fn refresh_deposit_position_handler(ctx: Context<RefreshDepositPosition>) ->
 
     margin_account.set_position_price(
         &config.mint,
-        &price_info.to_price_info(sys().unix_timestamp() as UnixTimestamp),
+        &price_info.to_price_info(
+            sys().unix_timestamp() as UnixTimestamp,
+            &config.oracle_limits,
+        ),
     )?;
 
     Ok(())
@@ -50,13 +50,7 @@ pub fn refresh_position_config_handler(ctx: Context<RefreshPositionConfig>) -> R
     )?;
     let config = &ctx.accounts.config;
 
-    account.refresh_position_metadata(
-        &config.mint,
-        config.token_kind,
-        config.value_modifier,
-        config.max_staleness,
-        config.token_features,
-    )?;
+    account.refresh_position_metadata(config)?;
 
     // This is the only instance where a restricted feature could be assigned to a margin account.
     // If a token has become restricted, and the margin account has no features, set it as violating.
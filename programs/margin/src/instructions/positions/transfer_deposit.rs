@@ -22,8 +22,10 @@ use anchor_spl::{
 };
 
 use crate::{
+    adapter,
     // events,
     syscall::{sys, Sys},
+    AccountConstraintTicket,
     ErrorCode,
     MarginAccount,
     SignerSeeds,
@@ -62,7 +64,11 @@ pub struct TransferDeposit<'info> {
     pub token_program: Interface<'info, TokenInterface>,
 }
 
-pub fn transfer_deposit_handler(ctx: Context<TransferDeposit>, amount: u64) -> Result<()> {
+pub fn transfer_deposit_handler<'a, 'b, 'c: 'info, 'info>(
+    ctx: Context<'a, 'b, 'c, 'info, TransferDeposit<'info>>,
+    amount: u64,
+) -> Result<()> {
+    let debt_snapshot = adapter::DebtSnapshot::new(&*ctx.accounts.margin_account.load()?);
     let (position, signer_seeds) = {
         let margin_account = &mut ctx.accounts.margin_account.load_mut()?;
 
@@ -79,6 +85,22 @@ pub fn transfer_deposit_handler(ctx: Context<TransferDeposit>, amount: u64) -> R
             {
                 return err!(crate::ErrorCode::AccountConstraintWithdrawal);
             }
+
+            // Tokens leaving the margin account must go to an allowlisted destination
+            if margin_account
+                .constraints
+                .contains(crate::AccountConstraints::RESTRICT_DESTINATIONS)
+                && ctx.accounts.destination.owner != ctx.accounts.margin_account.key()
+            {
+                let ticket = AccountConstraintTicket::load_checked(
+                    ctx.remaining_accounts.first(),
+                    &ctx.accounts.margin_account.key(),
+                )?;
+                require!(
+                    ticket.allows_destination(&ctx.accounts.destination.key()),
+                    crate::ErrorCode::AccountConstraintWithdrawal
+                );
+            }
         }
         let seeds = margin_account.signer_seeds_owned();
         let _ = margin_account;
@@ -115,6 +137,11 @@ pub fn transfer_deposit_handler(ctx: Context<TransferDeposit>, amount: u64) -> R
             source.amount,
             sys().unix_timestamp(),
         )?;
+
+        // Withdrawing collateral increases the account's risk
+        margin_account
+            .valuation(sys().unix_timestamp())?
+            .verify_initial_margin()?;
     } else {
         // Source is not margin-owned; this is a wallet-authority path depositing into margin
         // Allow deposits (wallet -> margin) regardless of delegate flag
@@ -145,5 +172,16 @@ pub fn transfer_deposit_handler(ctx: Context<TransferDeposit>, amount: u64) -> R
         )?;
     };
 
+    // Depositing an isolated collateral puts the account's existing claims against its ceiling
+    adapter::record_isolated_debt(
+        &ctx.accounts.margin_account,
+        &debt_snapshot,
+        ctx.remaining_accounts,
+    )?;
+    ctx.accounts
+        .margin_account
+        .load()?
+        .assert_isolation_mode()?;
+
     Ok(())
 }
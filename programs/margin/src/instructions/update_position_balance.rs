@@ -19,6 +19,7 @@ use anchor_lang::prelude::*;
 use anchor_spl::token_interface::TokenAccount;
 
 use crate::{
+    adapter,
     // events,
     syscall::{sys, Sys},
     MarginAccount,
@@ -34,16 +35,33 @@ pub struct UpdatePositionBalance<'info> {
     pub token_account: InterfaceAccount<'info, TokenAccount>,
 }
 
-pub fn update_position_balance_handler(ctx: Context<UpdatePositionBalance>) -> Result<()> {
-    let margin_account = &mut ctx.accounts.margin_account.load_mut()?;
-    let token_account = &ctx.accounts.token_account;
+pub fn update_position_balance_handler<'a, 'b, 'c: 'info, 'info>(
+    ctx: Context<'a, 'b, 'c, 'info, UpdatePositionBalance<'info>>,
+) -> Result<()> {
+    let debt_snapshot = adapter::DebtSnapshot::new(&*ctx.accounts.margin_account.load()?);
+    {
+        let margin_account = &mut ctx.accounts.margin_account.load_mut()?;
+        let token_account = &ctx.accounts.token_account;
 
-    margin_account.set_position_balance(
-        &token_account.mint,
-        &token_account.key(),
-        token_account.amount,
-        sys().unix_timestamp(),
+        margin_account.set_position_balance(
+            &token_account.mint,
+            &token_account.key(),
+            token_account.amount,
+            sys().unix_timestamp(),
+        )?;
+    }
+
+    // An isolated collateral received outside of margin puts the account's claims against
+    // its ceiling, which requires its token config as a remaining account
+    adapter::record_isolated_debt(
+        &ctx.accounts.margin_account,
+        &debt_snapshot,
+        ctx.remaining_accounts,
     )?;
+    ctx.accounts
+        .margin_account
+        .load()?
+        .assert_isolation_mode()?;
 
     Ok(())
 }
@@ -43,7 +43,11 @@ pub use adapter::{
     AdapterResult, IxData, PositionChange, PriceChangeInfo, TokenBalanceChange,
     TokenBalanceChangeCause,
 };
-pub use instructions::TokenConfigUpdate;
+pub use instructions::{
+    AdapterPermissionsUpdate, DeleverageOrderParams, IsolationUpdate, StalePosition,
+    TokenConfigUpdate, TokenRiskUpdate, TriggerOrderParams, ValuationSummary,
+    ValueModifierRampUpdate, MAX_SUMMARY_STALE_POSITIONS,
+};
 
 /// The maximum confidence deviation allowed for an oracle price.
 ///
@@ -70,6 +74,14 @@ pub const MAX_ORACLE_STALENESS: u64 = 60;
 #[constant]
 pub const MAX_PRICE_QUOTE_AGE: u64 = 30;
 
+/// The upper limit of a per-token override of [MAX_ORACLE_CONFIDENCE]
+#[constant]
+pub const MAX_ORACLE_CONFIDENCE_OVERRIDE: u16 = 20_00;
+
+/// The upper limit of a per-token override of [MAX_ORACLE_STALENESS] or [MAX_PRICE_QUOTE_AGE]
+#[constant]
+pub const MAX_ORACLE_STALENESS_OVERRIDE: u64 = 600;
+
 /// The maximum amount of equity that can be deducted from an account during
 /// liquidation as a proportion of the account's entire liabilities value. This
 /// is the degree-1 coefficient (slope) in the linear equation defining max
@@ -87,6 +99,11 @@ pub const LIQUIDATION_MAX_EQUITY_LOSS_CONSTANT: u64 = 1;
 #[constant]
 pub const LIQUIDATION_TIMEOUT: UnixTimestamp = 60;
 
+/// The lamports paid to a keeper for ending a liquidation that has passed the timeout,
+/// out of the rent of the liquidation state
+#[constant]
+pub const LIQUIDATION_TIMEOUT_KEEPER_BOUNTY: u64 = 1_000_000;
+
 /// The maximum number of positions that a user can register.
 /// This may be exceeded by a liquidator.
 pub const MAX_USER_POSITIONS: u64 = 24;
@@ -101,6 +118,15 @@ pub const LIQUIDATION_FEE_BPS: u16 = 500;
 /// than the liquidation fee. This would result in the transaction failing.
 const _: () = assert!(LIQUIDATION_MAX_EQUITY_LOSS_PROPORTION_BPS >= LIQUIDATION_FEE_BPS);
 
+/// The default bonus on the value of collateral that a liquidator receives when it repays
+/// a debt from its own margin account and seizes collateral in return. A token config can
+/// override it for its collateral.
+pub const LIQUIDATION_SEIZURE_BONUS_BPS: u16 = 500;
+
+/// The seizure bonus is lost equity of the liquidated account, so it has to fit within
+/// the max equity loss of a liquidation.
+const _: () = assert!(LIQUIDATION_MAX_EQUITY_LOSS_PROPORTION_BPS >= LIQUIDATION_SEIZURE_BONUS_BPS);
+
 /// The percentage increase in required collateral that is allowed on liquidation.
 ///
 /// An account under liquidation will have negative available collateral (AC). If a liquidator
@@ -152,6 +178,13 @@ pub mod margin {
     ///            seed values.
     /// * `features` - A bitset of [AccountFeatureFlags] to enable for the new margin account.
     ///
+    /// An account that only accepts SOL based tokens can also enable
+    /// [AccountFeatureFlags::SOL_NUMERAIRE], so that its positions are valued in SOL instead
+    /// of USD. The numeraire cannot be changed after the account is created. Limits that are
+    /// denominated in USD can't be applied to SOL values, so such an account cannot hold
+    /// isolated tokens or tokens with a size haircut, and cannot invoke adapters with outflow
+    /// limits.
+    ///
     /// # [Accounts](margin::accounts::CreateAccount)
     ///
     /// |     |     |     |
@@ -260,7 +293,9 @@ pub mod margin {
     /// | **Event Name** | **Description** |
     /// | [`events::PositionBalanceUpdated`] | Marks the updating of the position balance. |
     ///
-    pub fn update_position_balance(ctx: Context<UpdatePositionBalance>) -> Result<()> {
+    pub fn update_position_balance<'a, 'b, 'c: 'info, 'info>(
+        ctx: Context<'a, 'b, 'c, 'info, UpdatePositionBalance<'info>>,
+    ) -> Result<()> {
         update_position_balance_handler(ctx)
     }
 
@@ -317,6 +352,15 @@ pub mod margin {
         verify_healthy_handler(ctx)
     }
 
+    /// Compute the valuation of a margin account without changing any state.
+    ///
+    /// The valuation is returned through the transaction return data as a
+    /// [`ValuationSummary`], so that clients can simulate this instruction to get the
+    /// exact result computed by the program.
+    pub fn get_valuation(ctx: Context<GetValuation>) -> Result<ValuationSummary> {
+        get_valuation_handler(ctx)
+    }
+
     /// Verify that the account is unhealthy, by validating the collateralization
     /// ratio is below the minimum.
     ///
@@ -355,6 +399,11 @@ pub mod margin {
     /// All extra accounts passed in are used as the input accounts when invoking
     /// the provided adapter program.
     ///
+    /// The account has to be healthy after the invocation, unless it was already unhealthy
+    /// before. The owner can then deleverage the account without paying a liquidation fee,
+    /// as long as its health strictly improves and it loses no more equity than allowed
+    /// during a liquidation, in proportion to the liabilities it repays.
+    ///
     /// # Parameters
     ///
     /// * `data` - The instruction data to pass to the adapter program
@@ -384,6 +433,334 @@ pub mod margin {
         adapter_invoke_handler(ctx, instructions)
     }
 
+    /// Create an order that a keeper can execute on behalf of the margin account owner once
+    /// the oracle price of the trigger token meets the order's condition.
+    ///
+    /// # Parameters
+    ///
+    /// * `params` - The trigger, swap route, size, maximum slippage and keeper fee of the order
+    ///
+    /// # [Accounts](margin::accounts::CreateTriggerOrder)
+    ///
+    /// |     |     |     |
+    /// | --- | --- | --- |
+    /// | **Name** | **Type** | **Description** |
+    /// | `owner` | `signer` | The owner of the margin account. |
+    /// | `payer` | `signer` | The address paying for rent. |
+    /// | `margin_account` | `read_only` | The margin account the order is executed for. |
+    /// | `order` | `writable` | The account storing the order. |
+    /// | `system_program` | `read_only` | The [system native program](https://docs.solana.com/developing/runtime-facilities/programs#system-program). |
+    ///
+    /// # Events
+    ///
+    /// |     |     |
+    /// | --- | --- |
+    /// | **Event Name** | **Description** |
+    /// | [`events::TriggerOrderCreated`] | Marks the creation of the order. |
+    pub fn create_trigger_order(
+        ctx: Context<CreateTriggerOrder>,
+        params: TriggerOrderParams,
+    ) -> Result<()> {
+        create_trigger_order_handler(ctx, params)
+    }
+
+    /// Cancel a trigger order, returning its rent.
+    ///
+    /// # [Accounts](margin::accounts::CancelTriggerOrder)
+    ///
+    /// |     |     |     |
+    /// | --- | --- | --- |
+    /// | **Name** | **Type** | **Description** |
+    /// | `owner` | `signer` | The owner of the margin account. |
+    /// | `receiver` | `writable` | The account to receive the rent of the order. |
+    /// | `margin_account` | `read_only` | The margin account the order is executed for. |
+    /// | `order` | `writable` | The order to cancel. |
+    ///
+    /// # Events
+    ///
+    /// |     |     |
+    /// | --- | --- |
+    /// | **Event Name** | **Description** |
+    /// | [`events::TriggerOrderCancelled`] | Marks the cancellation of the order. |
+    pub fn cancel_trigger_order(ctx: Context<CancelTriggerOrder>) -> Result<()> {
+        cancel_trigger_order_handler(ctx)
+    }
+
+    /// Execute a trigger order by invoking the adapter of its swap route on behalf of
+    /// the margin account owner.
+    ///
+    /// Any keeper may execute the order, but only while the oracle price of the trigger
+    /// token meets its condition. Only adapters permitted to be invoked by orders in the
+    /// airspace can be used, and only through the adapter and instruction of the order's
+    /// route, with no positions of the account other than those of the route. The swap
+    /// has to sell no more than the order's size of the input token of the route for its
+    /// output token, within the maximum slippage of the order at the current oracle
+    /// prices, and the keeper is paid its fee out of the received tokens. The order is
+    /// closed once executed.
+    ///
+    /// # Parameters
+    ///
+    /// * `instructions` - The instruction data to pass to the adapter program
+    ///
+    /// # [Accounts](margin::accounts::ExecuteTriggerOrder)
+    ///
+    /// |     |     |     |
+    /// | --- | --- | --- |
+    /// | **Name** | **Type** | **Description** |
+    /// | `keeper` | `signer` | The keeper executing the order. |
+    /// | `owner` | `writable` | The owner of the margin account, which receives the rent of the order. |
+    /// | `margin_account` | `writable` | The margin account to execute the order for. |
+    /// | `order` | `writable` | The order to execute. |
+    /// | `trigger_config` | `read_only` | The token config of the trigger token. |
+    /// | `price_oracle` | `read_only` | The oracle of the trigger token. |
+    /// | `redemption_quote_oracle` | `read_only` | The oracle of the quote token, if the trigger token uses a redemption rate. |
+    /// | `input_config` | `read_only` | The token config of the input token. |
+    /// | `input_price_oracle` | `read_only` | The oracle of the input token. |
+    /// | `input_redemption_quote_oracle` | `read_only` | The oracle of the quote token, if the input token uses a redemption rate. |
+    /// | `output_config` | `read_only` | The token config of the output token. |
+    /// | `output_price_oracle` | `read_only` | The oracle of the output token. |
+    /// | `output_redemption_quote_oracle` | `read_only` | The oracle of the quote token, if the output token uses a redemption rate. |
+    /// | `output_mint` | `read_only` | The mint of the output token. |
+    /// | `fee_source` | `writable` | The output token position paying the keeper fee. |
+    /// | `keeper_fee_token` | `writable` | The keeper's token account receiving the fee. |
+    /// | `token_program` | `read_only` | The token program of the output token. |
+    ///
+    /// # Events
+    ///
+    /// |     |     |
+    /// | --- | --- |
+    /// | **Event Name** | **Description** |
+    /// | [`events::TriggerOrderExecuted`] | Marks the execution of the order. |
+    pub fn execute_trigger_order<'a, 'b, 'c: 'info, 'info>(
+        ctx: Context<'a, 'b, 'c, 'info, ExecuteTriggerOrder<'info>>,
+        instructions: Vec<crate::adapter::IxData>,
+    ) -> Result<()> {
+        execute_trigger_order_handler(ctx, instructions)
+    }
+
+    /// Create an order that a keeper can execute on behalf of the margin account owner to
+    /// repay debt while the effective c-ratio of the account is below a threshold.
+    ///
+    /// # Parameters
+    ///
+    /// * `params` - The threshold, repayment bound, swap route, maximum slippage and keeper fee of the order
+    ///
+    /// # [Accounts](margin::accounts::CreateDeleverageOrder)
+    ///
+    /// |     |     |     |
+    /// | --- | --- | --- |
+    /// | **Name** | **Type** | **Description** |
+    /// | `owner` | `signer` | The owner of the margin account. |
+    /// | `payer` | `signer` | The address paying for rent. |
+    /// | `margin_account` | `read_only` | The margin account the order is executed for. |
+    /// | `order` | `writable` | The account storing the order. |
+    /// | `system_program` | `read_only` | The [system native program](https://docs.solana.com/developing/runtime-facilities/programs#system-program). |
+    ///
+    /// # Events
+    ///
+    /// |     |     |
+    /// | --- | --- |
+    /// | **Event Name** | **Description** |
+    /// | [`events::DeleverageOrderCreated`] | Marks the creation of the order. |
+    pub fn create_deleverage_order(
+        ctx: Context<CreateDeleverageOrder>,
+        params: DeleverageOrderParams,
+    ) -> Result<()> {
+        create_deleverage_order_handler(ctx, params)
+    }
+
+    /// Cancel a deleverage order, returning its rent.
+    ///
+    /// # [Accounts](margin::accounts::CancelDeleverageOrder)
+    ///
+    /// |     |     |     |
+    /// | --- | --- | --- |
+    /// | **Name** | **Type** | **Description** |
+    /// | `owner` | `signer` | The owner of the margin account. |
+    /// | `receiver` | `writable` | The account to receive the rent of the order. |
+    /// | `margin_account` | `read_only` | The margin account the order is executed for. |
+    /// | `order` | `writable` | The order to cancel. |
+    ///
+    /// # Events
+    ///
+    /// |     |     |
+    /// | --- | --- |
+    /// | **Event Name** | **Description** |
+    /// | [`events::DeleverageOrderCancelled`] | Marks the cancellation of the order. |
+    pub fn cancel_deleverage_order(ctx: Context<CancelDeleverageOrder>) -> Result<()> {
+        cancel_deleverage_order_handler(ctx)
+    }
+
+    /// Execute a deleverage order by swapping collateral to repay debt through the adapter
+    /// of its route, on behalf of the margin account owner.
+    ///
+    /// Any keeper may execute the order, but only while the effective c-ratio of the account
+    /// is below the order's threshold. The execution must repay at most the order's share of
+    /// the liabilities, improve the effective c-ratio and stay within the order's maximum
+    /// slippage. The keeper is paid its fee out of the sold collateral. The order is closed
+    /// once executed.
+    ///
+    /// # Parameters
+    ///
+    /// * `instructions` - The instruction data to pass to the adapter program
+    ///
+    /// # [Accounts](margin::accounts::ExecuteDeleverageOrder)
+    ///
+    /// |     |     |     |
+    /// | --- | --- | --- |
+    /// | **Name** | **Type** | **Description** |
+    /// | `keeper` | `signer` | The keeper executing the order. |
+    /// | `owner` | `writable` | The owner of the margin account, which receives the rent of the order. |
+    /// | `margin_account` | `writable` | The margin account to execute the order for. |
+    /// | `order` | `writable` | The order to execute. |
+    /// | `input_mint` | `read_only` | The mint of the collateral sold by the order. |
+    /// | `fee_source` | `writable` | The collateral position paying the keeper fee. |
+    /// | `keeper_fee_token` | `writable` | The keeper's token account receiving the fee. |
+    /// | `token_program` | `read_only` | The token program of the collateral. |
+    ///
+    /// # Events
+    ///
+    /// |     |     |
+    /// | --- | --- |
+    /// | **Event Name** | **Description** |
+    /// | [`events::DeleverageOrderExecuted`] | Marks the execution of the order. |
+    pub fn execute_deleverage_order<'a, 'b, 'c: 'info, 'info>(
+        ctx: Context<'a, 'b, 'c, 'info, ExecuteDeleverageOrder<'info>>,
+        instructions: Vec<crate::adapter::IxData>,
+    ) -> Result<()> {
+        execute_deleverage_order_handler(ctx, instructions)
+    }
+
+    /// Open a credit line from a lender margin account to a borrower margin account.
+    ///
+    /// The limit of the line counts as collateral of the borrower, and as a claim of the
+    /// lender. Both owners must sign, and the lender must meet its initial margin with
+    /// the limit reserved from its collateral. An account that borrows through a line
+    /// cannot lend through another, so a lender's margin never counts credit it borrows.
+    ///
+    /// # Parameters
+    ///
+    /// * `limit` - The value of the line in the numeraire of the accounts, with
+    ///             [CREDIT_LINE_DECIMALS]
+    ///
+    /// # [Accounts](margin::accounts::OpenCreditLine)
+    ///
+    /// |     |     |     |
+    /// | --- | --- | --- |
+    /// | **Name** | **Type** | **Description** |
+    /// | `lender_owner` | `signer` | The owner of the lender account. |
+    /// | `borrower_owner` | `signer` | The owner of the borrower account. |
+    /// | `payer` | `signer` | The address paying for rent. |
+    /// | `lender_account` | `writable` | The margin account lending its collateral. |
+    /// | `borrower_account` | `writable` | The margin account borrowing against it. |
+    /// | `credit_line` | `writable` | The account storing the line. |
+    /// | `system_program` | `read_only` | The system program. |
+    ///
+    /// # Events
+    ///
+    /// |     |     |
+    /// | --- | --- |
+    /// | **Event Name** | **Description** |
+    /// | [`events::CreditLineOpened`] | Marks the opening of the line. |
+    pub fn open_credit_line(ctx: Context<OpenCreditLine>, limit: u64) -> Result<()> {
+        open_credit_line_handler(ctx, limit)
+    }
+
+    /// Change the limit of a credit line.
+    ///
+    /// Raising the limit requires the lender to meet its initial margin, and lowering it
+    /// requires the borrower to remain healthy.
+    ///
+    /// # Parameters
+    ///
+    /// * `limit` - The new value of the line
+    ///
+    /// # [Accounts](margin::accounts::SetCreditLineLimit)
+    ///
+    /// |     |     |     |
+    /// | --- | --- | --- |
+    /// | **Name** | **Type** | **Description** |
+    /// | `lender_owner` | `signer` | The owner of the lender account. |
+    /// | `lender_account` | `writable` | The margin account lending its collateral. |
+    /// | `borrower_account` | `writable` | The margin account borrowing against it. |
+    /// | `credit_line` | `writable` | The line to change. |
+    ///
+    /// # Events
+    ///
+    /// |     |     |
+    /// | --- | --- |
+    /// | **Event Name** | **Description** |
+    /// | [`events::CreditLineLimitSet`] | Marks the change of the limit. |
+    pub fn set_credit_line_limit(ctx: Context<SetCreditLineLimit>, limit: u64) -> Result<()> {
+        set_credit_line_limit_handler(ctx, limit)
+    }
+
+    /// Close a credit line, removing its positions and returning its rent.
+    ///
+    /// Either owner may close the line, as long as the borrower remains healthy.
+    ///
+    /// # [Accounts](margin::accounts::CloseCreditLine)
+    ///
+    /// |     |     |     |
+    /// | --- | --- | --- |
+    /// | **Name** | **Type** | **Description** |
+    /// | `authority` | `signer` | The owner of the lender or the borrower account. |
+    /// | `receiver` | `writable` | The account that paid the rent of the line, which receives it back. |
+    /// | `lender_account` | `writable` | The margin account lending its collateral. |
+    /// | `borrower_account` | `writable` | The margin account borrowing against it. |
+    /// | `credit_line` | `writable` | The line to close. |
+    ///
+    /// # Events
+    ///
+    /// |     |     |
+    /// | --- | --- |
+    /// | **Event Name** | **Description** |
+    /// | [`events::CreditLineClosed`] | Marks the closing of the line. |
+    pub fn close_credit_line(ctx: Context<CloseCreditLine>) -> Result<()> {
+        close_credit_line_handler(ctx)
+    }
+
+    /// Seize collateral from the lender of a credit line into the borrower being
+    /// liquidated, reducing the limit of the line by the value seized.
+    ///
+    /// The seized tokens count as collateral of the borrower in place of the line, so
+    /// that its liquidator can use them to repay its debt.
+    ///
+    /// The line counts in full as collateral of the borrower, while the seized tokens are
+    /// weighted by their collateral weight. The equity of the borrower is unchanged, apart
+    /// from rounding in favor of the lender, but its weighted collateral drops by the value
+    /// seized times one minus the weight of the collateral. The value seized is therefore
+    /// limited to the collateral the borrower lacks to meet its required collateral.
+    ///
+    /// # Parameters
+    ///
+    /// * `amount` - The amount of collateral tokens to seize
+    ///
+    /// # [Accounts](margin::accounts::LiquidatorSeizeCredit)
+    ///
+    /// |     |     |     |
+    /// | --- | --- | --- |
+    /// | **Name** | **Type** | **Description** |
+    /// | `liquidator` | `signer` | The liquidator processing the borrower. |
+    /// | `liquidation` | `read_only` | The state of the liquidation. |
+    /// | `margin_account` | `writable` | The borrower being liquidated. |
+    /// | `lender_account` | `writable` | The lender whose collateral is seized. |
+    /// | `credit_line` | `writable` | The line backing the borrower. |
+    /// | `seize_mint` | `read_only` | The mint of the collateral being seized. |
+    /// | `seize_source` | `writable` | The collateral position of the lender. |
+    /// | `seize_destination` | `writable` | The position of the borrower receiving it. |
+    /// | `seize_token_program` | `read_only` | The token program of the collateral. |
+    ///
+    /// # Events
+    ///
+    /// |     |     |
+    /// | --- | --- |
+    /// | **Event Name** | **Description** |
+    /// | [`events::CreditLineSeized`] | Marks the seizure of the collateral. |
+    pub fn liquidator_seize_credit(ctx: Context<LiquidatorSeizeCredit>, amount: u64) -> Result<()> {
+        liquidator_seize_credit_handler(ctx, amount)
+    }
+
     /// Perform an action by invoking other programs, allowing them only to
     /// refresh the state of the margin account to be consistent with the actual
     /// underlying prices or positions, but not permitting new position changes.
@@ -430,7 +807,8 @@ pub mod margin {
     /// until the liquidator process is complete.
     ///
     /// Requires the `liquidator_metadata` account, which restricts the signer to
-    /// those approved by protocol governance.
+    /// those approved by protocol governance. If the airspace allows permissionless
+    /// liquidation, the permit can be omitted, allowing any signer to liquidate.
     ///
     /// # [Accounts](margin::accounts::LiquidateBegin)
     ///
@@ -440,7 +818,8 @@ pub mod margin {
     /// | `margin_account` | `writable` | The account in need of liquidation. |
     /// | `payer` | `signer` | The address paying rent. |
     /// | `liquidator` | `signer` | The liquidator account performing the liquidation. |
-    /// | `permit`| `account`| The permit allowing the liquidator to do this. |
+    /// | `permit`| `account`| The permit allowing the liquidator to do this, optional if liquidation is permissionless. |
+    /// | `airspace` | `read_only` | The airspace of the margin account, which sets whether a permit is required and the protocol's share of the liquidation fees. |
     /// | `liquidation` | `writable` | The account to persist the state of liquidation. |
     /// | `system_program` | `read_only` | The [system native program](https://docs.solana.com/developing/runtime-facilities/programs#system-program). |
     ///
@@ -478,12 +857,45 @@ pub mod margin {
         liquidate_end_handler(ctx)
     }
 
+    /// End a batch of liquidations that have passed the [timeout period](margin::LIQUIDATION_TIMEOUT)
+    ///
+    /// Anyone may call this to clean up liquidations abandoned by their liquidator. The keeper
+    /// is paid the [bounty](margin::LIQUIDATION_TIMEOUT_KEEPER_BOUNTY) for each liquidation it
+    /// ends, out of the rent of the liquidation state, and the rest of the rent is returned to
+    /// the liquidator. Liquidations that have already ended or have not timed out yet are
+    /// skipped, so that competing keepers do not fail each other's batches.
+    ///
+    /// # [Accounts](margin::accounts::EndTimedOutLiquidations)
+    ///
+    /// |     |     |     |
+    /// | --- | --- | --- |
+    /// | **Name** | **Type** | **Description** |
+    /// | `keeper` | `signer` | The pubkey ending the liquidations, which receives the bounty. |
+    ///
+    /// The remaining accounts are triples of `margin_account`, `liquidation` and `liquidator`,
+    /// all `writable`, see [TIMED_OUT_LIQUIDATION_ACCOUNTS].
+    ///
+    /// # Events
+    ///
+    /// |     |     |
+    /// | --- | --- |
+    /// | **Event Name** | **Description** |
+    /// | [`events::LiquidationEnded`] | Marks the ending of each liquidation. |
+    pub fn end_timed_out_liquidations<'a, 'b, 'c: 'info, 'info>(
+        ctx: Context<'a, 'b, 'c, 'info, EndTimedOutLiquidations<'info>>,
+    ) -> Result<()> {
+        end_timed_out_liquidations_handler(ctx)
+    }
+
     /// Perform an action by invoking another program, for the purposes of
     /// liquidating a margin account.
     ///
     /// Requires the account already be in the liquidation state, and the signer must
     /// be the same liquidator that started the liquidation state.
     ///
+    /// The liquidator accrues a fee in every mint repaid by the invoked instructions,
+    /// growing the liquidation account if it has no free slot for a mint.
+    ///
     /// # [Accounts](margin::accounts::LiquidatorInvoke)
     /// |     |     |     |
     /// | --- | --- | --- |
@@ -491,6 +903,7 @@ pub mod margin {
     /// | `liquidator` | `signer` | The liquidator processing the margin account. |
     /// | `liquidation` | `writable` | The account to persist the state of liquidation. |
     /// | `margin_account` | `writable` | The margin account to proxy an action for. |
+    /// | `system_program` | `read_only` | The system program, to pay for growing the liquidation account. |
     /// | `adapter_program` | `read_only` | The program to be invoked. |
     /// | `adapter_metadata` | `read_only` | The metadata about the proxy program. |
     ///
@@ -509,6 +922,53 @@ pub mod margin {
         liquidator_invoke_handler(ctx, instructions)
     }
 
+    /// Repay a debt of a margin account being liquidated from the liquidator's own margin
+    /// account, and seize its collateral in return.
+    ///
+    /// The repayment is transferred into the liquidated account and used by the given
+    /// adapter instructions, e.g. a pool repay, so the liquidation does not depend on
+    /// swapping the collateral. The liquidator's account then receives collateral worth
+    /// the repaid value plus the liquidation bonus of the seized token, which counts towards
+    /// the equity loss of the liquidation. The liquidator's account has to remain healthy.
+    ///
+    /// # Parameters
+    ///
+    /// * `repay_amount` - The amount of tokens to transfer from the liquidator's account.
+    /// * `instructions` - The adapter instructions that repay the debt.
+    ///
+    /// # [Accounts](margin::accounts::LiquidatorSeize)
+    /// |     |     |     |
+    /// | --- | --- | --- |
+    /// | **Name** | **Type** | **Description** |
+    /// | `liquidator` | `signer` | The liquidator processing the margin account. |
+    /// | `liquidation` | `writable` | The account to persist the state of liquidation. |
+    /// | `margin_account` | `writable` | The margin account being liquidated. |
+    /// | `liquidator_account` | `writable` | The liquidator's margin account. |
+    /// | `repay_mint` | `read_only` | The mint of the debt being repaid. |
+    /// | `repay_source` | `writable` | The deposit of the liquidator's account funding the repayment. |
+    /// | `repay_destination` | `writable` | The deposit of the liquidated account the debt is repaid from. |
+    /// | `repay_token_program` | `read_only` | The token program of the repaid token. |
+    /// | `seize_mint` | `read_only` | The mint of the seized collateral. |
+    /// | `seize_token_config` | `read_only` | The config of the seized collateral, which sets the bonus. |
+    /// | `seize_source` | `writable` | The collateral position of the liquidated account. |
+    /// | `seize_destination` | `writable` | The position of the liquidator's account receiving the collateral. |
+    /// | `seize_token_program` | `read_only` | The token program of the seized collateral. |
+    ///
+    /// # Events
+    ///
+    /// |     |     |
+    /// | --- | --- |
+    /// | **Event Name** | **Description** |
+    /// | [`events::PositionEvent`] _(Note that each single event represents an different adapter position)_ | The [PositionEvent](events::PositionEvent) describing the change in position. |
+    /// | [`events::LiquidatorSeize`] | Marks the repayment and the seized collateral. |
+    pub fn liquidator_seize<'a, 'b, 'c: 'info, 'info>(
+        ctx: Context<'a, 'b, 'c, 'info, LiquidatorSeize<'info>>,
+        repay_amount: u64,
+        instructions: Vec<crate::adapter::IxData>,
+    ) -> Result<()> {
+        liquidator_seize_handler(ctx, repay_amount, instructions)
+    }
+
     /// Update the config for a token position stored in the margin account,
     /// in the case where the token config has changed after the position was
     /// created.
@@ -517,7 +977,9 @@ pub mod margin {
     }
 
     /// Refresh the price/balance for a deposit position
-    pub fn refresh_deposit_position(ctx: Context<RefreshDepositPosition>) -> Result<()> {
+    pub fn refresh_deposit_position<'a, 'b, 'c: 'info, 'info>(
+        ctx: Context<'a, 'b, 'c, 'info, RefreshDepositPosition<'info>>,
+    ) -> Result<()> {
         refresh_deposit_position_handler(ctx)
     }
 
@@ -527,7 +989,13 @@ pub mod margin {
     }
 
     /// Transfer tokens into or out of a token account being used for deposits.
-    pub fn transfer_deposit(ctx: Context<TransferDeposit>, amount: u64) -> Result<()> {
+    ///
+    /// The token config of an isolated collateral has to be provided as a remaining account
+    /// if the transfer gives the account an isolated collateral while it has claims.
+    pub fn transfer_deposit<'a, 'b, 'c: 'info, 'info>(
+        ctx: Context<'a, 'b, 'c, 'info, TransferDeposit<'info>>,
+        amount: u64,
+    ) -> Result<()> {
         transfer_deposit_handler(ctx, amount)
     }
 
@@ -540,6 +1008,42 @@ pub mod margin {
         configure_token_handler(ctx, update)
     }
 
+    /// Set the risk parameters of a token.
+    ///
+    /// * `initial_value_modifier` - A stricter value modifier than the token's value modifier,
+    ///   used instead of it when checking that an instruction does not leave a margin account
+    ///   below its initial margin requirement. Zero disables the initial margin.
+    /// * `value_modifier_ramp` - Moves the value modifier linearly from its currently effective
+    ///   value to a target, which gives margin accounts time to deleverage when collateral
+    ///   weights are lowered.
+    /// * `isolation` - An account holding an isolated collateral may only borrow claims that
+    ///   are borrowable in isolation, and the total debt backed by the isolated collateral
+    ///   across the airspace is limited to a debt ceiling. The debt ceiling is a USD value with
+    ///   an exponent of [ISOLATED_DEBT_EXPONENT].
+    /// * `max_collateral_share` - A token only counts towards the weighted collateral of a
+    ///   margin account up to this percentage of the account's total weighted collateral.
+    ///   Tokens in the same concentration group, such as a token and the deposit notes of its
+    ///   pool, count towards a single limit. Zero removes the limit.
+    /// * `size_haircut_threshold` - Above this collateral value, the weight of a position
+    ///   decays with the square root of its value, as large positions in thin markets cost
+    ///   more to liquidate. The threshold is a USD value with an exponent of
+    ///   [SIZE_HAIRCUT_THRESHOLD_EXPONENT]. Zero removes the haircut.
+    /// * `liquidation_bonus_bps` - The bonus a liquidator receives on the value of this
+    ///   collateral when seizing it, which can be at most the max equity loss of a
+    ///   liquidation. Zero restores the [default](LIQUIDATION_SEIZURE_BONUS_BPS).
+    ///
+    /// Parameters that are not set in the update are left unchanged. Positions pick up the
+    /// changes when their config is refreshed, except for the liquidation bonus, which is
+    /// read from the token config when collateral is seized.
+    ///
+    /// Changing the risk parameters requires the airspace authority to sign.
+    pub fn configure_token_risk(
+        ctx: Context<ConfigureTokenRisk>,
+        update: TokenRiskUpdate,
+    ) -> Result<()> {
+        configure_token_risk_handler(ctx, update)
+    }
+
     /// Set the configuration for an adapter.
     ///
     /// The configuration for a token only applies for the associated airspace, and changing any
@@ -551,6 +1055,36 @@ pub mod margin {
         configure_adapter_handler(ctx, is_adapter)
     }
 
+    /// Set the invoke permissions for an adapter.
+    ///
+    /// This controls whether the adapter can be invoked through `liquidator_invoke` and
+    /// `accounting_invoke`, and optionally restricts the adapter instructions that can be
+    /// invoked to an allowlist of instruction discriminators.
+    ///
+    /// Changing the permissions requires the airspace authority to sign.
+    pub fn configure_adapter_permissions(
+        ctx: Context<ConfigureAdapterPermissions>,
+        update: AdapterPermissionsUpdate,
+    ) -> Result<()> {
+        configure_adapter_permissions_handler(ctx, update)
+    }
+
+    /// Set the limits on the value that may flow out of margin accounts through an adapter
+    /// during `adapter_invoke`.
+    ///
+    /// A window limit is shared by all margin accounts in the airspace, and its outflow is
+    /// recorded in the adapter config. Every `adapter_invoke` of an adapter with a window
+    /// limit then has to pass the config as writable, which serializes those invocations
+    /// across all users of the adapter.
+    ///
+    /// Changing the limits requires the airspace authority to sign.
+    pub fn configure_adapter_outflow_limits(
+        ctx: Context<ConfigureAdapterPermissions>,
+        limits: AdapterOutflowLimits,
+    ) -> Result<()> {
+        configure_adapter_outflow_limits_handler(ctx, limits)
+    }
+
     /// Set the configuration for a liquidator.
     ///
     /// The configuration for a token only applies for the associated airspace, and changing any
@@ -608,16 +1142,40 @@ pub mod margin {
         append_to_lookup_handler(ctx, addresses)
     }
 
-    /// Collect accrued liquidation fees
-    pub fn collect_liquidation_fee(ctx: Context<CollectLiquidationFee>) -> Result<()> {
+    /// Collect the liquidation fees accrued in any number of mints.
+    ///
+    /// The accounts of each collected fee are passed as remaining accounts, see
+    /// [FEE_COLLECTION_ACCOUNTS]. The protocol's share of each fee goes to the
+    /// liquidation fee treasury of the airspace. If the liquidation began while the
+    /// airspace did not share fees with the protocol, the treasury token account can be
+    /// omitted by passing the margin program id in its place.
+    pub fn collect_liquidation_fee<'a, 'b, 'c: 'info, 'info>(
+        ctx: Context<'a, 'b, 'c, 'info, CollectLiquidationFee<'info>>,
+    ) -> Result<()> {
         collect_liquidation_fee_handler(ctx)
     }
 
+    /// Claim tokens from the treasury that receives the protocol's share of the
+    /// liquidation fees in an airspace, see [glow_airspace::state::Airspace].
+    ///
+    /// Only the airspace authority may claim from the treasury.
+    pub fn claim_liquidation_fee_treasury(
+        ctx: Context<ClaimLiquidationFeeTreasury>,
+        amount: u64,
+    ) -> Result<()> {
+        claim_liquidation_fee_treasury_handler(ctx, amount)
+    }
+
     /// Migrate token configs
     pub fn migrate_token_config(ctx: Context<MigrateTokenConfig>) -> Result<()> {
         migrate_token_config_handler(ctx)
     }
 
+    /// Migrate adapter configs
+    pub fn migrate_adapter_config(ctx: Context<MigrateAdapterConfig>) -> Result<()> {
+        migrate_adapter_config_handler(ctx)
+    }
+
     /// Configure margin account constraints
     pub fn configure_account_constraints(
         ctx: Context<ConfigureAccountConstraints>,
@@ -625,6 +1183,22 @@ pub mod margin {
     ) -> Result<()> {
         configure_account_constraints_handler(ctx, account_constraints)
     }
+
+    /// Configure the destinations that tokens may be sent to from a margin account whose
+    /// destinations are restricted by the signing adapter
+    pub fn configure_constraint_destinations(
+        ctx: Context<ConfigureConstraintDestinations>,
+        destinations: Vec<Pubkey>,
+    ) -> Result<()> {
+        configure_constraint_destinations_handler(ctx, destinations)
+    }
+
+    /// Migrate an account constraint ticket to support multiple constraint holders
+    pub fn migrate_account_constraint_ticket(
+        ctx: Context<MigrateAccountConstraintTicket>,
+    ) -> Result<()> {
+        migrate_account_constraint_ticket_handler(ctx)
+    }
 }
 
 #[error_code]
@@ -854,6 +1428,155 @@ pub enum ErrorCode {
     /// 141090 - Delegate accounts cannot withdraw directly to wallets
     #[msg("delegate account cannot withdraw directly to wallet")]
     AccountConstraintWithdrawal = 135_090,
+
+    /// 141091 - The adapter is not permitted to be invoked through this instruction
+    #[msg("adapter is not permitted to be invoked through this instruction")]
+    AdapterInvokeNotPermitted,
+
+    /// 141092 - The adapter instruction is not in the adapter's allowlist
+    #[msg("adapter instruction is not allowed")]
+    AdapterInstructionNotAllowed,
+
+    /// 141093 - Too many instructions allowlisted for an adapter
+    #[msg("Invalid configuration (too many allowed adapter instructions)")]
+    InvalidConfigAdapterInstructions,
+
+    /// 141094 - The value leaving a margin account through an adapter exceeds its limits
+    #[msg("adapter outflow limit exceeded")]
+    AdapterOutflowLimitExceeded,
+
+    /// 141095 - An outflow window limit was configured without a window duration
+    #[msg("Invalid configuration (adapter outflow window)")]
+    InvalidConfigAdapterOutflowLimits,
+
+    /// 141096 - The adapter does not hold constraints on the margin account
+    #[msg("adapter does not hold constraints on the margin account")]
+    AccountConstraintNotHeld,
+
+    /// 141097 - No more adapters can hold constraints on the margin account
+    #[msg("margin account constraint holders are full")]
+    AccountConstraintHoldersFull,
+
+    /// 141098 - The account constraint ticket is missing or does not belong to the margin account
+    #[msg("invalid account constraint ticket")]
+    InvalidAccountConstraintTicket,
+
+    /// 141099 - Too many destinations allowlisted for a constraint holder
+    #[msg("too many allowed destinations for the account constraint")]
+    AccountConstraintDestinationsFull,
+
+    /// 141100 - The account does not meet its initial margin requirement
+    #[msg("account does not meet its initial margin requirement")]
+    InitialMarginNotMet,
+
+    /// 141101 - The initial value modifier is less strict than the value modifier
+    #[msg("invalid initial value modifier")]
+    InvalidConfigInitialValueModifier,
+
+    /// 141102 - The oracle limits of a token exceed their allowed range
+    #[msg("invalid oracle limits")]
+    InvalidConfigOracleLimits,
+
+    /// 141103 - The value modifier ramp is invalid
+    #[msg("invalid value modifier ramp")]
+    InvalidConfigValueModifierRamp,
+
+    /// 141104 - The isolation mode of a token is invalid
+    #[msg("invalid isolation mode configuration")]
+    InvalidConfigIsolation,
+
+    /// 141105 - The debt backed by an isolated collateral would exceed its debt ceiling
+    #[msg("isolated collateral debt ceiling exceeded")]
+    IsolationDebtCeilingExceeded,
+
+    /// 141106 - An account holding an isolated collateral has positions not allowed in isolation
+    #[msg("position not allowed in isolation mode")]
+    IsolationModeViolation,
+
+    /// 141107 - The token config of an isolated collateral was not provided
+    #[msg("isolated collateral token config is required")]
+    IsolationConfigMissing,
+
+    /// 141108 - The concentration limit of a token is invalid
+    #[msg("invalid concentration limit")]
+    InvalidConfigConcentrationLimit,
+
+    /// 141109 - The size haircut of a token is invalid
+    #[msg("invalid size haircut")]
+    InvalidConfigSizeHaircut,
+
+    /// 141110 - The collateral seized by a liquidator is not covered by its repayment
+    #[msg("invalid collateral seizure")]
+    InvalidLiquidationSeizure,
+
+    /// 141111 - Deleveraging an unhealthy account lost more equity than allowed
+    #[msg("deleveraging lost too much value")]
+    DeleverageLostValue,
+
+    /// 141112 - The parameters of a trigger order are invalid
+    #[msg("invalid trigger order")]
+    InvalidTriggerOrder,
+
+    /// 141113 - The oracle price does not meet the condition of a trigger order
+    #[msg("trigger order condition not met")]
+    TriggerOrderNotTriggered,
+
+    /// 141114 - A trigger order was executed with a swap outside of its route
+    #[msg("trigger order route violated")]
+    TriggerOrderRouteViolated,
+
+    /// 141115 - The swap of a trigger order lost more value than its maximum slippage
+    #[msg("trigger order slippage exceeded")]
+    TriggerOrderSlippageExceeded,
+
+    /// 141116 - A deleverage order repaid debt outside of its bounds, or did not improve the account
+    #[msg("invalid deleverage order repayment")]
+    DeleverageOrderRepaymentInvalid,
+
+    /// 141117 - The margin accounts of a credit line are invalid
+    #[msg("invalid credit line")]
+    InvalidCreditLine,
+
+    /// 141118 - More value was seized from a lender than the limit of its credit line
+    #[msg("credit line limit exceeded")]
+    CreditLineLimitExceeded,
+
+    /// 141119 - The invoke flags of an adapter are unknown
+    #[msg("invalid adapter invoke flags")]
+    InvalidConfigAdapterInvokeFlags,
+
+    /// 141120 - A limit denominated in USD does not apply to the numeraire of the account
+    #[msg("limit in USD cannot be applied in the numeraire of the account")]
+    UsdLimitInNumeraire,
+
+    /// 141121 - The liquidation bonus of a token is not valid
+    #[msg("invalid liquidation bonus")]
+    InvalidConfigLiquidationBonus,
+
+    /// 141122 - A liquidation that shares its fees with the protocol needs the token
+    /// account of the liquidation fee treasury
+    #[msg("liquidation fee treasury token account required")]
+    LiquidationFeeTreasuryRequired,
+
+    /// 141123 - The swap of a trigger order sold more than the size of the order
+    #[msg("trigger order size exceeded")]
+    TriggerOrderSizeExceeded,
+
+    /// 141124 - The constraints of a margin account can only change while it has no positions
+    #[msg("account constraints cannot change while the account has open positions")]
+    ConstraintsWithOpenPositions,
+
+    /// 141125 - Only collateral held by a margin account can be seized from it
+    #[msg("seized token is not collateral held by the margin account")]
+    SeizedTokenNotCollateral,
+
+    /// 141126 - More value was seized from a lender than the collateral its borrower lacks
+    #[msg("credit line seizure exceeds the shortfall of the borrower")]
+    CreditLineSeizureExceedsShortfall,
+
+    /// 141127 - A credit line would be backed by the credit of another line
+    #[msg("credit line backed by another credit line")]
+    CreditLineRelending,
 }
 
 /// Writes the result of position changes from an adapter invocation.
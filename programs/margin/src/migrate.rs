@@ -1,6 +1,6 @@
 use anchor_lang::prelude::*;
 
-use crate::{TokenAdmin, TokenKind};
+use crate::{AccountConstraints, TokenAdmin, TokenKind};
 
 #[account]
 #[derive(Debug, Eq, PartialEq)]
@@ -36,3 +36,22 @@ pub struct TokenConfig {
     /// about (e.g. prices) and otherwise modify position states for these tokens.
     pub admin: TokenAdmin,
 }
+
+/// Configuration for allowed adapters, before invoke permissions were added
+#[account]
+#[derive(Debug, Eq, PartialEq)]
+pub struct AdapterConfig {
+    /// The airspace this adapter can be used in
+    pub airspace: Pubkey,
+
+    /// The program address allowed to be called as an adapter
+    pub adapter_program: Pubkey,
+}
+
+/// A constraint ticket, before multiple adapters could hold constraints
+#[account]
+pub struct AccountConstraintTicket {
+    pub adapter: Pubkey,
+    pub margin_account: Pubkey,
+    pub constraints: AccountConstraints,
+}
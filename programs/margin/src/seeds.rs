@@ -34,3 +34,15 @@ pub const PERMIT_SEED: &[u8] = b"permit";
 
 #[constant]
 pub const MARGIN_ACCOUNT_CONSTRAINT_SEED: &[u8] = b"margin-account-constraint";
+
+#[constant]
+pub const LIQUIDATION_FEE_TREASURY_SEED: &[u8] = b"liquidation-fee-treasury";
+
+#[constant]
+pub const TRIGGER_ORDER_SEED: &[u8] = b"trigger-order";
+
+#[constant]
+pub const DELEVERAGE_ORDER_SEED: &[u8] = b"deleverage-order";
+
+#[constant]
+pub const CREDIT_LINE_SEED: &[u8] = b"credit-line";
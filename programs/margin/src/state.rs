@@ -1,5 +1,9 @@
 mod account;
 mod config;
+mod credit_line;
+mod order;
 
 pub use account::*;
 pub use config::*;
+pub use credit_line::*;
+pub use order::*;
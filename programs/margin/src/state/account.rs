@@ -23,6 +23,7 @@ use bytemuck::{Contiguous, Pod, Zeroable};
 use serde::ser::{Serialize, SerializeStruct, Serializer};
 
 use glow_program_common::Number128;
+use glow_program_proc_macros::assert_size;
 
 use anchor_lang::Result as AnchorResult;
 use std::result::Result;
@@ -30,14 +31,15 @@ use std::result::Result;
 use crate::{
     syscall::{sys, Sys},
     util::{Invocation, Require},
-    ErrorCode, TokenKind, MAX_PRICE_QUOTE_AGE, MAX_USER_POSITIONS,
+    CreditLine, ErrorCode, TokenConfig, TokenKind, LIQUIDATION_MAX_EQUITY_LOSS_PROPORTION_BPS,
+    MAX_USER_POSITIONS,
 };
 
 mod positions;
 
 pub use positions::*;
 
-use super::TokenFeatures;
+use super::{IsolationFlags, TokenFeatures};
 
 #[account(zero_copy)]
 #[repr(C)]
@@ -81,10 +83,21 @@ bitflags! {
         const ACCEPTS_SOL_BASED = 1 << 2;
         /// The account only accepts WBTC-based tokens (e.g. WBTC, lBTC, et al)
         const ACCEPTS_WBTC_BASED = 1 << 3;
-
+        /// The account is valued in SOL rather than USD, so that its health does not
+        /// depend on the SOL/USD price. Requires `ACCEPTS_SOL_BASED`.
+        const SOL_NUMERAIRE = 1 << 4;
     }
 }
 
+/// The unit of account in which the positions of a margin account are valued
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Numeraire {
+    /// Positions are valued in USD
+    Usd,
+    /// Positions are valued in SOL
+    Sol,
+}
+
 /// If a margin account has any constraint, it cannot be closed except by the adapter that set the constraint.
 #[repr(transparent)]
 #[derive(
@@ -101,6 +114,11 @@ bitflags! {
         const DENY_DEPOSITS = 1 << 1;
         /// Deny transfers between margin accounts.
         const DENY_TRANSFERS = 1 << 2;
+        /// Deny borrowing, so that the margin account cannot take on debt.
+        const DENY_BORROWS = 1 << 3;
+        /// Only allow tokens to leave the margin account to destinations allowlisted by
+        /// the holder of this constraint on the [AccountConstraintTicket].
+        const RESTRICT_DESTINATIONS = 1 << 4;
     }
 }
 
@@ -136,7 +154,37 @@ impl AccountFeatureFlags {
         token_features: TokenFeatures,
     ) -> anchor_lang::Result<bool> {
         let other = Self::try_from(token_features)?;
-        Ok(*self == other)
+        // The numeraire does not restrict which tokens are accepted
+        let mut flags = *self;
+        flags.remove(Self::SOL_NUMERAIRE);
+        Ok(flags == other)
+    }
+
+    /// Check that the flags can be set on a new margin account.
+    ///
+    /// At most one token restriction can be set, and a SOL numeraire can only be used by
+    /// accounts that only accept SOL based tokens.
+    pub fn check_valid_for_new_account(&self) -> anchor_lang::Result<()> {
+        require!(
+            !self.contains_unknown_bits(),
+            ErrorCode::UnknownFeatureFlags
+        );
+        require!(
+            !self.contains(Self::VIOLATION),
+            ErrorCode::InvalidFeatureFlags
+        );
+        let mut restrictions = *self;
+        restrictions.remove(Self::SOL_NUMERAIRE);
+        require!(
+            restrictions.bits().count_ones() <= 1,
+            ErrorCode::InvalidFeatureFlags
+        );
+        require!(
+            !self.contains(Self::SOL_NUMERAIRE) || self.contains(Self::ACCEPTS_SOL_BASED),
+            ErrorCode::InvalidFeatureFlags
+        );
+
+        Ok(())
     }
 }
 
@@ -156,9 +204,16 @@ impl AccountFeatureFlags {
 ///
 /// This enforces that only the vault program will be able to unconstrain the margin account.
 ///
+/// Several adapters can hold constraints on the same margin account, each in its own
+/// [ConstraintHolder] slot. The constraints of all holders are combined into
+/// [MarginAccount::constraints], and each adapter can only change or release its own.
+/// Each holder also carries the destinations it allows, so tickets of the single adapter
+/// layout only need one migration to this layout.
+///
 /// When the owner wants to close the margin account, they cannot close the account as long
 /// as it has constraints, allowing the vault program to be the only program that can release
-/// the constraint by deregistering the account from its list of positions.
+/// the constraint by deregistering the account from its list of positions. The account can
+/// only be closed once every holder has released its constraints.
 ///
 /// We considered alternatives like:
 /// * Amending `create_account` to take account constraints - this requires a [Permit]
@@ -171,9 +226,131 @@ impl AccountFeatureFlags {
 ///   accounting to that adapter would cause accounting issues or be a risk of loss of funds.
 #[account]
 pub struct AccountConstraintTicket {
-    pub adapter: Pubkey,
     pub margin_account: Pubkey,
+    pub holders: [ConstraintHolder; MAX_CONSTRAINT_HOLDERS],
+}
+
+/// The maximum number of adapters that can hold constraints on a margin account
+pub const MAX_CONSTRAINT_HOLDERS: usize = 4;
+
+/// The maximum number of destinations an adapter can allowlist for a margin account
+pub const MAX_CONSTRAINT_DESTINATIONS: usize = 4;
+
+/// The constraints placed on a margin account by a single adapter
+#[derive(AnchorSerialize, AnchorDeserialize, Default, Clone, Copy, PartialEq, Debug)]
+pub struct ConstraintHolder {
+    /// The adapter holding the constraints, or the default address for an empty slot
+    pub adapter: Pubkey,
+
+    /// The constraints placed by the adapter
     pub constraints: AccountConstraints,
+
+    /// The token accounts that tokens may be sent to when the adapter restricts
+    /// destinations, with the default address for empty slots
+    pub allowed_destinations: [Pubkey; MAX_CONSTRAINT_DESTINATIONS],
+}
+
+impl AccountConstraintTicket {
+    /// The constraints of all holders combined
+    pub fn combined_constraints(&self) -> AccountConstraints {
+        self.holders
+            .iter()
+            .fold(AccountConstraints::empty(), |all, holder| {
+                all | holder.constraints
+            })
+    }
+
+    /// Check if no adapter holds any constraints
+    pub fn is_released(&self) -> bool {
+        self.holders
+            .iter()
+            .all(|holder| holder.adapter == Pubkey::default())
+    }
+
+    /// Set the constraints held by an adapter, adding it as a holder if it is not one yet.
+    ///
+    /// Empty constraints release the adapter's hold on the account.
+    pub fn set_holder_constraints(
+        &mut self,
+        adapter: Pubkey,
+        constraints: AccountConstraints,
+    ) -> AnchorResult<()> {
+        if let Some(holder) = self.holders.iter_mut().find(|h| h.adapter == adapter) {
+            match constraints.is_empty() {
+                true => *holder = ConstraintHolder::default(),
+                false => holder.constraints = constraints,
+            }
+            return Ok(());
+        }
+
+        // An adapter that holds no constraints has nothing to release
+        require!(!constraints.is_empty(), ErrorCode::AccountConstraintNotHeld);
+
+        let slot = self
+            .holders
+            .iter_mut()
+            .find(|h| h.adapter == Pubkey::default())
+            .ok_or(ErrorCode::AccountConstraintHoldersFull)?;
+        *slot = ConstraintHolder {
+            adapter,
+            constraints,
+            ..Default::default()
+        };
+
+        Ok(())
+    }
+
+    /// Set the destinations that an adapter allows tokens to be sent to
+    pub fn set_holder_destinations(
+        &mut self,
+        adapter: Pubkey,
+        destinations: &[Pubkey],
+    ) -> AnchorResult<()> {
+        require!(
+            destinations.len() <= MAX_CONSTRAINT_DESTINATIONS,
+            ErrorCode::AccountConstraintDestinationsFull
+        );
+
+        let holder = self
+            .holders
+            .iter_mut()
+            .find(|h| h.adapter == adapter && adapter != Pubkey::default())
+            .ok_or(ErrorCode::AccountConstraintNotHeld)?;
+
+        holder.allowed_destinations = Default::default();
+        holder.allowed_destinations[..destinations.len()].copy_from_slice(destinations);
+
+        Ok(())
+    }
+
+    /// Check if tokens may be sent to a destination, which has to be allowlisted by
+    /// every holder that restricts destinations.
+    pub fn allows_destination(&self, destination: &Pubkey) -> bool {
+        self.holders
+            .iter()
+            .filter(|h| {
+                h.constraints
+                    .contains(AccountConstraints::RESTRICT_DESTINATIONS)
+            })
+            .all(|h| h.allowed_destinations.contains(destination))
+    }
+
+    /// Read the ticket of a margin account from an account passed to an instruction
+    pub fn load_checked(info: Option<&AccountInfo>, margin_account: &Pubkey) -> AnchorResult<Self> {
+        let info = info.ok_or(ErrorCode::InvalidAccountConstraintTicket)?;
+        require!(
+            info.owner == &crate::ID,
+            ErrorCode::InvalidAccountConstraintTicket
+        );
+
+        let ticket = Self::try_deserialize(&mut &info.try_borrow_data()?[..])?;
+        require!(
+            &ticket.margin_account == margin_account,
+            ErrorCode::InvalidAccountConstraintTicket
+        );
+
+        Ok(ticket)
+    }
 }
 
 #[account(zero_copy)]
@@ -291,6 +468,32 @@ impl MarginAccount {
         }
     }
 
+    /// The unit of account in which the positions of this account are valued
+    pub fn numeraire(&self) -> Numeraire {
+        if self.features.contains(AccountFeatureFlags::SOL_NUMERAIRE) {
+            Numeraire::Sol
+        } else {
+            Numeraire::Usd
+        }
+    }
+
+    /// Check that the limits of a token which are denominated in USD can be applied to the
+    /// positions of this account, which is not the case in any other numeraire
+    fn check_usd_limits(
+        &self,
+        isolation: IsolationFlags,
+        size_haircut_threshold: u16,
+    ) -> Result<(), ErrorCode> {
+        if self.numeraire() != Numeraire::Usd
+            && (isolation.contains(IsolationFlags::ISOLATED) || size_haircut_threshold > 0)
+        {
+            msg!("tokens with limits in USD cannot be held in another numeraire");
+            return Err(ErrorCode::UsdLimitInNumeraire);
+        }
+
+        Ok(())
+    }
+
     pub fn is_liquidating(&self) -> bool {
         self.liquidator != Pubkey::default()
     }
@@ -340,6 +543,7 @@ impl MarginAccount {
         if self.airspace != config.airspace {
             return err!(ErrorCode::WrongAirspace);
         }
+        self.check_usd_limits(config.isolation, config.size_haircut_threshold)?;
 
         let token_features = config.token_features;
         // Check the position's feature flags, if the account's feature flags aren't empty.
@@ -365,9 +569,16 @@ impl MarginAccount {
             free_position.exponent = -(config.decimals as i16);
             free_position.address = config.address;
             free_position.adapter = config.adapter;
-            free_position.kind = config.kind.into_integer();
+            free_position.kind = config.kind.into_integer() as u8;
             free_position.balance = 0;
             free_position.value_modifier = config.value_modifier;
+            free_position.initial_value_modifier = config.initial_value_modifier;
+            free_position.set_oracle_limits(&config.oracle_limits);
+            free_position.set_value_modifier_ramp(&config.value_modifier_ramp);
+            free_position.isolation = config.isolation;
+            free_position.max_collateral_share = config.max_collateral_share;
+            free_position.concentration_group = config.concentration_group;
+            free_position.size_haircut_threshold = config.size_haircut_threshold;
             free_position.max_staleness = config.max_staleness;
             // NIT: This isn't a great way of indicating token support, because what happens if
             // there is token_2026 in future?
@@ -429,21 +640,26 @@ impl MarginAccount {
 
     pub fn refresh_position_metadata(
         &mut self,
-        mint: &Pubkey,
-        kind: TokenKind,
-        value_modifier: u16,
-        max_staleness: u64,
-        token_features: TokenFeatures,
+        config: &TokenConfig,
     ) -> Result<AccountPosition, ErrorCode> {
-        let position = match self.position_list_mut().get_mut(mint) {
+        self.check_usd_limits(config.isolation.flags, config.size_haircut_threshold)?;
+
+        let position = match self.position_list_mut().get_mut(&config.mint) {
             None => return Err(ErrorCode::PositionNotRegistered),
             Some(p) => p,
         };
 
-        position.kind = kind.into_integer();
-        position.value_modifier = value_modifier;
-        position.max_staleness = max_staleness;
-        position.token_features = token_features;
+        position.kind = config.token_kind.into_integer() as u8;
+        position.value_modifier = config.value_modifier;
+        position.initial_value_modifier = config.initial_value_modifier;
+        position.max_staleness = config.max_staleness;
+        position.token_features = config.token_features;
+        position.set_oracle_limits(&config.oracle_limits);
+        position.set_value_modifier_ramp(&config.value_modifier_ramp);
+        position.isolation = config.isolation.flags;
+        position.max_collateral_share = config.max_collateral_share;
+        position.concentration_group = config.concentration_group;
+        position.size_haircut_threshold = config.size_haircut_threshold;
 
         Ok(*position)
     }
@@ -565,21 +781,35 @@ impl MarginAccount {
 
         //  Accumulated from Collateral positions, adjusted by a collateral weight (value_modifier):
         let mut weighted_collateral = Number128::ZERO;
+
+        //  The same totals, using the stricter initial_value_modifier of each position
+        let mut initial_required_collateral = Number128::ZERO;
+        let mut initial_weighted_collateral = Number128::ZERO;
         let mut stale_collateral_list = vec![];
         let mut equity = Number128::ZERO;
 
+        // Weighted collateral counted towards concentration limits, per group or position
+        let mut concentrated = [ConcentratedCollateral::default(); 32];
+        let mut concentrated_len = 0;
+
         // Iterates through all the positions in the margin account
         for position in self.positions() {
-            if position.balance == 0 {
+            let kind = position.kind();
+
+            // Non-collateral tokens count for nothing, so their prices are irrelevant
+            if position.balance == 0 || kind == TokenKind::NonCollateral {
                 continue;
             }
-            let kind = position.kind();
             let stale_reason = {
                 let balance_age = timestamp - position.balance_timestamp;
                 let price_quote_age = timestamp - position.price.timestamp;
 
+                // credit lines have a fixed price, and their balance is always current
+                if CreditLine::is_position(position) {
+                    None
+                }
                 // collateral with bad prices
-                if !position.price.is_valid() {
+                else if !position.price.is_valid() {
                     msg!("Bad collateral {:?}", position);
                     Some(ErrorCode::InvalidPrice)
                 }
@@ -588,7 +818,7 @@ impl MarginAccount {
                     Some(ErrorCode::OutdatedBalance)
                 }
                 // outdated price
-                else if price_quote_age > MAX_PRICE_QUOTE_AGE {
+                else if price_quote_age > position.oracle_limits().max_price_quote_age() {
                     Some(ErrorCode::OutdatedPrice)
                 } else {
                     None
@@ -605,7 +835,9 @@ impl MarginAccount {
 
                     equity -= position.value();
                     liabilities += position.value();
-                    required_collateral += position.required_collateral_value();
+                    required_collateral += position.required_collateral_value(timestamp);
+                    initial_required_collateral +=
+                        position.initial_required_collateral_value(timestamp);
                 }
                 (TokenKind::Claim, Some(error)) => {
                     msg!("claim position is stale: {:?}", position);
@@ -613,25 +845,81 @@ impl MarginAccount {
                 }
 
                 (TokenKind::AdapterCollateral | TokenKind::Collateral, None) => {
+                    let collateral_value = position.collateral_value(timestamp);
+                    let initial_collateral_value = position.initial_collateral_value(timestamp);
+
                     equity += position.value();
-                    weighted_collateral += position.collateral_value();
+                    weighted_collateral += collateral_value;
+                    initial_weighted_collateral += initial_collateral_value;
+
+                    if position.max_collateral_share > 0 || position.concentration_group > 0 {
+                        let group = position.concentration_group;
+                        let existing = match group {
+                            0 => None,
+                            _ => concentrated[..concentrated_len]
+                                .iter()
+                                .position(|c| c.group == group),
+                        };
+                        let index = existing.unwrap_or(concentrated_len);
+                        let Some(collateral) = concentrated.get_mut(index) else {
+                            msg!("too many concentrated positions");
+                            return err!(ErrorCode::MaxPositions);
+                        };
+                        if index == concentrated_len {
+                            concentrated_len += 1;
+                        }
+
+                        collateral.add(
+                            group,
+                            position.max_collateral_share,
+                            collateral_value,
+                            initial_collateral_value,
+                        );
+                    }
                 }
 
                 // Stale Collateral is excluded from being counted, added to stale_collateral_list
                 (TokenKind::AdapterCollateral | TokenKind::Collateral, Some(e)) => {
                     stale_collateral_list.push((position.token, e));
                 }
+
+                // Skipped above, as non-collateral tokens are never valued
+                (TokenKind::NonCollateral, _) => (),
+            }
+        }
+
+        // A token or concentration group only counts towards collateral up to its max share
+        // of the weighted collateral, including its counted value
+        let mut concentration_excess = Number128::ZERO;
+        let mut initial_concentration_excess = Number128::ZERO;
+        for collateral in &concentrated[..concentrated_len] {
+            if collateral.max_share == 0 || collateral.max_share >= 100 {
+                continue;
             }
+
+            concentration_excess += collateral.max_share_excess(
+                collateral.collateral_value,
+                weighted_collateral - collateral.collateral_value,
+            );
+            initial_concentration_excess += collateral.max_share_excess(
+                collateral.initial_collateral_value,
+                initial_weighted_collateral - collateral.initial_collateral_value,
+            );
         }
+        weighted_collateral -= concentration_excess;
+        initial_weighted_collateral -= initial_concentration_excess;
 
         Ok(Valuation {
             equity,
             liabilities,
             past_due,
+            concentration_excess,
             required_collateral,
             weighted_collateral,
             // The collateral value (USD) avail after subtracting debts
             effective_collateral: weighted_collateral - liabilities,
+            initial_required_collateral,
+            initial_effective_collateral: initial_weighted_collateral - liabilities,
             stale_collateral_list,
         })
     }
@@ -679,6 +967,59 @@ impl MarginAccount {
         Ok(())
     }
 
+    /// The isolated collateral held by this account, if any
+    pub fn isolated_position(&self) -> Option<&AccountPosition> {
+        self.positions()
+            .find(|p| p.balance > 0 && p.isolation.contains(IsolationFlags::ISOLATED))
+    }
+
+    /// The total value of the claims of this account, ignoring max leverage
+    pub fn claims_value(&self) -> Number128 {
+        self.positions()
+            .filter(|p| p.balance > 0 && p.kind() == TokenKind::Claim)
+            .fold(Number128::ZERO, |total, p| total + p.value())
+    }
+
+    /// Assert that an account holding an isolated collateral is in a valid isolation mode.
+    ///
+    /// An account in isolation mode may only hold a single isolated collateral, and may only
+    /// have claims that are [IsolationFlags::BORROWABLE_IN_ISOLATION].
+    pub fn assert_isolation_mode(&self) -> AnchorResult<()> {
+        let mut isolated = self
+            .positions()
+            .filter(|p| p.balance > 0 && p.isolation.contains(IsolationFlags::ISOLATED));
+        let isolated_position = match isolated.next() {
+            Some(position) => position,
+            None => return Ok(()),
+        };
+
+        if let Some(other) = isolated.next() {
+            msg!(
+                "account cannot hold isolated collateral {} with {}",
+                other.token,
+                isolated_position.token
+            );
+            return err!(ErrorCode::IsolationModeViolation);
+        }
+
+        if let Some(claim) = self.positions().find(|p| {
+            p.balance > 0
+                && p.kind() == TokenKind::Claim
+                && !p
+                    .isolation
+                    .contains(IsolationFlags::BORROWABLE_IN_ISOLATION)
+        }) {
+            msg!(
+                "claim {} is not borrowable with isolated collateral {}",
+                claim.token,
+                isolated_position.token
+            );
+            return err!(ErrorCode::IsolationModeViolation);
+        }
+
+        Ok(())
+    }
+
     fn position_list(&self) -> &AccountPositionList {
         bytemuck::from_bytes(&self.positions.positions)
     }
@@ -735,6 +1076,9 @@ pub enum Approver {
     Adapter(Pubkey),
 }
 
+/// The number of liquidation fees stored in [Liquidation] itself
+pub const LIQUIDATION_FEE_SLOTS: usize = 6;
+
 /// State of an in-progress liquidation
 #[account(zero_copy)]
 #[repr(C, align(8))]
@@ -748,6 +1092,7 @@ pub struct LiquidationState {
     pub state: Liquidation,
 }
 
+#[assert_size(320)]
 #[repr(C)]
 #[derive(Zeroable, Pod, AnchorDeserialize, AnchorSerialize, Debug, Default, Clone, Copy)]
 pub struct Liquidation {
@@ -768,9 +1113,19 @@ pub struct Liquidation {
     /// Marker to prevent a liquidator from collecting fees then liquidating further.
     /// Once this is set, the liquidator has to collect fees, then end a liquidation.
     pub is_collecting_fees: u8, // bool
-    pub __padding: [u8; 7],
+    pub __padding0: u8,
 
-    pub accrued_liquidation_fees: [LiquidationFee; 6],
+    /// The share of the liquidation fees, in basis points, that goes to the liquidation
+    /// fee treasury of the airspace, as configured when the liquidation began.
+    ///
+    /// This takes up former padding, so it is zero for liquidations that began before
+    /// fees were shared with the treasury.
+    pub protocol_fee_share_bps: u16,
+    pub __padding: [u8; 4],
+
+    /// The first fees accrued by the liquidator, further fees are appended to the
+    /// liquidation account (see [LiquidationState::read_fees]).
+    pub accrued_liquidation_fees: [LiquidationFee; LIQUIDATION_FEE_SLOTS],
 }
 
 impl Liquidation {
@@ -778,6 +1133,7 @@ impl Liquidation {
         start_time: i64,
         max_equity_loss: Number128,
         max_available_collateral_limit: Number128,
+        protocol_fee_share_bps: u16,
     ) -> Self {
         Self {
             start_time,
@@ -786,8 +1142,10 @@ impl Liquidation {
             collateral_change: 0,
             max_available_collateral_limit: max_available_collateral_limit.to_i128(),
             is_collecting_fees: 0,
-            __padding: [0; 7],
-            accrued_liquidation_fees: [Default::default(); 6],
+            __padding0: 0,
+            protocol_fee_share_bps,
+            __padding: [0; 4],
+            accrued_liquidation_fees: [Default::default(); LIQUIDATION_FEE_SLOTS],
         }
     }
 
@@ -819,47 +1177,149 @@ impl Liquidation {
         bytemuck::cast_ref(&self.max_available_collateral_limit)
     }
 
-    pub fn accrue_liquidation_fee(&mut self, mint: Pubkey, amount: u64) -> anchor_lang::Result<()> {
-        // Find a slot that has the token
-        let slot = self
-            .accrued_liquidation_fees
-            .iter_mut()
-            .find(|p| p.mint == mint);
-        if let Some(slot) = slot {
-            slot.amount = slot.amount.checked_add(amount).unwrap(); // TODO: replace with error
+    /// The protocol's part of the tokens collected for a liquidation fee
+    pub fn protocol_share_of(&self, collected: u64) -> u64 {
+        (collected as u128 * self.protocol_fee_share_bps as u128 / 10_000) as u64
+    }
+}
+
+#[assert_size(40)]
+#[repr(C)]
+#[derive(Zeroable, Pod, AnchorDeserialize, AnchorSerialize, Debug, Default, Clone, Copy)]
+pub struct LiquidationFee {
+    pub mint: Pubkey,
+    /// The whole fee, including the protocol's share
+    pub amount: u64,
+}
+
+impl LiquidationFee {
+    /// Adds to the fee accrued in a mint, taking a new slot if the mint has none yet
+    pub fn accrue(fees: &mut Vec<LiquidationFee>, mint: Pubkey, amount: u64) -> AnchorResult<()> {
+        if amount == 0 {
             return Ok(());
         }
-        // Find an empty slot
-        let empty_slot = self
-            .accrued_liquidation_fees
-            .iter_mut()
-            .find(|p| p.mint == Pubkey::default())
-            .ok_or(crate::ErrorCode::LiquidationFeeSlotsFull)?;
-
-        empty_slot.mint = mint;
-        empty_slot.amount = amount;
+        match fees.iter_mut().find(|fee| fee.mint == mint) {
+            Some(fee) => {
+                fee.amount = fee
+                    .amount
+                    .checked_add(amount)
+                    .ok_or(ErrorCode::MathOpFailed)?
+            }
+            None => fees.push(LiquidationFee { mint, amount }),
+        }
 
         Ok(())
     }
+}
 
-    pub fn clear_liquidation_fee(&mut self, mint: Pubkey) -> bool {
-        let slot = self
+impl LiquidationState {
+    /// The space of a liquidation account that holds `fee_count` accrued fees.
+    ///
+    /// The first [LIQUIDATION_FEE_SLOTS] fees are held in the [Liquidation], any
+    /// further fees are appended to the account after the state.
+    pub fn space(fee_count: usize) -> usize {
+        let appended = fee_count.saturating_sub(LIQUIDATION_FEE_SLOTS);
+        8 + std::mem::size_of::<LiquidationState>()
+            + appended * std::mem::size_of::<LiquidationFee>()
+    }
+
+    /// Read the state of a liquidation from the data of its account, which may have
+    /// grown to hold more fees
+    pub fn read_state(data: &[u8]) -> AnchorResult<LiquidationState> {
+        let state_end = Self::space(0);
+        if data.len() < state_end {
+            return err!(ErrorCode::WrongLiquidationState);
+        }
+
+        Ok(bytemuck::pod_read_unaligned(&data[8..state_end]))
+    }
+
+    /// Read every fee accrued by a liquidation from the data of its account
+    pub fn read_fees(data: &[u8]) -> AnchorResult<Vec<LiquidationFee>> {
+        let state_end = Self::space(0);
+        let state = Self::read_state(data)?;
+
+        Ok(state
+            .state
             .accrued_liquidation_fees
-            .iter_mut()
-            .find(|p| p.mint == mint);
-        if let Some(slot) = slot {
-            *slot = Default::default();
-            return true;
+            .into_iter()
+            .chain(
+                data[state_end..]
+                    .chunks_exact(std::mem::size_of::<LiquidationFee>())
+                    .map(bytemuck::pod_read_unaligned),
+            )
+            .filter(|fee| fee.mint != Pubkey::default())
+            .collect())
+    }
+
+    /// Write the fees accrued by a liquidation into the data of its account, clearing
+    /// any slots that are no longer used. The account needs the [space](Self::space)
+    /// for all the fees.
+    pub fn write_fees(data: &mut [u8], fees: &[LiquidationFee]) -> AnchorResult<()> {
+        let state_end = Self::space(0);
+        if data.len() < Self::space(fees.len()) {
+            return err!(ErrorCode::LiquidationFeeSlotsFull);
+        }
+        let mut slots = fees
+            .iter()
+            .copied()
+            .chain(std::iter::repeat(LiquidationFee::default()));
+
+        let mut state: LiquidationState = bytemuck::pod_read_unaligned(&data[8..state_end]);
+        state
+            .state
+            .accrued_liquidation_fees
+            .fill_with(|| slots.next().unwrap());
+        data[8..state_end].copy_from_slice(bytemuck::bytes_of(&state));
+
+        for slot in data[state_end..].chunks_exact_mut(std::mem::size_of::<LiquidationFee>()) {
+            slot.copy_from_slice(bytemuck::bytes_of(&slots.next().unwrap()));
         }
-        false
+
+        Ok(())
     }
 }
 
-#[repr(C)]
-#[derive(Zeroable, Pod, AnchorDeserialize, AnchorSerialize, Debug, Default, Clone, Copy)]
-pub struct LiquidationFee {
-    pub mint: Pubkey,
-    pub amount: u64,
+/// The weighted collateral counted towards a concentration limit, which is shared by
+/// the positions in a concentration group
+#[derive(Default, Clone, Copy)]
+struct ConcentratedCollateral {
+    /// The concentration group, or 0 for a single position outside of a group
+    group: u8,
+
+    /// The strictest limit of the positions, or 0 if none of them are limited
+    max_share: u8,
+
+    collateral_value: Number128,
+    initial_collateral_value: Number128,
+}
+
+impl ConcentratedCollateral {
+    fn add(
+        &mut self,
+        group: u8,
+        max_share: u8,
+        collateral_value: Number128,
+        initial_collateral_value: Number128,
+    ) {
+        self.group = group;
+        if max_share > 0 && (self.max_share == 0 || max_share < self.max_share) {
+            self.max_share = max_share;
+        }
+        self.collateral_value += collateral_value;
+        self.initial_collateral_value += initial_collateral_value;
+    }
+
+    /// The value above the max share, given the value of the other collateral.
+    ///
+    /// The counted value `allowed` is limited so that `allowed / (allowed + other)` is at
+    /// most the max share, i.e. `allowed = share / (1 - share) * other`.
+    fn max_share_excess(&self, value: Number128, other_value: Number128) -> Number128 {
+        let allowed = other_value * Number128::from_decimal(self.max_share, 0)
+            / Number128::from_decimal(100 - self.max_share, 0);
+
+        (value - allowed).max(Number128::ZERO)
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -879,6 +1339,15 @@ pub struct Valuation {
     /// weighted_collateral minus debt. the remaining portion of collateral allocated for required_collateral after deposits and borrows offset
     pub effective_collateral: Number128,
 
+    /// The required collateral using the stricter initial margin value modifiers of claims
+    pub initial_required_collateral: Number128,
+
+    /// The effective collateral using the stricter initial margin value modifiers of deposits
+    pub initial_effective_collateral: Number128,
+
+    /// The weighted collateral that is not counted due to the concentration limits of tokens
+    pub concentration_excess: Number128,
+
     /// Errors that resulted in collateral positions from being excluded from collateral and equity totals
     stale_collateral_list: Vec<(Pubkey, ErrorCode)>,
 
@@ -903,6 +1372,10 @@ impl Valuation {
         self.past_due
     }
 
+    pub fn stale_collateral_list(&self) -> &[(Pubkey, ErrorCode)] {
+        &self.stale_collateral_list
+    }
+
     /// Check that the overall health of the account is acceptable, by comparing the
     /// total value of the claims versus the available collateral. If the collateralization
     /// ratio is above the minimum, then the account is considered healthy.
@@ -923,6 +1396,75 @@ impl Valuation {
         Ok(())
     }
 
+    /// The amount of collateral missing to meet the initial margin requirement,
+    /// or zero if the requirement is met.
+    pub fn initial_margin_shortfall(&self) -> Number128 {
+        if self.initial_required_collateral > self.initial_effective_collateral {
+            self.initial_required_collateral - self.initial_effective_collateral
+        } else {
+            Number128::ZERO
+        }
+    }
+
+    /// Check that the account meets the initial margin requirement, which is stricter than
+    /// the maintenance margin checked by [Self::verify_healthy]. Instructions that increase
+    /// the risk of an account must leave it above its initial margin.
+    pub fn verify_initial_margin(&self) -> AnchorResult<()> {
+        self.verify_healthy()?;
+
+        if self.initial_required_collateral > self.initial_effective_collateral {
+            msg!(
+                "account does not meet initial margin: K_e = {}, K_r = {}",
+                self.initial_effective_collateral,
+                self.initial_required_collateral
+            );
+            return err!(ErrorCode::InitialMarginNotMet);
+        }
+
+        Ok(())
+    }
+
+    /// Whether the account lacks the collateral it requires, based on positions with
+    /// fresh prices only.
+    pub fn is_undercollateralized(&self) -> bool {
+        self.stale_collateral_list.is_empty()
+            && self.required_collateral > self.effective_collateral
+    }
+
+    /// Check that an unhealthy account was deleveraged from the `start` valuation, which
+    /// requires its health to strictly improve.
+    ///
+    /// The equity lost may not exceed the max equity loss proportion of a liquidation,
+    /// applied to the liabilities that were repaid. Since every invocation can only lose
+    /// equity for the debt it repays, the total loss over any number of invocations stays
+    /// within the loss allowed when liquidating the starting liabilities.
+    pub fn verify_deleveraged(&self, start: &Valuation) -> AnchorResult<()> {
+        const M: Number128 =
+            Number128::const_from_bps(LIQUIDATION_MAX_EQUITY_LOSS_PROPORTION_BPS as i128);
+
+        if self.available_collateral() <= start.available_collateral() {
+            msg!(
+                "deleveraging did not improve the account: available collateral from {} to {}",
+                start.available_collateral(),
+                self.available_collateral()
+            );
+            return err!(ErrorCode::Unhealthy);
+        }
+
+        let max_equity_loss = M * (start.liabilities - self.liabilities);
+        let equity_loss = start.equity - self.equity;
+        if equity_loss > max_equity_loss {
+            msg!(
+                "deleveraging lost {} equity which exceeds the max equity loss of {}",
+                equity_loss,
+                max_equity_loss
+            );
+            return err!(ErrorCode::DeleverageLostValue);
+        }
+
+        Ok(())
+    }
+
     /// Check that the overall health of the account is *not* acceptable.
     pub fn verify_unhealthy(&self) -> AnchorResult<()> {
         // If there is stale collateral, position is marked as unhealthy
@@ -947,7 +1489,10 @@ impl Valuation {
 #[cfg(test)]
 mod tests {
 
-    use crate::{mock_sys, util::Invocation};
+    use crate::{
+        adapter::DebtSnapshot, mock_sys, util::Invocation, IsolationConfig, OracleLimits,
+        ValueModifierRamp,
+    };
 
     use super::*;
     use itertools::Itertools;
@@ -1014,6 +1559,13 @@ mod tests {
                 max_staleness: 40,
                 token_program: anchor_spl::token::ID,
                 token_features: TokenFeatures::empty(),
+                initial_value_modifier: 0,
+                oracle_limits: Default::default(),
+                value_modifier_ramp: Default::default(),
+                isolation: Default::default(),
+                max_collateral_share: 0,
+                concentration_group: 0,
+                size_haircut_threshold: 0,
             },
             approvals,
         )
@@ -1048,18 +1600,7 @@ mod tests {
 
     #[test]
     fn margin_account_serialize() {
-        let account = MarginAccount {
-            version: 1,
-            bump_seed: [0],
-            user_seed: [0; 2],
-            features: AccountFeatureFlags::default(),
-            constraints: AccountConstraints::default(),
-            owner: Pubkey::default(),
-            airspace: Pubkey::default(),
-            liquidator: Pubkey::default(),
-            invocation: Invocation::default(),
-            positions: [0; 7432].into(),
-        };
+        let account = blank_account();
 
         assert_ser_tokens(
             &account,
@@ -1162,6 +1703,72 @@ mod tests {
         assert!(margin_account.valuation(ARBITRARY_TIME).is_err());
     }
 
+    #[test]
+    fn valuation_ignores_non_collateral_without_price() {
+        let mut margin_account = MarginAccount {
+            version: 1,
+            bump_seed: [0],
+            user_seed: [0; 2],
+            features: AccountFeatureFlags::default(),
+            constraints: AccountConstraints::default(),
+            owner: Pubkey::new_unique(),
+            airspace: Pubkey::default(),
+            liquidator: Pubkey::default(),
+            invocation: Invocation::default(),
+            positions: [0; 7432].into(),
+        };
+
+        let pos = register_position(&mut margin_account, 0, TokenKind::NonCollateral);
+        margin_account
+            .set_position_balance(&pos, &pos, 1_000, 0)
+            .unwrap();
+
+        // Neither the missing price nor the stale balance are reported
+        let valuation = margin_account.valuation(ARBITRARY_TIME).unwrap();
+        assert_eq!(valuation.equity, Number128::ZERO);
+        assert_eq!(valuation.weighted_collateral, Number128::ZERO);
+        assert!(valuation.stale_collateral_list.is_empty());
+        valuation.verify_healthy().unwrap();
+    }
+
+    #[test]
+    fn credit_line_positions_are_never_stale() {
+        let address = Pubkey::new_unique();
+        let approvals = [
+            Approver::MarginAccountAuthority,
+            Approver::Adapter(crate::ID),
+        ];
+        let mut lender = blank_account();
+        let mut borrower = blank_account();
+        lender
+            .register_position(
+                CreditLine::position_config(address, &lender, TokenKind::Claim),
+                &approvals,
+            )
+            .unwrap();
+        borrower
+            .register_position(
+                CreditLine::position_config(address, &borrower, TokenKind::AdapterCollateral),
+                &approvals,
+            )
+            .unwrap();
+
+        let line = CreditLine {
+            limit: 1_000_000,
+            ..Default::default()
+        };
+        line.update_positions(&address, &mut lender, &mut borrower, 0)
+            .unwrap();
+
+        // Long after the positions were last updated, the line is still worth its limit
+        let lender_valuation = lender.valuation(ARBITRARY_TIME).unwrap();
+        assert_eq!(lender_valuation.liabilities, Number128::ONE);
+
+        let borrower_valuation = borrower.valuation(ARBITRARY_TIME).unwrap();
+        assert!(borrower_valuation.stale_collateral_list().is_empty());
+        assert_eq!(borrower_valuation.equity, Number128::ONE);
+    }
+
     #[test]
     fn valuation_succeeds_ignoring_stale_adapter_collateral_with_balance() {
         let mut margin_account = MarginAccount {
@@ -1242,6 +1849,13 @@ mod tests {
                     max_staleness: 2,
                     token_program: anchor_spl::token::ID,
                     token_features: TokenFeatures::empty(),
+                    initial_value_modifier: 0,
+                    oracle_limits: Default::default(),
+                    value_modifier_ramp: Default::default(),
+                    isolation: Default::default(),
+                    max_collateral_share: 0,
+                    concentration_group: 0,
+                    size_haircut_threshold: 0,
                 },
                 user_approval,
             )
@@ -1260,6 +1874,13 @@ mod tests {
                     max_staleness: 2,
                     token_program: anchor_spl::token::ID,
                     token_features: TokenFeatures::empty(),
+                    initial_value_modifier: 0,
+                    oracle_limits: Default::default(),
+                    value_modifier_ramp: Default::default(),
+                    isolation: Default::default(),
+                    max_collateral_share: 0,
+                    concentration_group: 0,
+                    size_haircut_threshold: 0,
                 },
                 adapter_approval,
             )
@@ -1278,6 +1899,13 @@ mod tests {
                     max_staleness: 2,
                     token_program: anchor_spl::token::ID,
                     token_features: TokenFeatures::empty(),
+                    initial_value_modifier: 0,
+                    oracle_limits: Default::default(),
+                    value_modifier_ramp: Default::default(),
+                    isolation: Default::default(),
+                    max_collateral_share: 0,
+                    concentration_group: 0,
+                    size_haircut_threshold: 0,
                 },
                 user_approval,
             )
@@ -1314,6 +1942,13 @@ mod tests {
                     max_staleness: 2,
                     token_program: anchor_spl::token::ID,
                     token_features: TokenFeatures::empty(),
+                    initial_value_modifier: 0,
+                    oracle_limits: Default::default(),
+                    value_modifier_ramp: Default::default(),
+                    isolation: Default::default(),
+                    max_collateral_share: 0,
+                    concentration_group: 0,
+                    size_haircut_threshold: 0,
                 },
                 user_approval,
             )
@@ -1333,6 +1968,13 @@ mod tests {
                     max_staleness: 2,
                     token_program: anchor_spl::token::ID,
                     token_features: TokenFeatures::empty(),
+                    initial_value_modifier: 0,
+                    oracle_limits: Default::default(),
+                    value_modifier_ramp: Default::default(),
+                    isolation: Default::default(),
+                    max_collateral_share: 0,
+                    concentration_group: 0,
+                    size_haircut_threshold: 0,
                 },
                 user_approval,
             )
@@ -1393,6 +2035,13 @@ mod tests {
                     max_staleness: 2,
                     token_program: anchor_spl::token::ID,
                     token_features: TokenFeatures::empty(),
+                    initial_value_modifier: 0,
+                    oracle_limits: Default::default(),
+                    value_modifier_ramp: Default::default(),
+                    isolation: Default::default(),
+                    max_collateral_share: 0,
+                    concentration_group: 0,
+                    size_haircut_threshold: 0,
                 },
                 &[],
             )
@@ -1410,6 +2059,13 @@ mod tests {
                     max_staleness: 0,
                     token_program: anchor_spl::token::ID,
                     token_features: TokenFeatures::empty(),
+                    initial_value_modifier: 0,
+                    oracle_limits: Default::default(),
+                    value_modifier_ramp: Default::default(),
+                    isolation: Default::default(),
+                    max_collateral_share: 0,
+                    concentration_group: 0,
+                    size_haircut_threshold: 0,
                 },
                 &[Approver::MarginAccountAuthority],
             )
@@ -1427,6 +2083,13 @@ mod tests {
                     max_staleness: 0,
                     token_program: anchor_spl::token::ID,
                     token_features: TokenFeatures::empty(),
+                    initial_value_modifier: 0,
+                    oracle_limits: Default::default(),
+                    value_modifier_ramp: Default::default(),
+                    isolation: Default::default(),
+                    max_collateral_share: 0,
+                    concentration_group: 0,
+                    size_haircut_threshold: 0,
                 },
                 &[Approver::Adapter(adapter)],
             )
@@ -1444,6 +2107,13 @@ mod tests {
                     max_staleness: 0,
                     token_program: anchor_spl::token::ID,
                     token_features: TokenFeatures::empty(),
+                    initial_value_modifier: 0,
+                    oracle_limits: Default::default(),
+                    value_modifier_ramp: Default::default(),
+                    isolation: Default::default(),
+                    max_collateral_share: 0,
+                    concentration_group: 0,
+                    size_haircut_threshold: 0,
                 },
                 &[Approver::MarginAccountAuthority, Approver::Adapter(adapter)],
             )
@@ -1482,6 +2152,13 @@ mod tests {
                     max_staleness: 0,
                     token_program: anchor_spl::token::ID,
                     token_features: TokenFeatures::empty(),
+                    initial_value_modifier: 0,
+                    oracle_limits: Default::default(),
+                    value_modifier_ramp: Default::default(),
+                    isolation: Default::default(),
+                    max_collateral_share: 0,
+                    concentration_group: 0,
+                    size_haircut_threshold: 0,
                 },
                 &[Approver::MarginAccountAuthority, Approver::Adapter(adapter)],
             )
@@ -1490,18 +2167,7 @@ mod tests {
 
     #[test]
     fn margin_account_past_due() {
-        let mut acc = MarginAccount {
-            version: 1,
-            bump_seed: [0],
-            user_seed: [0; 2],
-            features: AccountFeatureFlags::default(),
-            constraints: AccountConstraints::default(),
-            owner: Pubkey::default(),
-            airspace: Pubkey::default(),
-            liquidator: Pubkey::default(),
-            invocation: Invocation::default(),
-            positions: [0; 7432].into(),
-        };
+        let mut acc = blank_account();
         let collateral = register_position(&mut acc, 0, TokenKind::Collateral);
         let claim = register_position(&mut acc, 1, TokenKind::Claim);
         set_price(&mut acc, collateral, 100);
@@ -1521,6 +2187,346 @@ mod tests {
             .unwrap();
     }
 
+    #[test]
+    fn initial_margin_is_stricter_than_maintenance() {
+        let mut acc = blank_account();
+        let collateral = register_position(&mut acc, 0, TokenKind::Collateral);
+        let claim = register_position(&mut acc, 1, TokenKind::Claim);
+        acc.get_position_mut(&collateral)
+            .require()
+            .unwrap()
+            .initial_value_modifier = 1;
+        acc.get_position_mut(&claim)
+            .require()
+            .unwrap()
+            .initial_value_modifier = 10;
+        set_price(&mut acc, collateral, 100);
+        set_price(&mut acc, claim, 100);
+        acc.set_position_balance(&claim, &claim, 1, ARBITRARY_TIME)
+            .unwrap();
+        acc.set_position_balance(&collateral, &collateral, 100, ARBITRARY_TIME)
+            .unwrap();
+
+        // healthy under the maintenance margin, but below the initial margin
+        assert_healthy(&acc);
+        let valuation = acc.valuation(ARBITRARY_TIME).unwrap();
+        assert!(valuation.initial_margin_shortfall() > Number128::ZERO);
+        assert_eq!(
+            valuation.verify_initial_margin().unwrap_err(),
+            error!(ErrorCode::InitialMarginNotMet)
+        );
+
+        acc.set_position_balance(&collateral, &collateral, 20_000, ARBITRARY_TIME)
+            .unwrap();
+        let valuation = acc.valuation(ARBITRARY_TIME).unwrap();
+        assert_eq!(valuation.initial_margin_shortfall(), Number128::ZERO);
+        valuation.verify_initial_margin().unwrap();
+    }
+
+    #[test]
+    fn valuation_uses_position_price_quote_age() {
+        let mut acc = blank_account();
+        let collateral = register_position(&mut acc, 0, TokenKind::Collateral);
+        set_price(&mut acc, collateral, 100);
+        let now = ARBITRARY_TIME + 60;
+        acc.set_position_balance(&collateral, &collateral, 100, now)
+            .unwrap();
+
+        // the quote is older than the default limit
+        let valuation = acc.valuation(now).unwrap();
+        assert_eq!(valuation.stale_collateral_list().len(), 1);
+        assert_eq!(valuation.weighted_collateral, Number128::ZERO);
+
+        acc.get_position_mut(&collateral)
+            .require()
+            .unwrap()
+            .set_oracle_limits(&OracleLimits {
+                max_price_quote_age: 90,
+                ..Default::default()
+            });
+        let valuation = acc.valuation(now).unwrap();
+        assert!(valuation.stale_collateral_list().is_empty());
+        assert!(valuation.weighted_collateral > Number128::ZERO);
+    }
+
+    #[test]
+    fn valuation_interpolates_value_modifier_ramp() {
+        let mut acc = blank_account();
+        let collateral = register_position(&mut acc, 0, TokenKind::Collateral);
+        set_price(&mut acc, collateral, 100);
+        acc.set_position_balance(&collateral, &collateral, 100, ARBITRARY_TIME)
+            .unwrap();
+        let position = acc.get_position_mut(&collateral).require().unwrap();
+        position.value_modifier = 50;
+        position.set_value_modifier_ramp(&ValueModifierRamp {
+            start_value_modifier: 100,
+            start_timestamp: (ARBITRARY_TIME - 50) as u32,
+            end_timestamp: (ARBITRARY_TIME + 50) as u32,
+        });
+        let value = acc.get_position(&collateral).unwrap().value();
+
+        // halfway through the ramp
+        let valuation = acc.valuation(ARBITRARY_TIME).unwrap();
+        assert_eq!(
+            valuation.weighted_collateral,
+            value * Number128::from_decimal(75, -2)
+        );
+        assert_eq!(
+            valuation.initial_effective_collateral,
+            valuation.effective_collateral
+        );
+
+        // the target applies once the ramp has ended
+        let position = acc.get_position_mut(&collateral).require().unwrap();
+        position.set_value_modifier_ramp(&ValueModifierRamp {
+            start_value_modifier: 100,
+            start_timestamp: (ARBITRARY_TIME - 100) as u32,
+            end_timestamp: ARBITRARY_TIME as u32,
+        });
+        let valuation = acc.valuation(ARBITRARY_TIME).unwrap();
+        assert_eq!(
+            valuation.weighted_collateral,
+            value * Number128::from_decimal(50, -2)
+        );
+    }
+
+    #[test]
+    fn valuation_applies_size_haircut() {
+        let mut acc = blank_account();
+        let collateral = register_position(&mut acc, 0, TokenKind::Collateral);
+        set_price(&mut acc, collateral, 100);
+        acc.set_position_balance(&collateral, &collateral, 40_000, ARBITRARY_TIME)
+            .unwrap();
+        let position = acc.get_position_mut(&collateral).require().unwrap();
+        position.value_modifier = 50;
+        assert_eq!(position.value(), Number128::from_decimal(400_000, 0));
+
+        // below the threshold, the weight is unchanged
+        position.size_haircut_threshold = 400;
+        assert_eq!(
+            position.collateral_value(ARBITRARY_TIME),
+            Number128::from_decimal(200_000, 0)
+        );
+
+        // 4x the threshold halves the weight
+        position.size_haircut_threshold = 100;
+        assert_eq!(
+            position.collateral_value(ARBITRARY_TIME),
+            Number128::from_decimal(100_000, 0)
+        );
+        let valuation = acc.valuation(ARBITRARY_TIME).unwrap();
+        assert_eq!(
+            valuation.weighted_collateral,
+            Number128::from_decimal(100_000, 0)
+        );
+    }
+
+    #[test]
+    fn valuation_caps_concentrated_collateral() {
+        let mut acc = blank_account();
+        let stable = register_position(&mut acc, 0, TokenKind::Collateral);
+        let thin = register_position(&mut acc, 1, TokenKind::Collateral);
+        for (key, balance) in [(stable, 100), (thin, 300)] {
+            set_price(&mut acc, key, 100);
+            acc.set_position_balance(&key, &key, balance, ARBITRARY_TIME)
+                .unwrap();
+        }
+        let uncapped = acc.valuation(ARBITRARY_TIME).unwrap();
+        assert_eq!(uncapped.concentration_excess, Number128::ZERO);
+
+        // the thin token counts for at most a quarter of the weighted collateral
+        acc.get_position_mut(&thin)
+            .require()
+            .unwrap()
+            .max_collateral_share = 25;
+        let valuation = acc.valuation(ARBITRARY_TIME).unwrap();
+        let thin_value = acc
+            .get_position(&thin)
+            .unwrap()
+            .collateral_value(ARBITRARY_TIME);
+        let counted = thin_value - valuation.concentration_excess;
+        assert_eq!(
+            valuation.weighted_collateral,
+            uncapped.weighted_collateral - valuation.concentration_excess
+        );
+        assert_share(counted, &valuation, 0.25);
+        assert_eq!(valuation.equity, uncapped.equity);
+    }
+
+    /// Assert the share of the weighted collateral that a value counts for
+    fn assert_share(counted: Number128, valuation: &Valuation, share: f64) {
+        let effective_share = (counted / valuation.weighted_collateral).as_f64();
+        assert!(
+            (effective_share - share).abs() < 1e-9,
+            "effective share of {effective_share} instead of {share}"
+        );
+    }
+
+    #[test]
+    fn valuation_caps_concentration_groups() {
+        let mut acc = blank_account();
+        let stable = register_position(&mut acc, 0, TokenKind::Collateral);
+        let thin = register_position(&mut acc, 1, TokenKind::Collateral);
+        let thin_notes = register_position(&mut acc, 2, TokenKind::Collateral);
+        for (key, balance) in [(stable, 200), (thin, 100), (thin_notes, 100)] {
+            set_price(&mut acc, key, 100);
+            acc.set_position_balance(&key, &key, balance, ARBITRARY_TIME)
+                .unwrap();
+        }
+        let uncapped = acc.valuation(ARBITRARY_TIME).unwrap();
+
+        // each position is within the limit on its own
+        for key in [thin, thin_notes] {
+            acc.get_position_mut(&key)
+                .require()
+                .unwrap()
+                .max_collateral_share = 30;
+        }
+        let valuation = acc.valuation(ARBITRARY_TIME).unwrap();
+        assert_eq!(valuation.concentration_excess, Number128::ZERO);
+
+        // in the same group, the positions share a single limit
+        for key in [thin, thin_notes] {
+            acc.get_position_mut(&key)
+                .require()
+                .unwrap()
+                .concentration_group = 1;
+        }
+        let valuation = acc.valuation(ARBITRARY_TIME).unwrap();
+        let group_value = uncapped.weighted_collateral * Number128::from_decimal(50, -2);
+        assert!(valuation.concentration_excess > Number128::ZERO);
+        assert_share(
+            group_value - valuation.concentration_excess,
+            &valuation,
+            0.3,
+        );
+    }
+
+    #[test]
+    fn isolation_mode_restricts_claims() {
+        let mut acc = blank_account();
+        let isolated = register_position(&mut acc, 0, TokenKind::Collateral);
+        let other_isolated = register_position(&mut acc, 1, TokenKind::Collateral);
+        let borrowable = register_position(&mut acc, 2, TokenKind::Claim);
+        let claim = register_position(&mut acc, 3, TokenKind::Claim);
+        for (key, flags) in [
+            (isolated, IsolationFlags::ISOLATED),
+            (other_isolated, IsolationFlags::ISOLATED),
+            (borrowable, IsolationFlags::BORROWABLE_IN_ISOLATION),
+        ] {
+            acc.get_position_mut(&key).require().unwrap().isolation = flags;
+        }
+        for key in [isolated, borrowable, claim] {
+            set_price(&mut acc, key, 100);
+        }
+
+        // without isolated collateral, any claim can be borrowed
+        acc.set_position_balance(&claim, &claim, 10, ARBITRARY_TIME)
+            .unwrap();
+        assert!(acc.isolated_position().is_none());
+        acc.assert_isolation_mode().unwrap();
+
+        acc.set_position_balance(&isolated, &isolated, 100, ARBITRARY_TIME)
+            .unwrap();
+        assert_eq!(acc.isolated_position().unwrap().token, isolated);
+        assert!(acc.assert_isolation_mode().is_err());
+
+        // only claims borrowable in isolation are allowed
+        acc.set_position_balance(&claim, &claim, 0, ARBITRARY_TIME)
+            .unwrap();
+        acc.set_position_balance(&borrowable, &borrowable, 10, ARBITRARY_TIME)
+            .unwrap();
+        acc.assert_isolation_mode().unwrap();
+        assert_eq!(
+            acc.claims_value(),
+            acc.get_position(&borrowable).unwrap().value()
+        );
+
+        // a second isolated collateral is not allowed
+        acc.set_position_balance(&other_isolated, &other_isolated, 1, ARBITRARY_TIME)
+            .unwrap();
+        assert!(acc.assert_isolation_mode().is_err());
+    }
+
+    #[test]
+    fn isolated_debt_is_measured_at_snapshot_prices() {
+        let mut acc = blank_account();
+        let isolated = register_position(&mut acc, 0, TokenKind::Collateral);
+        let claim = register_position(&mut acc, 1, TokenKind::Claim);
+        acc.get_position_mut(&isolated).require().unwrap().isolation = IsolationFlags::ISOLATED;
+        acc.get_position_mut(&claim).require().unwrap().isolation =
+            IsolationFlags::BORROWABLE_IN_ISOLATION;
+        set_price(&mut acc, isolated, 100);
+        set_price(&mut acc, claim, 100);
+        acc.set_position_balance(&claim, &claim, 10, ARBITRARY_TIME)
+            .unwrap();
+        let none = Number128::ZERO;
+
+        // receiving the isolated collateral borrows the existing claims against it
+        let snapshot = DebtSnapshot::new(&acc);
+        acc.set_position_balance(&isolated, &isolated, 100, ARBITRARY_TIME)
+            .unwrap();
+        assert_eq!(
+            snapshot.debt_change(&acc, true),
+            (Number128::from_decimal(100, 0), none)
+        );
+
+        // a price refreshed after the snapshot does not change the debt
+        let snapshot = DebtSnapshot::new(&acc);
+        set_price(&mut acc, claim, 200);
+        assert_eq!(snapshot.debt_change(&acc, false), (none, none));
+
+        // borrowing and repaying is valued at the price of the snapshot
+        acc.set_position_balance(&claim, &claim, 15, ARBITRARY_TIME)
+            .unwrap();
+        assert_eq!(
+            snapshot.debt_change(&acc, false),
+            (Number128::from_decimal(50, 0), none)
+        );
+        acc.set_position_balance(&claim, &claim, 4, ARBITRARY_TIME)
+            .unwrap();
+        assert_eq!(
+            snapshot.debt_change(&acc, false),
+            (none, Number128::from_decimal(60, 0))
+        );
+    }
+
+    #[test]
+    fn repaid_isolated_debt_can_be_borrowed_again() {
+        let mut acc = blank_account();
+        let isolated = register_position(&mut acc, 0, TokenKind::Collateral);
+        let claim = register_position(&mut acc, 1, TokenKind::Claim);
+        acc.get_position_mut(&isolated).require().unwrap().isolation = IsolationFlags::ISOLATED;
+        acc.get_position_mut(&claim).require().unwrap().isolation =
+            IsolationFlags::BORROWABLE_IN_ISOLATION;
+        set_price(&mut acc, isolated, 100);
+        set_price(&mut acc, claim, 100);
+        acc.set_position_balance(&isolated, &isolated, 100, ARBITRARY_TIME)
+            .unwrap();
+
+        // a ceiling of 100 USD
+        let mut isolation = IsolationConfig {
+            flags: IsolationFlags::ISOLATED,
+            debt_ceiling: 100_000_000,
+            isolated_debt: 0,
+        };
+        let mut set_claim_balance = |acc: &mut MarginAccount, balance: u64| {
+            let snapshot = DebtSnapshot::new(acc);
+            acc.set_position_balance(&claim, &claim, balance, ARBITRARY_TIME)
+                .unwrap();
+            let (borrowed, repaid) = snapshot.debt_change(acc, false);
+            isolation.record_debt_change(borrowed, repaid)?;
+            Ok::<_, anchor_lang::error::Error>(isolation.isolated_debt)
+        };
+
+        assert_eq!(100_000_000, set_claim_balance(&mut acc, 10).unwrap());
+        assert_eq!(0, set_claim_balance(&mut acc, 0).unwrap());
+
+        // the repaid debt frees the ceiling up to its full amount
+        assert_eq!(100_000_000, set_claim_balance(&mut acc, 10).unwrap());
+        assert!(set_claim_balance(&mut acc, 11).is_err());
+    }
+
     fn register_position(acc: &mut MarginAccount, index: u8, kind: TokenKind) -> Pubkey {
         try_register_position(acc, index, kind).unwrap()
     }
@@ -1552,6 +2558,13 @@ mod tests {
                 max_staleness: 2,
                 token_program: anchor_spl::token::ID,
                 token_features: TokenFeatures::empty(),
+                initial_value_modifier: 0,
+                oracle_limits: Default::default(),
+                value_modifier_ramp: Default::default(),
+                isolation: Default::default(),
+                max_collateral_share: 0,
+                concentration_group: 0,
+                size_haircut_threshold: 0,
             },
             &approvals,
         )?;
@@ -1714,4 +2727,270 @@ mod tests {
             positions: [0; 7432].into(),
         }
     }
+
+    #[test]
+    fn constraint_ticket_combines_holders() {
+        let mut ticket = AccountConstraintTicket {
+            margin_account: Pubkey::new_unique(),
+            holders: Default::default(),
+        };
+        let vault = Pubkey::new_unique();
+        let other = Pubkey::new_unique();
+
+        ticket
+            .set_holder_constraints(vault, AccountConstraints::DENY_WITHDRAWALS)
+            .unwrap();
+        ticket
+            .set_holder_constraints(other, AccountConstraints::DENY_TRANSFERS)
+            .unwrap();
+        assert_eq!(
+            ticket.combined_constraints(),
+            AccountConstraints::DENY_WITHDRAWALS | AccountConstraints::DENY_TRANSFERS
+        );
+
+        // A holder replaces its own constraints only
+        ticket
+            .set_holder_constraints(vault, AccountConstraints::DENY_DEPOSITS)
+            .unwrap();
+        assert_eq!(
+            ticket.combined_constraints(),
+            AccountConstraints::DENY_DEPOSITS | AccountConstraints::DENY_TRANSFERS
+        );
+
+        // The ticket is released once every holder has released
+        ticket
+            .set_holder_constraints(vault, AccountConstraints::empty())
+            .unwrap();
+        assert_eq!(
+            ticket.combined_constraints(),
+            AccountConstraints::DENY_TRANSFERS
+        );
+        assert!(!ticket.is_released());
+        ticket
+            .set_holder_constraints(other, AccountConstraints::empty())
+            .unwrap();
+        assert!(ticket.combined_constraints().is_empty());
+        assert!(ticket.is_released());
+    }
+
+    #[test]
+    fn constraint_ticket_rejects_unknown_and_excess_holders() {
+        let mut ticket = AccountConstraintTicket {
+            margin_account: Pubkey::new_unique(),
+            holders: Default::default(),
+        };
+
+        // Adapters that hold nothing cannot release
+        assert!(ticket
+            .set_holder_constraints(Pubkey::new_unique(), AccountConstraints::empty())
+            .is_err());
+
+        for _ in 0..MAX_CONSTRAINT_HOLDERS {
+            ticket
+                .set_holder_constraints(Pubkey::new_unique(), AccountConstraints::DENY_DEPOSITS)
+                .unwrap();
+        }
+        assert!(ticket
+            .set_holder_constraints(Pubkey::new_unique(), AccountConstraints::DENY_DEPOSITS)
+            .is_err());
+    }
+
+    #[test]
+    fn constraint_ticket_destinations() {
+        let mut ticket = AccountConstraintTicket {
+            margin_account: Pubkey::new_unique(),
+            holders: Default::default(),
+        };
+        let vault = Pubkey::new_unique();
+        let other = Pubkey::new_unique();
+        let treasury = Pubkey::new_unique();
+        let wallet = Pubkey::new_unique();
+
+        // Only holders can allowlist destinations
+        assert!(ticket.set_holder_destinations(vault, &[treasury]).is_err());
+
+        ticket
+            .set_holder_constraints(vault, AccountConstraints::DENY_BORROWS)
+            .unwrap();
+        ticket.set_holder_destinations(vault, &[treasury]).unwrap();
+        assert!(ticket
+            .set_holder_destinations(vault, &[treasury; MAX_CONSTRAINT_DESTINATIONS + 1])
+            .is_err());
+
+        // Destinations are only restricted by holders with the constraint
+        assert!(ticket.allows_destination(&wallet));
+        ticket
+            .set_holder_constraints(
+                vault,
+                AccountConstraints::DENY_BORROWS | AccountConstraints::RESTRICT_DESTINATIONS,
+            )
+            .unwrap();
+        assert!(ticket.allows_destination(&treasury));
+        assert!(!ticket.allows_destination(&wallet));
+
+        // Every restricting holder has to allow the destination
+        ticket
+            .set_holder_constraints(other, AccountConstraints::RESTRICT_DESTINATIONS)
+            .unwrap();
+        assert!(!ticket.allows_destination(&treasury));
+        ticket.set_holder_destinations(other, &[treasury]).unwrap();
+        assert!(ticket.allows_destination(&treasury));
+    }
+
+    #[test]
+    fn sol_numeraire_feature_flags() {
+        let sol = AccountFeatureFlags::ACCEPTS_SOL_BASED;
+        let sol_numeraire = sol | AccountFeatureFlags::SOL_NUMERAIRE;
+        sol.check_valid_for_new_account().unwrap();
+        sol_numeraire.check_valid_for_new_account().unwrap();
+        AccountFeatureFlags::SOL_NUMERAIRE
+            .check_valid_for_new_account()
+            .unwrap_err();
+        (AccountFeatureFlags::ACCEPTS_STABLECOINS | AccountFeatureFlags::SOL_NUMERAIRE)
+            .check_valid_for_new_account()
+            .unwrap_err();
+        (sol | AccountFeatureFlags::ACCEPTS_STABLECOINS)
+            .check_valid_for_new_account()
+            .unwrap_err();
+
+        // The numeraire doesn't change which tokens are accepted
+        assert!(sol_numeraire
+            .are_token_features_compatible(TokenFeatures::SOL_BASED)
+            .unwrap());
+        assert!(!sol_numeraire
+            .are_token_features_compatible(TokenFeatures::USD_STABLECOIN)
+            .unwrap());
+
+        let mut account = blank_account();
+        assert_eq!(account.numeraire(), Numeraire::Usd);
+        account.features = sol_numeraire;
+        assert_eq!(account.numeraire(), Numeraire::Sol);
+    }
+
+    #[test]
+    fn sol_numeraire_rejects_usd_limits() {
+        let mut account = blank_account();
+        account
+            .check_usd_limits(IsolationFlags::ISOLATED, 100)
+            .unwrap();
+
+        account.features =
+            AccountFeatureFlags::ACCEPTS_SOL_BASED | AccountFeatureFlags::SOL_NUMERAIRE;
+        account
+            .check_usd_limits(IsolationFlags::BORROWABLE_IN_ISOLATION, 0)
+            .unwrap();
+        assert!(matches!(
+            account.check_usd_limits(IsolationFlags::ISOLATED, 0),
+            Err(ErrorCode::UsdLimitInNumeraire)
+        ));
+        assert!(matches!(
+            account.check_usd_limits(IsolationFlags::empty(), 100),
+            Err(ErrorCode::UsdLimitInNumeraire)
+        ));
+    }
+
+    fn valuation_with(
+        equity: i128,
+        liabilities: i128,
+        effective: i128,
+        required: i128,
+    ) -> Valuation {
+        Valuation {
+            equity: Number128::from_decimal(equity, 0),
+            liabilities: Number128::from_decimal(liabilities, 0),
+            required_collateral: Number128::from_decimal(required, 0),
+            weighted_collateral: Number128::ZERO,
+            effective_collateral: Number128::from_decimal(effective, 0),
+            initial_required_collateral: Number128::ZERO,
+            initial_effective_collateral: Number128::ZERO,
+            concentration_excess: Number128::ZERO,
+            stale_collateral_list: vec![],
+            past_due: false,
+        }
+    }
+
+    #[test]
+    fn deleveraging_must_improve_health_within_equity_loss() {
+        let start = valuation_with(1_000, 2_000, 100, 150);
+        assert!(start.is_undercollateralized());
+        assert!(!valuation_with(1_000, 2_000, 150, 150).is_undercollateralized());
+
+        // Still unhealthy, but improved, losing 5% of the 100 repaid
+        valuation_with(995, 1_900, 110, 140)
+            .verify_deleveraged(&start)
+            .unwrap();
+
+        // Health did not improve
+        assert!(valuation_with(1_000, 1_900, 100, 150)
+            .verify_deleveraged(&start)
+            .is_err());
+
+        // Too much equity lost for the repaid liabilities
+        assert!(valuation_with(994, 1_900, 150, 100)
+            .verify_deleveraged(&start)
+            .is_err());
+
+        // Nothing was repaid, so no equity may be lost
+        assert!(valuation_with(999, 2_000, 150, 100)
+            .verify_deleveraged(&start)
+            .is_err());
+
+        // Repeated deleveraging loses at most the proportion of the total repaid liabilities
+        let mut previous = start;
+        for step in 1..=10 {
+            let next = valuation_with(1_000 - 5 * step, 2_000 - 100 * step, 100 + step, 150);
+            next.verify_deleveraged(&previous).unwrap();
+            previous = next;
+        }
+        assert!(valuation_with(949, 1_000, 111, 150)
+            .verify_deleveraged(&previous)
+            .is_err());
+    }
+
+    #[test]
+    fn protocol_share_of_liquidation_fees() {
+        let liquidation = Liquidation::new(0, Number128::ZERO, Number128::ZERO, 2_000);
+        assert_eq!(liquidation.protocol_share_of(10), 2);
+        assert_eq!(liquidation.protocol_share_of(4), 0);
+
+        // Liquidations that began before fees were shared left the field zeroed
+        let liquidation = Liquidation::new(0, Number128::ZERO, Number128::ZERO, 0);
+        assert_eq!(liquidation.protocol_share_of(u64::MAX), 0);
+    }
+
+    #[test]
+    fn liquidation_fees_grow_beyond_the_state() {
+        let mints = (0..LIQUIDATION_FEE_SLOTS + 2)
+            .map(|_| Pubkey::new_unique())
+            .collect::<Vec<_>>();
+        let mut data = vec![0u8; LiquidationState::space(0)];
+        let mut fees = LiquidationState::read_fees(&data).unwrap();
+        assert!(fees.is_empty());
+
+        for (i, mint) in mints.iter().enumerate() {
+            LiquidationFee::accrue(&mut fees, *mint, i as u64 + 1).unwrap();
+        }
+        LiquidationFee::accrue(&mut fees, mints[0], 10).unwrap();
+        // Nothing is accrued for a zero fee
+        LiquidationFee::accrue(&mut fees, Pubkey::new_unique(), 0).unwrap();
+        assert_eq!(fees.len(), mints.len());
+
+        // The account has to grow for fees beyond the slots in the state
+        LiquidationState::write_fees(&mut data, &fees).unwrap_err();
+        data.resize(LiquidationState::space(fees.len()), 0);
+        LiquidationState::write_fees(&mut data, &fees).unwrap();
+
+        let read = LiquidationState::read_fees(&data).unwrap();
+        assert_eq!(read.len(), mints.len());
+        assert_eq!(read[0].amount, 11);
+        assert_eq!(read[mints.len() - 1].mint, mints[mints.len() - 1]);
+
+        // Collected fees free up their slots for reuse
+        fees.remove(0);
+        fees.remove(0);
+        LiquidationState::write_fees(&mut data, &fees).unwrap();
+        let read = LiquidationState::read_fees(&data).unwrap();
+        assert_eq!(read.len(), mints.len() - 2);
+        assert_eq!(read[0].mint, mints[2]);
+    }
 }
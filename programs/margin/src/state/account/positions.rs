@@ -27,7 +27,10 @@ use serde::ser::{Serialize, SerializeStruct, Serializer};
 
 use super::Approver;
 
-use crate::{ErrorCode, TokenConfig, TokenFeatures, TokenKind};
+use crate::{
+    ErrorCode, IsolationFlags, OracleLimits, TokenConfig, TokenFeatures, TokenKind,
+    ValueModifierRamp, SIZE_HAIRCUT_THRESHOLD_EXPONENT,
+};
 
 const POS_PRICE_VALID: u8 = 1;
 
@@ -119,7 +122,14 @@ pub struct AccountPosition {
     pub price: PriceInfo,
 
     /// The kind of balance this position contains
-    pub kind: u32,
+    pub kind: u8,
+
+    /// The concentration group of the position token, or 0 if it is not in a group
+    pub concentration_group: u8,
+
+    /// The collateral value (thousands of USD) above which the weight of this position
+    /// decays, or 0
+    pub size_haircut_threshold: u16,
 
     /// The exponent for the token value
     pub exponent: i16,
@@ -141,8 +151,34 @@ pub struct AccountPosition {
     /// Token feature flags inherited by the position when it was created
     pub token_features: TokenFeatures,
 
-    /// Unused
-    pub _reserved: [u8; 20],
+    /// A stricter weight on the value of this asset used for the initial margin requirement.
+    /// When zero, `value_modifier` is used instead.
+    pub initial_value_modifier: u16,
+
+    /// The max oracle confidence (bps) for prices of this position, or 0 for the default
+    pub max_oracle_confidence: u16,
+
+    /// The max oracle price age (seconds) for prices of this position, or 0 for the default
+    pub max_oracle_staleness: u16,
+
+    /// The max age of the price quote (seconds) for this position, or 0 for the default
+    pub max_price_quote_age: u16,
+
+    /// The time a ramp of the value modifier starts
+    pub ramp_start_timestamp: u32,
+
+    /// The time a ramp of the value modifier ends, or 0 if there is no ramp
+    pub ramp_end_timestamp: u32,
+
+    /// The value modifier when a ramp starts, which moves towards `value_modifier`
+    pub ramp_start_value_modifier: u16,
+
+    /// Isolation mode flags inherited from the token config
+    pub isolation: IsolationFlags,
+
+    /// The max share (percent) of the account's weighted collateral counted from this
+    /// position and the others in its concentration group, or 0 for no limit
+    pub max_collateral_share: u8,
 }
 
 #[repr(transparent)]
@@ -174,7 +210,7 @@ mod _idl {
 
 impl AccountPosition {
     pub fn kind(&self) -> TokenKind {
-        TokenKind::from_integer(self.kind).unwrap_or_default()
+        TokenKind::from_integer(self.kind as u32).unwrap_or_default()
     }
 
     // Raw USD value of a position (balance * price)
@@ -188,18 +224,101 @@ impl AccountPosition {
         Number128::from_bits(self.value)
     }
 
-    pub fn collateral_value(&self) -> Number128 {
+    /// The value modifier in effect at a time, which differs from `value_modifier`
+    /// while a ramp towards it is running
+    pub fn value_modifier_at(&self, timestamp: u64) -> u16 {
+        self.value_modifier_ramp()
+            .value_modifier_at(self.value_modifier, timestamp)
+    }
+
+    pub fn collateral_value(&self, timestamp: u64) -> Number128 {
         assert!(
             self.kind() == TokenKind::Collateral || self.kind() == TokenKind::AdapterCollateral
         );
 
-        Number128::from_decimal(self.value_modifier, -2) * self.value()
+        self.weighted_value(self.value_modifier_at(timestamp))
     }
 
-    pub fn required_collateral_value(&self) -> Number128 {
+    pub fn required_collateral_value(&self, timestamp: u64) -> Number128 {
+        self.required_collateral_value_with(self.value_modifier_at(timestamp))
+    }
+
+    /// The oracle limits that apply to prices of this position
+    pub fn oracle_limits(&self) -> OracleLimits {
+        OracleLimits {
+            max_confidence: self.max_oracle_confidence,
+            max_oracle_staleness: self.max_oracle_staleness,
+            max_price_quote_age: self.max_price_quote_age,
+        }
+    }
+
+    /// Set the oracle limits that apply to prices of this position
+    pub fn set_oracle_limits(&mut self, limits: &OracleLimits) {
+        self.max_oracle_confidence = limits.max_confidence;
+        self.max_oracle_staleness = limits.max_oracle_staleness;
+        self.max_price_quote_age = limits.max_price_quote_age;
+    }
+
+    /// The scheduled ramp of the value modifier of this position
+    pub fn value_modifier_ramp(&self) -> ValueModifierRamp {
+        ValueModifierRamp {
+            start_value_modifier: self.ramp_start_value_modifier,
+            start_timestamp: self.ramp_start_timestamp,
+            end_timestamp: self.ramp_end_timestamp,
+        }
+    }
+
+    /// Set the scheduled ramp of the value modifier of this position
+    pub fn set_value_modifier_ramp(&mut self, ramp: &ValueModifierRamp) {
+        self.ramp_start_value_modifier = ramp.start_value_modifier;
+        self.ramp_start_timestamp = ramp.start_timestamp;
+        self.ramp_end_timestamp = ramp.end_timestamp;
+    }
+
+    /// The value modifier applied when checking the initial margin requirement,
+    /// which is never less strict than the value modifier in effect
+    pub fn initial_value_modifier(&self, timestamp: u64) -> u16 {
+        let value_modifier = self.value_modifier_at(timestamp);
+        match self.initial_value_modifier {
+            0 => value_modifier,
+            modifier => modifier.min(value_modifier),
+        }
+    }
+
+    pub fn initial_collateral_value(&self, timestamp: u64) -> Number128 {
+        assert!(
+            self.kind() == TokenKind::Collateral || self.kind() == TokenKind::AdapterCollateral
+        );
+
+        self.weighted_value(self.initial_value_modifier(timestamp))
+    }
+
+    /// The value weighted by a value modifier, including the size haircut.
+    ///
+    /// Above the size haircut threshold `T`, the weight decays with the square root of the
+    /// position value `V` to `value_modifier * sqrt(T / V)`, as large positions cost more to
+    /// liquidate. The weighted value still grows with the position size.
+    fn weighted_value(&self, value_modifier: u16) -> Number128 {
+        let value = self.value();
+        let weighted_value = Number128::from_decimal(value_modifier, -2) * value;
+        let threshold =
+            Number128::from_decimal(self.size_haircut_threshold, SIZE_HAIRCUT_THRESHOLD_EXPONENT);
+
+        if self.size_haircut_threshold == 0 || value <= threshold {
+            return weighted_value;
+        }
+
+        weighted_value * (threshold / value).sqrt()
+    }
+
+    pub fn initial_required_collateral_value(&self, timestamp: u64) -> Number128 {
+        self.required_collateral_value_with(self.initial_value_modifier(timestamp))
+    }
+
+    fn required_collateral_value_with(&self, value_modifier: u16) -> Number128 {
         assert_eq!(self.kind(), TokenKind::Claim);
 
-        let modifier = Number128::from_decimal(self.value_modifier, -2);
+        let modifier = Number128::from_decimal(value_modifier, -2);
 
         if modifier == Number128::ZERO {
             msg!("no leverage configured for claim {}", &self.token);
@@ -242,7 +361,9 @@ impl AccountPosition {
 
         match self.kind() {
             // Only Authority can close it
-            TokenKind::Collateral => authority_approved && !adapter_approved,
+            TokenKind::Collateral | TokenKind::NonCollateral => {
+                authority_approved && !adapter_approved
+            }
             // Requires Authority AND matching Adapter
             TokenKind::Claim | TokenKind::AdapterCollateral => {
                 authority_approved && adapter_approved
@@ -283,6 +404,7 @@ impl Serialize for TokenKind {
             TokenKind::Claim => "Claim",
             TokenKind::Collateral => "Collateral",
             TokenKind::AdapterCollateral => "AdapterCollateral",
+            TokenKind::NonCollateral => "NonCollateral",
         })
     }
 }
@@ -472,8 +594,29 @@ pub struct PositionConfigUpdate {
     /// Max staleness in seconds for the position balance
     pub max_staleness: u64,
 
+    /// The collateral value (thousands of USD) above which the weight of the position decays
+    pub size_haircut_threshold: u16,
+
     /// Token features
     pub token_features: TokenFeatures,
+
+    /// A stricter weight on the value of this asset for the initial margin requirement
+    pub initial_value_modifier: u16,
+
+    /// Overrides of the global oracle limits for the position token
+    pub oracle_limits: OracleLimits,
+
+    /// A scheduled ramp of the value modifier
+    pub value_modifier_ramp: ValueModifierRamp,
+
+    /// Isolation mode flags of the position token
+    pub isolation: IsolationFlags,
+
+    /// The max share (percent) of weighted collateral counted from the position token
+    pub max_collateral_share: u8,
+
+    /// The concentration group of the position token
+    pub concentration_group: u8,
 }
 
 impl PositionConfigUpdate {
@@ -485,7 +628,9 @@ impl PositionConfigUpdate {
         adapter: Pubkey,
     ) -> anchor_lang::Result<Self> {
         let token_program = match config.token_kind {
-            TokenKind::Collateral => config.underlying_mint_token_program,
+            TokenKind::Collateral | TokenKind::NonCollateral => {
+                config.underlying_mint_token_program
+            }
             TokenKind::Claim => config.mint_token_program,
             TokenKind::AdapterCollateral => config.mint_token_program,
         };
@@ -505,6 +650,13 @@ impl PositionConfigUpdate {
             value_modifier: config.value_modifier,
             max_staleness: config.max_staleness,
             token_features: config.token_features,
+            initial_value_modifier: config.initial_value_modifier,
+            oracle_limits: config.oracle_limits,
+            value_modifier_ramp: config.value_modifier_ramp,
+            isolation: config.isolation.flags,
+            max_collateral_share: config.max_collateral_share,
+            concentration_group: config.concentration_group,
+            size_haircut_threshold: config.size_haircut_threshold,
         })
     }
 }
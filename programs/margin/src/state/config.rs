@@ -18,15 +18,26 @@
 use anchor_lang::{prelude::*, Owners};
 use bitflags::bitflags;
 use bytemuck::{Contiguous, Pod, Zeroable};
-use glow_program_common::oracle::TokenPriceOracle;
+use glow_program_common::{oracle::TokenPriceOracle, Number128};
 
-use crate::{ErrorCode, TokenConfigUpdate};
+use crate::{
+    ErrorCode, TokenConfigUpdate, LIQUIDATION_MAX_EQUITY_LOSS_PROPORTION_BPS,
+    LIQUIDATION_SEIZURE_BONUS_BPS, MAX_CLAIM_VALUE_MODIFIER, MAX_COLLATERAL_VALUE_MODIFIER,
+    MAX_ORACLE_CONFIDENCE, MAX_ORACLE_CONFIDENCE_OVERRIDE, MAX_ORACLE_STALENESS,
+    MAX_ORACLE_STALENESS_OVERRIDE, MAX_PRICE_QUOTE_AGE,
+};
 
 /// The current [TokenConfig] version, created in June 2025.
 ///
 /// Version 1 was the original untagged version.
 pub const TOKEN_CONFIG_VERSION: u8 = 2;
 
+/// The exponent of the USD values tracked as debt backed by isolated tokens
+pub const ISOLATED_DEBT_EXPONENT: i32 = -6;
+
+/// The exponent of the USD values of size haircut thresholds, which are in thousands of USD
+pub const SIZE_HAIRCUT_THRESHOLD_EXPONENT: i32 = 3;
+
 /// Description of the token's usage
 #[derive(AnchorSerialize, AnchorDeserialize, Contiguous, Eq, PartialEq, Clone, Copy, Debug)]
 #[repr(u32)]
@@ -41,6 +52,10 @@ pub enum TokenKind {
     /// custodied by that adapter. The token account is owned by the adapter. Collateral
     /// is accessed through instructions to the adapter.
     AdapterCollateral,
+
+    /// The token can be held by a margin account, but has no value as collateral.
+    /// Positions of this kind are not priced and are ignored by the account valuation.
+    NonCollateral,
 }
 
 impl Default for TokenKind {
@@ -49,10 +64,50 @@ impl Default for TokenKind {
     }
 }
 
+impl TokenKind {
+    /// Check that a value modifier is within the limits for the token kind
+    pub fn check_value_modifier_limits(&self, value_modifier: u16) -> Result<()> {
+        // Ensure value modifier cannot exceed limit for all token kinds.
+        // Use match in case we extend the token kinds which forces us to
+        // update the match arms here.
+        match self {
+            TokenKind::Collateral | TokenKind::AdapterCollateral => {
+                if value_modifier > MAX_COLLATERAL_VALUE_MODIFIER {
+                    msg!(
+                        "collateral value modifier cannot exceed limit, got: {}",
+                        value_modifier
+                    );
+                    return err!(ErrorCode::InvalidConfigCollateralValueModifierLimit);
+                }
+            }
+            TokenKind::NonCollateral => {
+                if value_modifier != 0 {
+                    msg!(
+                        "non-collateral value modifier must be zero, got: {}",
+                        value_modifier
+                    );
+                    return err!(ErrorCode::InvalidConfigCollateralValueModifierLimit);
+                }
+            }
+            TokenKind::Claim => {
+                if value_modifier > MAX_CLAIM_VALUE_MODIFIER {
+                    msg!(
+                        "claim value modifier cannot exceed limit, got: {}",
+                        value_modifier
+                    );
+                    return err!(ErrorCode::InvalidConfigClaimValueModifierLimit);
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
 impl From<glow_metadata::TokenKind> for TokenKind {
     fn from(kind: glow_metadata::TokenKind) -> Self {
         match kind {
-            glow_metadata::TokenKind::NonCollateral => Self::Collateral,
+            glow_metadata::TokenKind::NonCollateral => Self::NonCollateral,
             glow_metadata::TokenKind::Collateral => Self::Collateral,
             glow_metadata::TokenKind::Claim => Self::Claim,
             glow_metadata::TokenKind::AdapterCollateral => Self::AdapterCollateral,
@@ -124,6 +179,27 @@ impl TokenFeatures {
     }
 }
 
+/// Flags that place a token in isolation mode within an airspace.
+///
+/// Isolation mode allows listing volatile tokens as collateral, while limiting the total
+/// debt that can be backed by them across the airspace.
+#[derive(
+    Zeroable, Pod, Debug, Eq, PartialEq, Default, AnchorSerialize, AnchorDeserialize, Clone, Copy,
+)]
+#[repr(transparent)]
+pub struct IsolationFlags(u8);
+
+bitflags! {
+    impl IsolationFlags: u8 {
+        /// The token is an isolated collateral. An account holding it may only borrow
+        /// claims that are borrowable in isolation, up to the debt ceiling of the token.
+        const ISOLATED                  = 1 << 0;
+
+        /// The claim may be borrowed by accounts that hold an isolated collateral.
+        const BORROWABLE_IN_ISOLATION   = 1 << 1;
+    }
+}
+
 mod _idl {
     use super::*;
 
@@ -131,6 +207,11 @@ mod _idl {
     pub struct TokenFeatures {
         pub flags: u16,
     }
+
+    #[derive(Zeroable, AnchorSerialize, AnchorDeserialize, Default)]
+    pub struct IsolationFlags {
+        pub flags: u8,
+    }
 }
 
 /// The configuration account specifying parameters for a token when used
@@ -175,8 +256,42 @@ pub struct TokenConfig {
     /// The version of the token config. Introduced in June 2025.
     pub version: u8,
 
+    /// A stricter value modifier used for the initial margin requirement, which any
+    /// instruction that increases the risk of a margin account has to satisfy.
+    ///
+    /// A value of 0 means that the initial margin is the same as the maintenance
+    /// margin given by `value_modifier`.
+    pub initial_value_modifier: u16,
+
+    /// Overrides of the global oracle limits for this token
+    pub oracle_limits: OracleLimits,
+
+    /// A scheduled change of the value modifier towards `value_modifier`, if any
+    pub value_modifier_ramp: ValueModifierRamp,
+
+    /// The isolation mode of the token, and the debt backed by it if it is isolated
+    pub isolation: IsolationConfig,
+
+    /// The max share (percent) of an account's weighted collateral that may be counted
+    /// from this token. A value of 0 means there is no limit.
+    pub max_collateral_share: u8,
+
+    /// The collateral value of a position above which its weight decays with the square
+    /// root of the position size, in USD with [SIZE_HAIRCUT_THRESHOLD_EXPONENT] as the
+    /// exponent. A value of 0 means there is no size haircut.
+    pub size_haircut_threshold: u16,
+
+    /// Tokens in the same nonzero concentration group, such as a token and the deposit
+    /// notes of its pool, count towards a single concentration limit. A value of 0 means
+    /// the token is not in a group.
+    pub concentration_group: u8,
+
+    /// The bonus (bps) on the value of this collateral that a liquidator receives when
+    /// seizing it. A value of 0 means the default [LIQUIDATION_SEIZURE_BONUS_BPS].
+    pub liquidation_bonus_bps: u16,
+
     // /// Bytes that are reserved for future versions
-    pub reserved: [u8; 64],
+    pub reserved: [u8; 23],
 }
 
 impl Owners for TokenConfig {
@@ -194,6 +309,7 @@ impl PartialEq<TokenConfigUpdate> for TokenConfig {
             && self.value_modifier == other.value_modifier
             && self.max_staleness == other.max_staleness
             && self.token_features == other.token_features
+            && self.oracle_limits == other.oracle_limits
     }
 }
 
@@ -220,6 +336,127 @@ impl TokenConfig {
         Ok(())
     }
 
+    /// The value modifier in effect at a time, which differs from `value_modifier`
+    /// while a ramp towards it is running
+    pub fn effective_value_modifier(&self, timestamp: u64) -> u16 {
+        self.value_modifier_ramp
+            .value_modifier_at(self.value_modifier, timestamp)
+    }
+
+    /// Check that an initial value modifier is at least as strict as the maintenance
+    /// `value_modifier`.
+    ///
+    /// A lower modifier is stricter for all token kinds, as it reduces the collateral weight
+    /// of deposits and the max leverage of claims.
+    pub fn check_initial_value_modifier(&self, initial_value_modifier: u16) -> Result<()> {
+        if initial_value_modifier == 0 {
+            return Ok(());
+        }
+
+        match self.token_kind {
+            TokenKind::Collateral | TokenKind::AdapterCollateral | TokenKind::Claim => {
+                if initial_value_modifier > self.value_modifier {
+                    msg!(
+                        "initial value modifier {} cannot exceed the value modifier {}",
+                        initial_value_modifier,
+                        self.value_modifier
+                    );
+                    return err!(ErrorCode::InvalidConfigInitialValueModifier);
+                }
+            }
+            TokenKind::NonCollateral => {
+                msg!("non-collateral tokens cannot have an initial value modifier");
+                return err!(ErrorCode::InvalidConfigInitialValueModifier);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Check that a concentration limit is a valid share of collateral for the token
+    pub fn check_max_collateral_share(&self, max_collateral_share: u8) -> Result<()> {
+        if max_collateral_share > 100 {
+            msg!(
+                "max collateral share cannot exceed 100, got: {}",
+                max_collateral_share
+            );
+            return err!(ErrorCode::InvalidConfigConcentrationLimit);
+        }
+
+        if max_collateral_share > 0
+            && !matches!(
+                self.token_kind,
+                TokenKind::Collateral | TokenKind::AdapterCollateral
+            )
+        {
+            msg!("only collateral tokens can have a concentration limit");
+            return err!(ErrorCode::InvalidConfigConcentrationLimit);
+        }
+
+        Ok(())
+    }
+
+    /// Check that the token can be counted towards the concentration limit of a group
+    pub fn check_concentration_group(&self, concentration_group: u8) -> Result<()> {
+        if concentration_group > 0
+            && !matches!(
+                self.token_kind,
+                TokenKind::Collateral | TokenKind::AdapterCollateral
+            )
+        {
+            msg!("only collateral tokens can be in a concentration group");
+            return err!(ErrorCode::InvalidConfigConcentrationLimit);
+        }
+
+        Ok(())
+    }
+
+    /// Check that a size haircut can be applied to the token
+    pub fn check_size_haircut_threshold(&self, size_haircut_threshold: u16) -> Result<()> {
+        if size_haircut_threshold > 0
+            && !matches!(
+                self.token_kind,
+                TokenKind::Collateral | TokenKind::AdapterCollateral
+            )
+        {
+            msg!("only collateral tokens can have a size haircut");
+            return err!(ErrorCode::InvalidConfigSizeHaircut);
+        }
+
+        Ok(())
+    }
+
+    /// Check that the liquidation bonus of the token fits within the max equity loss
+    /// of a liquidation
+    pub fn check_liquidation_bonus(&self, liquidation_bonus_bps: u16) -> Result<()> {
+        if liquidation_bonus_bps > 0
+            && !matches!(
+                self.token_kind,
+                TokenKind::Collateral | TokenKind::AdapterCollateral
+            )
+        {
+            msg!("only collateral tokens can have a liquidation bonus");
+            return err!(ErrorCode::InvalidConfigLiquidationBonus);
+        }
+        if liquidation_bonus_bps > LIQUIDATION_MAX_EQUITY_LOSS_PROPORTION_BPS {
+            msg!(
+                "the liquidation bonus cannot exceed {} bps",
+                LIQUIDATION_MAX_EQUITY_LOSS_PROPORTION_BPS
+            );
+            return err!(ErrorCode::InvalidConfigLiquidationBonus);
+        }
+
+        Ok(())
+    }
+
+    /// The bonus on the value of this collateral when it is seized by a liquidator
+    pub fn liquidation_bonus(&self) -> u16 {
+        match self.liquidation_bonus_bps {
+            0 => LIQUIDATION_SEIZURE_BONUS_BPS,
+            bonus => bonus,
+        }
+    }
+
     pub fn adapter_program(&self) -> Option<Pubkey> {
         match self.admin {
             TokenAdmin::Adapter(address) => Some(address),
@@ -294,6 +531,200 @@ impl TokenConfig {
     }
 }
 
+/// Per-token overrides of the limits applied to oracle prices.
+///
+/// Tokens with less liquid markets, such as long-tail LSTs, naturally have wider confidence
+/// intervals and less frequent updates than the majors. A value of 0 uses the global default.
+#[derive(AnchorSerialize, AnchorDeserialize, Debug, Default, Eq, PartialEq, Clone, Copy)]
+pub struct OracleLimits {
+    /// The maximum confidence interval of a price (bps), overriding [MAX_ORACLE_CONFIDENCE]
+    pub max_confidence: u16,
+
+    /// The maximum age of an oracle price (seconds), overriding [MAX_ORACLE_STALENESS]
+    pub max_oracle_staleness: u16,
+
+    /// The maximum age of a position's price quote (seconds), overriding [MAX_PRICE_QUOTE_AGE]
+    pub max_price_quote_age: u16,
+}
+
+impl OracleLimits {
+    pub fn check_valid_configuration(&self) -> Result<()> {
+        if self.max_confidence > MAX_ORACLE_CONFIDENCE_OVERRIDE
+            || self.max_oracle_staleness as u64 > MAX_ORACLE_STALENESS_OVERRIDE
+            || self.max_price_quote_age as u64 > MAX_ORACLE_STALENESS_OVERRIDE
+        {
+            msg!("oracle limits exceed their allowed range: {:?}", self);
+            return err!(ErrorCode::InvalidConfigOracleLimits);
+        }
+
+        Ok(())
+    }
+
+    pub fn max_confidence(&self) -> u16 {
+        match self.max_confidence {
+            0 => MAX_ORACLE_CONFIDENCE,
+            value => value,
+        }
+    }
+
+    pub fn max_oracle_staleness(&self) -> u64 {
+        match self.max_oracle_staleness {
+            0 => MAX_ORACLE_STALENESS,
+            value => value as u64,
+        }
+    }
+
+    pub fn max_price_quote_age(&self) -> u64 {
+        match self.max_price_quote_age {
+            0 => MAX_PRICE_QUOTE_AGE,
+            value => value as u64,
+        }
+    }
+}
+
+/// A scheduled change of a token's value modifier, which is interpolated linearly
+/// from a start value to the token's configured `value_modifier` over time.
+///
+/// Lowering a collateral weight at once can make many accounts liquidatable instantly,
+/// a ramp gives users time to deleverage instead.
+#[derive(AnchorSerialize, AnchorDeserialize, Debug, Default, Eq, PartialEq, Clone, Copy)]
+pub struct ValueModifierRamp {
+    /// The value modifier when the ramp starts
+    pub start_value_modifier: u16,
+
+    /// The time the ramp starts
+    pub start_timestamp: u32,
+
+    /// The time the ramp ends, from which the target value modifier applies.
+    /// A value of 0 means that there is no ramp.
+    pub end_timestamp: u32,
+}
+
+impl ValueModifierRamp {
+    /// Create a ramp from the value modifier effective now to the target at `end_timestamp`
+    pub fn new(start_value_modifier: u16, now: u64, end_timestamp: u64) -> Result<Self> {
+        if end_timestamp <= now {
+            msg!("the ramp must end in the future, got: {}", end_timestamp);
+            return err!(ErrorCode::InvalidConfigValueModifierRamp);
+        }
+
+        Ok(Self {
+            start_value_modifier,
+            start_timestamp: u32::try_from(now)
+                .map_err(|_| error!(ErrorCode::InvalidConfigValueModifierRamp))?,
+            end_timestamp: u32::try_from(end_timestamp)
+                .map_err(|_| error!(ErrorCode::InvalidConfigValueModifierRamp))?,
+        })
+    }
+
+    /// The value modifier in effect at a time, given the target of the ramp
+    pub fn value_modifier_at(&self, target: u16, timestamp: u64) -> u16 {
+        let start = self.start_timestamp as u64;
+        let end = self.end_timestamp as u64;
+
+        if self.end_timestamp == 0 || timestamp >= end {
+            return target;
+        }
+        if timestamp <= start {
+            return self.start_value_modifier;
+        }
+
+        let elapsed = (timestamp - start) as i64;
+        let duration = (end - start) as i64;
+        let change = target as i64 - self.start_value_modifier as i64;
+
+        (self.start_value_modifier as i64 + change * elapsed / duration) as u16
+    }
+}
+
+/// The isolation mode settings of a token.
+///
+/// Debt values are in USD with [ISOLATED_DEBT_EXPONENT] as the exponent.
+#[derive(AnchorSerialize, AnchorDeserialize, Debug, Default, Eq, PartialEq, Clone, Copy)]
+pub struct IsolationConfig {
+    /// Whether the token is isolated, or borrowable by accounts holding an isolated token
+    pub flags: IsolationFlags,
+
+    /// The max value of debt that may be backed by an isolated token in the airspace
+    pub debt_ceiling: u64,
+
+    /// The current value of debt backed by an isolated token in the airspace.
+    ///
+    /// This is tracked at the value of the tokens when they were borrowed or repaid, so it
+    /// does not include accrued interest.
+    pub isolated_debt: u64,
+}
+
+impl IsolationConfig {
+    pub fn is_isolated(&self) -> bool {
+        self.flags.contains(IsolationFlags::ISOLATED)
+    }
+
+    /// Check that the flags are known and that a token is not both isolated and borrowable
+    pub fn check_valid_configuration(&self, token_kind: TokenKind) -> Result<()> {
+        require!(
+            IsolationFlags::from_bits(self.flags.bits()).is_some(),
+            ErrorCode::InvalidConfigIsolation
+        );
+
+        if self.flags.contains(IsolationFlags::ISOLATED)
+            && !matches!(
+                token_kind,
+                TokenKind::Collateral | TokenKind::AdapterCollateral
+            )
+        {
+            msg!("only collateral tokens can be isolated");
+            return err!(ErrorCode::InvalidConfigIsolation);
+        }
+
+        if self.flags.contains(IsolationFlags::BORROWABLE_IN_ISOLATION)
+            && token_kind != TokenKind::Claim
+        {
+            msg!("only claims can be borrowable in isolation");
+            return err!(ErrorCode::InvalidConfigIsolation);
+        }
+
+        Ok(())
+    }
+
+    /// Record new debt backed by the isolated token, which may not exceed the debt ceiling
+    pub fn add_debt(&mut self, value: u64) -> Result<()> {
+        let isolated_debt = self
+            .isolated_debt
+            .checked_add(value)
+            .ok_or(ErrorCode::MathOpFailed)?;
+
+        if isolated_debt > self.debt_ceiling {
+            msg!(
+                "isolated debt of {} would exceed the debt ceiling of {}",
+                isolated_debt,
+                self.debt_ceiling
+            );
+            return err!(ErrorCode::IsolationDebtCeilingExceeded);
+        }
+        self.isolated_debt = isolated_debt;
+
+        Ok(())
+    }
+
+    /// Record repaid debt backed by the isolated token.
+    ///
+    /// Repayments include interest, so the debt saturates at zero.
+    pub fn remove_debt(&mut self, value: u64) {
+        self.isolated_debt = self.isolated_debt.saturating_sub(value);
+    }
+
+    /// Record the debt borrowed and repaid against the isolated token by an account.
+    pub fn record_debt_change(&mut self, borrowed: Number128, repaid: Number128) -> Result<()> {
+        if borrowed > repaid {
+            self.add_debt((borrowed - repaid).as_u64(ISOLATED_DEBT_EXPONENT))
+        } else {
+            self.remove_debt((repaid - borrowed).as_u64(ISOLATED_DEBT_EXPONENT));
+            Ok(())
+        }
+    }
+}
+
 /// Description of which program administers a token
 #[derive(AnchorSerialize, AnchorDeserialize, Debug, Eq, PartialEq, Clone, Copy)]
 pub enum TokenAdmin {
@@ -370,6 +801,36 @@ bitflags! {
     }
 }
 
+/// The current [AdapterConfig] version.
+///
+/// Version 1 was the original untagged version, without invoke permissions.
+pub const ADAPTER_CONFIG_VERSION: u8 = 2;
+
+/// The maximum number of instructions that can be allowlisted for an adapter
+pub const MAX_ADAPTER_ALLOWED_INSTRUCTIONS: usize = 16;
+
+/// The margin instructions through which an adapter may be invoked, in addition
+/// to `adapter_invoke` which is always allowed for a registered adapter.
+#[derive(
+    Zeroable, Pod, Debug, Eq, PartialEq, Default, AnchorSerialize, AnchorDeserialize, Clone, Copy,
+)]
+#[repr(transparent)]
+pub struct AdapterInvokeFlags(u8);
+
+bitflags! {
+    impl AdapterInvokeFlags: u8 {
+        /// The adapter can be invoked by a liquidator through `liquidator_invoke`
+        const LIQUIDATOR_INVOKE         = 1 << 0;
+
+        /// The adapter can be invoked permissionlessly through `accounting_invoke`
+        const ACCOUNTING_INVOKE         = 1 << 1;
+
+        /// The adapter can be invoked by a keeper executing a trigger order through
+        /// `execute_trigger_order`
+        const ORDER_INVOKE              = 1 << 2;
+    }
+}
+
 /// Configuration for allowed adapters
 #[account]
 #[derive(Default, Debug, Eq, PartialEq)]
@@ -379,6 +840,164 @@ pub struct AdapterConfig {
 
     /// The program address allowed to be called as an adapter
     pub adapter_program: Pubkey,
+
+    /// The version of the config
+    pub version: u8,
+
+    /// The margin instructions through which the adapter may be invoked
+    pub invoke_flags: AdapterInvokeFlags,
+
+    /// The number of entries in `allowed_instructions` that are in use.
+    ///
+    /// When zero, any instruction of the adapter may be invoked.
+    pub allowed_instructions_len: u8,
+
+    /// The instruction discriminators (the first 8 bytes of instruction data)
+    /// that may be invoked on the adapter.
+    pub allowed_instructions: [[u8; 8]; MAX_ADAPTER_ALLOWED_INSTRUCTIONS],
+
+    /// Limits on the value that may flow out of margin accounts through the adapter
+    pub outflow_limits: AdapterOutflowLimits,
+
+    /// The time (unix seconds) at which the current outflow window started
+    pub window_start: u64,
+
+    /// The USD value (as [Number128] bits) that flowed out through the adapter
+    /// in the current outflow window
+    pub window_outflow: [u8; 16],
+}
+
+/// Limits on the USD value that may flow out of margin accounts through an adapter
+/// during `adapter_invoke`.
+///
+/// The outflow of an invocation is the decrease in the value of the margin account's
+/// positions, measured at their prices before the invocation. A limit of zero means no
+/// limit.
+#[derive(AnchorSerialize, AnchorDeserialize, Default, Debug, Eq, PartialEq, Clone, Copy)]
+pub struct AdapterOutflowLimits {
+    /// The maximum USD value that may flow out of a margin account in a single invocation
+    pub max_invoke_outflow: u64,
+
+    /// The maximum USD value that may flow out of all margin accounts in the airspace
+    /// within a single outflow window.
+    ///
+    /// The window is recorded in the adapter config, so invocations of the adapter must
+    /// pass the config as writable and can't be processed in parallel.
+    pub max_window_outflow: u64,
+
+    /// The duration (seconds) of an outflow window
+    pub window_duration: u64,
+}
+
+impl AdapterOutflowLimits {
+    /// Check if any limit is configured
+    pub fn is_enabled(&self) -> bool {
+        self.max_invoke_outflow > 0 || self.has_window()
+    }
+
+    /// Check if outflows should be tracked over a time window
+    pub fn has_window(&self) -> bool {
+        self.max_window_outflow > 0
+    }
+
+    pub fn check_valid_configuration(&self) -> Result<()> {
+        // A window limit needs a window to be measured over
+        require!(
+            !self.has_window() || self.window_duration > 0,
+            ErrorCode::InvalidConfigAdapterOutflowLimits
+        );
+
+        Ok(())
+    }
+}
+
+impl AdapterConfig {
+    /// The instruction discriminators that are allowlisted for the adapter
+    pub fn allowed_instructions(&self) -> &[[u8; 8]] {
+        let len = (self.allowed_instructions_len as usize).min(MAX_ADAPTER_ALLOWED_INSTRUCTIONS);
+        &self.allowed_instructions[..len]
+    }
+
+    /// Replace the allowlisted instruction discriminators.
+    ///
+    /// An empty list allows any instruction to be invoked.
+    pub fn set_allowed_instructions(&mut self, instructions: &[[u8; 8]]) -> Result<()> {
+        require!(
+            instructions.len() <= MAX_ADAPTER_ALLOWED_INSTRUCTIONS,
+            ErrorCode::InvalidConfigAdapterInstructions
+        );
+
+        self.allowed_instructions = Default::default();
+        self.allowed_instructions[..instructions.len()].copy_from_slice(instructions);
+        self.allowed_instructions_len = instructions.len() as u8;
+
+        Ok(())
+    }
+
+    /// The value that flowed out through the adapter in the current outflow window
+    pub fn window_outflow(&self) -> Number128 {
+        Number128::from_bits(self.window_outflow)
+    }
+
+    /// Check an outflow against the per-invocation limit
+    pub fn verify_invoke_outflow(&self, outflow: Number128) -> Result<()> {
+        let max_outflow = self.outflow_limits.max_invoke_outflow;
+
+        if max_outflow > 0 && outflow > Number128::from_decimal(max_outflow, 0) {
+            msg!(
+                "outflow of {} exceeds the adapter's invocation limit of {}",
+                outflow,
+                max_outflow
+            );
+            return err!(ErrorCode::AdapterOutflowLimitExceeded);
+        }
+
+        Ok(())
+    }
+
+    /// Record an outflow in the current outflow window, starting a new window if the
+    /// current one has elapsed, and check that the window limit is not exceeded.
+    pub fn record_window_outflow(&mut self, outflow: Number128, timestamp: u64) -> Result<()> {
+        let limits = self.outflow_limits;
+
+        if !limits.has_window() {
+            return Ok(());
+        }
+
+        if timestamp >= self.window_start.saturating_add(limits.window_duration) {
+            self.window_start = timestamp;
+            self.window_outflow = Number128::ZERO.into_bits();
+        }
+
+        let window_outflow = self.window_outflow() + outflow;
+
+        if window_outflow > Number128::from_decimal(limits.max_window_outflow, 0) {
+            msg!(
+                "outflow of {} in the current window exceeds the adapter's limit of {}",
+                window_outflow,
+                limits.max_window_outflow
+            );
+            return err!(ErrorCode::AdapterOutflowLimitExceeded);
+        }
+
+        self.window_outflow = window_outflow.into_bits();
+
+        Ok(())
+    }
+
+    /// Check if the instruction data invokes an instruction the adapter allows
+    pub fn is_instruction_allowed(&self, data: &[u8]) -> bool {
+        let allowed = self.allowed_instructions();
+
+        if allowed.is_empty() {
+            return true;
+        }
+
+        match data.get(..8) {
+            Some(discriminator) => allowed.iter().any(|d| d[..] == *discriminator),
+            None => false,
+        }
+    }
 }
 
 #[cfg(test)]
@@ -485,6 +1104,7 @@ mod tests {
             value_modifier: 8000,
             max_staleness: 300,
             token_features: TokenFeatures::empty(),
+            oracle_limits: Default::default(),
         }
     }
 
@@ -854,7 +1474,15 @@ mod tests {
             },
             token_features: TokenFeatures::empty(),
             version: TOKEN_CONFIG_VERSION,
-            reserved: [0; 64],
+            initial_value_modifier: 0,
+            oracle_limits: OracleLimits::default(),
+            value_modifier_ramp: ValueModifierRamp::default(),
+            isolation: IsolationConfig::default(),
+            max_collateral_share: 0,
+            size_haircut_threshold: 0,
+            concentration_group: 0,
+            liquidation_bonus_bps: 0,
+            reserved: [0; 23],
         }
     }
 
@@ -875,7 +1503,15 @@ mod tests {
             admin,
             token_features: TokenFeatures::empty(),
             version: TOKEN_CONFIG_VERSION,
-            reserved: [0; 64],
+            initial_value_modifier: 0,
+            oracle_limits: OracleLimits::default(),
+            value_modifier_ramp: ValueModifierRamp::default(),
+            isolation: IsolationConfig::default(),
+            max_collateral_share: 0,
+            size_haircut_threshold: 0,
+            concentration_group: 0,
+            liquidation_bonus_bps: 0,
+            reserved: [0; 23],
         }
     }
 
@@ -892,6 +1528,7 @@ mod tests {
             value_modifier: 8000,
             max_staleness: 300,
             token_features: TokenFeatures::empty(),
+            oracle_limits: Default::default(),
         }
     }
 
@@ -914,7 +1551,15 @@ mod tests {
             },
             token_features: features,
             version: TOKEN_CONFIG_VERSION,
-            reserved: [0; 64],
+            initial_value_modifier: 0,
+            oracle_limits: OracleLimits::default(),
+            value_modifier_ramp: ValueModifierRamp::default(),
+            isolation: IsolationConfig::default(),
+            max_collateral_share: 0,
+            size_haircut_threshold: 0,
+            concentration_group: 0,
+            liquidation_bonus_bps: 0,
+            reserved: [0; 23],
         }
     }
 
@@ -933,6 +1578,246 @@ mod tests {
             value_modifier: 8000,
             max_staleness: 300,
             token_features: features,
+            oracle_limits: Default::default(),
         }
     }
+
+    #[test]
+    fn test_check_initial_value_modifier() {
+        for token_kind in [
+            TokenKind::Collateral,
+            TokenKind::AdapterCollateral,
+            TokenKind::Claim,
+        ] {
+            let config = create_test_token_config(token_kind, Pubkey::new_unique());
+            assert!(config.check_initial_value_modifier(0).is_ok());
+            assert!(config.check_initial_value_modifier(6000).is_ok());
+            assert!(config.check_initial_value_modifier(8000).is_ok());
+            assert!(config.check_initial_value_modifier(8001).is_err());
+        }
+
+        let mut config = create_test_token_config(TokenKind::NonCollateral, Pubkey::new_unique());
+        config.value_modifier = 0;
+        assert!(config.check_initial_value_modifier(0).is_ok());
+        assert!(config.check_initial_value_modifier(1).is_err());
+    }
+
+    #[test]
+    fn test_oracle_limits_defaults_and_validation() {
+        let limits = OracleLimits::default();
+        assert!(limits.check_valid_configuration().is_ok());
+        assert_eq!(limits.max_confidence(), MAX_ORACLE_CONFIDENCE);
+        assert_eq!(limits.max_oracle_staleness(), MAX_ORACLE_STALENESS);
+        assert_eq!(limits.max_price_quote_age(), MAX_PRICE_QUOTE_AGE);
+
+        let limits = OracleLimits {
+            max_confidence: 10_00,
+            max_oracle_staleness: 120,
+            max_price_quote_age: 90,
+        };
+        assert!(limits.check_valid_configuration().is_ok());
+        assert_eq!(limits.max_confidence(), 10_00);
+        assert_eq!(limits.max_oracle_staleness(), 120);
+        assert_eq!(limits.max_price_quote_age(), 90);
+
+        for limits in [
+            OracleLimits {
+                max_confidence: MAX_ORACLE_CONFIDENCE_OVERRIDE + 1,
+                ..Default::default()
+            },
+            OracleLimits {
+                max_oracle_staleness: MAX_ORACLE_STALENESS_OVERRIDE as u16 + 1,
+                ..Default::default()
+            },
+            OracleLimits {
+                max_price_quote_age: MAX_ORACLE_STALENESS_OVERRIDE as u16 + 1,
+                ..Default::default()
+            },
+        ] {
+            assert!(limits.check_valid_configuration().is_err());
+        }
+    }
+
+    #[test]
+    fn test_value_modifier_ramp_interpolation() {
+        // No ramp
+        let ramp = ValueModifierRamp::default();
+        assert_eq!(ramp.value_modifier_at(50, 1_000), 50);
+
+        // Lowering the value modifier
+        let ramp = ValueModifierRamp::new(90, 1_000, 2_000).unwrap();
+        assert_eq!(ramp.value_modifier_at(50, 500), 90);
+        assert_eq!(ramp.value_modifier_at(50, 1_000), 90);
+        assert_eq!(ramp.value_modifier_at(50, 1_500), 70);
+        assert_eq!(ramp.value_modifier_at(50, 2_000), 50);
+        assert_eq!(ramp.value_modifier_at(50, 3_000), 50);
+
+        // Raising the value modifier
+        let ramp = ValueModifierRamp::new(50, 1_000, 2_000).unwrap();
+        assert_eq!(ramp.value_modifier_at(90, 1_250), 60);
+
+        // A ramp has to end in the future
+        assert!(ValueModifierRamp::new(90, 1_000, 1_000).is_err());
+        assert!(ValueModifierRamp::new(90, 1_000, u64::MAX).is_err());
+    }
+
+    #[test]
+    fn test_adapter_config_allows_any_instruction_without_allowlist() {
+        let config = AdapterConfig::default();
+
+        assert!(config.is_instruction_allowed(&[1, 2, 3, 4, 5, 6, 7, 8, 9]));
+        assert!(config.is_instruction_allowed(&[]));
+    }
+
+    #[test]
+    fn test_adapter_config_allowlist() {
+        let mut config = AdapterConfig::default();
+        config.set_allowed_instructions(&[[1; 8], [2; 8]]).unwrap();
+
+        assert_eq!(config.allowed_instructions(), &[[1; 8], [2; 8]]);
+        assert!(config.is_instruction_allowed(&[1; 8]));
+        assert!(config.is_instruction_allowed(&[2, 2, 2, 2, 2, 2, 2, 2, 9, 9]));
+        assert!(!config.is_instruction_allowed(&[3; 8]));
+        assert!(!config.is_instruction_allowed(&[1; 7]));
+
+        // Clearing the allowlist allows all instructions again
+        config.set_allowed_instructions(&[]).unwrap();
+        assert_eq!(config.allowed_instructions_len, 0);
+        assert_eq!(
+            config.allowed_instructions,
+            [[0; 8]; MAX_ADAPTER_ALLOWED_INSTRUCTIONS]
+        );
+        assert!(config.is_instruction_allowed(&[3; 8]));
+    }
+
+    #[test]
+    fn test_adapter_config_allowlist_too_long() {
+        let mut config = AdapterConfig::default();
+        let instructions = vec![[1; 8]; MAX_ADAPTER_ALLOWED_INSTRUCTIONS + 1];
+
+        assert!(config.set_allowed_instructions(&instructions).is_err());
+        assert!(config
+            .set_allowed_instructions(&instructions[..MAX_ADAPTER_ALLOWED_INSTRUCTIONS])
+            .is_ok());
+    }
+
+    #[test]
+    fn test_adapter_outflow_limits_validation() {
+        assert!(AdapterOutflowLimits::default()
+            .check_valid_configuration()
+            .is_ok());
+        assert!(AdapterOutflowLimits {
+            max_window_outflow: 1_000,
+            ..Default::default()
+        }
+        .check_valid_configuration()
+        .is_err());
+        assert!(AdapterOutflowLimits {
+            max_window_outflow: 1_000,
+            window_duration: 3_600,
+            ..Default::default()
+        }
+        .check_valid_configuration()
+        .is_ok());
+    }
+
+    #[test]
+    fn test_adapter_invoke_outflow_limit() {
+        let mut config = AdapterConfig::default();
+        assert!(config
+            .verify_invoke_outflow(Number128::from_decimal(1_000_000, 0))
+            .is_ok());
+
+        config.outflow_limits.max_invoke_outflow = 100;
+        assert!(config
+            .verify_invoke_outflow(Number128::from_decimal(100, 0))
+            .is_ok());
+        assert!(config
+            .verify_invoke_outflow(Number128::from_decimal(101, 0))
+            .is_err());
+    }
+
+    #[test]
+    fn test_adapter_window_outflow_limit() {
+        let mut config = AdapterConfig {
+            outflow_limits: AdapterOutflowLimits {
+                max_invoke_outflow: 0,
+                max_window_outflow: 100,
+                window_duration: 60,
+            },
+            ..Default::default()
+        };
+
+        config
+            .record_window_outflow(Number128::from_decimal(60, 0), 1_000)
+            .unwrap();
+        assert_eq!(config.window_start, 1_000);
+        assert!(config
+            .record_window_outflow(Number128::from_decimal(50, 0), 1_059)
+            .is_err());
+        assert_eq!(config.window_outflow(), Number128::from_decimal(60, 0));
+
+        // A new window starts once the duration has elapsed
+        config
+            .record_window_outflow(Number128::from_decimal(50, 0), 1_060)
+            .unwrap();
+        assert_eq!(config.window_start, 1_060);
+        assert_eq!(config.window_outflow(), Number128::from_decimal(50, 0));
+    }
+
+    #[test]
+    fn test_isolation_configuration() {
+        let isolated = IsolationConfig {
+            flags: IsolationFlags::ISOLATED,
+            ..Default::default()
+        };
+        assert!(isolated
+            .check_valid_configuration(TokenKind::Collateral)
+            .is_ok());
+        assert!(isolated
+            .check_valid_configuration(TokenKind::AdapterCollateral)
+            .is_ok());
+        assert!(isolated
+            .check_valid_configuration(TokenKind::Claim)
+            .is_err());
+
+        let borrowable = IsolationConfig {
+            flags: IsolationFlags::BORROWABLE_IN_ISOLATION,
+            ..Default::default()
+        };
+        assert!(borrowable
+            .check_valid_configuration(TokenKind::Claim)
+            .is_ok());
+        assert!(borrowable
+            .check_valid_configuration(TokenKind::Collateral)
+            .is_err());
+
+        let unknown = IsolationConfig {
+            flags: IsolationFlags::from_bits_retain(1 << 7),
+            ..Default::default()
+        };
+        assert!(unknown
+            .check_valid_configuration(TokenKind::Collateral)
+            .is_err());
+    }
+
+    #[test]
+    fn test_isolated_debt_ceiling() {
+        let mut isolation = IsolationConfig {
+            flags: IsolationFlags::ISOLATED,
+            debt_ceiling: 1_000,
+            isolated_debt: 0,
+        };
+
+        isolation.add_debt(600).unwrap();
+        assert!(isolation.add_debt(401).is_err());
+        assert_eq!(isolation.isolated_debt, 600);
+        isolation.add_debt(400).unwrap();
+
+        // Repayments with interest saturate at zero
+        isolation.remove_debt(700);
+        assert_eq!(isolation.isolated_debt, 300);
+        isolation.remove_debt(400);
+        assert_eq!(isolation.isolated_debt, 0);
+    }
 }
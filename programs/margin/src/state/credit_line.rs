@@ -0,0 +1,189 @@
+mod account;
+mod config;
+mod order;
+
+pub use account::*;
+pub use config::*;
+pub use order::*;
+
+use anchor_lang::prelude::*;
+
+use glow_program_common::Number128;
+
+use crate::{
+    AccountFeatureFlags, AccountPosition, ErrorCode, MarginAccount, PositionConfigUpdate,
+    PriceInfo, TokenFeatures, TokenKind,
+};
+
+/// The decimals of the balance of credit line positions, which are valued in the
+/// numeraire of the margin accounts
+pub const CREDIT_LINE_DECIMALS: u8 = 6;
+
+/// The value modifier of the lender's claim on a credit line.
+///
+/// The claim is counted in full in the lender's liabilities, which reserves the value
+/// of the line from its collateral. Unlike a loan, it is not backed by borrowed tokens
+/// held in the account, so it requires almost no collateral beyond that.
+pub const CREDIT_LINE_CLAIM_VALUE_MODIFIER: u16 = u16::MAX;
+
+/// A line of credit from a lender margin account to a borrower margin account.
+///
+/// The lender backs the claims of the borrower up to the limit of the line, which is
+/// tracked with a position in each account using the credit line address as its token:
+/// * The borrower holds it as collateral, so that the limit counts in its valuation.
+/// * The lender holds it as a claim, so that the limit is reserved from its collateral.
+///
+/// Each unit of the line is worth one unit of the numeraire, so the positions are
+/// valued at a fixed price of 1 and are never stale.
+///
+/// When the borrower is liquidated, its liquidator can seize collateral from the lender
+/// within the limit of the line, which reduces the line by the value seized.
+#[account]
+#[derive(Default, Debug, PartialEq, Eq)]
+pub struct CreditLine {
+    /// The margin account lending its collateral
+    pub lender: Pubkey,
+
+    /// The margin account borrowing against the collateral of the lender
+    pub borrower: Pubkey,
+
+    /// The value of the lender's collateral backing the borrower, in the numeraire of
+    /// the accounts with [CREDIT_LINE_DECIMALS]
+    pub limit: u64,
+
+    /// The total value seized from the lender by liquidators of the borrower, in the
+    /// numeraire of the accounts with [CREDIT_LINE_DECIMALS]
+    pub seized: u64,
+
+    /// The address that paid the rent of the line, which receives it when the line is closed
+    pub payer: Pubkey,
+}
+
+impl CreditLine {
+    /// The value of the limit of the line
+    pub fn limit_value(&self) -> Number128 {
+        Number128::from_decimal(self.limit, -(CREDIT_LINE_DECIMALS as i32))
+    }
+
+    /// The amount by which the limit is reduced when seizing the given value from the
+    /// lender, rounded up in favor of the lender
+    pub fn seized_amount(value: Number128) -> u64 {
+        let amount = value.as_u64(-(CREDIT_LINE_DECIMALS as i32));
+        if Number128::from_decimal(amount, -(CREDIT_LINE_DECIMALS as i32)) < value {
+            amount + 1
+        } else {
+            amount
+        }
+    }
+
+    /// Whether a position of a margin account tracks a credit line, which uses the
+    /// address of the line as its token and is managed by the margin program
+    pub fn is_position(position: &AccountPosition) -> bool {
+        position.adapter == crate::ID && position.token == position.address
+    }
+
+    /// Check that two margin accounts can be linked by a credit line
+    pub fn verify_accounts(lender: &MarginAccount, borrower: &MarginAccount) -> Result<()> {
+        // Positions are valued in the same numeraire, and must be accepted by both accounts
+        if lender.airspace != borrower.airspace || lender.features != borrower.features {
+            msg!("the lender and borrower must have the same airspace and features");
+            return err!(ErrorCode::InvalidCreditLine);
+        }
+
+        Ok(())
+    }
+
+    /// Check that the line is not backed by the credit of another line, which would count
+    /// in the margin of the lender. Accounts borrowing through a line cannot lend through
+    /// another, and accounts lending through a line cannot borrow through another.
+    pub fn verify_not_relending(lender: &MarginAccount, borrower: &MarginAccount) -> Result<()> {
+        let holds_line = |account: &MarginAccount, kind: TokenKind| {
+            account
+                .positions()
+                .any(|p| Self::is_position(p) && p.kind() == kind)
+        };
+        if holds_line(lender, TokenKind::AdapterCollateral)
+            || holds_line(borrower, TokenKind::Claim)
+        {
+            msg!("an account cannot both lend and borrow through credit lines");
+            return err!(ErrorCode::CreditLineRelending);
+        }
+
+        Ok(())
+    }
+
+    /// The configuration of the position of the line in the lender or the borrower
+    pub fn position_config(
+        address: Pubkey,
+        account: &MarginAccount,
+        kind: TokenKind,
+    ) -> PositionConfigUpdate {
+        let mut restrictions = account.features;
+        restrictions.remove(AccountFeatureFlags::VIOLATION | AccountFeatureFlags::SOL_NUMERAIRE);
+
+        PositionConfigUpdate {
+            mint: address,
+            token_program: anchor_spl::token::ID,
+            decimals: CREDIT_LINE_DECIMALS,
+            address,
+            airspace: account.airspace,
+            adapter: crate::ID,
+            kind,
+            value_modifier: match kind {
+                TokenKind::Claim => CREDIT_LINE_CLAIM_VALUE_MODIFIER,
+                _ => 100,
+            },
+            max_staleness: 0,
+            size_haircut_threshold: 0,
+            token_features: TokenFeatures::from_bits_truncate(restrictions.bits()),
+            initial_value_modifier: 0,
+            oracle_limits: Default::default(),
+            value_modifier_ramp: Default::default(),
+            isolation: Default::default(),
+            max_collateral_share: 0,
+            concentration_group: 0,
+        }
+    }
+
+    /// Set the balance and price of the positions of the line to its limit
+    pub fn update_positions(
+        &self,
+        address: &Pubkey,
+        lender: &mut MarginAccount,
+        borrower: &mut MarginAccount,
+        timestamp: u64,
+    ) -> Result<()> {
+        // Each unit of the line is worth one unit of the numeraire
+        let price = PriceInfo::new_valid(0, 1, timestamp);
+
+        for account in [lender, borrower] {
+            account.set_position_balance(address, address, self.limit, timestamp)?;
+            account.set_position_price(address, &price)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn seized_amount_is_rounded_up() {
+        assert_eq!(
+            CreditLine::seized_amount(Number128::from_decimal(15, -1)),
+            1_500_000
+        );
+        assert_eq!(
+            CreditLine::seized_amount(Number128::from_decimal(1_000_001, -7)),
+            100_001
+        );
+
+        let line = CreditLine {
+            limit: 2_500_000,
+            ..Default::default()
+        };
+        assert_eq!(line.limit_value(), Number128::from_decimal(25, -1));
+    }
+}
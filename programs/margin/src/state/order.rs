@@ -0,0 +1,370 @@
+mod account;
+mod config;
+
+pub use account::*;
+pub use config::*;
+
+use anchor_lang::prelude::*;
+
+use glow_program_common::Number128;
+
+use crate::{ErrorCode, Valuation};
+
+/// The direction in which the price of the trigger token has to move for an
+/// order to be executed
+#[derive(AnchorSerialize, AnchorDeserialize, Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TriggerCondition {
+    /// The order is executed once the price falls to the trigger price, e.g. a stop-loss
+    #[default]
+    AtOrBelow,
+
+    /// The order is executed once the price rises to the trigger price, e.g. a take-profit
+    AtOrAbove,
+}
+
+/// The swap a keeper is allowed to perform when executing an order
+#[derive(AnchorSerialize, AnchorDeserialize, Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SwapRoute {
+    /// The only adapter that may be invoked to execute the order
+    pub adapter_program: Pubkey,
+
+    /// The token sold by the order
+    pub input_token: Pubkey,
+
+    /// The token received by the order, which also pays the keeper's fee
+    pub output_token: Pubkey,
+
+    /// The discriminator (the first 8 bytes of instruction data) of the only adapter
+    /// instruction that may be invoked. When zero, any instruction allowed by the
+    /// adapter config may be invoked.
+    pub instruction: [u8; 8],
+}
+
+impl SwapRoute {
+    /// Whether the route allows invoking an adapter instruction with the given data
+    pub fn is_instruction_allowed(&self, data: &[u8]) -> bool {
+        self.instruction == [0; 8] || data.get(..8) == Some(&self.instruction[..])
+    }
+}
+
+/// An order attached to a margin account, which any keeper may execute
+/// through the `execute_trigger_order` instruction once the oracle price of
+/// the trigger token meets its condition.
+///
+/// Orders are executed once, after which the account is closed and its rent
+/// returned to the owner of the margin account.
+#[account]
+#[derive(Default, Debug, PartialEq, Eq)]
+pub struct TriggerOrder {
+    /// The margin account that the order is executed for
+    pub margin_account: Pubkey,
+
+    /// An identifier chosen by the owner, so that an account can have several orders
+    pub order_id: u64,
+
+    /// The token whose oracle price triggers the order
+    pub trigger_token: Pubkey,
+
+    /// The direction in which the price has to move to trigger the order
+    pub condition: TriggerCondition,
+
+    /// The price that triggers the order, in the numeraire of the margin account
+    pub trigger_price: i64,
+
+    /// The exponent of the trigger price
+    pub trigger_price_exponent: i32,
+
+    /// The swap performed when the order is executed
+    pub route: SwapRoute,
+
+    /// The maximum amount of the input token sold by the order
+    pub max_input_amount: u64,
+
+    /// The maximum value (bps) lost by the swap, compared to the value of the
+    /// tokens sold at oracle prices, before the keeper's fee
+    pub max_slippage_bps: u16,
+
+    /// The share (bps) of the received tokens paid to the keeper
+    pub keeper_fee_bps: u16,
+}
+
+impl TriggerOrder {
+    /// Validate the parameters of a new order
+    pub fn validate(&self) -> Result<()> {
+        if self.trigger_price <= 0
+            || self.max_input_amount == 0
+            || self.max_slippage_bps > 10_000
+            || self.keeper_fee_bps > 10_000
+            || self.route.input_token == self.route.output_token
+        {
+            msg!("invalid order: {:?}", self);
+            return err!(ErrorCode::InvalidTriggerOrder);
+        }
+
+        Ok(())
+    }
+
+    /// Whether the order may be executed at the given price of the trigger token
+    pub fn is_triggered(&self, price: Number128) -> bool {
+        let trigger_price =
+            Number128::from_decimal(self.trigger_price, self.trigger_price_exponent);
+
+        match self.condition {
+            TriggerCondition::AtOrBelow => price <= trigger_price,
+            TriggerCondition::AtOrAbove => price >= trigger_price,
+        }
+    }
+
+    /// Check that the swap did not sell more than the size of the order
+    pub fn verify_size(&self, sold: u64) -> Result<()> {
+        if sold > self.max_input_amount {
+            msg!(
+                "sold {} tokens, which exceeds the order size of {}",
+                sold,
+                self.max_input_amount
+            );
+            return err!(ErrorCode::TriggerOrderSizeExceeded);
+        }
+
+        Ok(())
+    }
+
+    /// Check that the value received by the swap is within the slippage of the
+    /// value sold
+    pub fn verify_slippage(&self, sold_value: Number128, received_value: Number128) -> Result<()> {
+        let min_received = sold_value * Number128::from_bps(10_000 - self.max_slippage_bps);
+
+        if received_value < min_received {
+            msg!(
+                "received {} for {}, which exceeds the maximum slippage of {} bps",
+                received_value,
+                sold_value,
+                self.max_slippage_bps
+            );
+            return err!(ErrorCode::TriggerOrderSlippageExceeded);
+        }
+
+        Ok(())
+    }
+
+    /// The share of the received tokens paid to the keeper
+    pub fn keeper_fee(&self, received: u64) -> u64 {
+        (received as u128 * self.keeper_fee_bps as u128 / 10_000) as u64
+    }
+}
+
+/// An order attached to a margin account, which any keeper may execute through the
+/// `execute_deleverage_order` instruction while the effective c-ratio of the account
+/// is below the order's threshold.
+///
+/// The execution swaps collateral through the route of the order to repay a bounded
+/// share of the account's debt. Orders are executed once, after which the account is
+/// closed and its rent returned to the owner of the margin account, so that a keeper
+/// cannot repeatedly sell collateral at the order's slippage.
+#[account]
+#[derive(Default, Debug, PartialEq, Eq)]
+pub struct DeleverageOrder {
+    /// The margin account that the order is executed for
+    pub margin_account: Pubkey,
+
+    /// An identifier chosen by the owner, so that an account can have several orders
+    pub order_id: u64,
+
+    /// The effective c-ratio (bps) below which the order can be executed
+    pub trigger_c_ratio_bps: u32,
+
+    /// The maximum share (bps) of the account's liabilities repaid by the execution
+    pub max_repay_bps: u16,
+
+    /// The swap performed when the order is executed, selling collateral for the
+    /// token being repaid
+    pub route: SwapRoute,
+
+    /// The maximum equity (bps) lost by the swap, compared to the value repaid,
+    /// before the keeper's fee
+    pub max_slippage_bps: u16,
+
+    /// The share (bps) of the sold collateral paid to the keeper
+    pub keeper_fee_bps: u16,
+}
+
+impl DeleverageOrder {
+    /// Validate the parameters of a new order
+    pub fn validate(&self) -> Result<()> {
+        // An account below a c-ratio of 1 can be liquidated anyway
+        if self.trigger_c_ratio_bps <= 10_000
+            || self.max_repay_bps == 0
+            || self.max_repay_bps > 10_000
+            || self.max_slippage_bps > 10_000
+            || self.keeper_fee_bps > 10_000
+            || self.route.input_token == self.route.output_token
+        {
+            msg!("invalid order: {:?}", self);
+            return err!(ErrorCode::InvalidTriggerOrder);
+        }
+
+        Ok(())
+    }
+
+    /// Whether the order may be executed at the given effective c-ratio
+    pub fn is_triggered(&self, effective_c_ratio: Number128) -> bool {
+        effective_c_ratio < Number128::const_from_bps(self.trigger_c_ratio_bps.into())
+    }
+
+    /// Check that an execution of the order repaid debt within its bounds and improved
+    /// the effective c-ratio of the account, from the `start` to the `end` valuation.
+    pub fn verify_repayment(&self, start: &Valuation, end: &Valuation) -> Result<()> {
+        let repaid = start.liabilities - end.liabilities;
+        let max_repaid = start.liabilities * Number128::from_bps(self.max_repay_bps);
+
+        if repaid <= Number128::ZERO || repaid > max_repaid {
+            msg!(
+                "repaid {} of debt, which must be positive and at most {}",
+                repaid,
+                max_repaid
+            );
+            return err!(ErrorCode::DeleverageOrderRepaymentInvalid);
+        }
+
+        if end.effective_c_ratio() <= start.effective_c_ratio() {
+            msg!(
+                "effective c-ratio did not improve from {} to {}",
+                start.effective_c_ratio(),
+                end.effective_c_ratio()
+            );
+            return err!(ErrorCode::DeleverageOrderRepaymentInvalid);
+        }
+
+        let equity_loss = start.equity - end.equity;
+        let max_equity_loss = repaid * Number128::from_bps(self.max_slippage_bps);
+        if equity_loss > max_equity_loss {
+            msg!(
+                "repaying {} lost {} equity, which exceeds the maximum slippage of {} bps",
+                repaid,
+                equity_loss,
+                self.max_slippage_bps
+            );
+            return err!(ErrorCode::TriggerOrderSlippageExceeded);
+        }
+
+        Ok(())
+    }
+
+    /// The share of the sold collateral paid to the keeper
+    pub fn keeper_fee(&self, sold: u64) -> u64 {
+        (sold as u128 * self.keeper_fee_bps as u128 / 10_000) as u64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn order(condition: TriggerCondition) -> TriggerOrder {
+        TriggerOrder {
+            condition,
+            trigger_price: 150,
+            trigger_price_exponent: 0,
+            max_input_amount: 1_000,
+            max_slippage_bps: 100,
+            keeper_fee_bps: 10,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn order_is_triggered_by_the_price_condition() {
+        let stop_loss = order(TriggerCondition::AtOrBelow);
+        assert!(stop_loss.is_triggered(Number128::from_decimal(149, 0)));
+        assert!(stop_loss.is_triggered(Number128::from_decimal(150, 0)));
+        assert!(!stop_loss.is_triggered(Number128::from_decimal(151, 0)));
+
+        let take_profit = order(TriggerCondition::AtOrAbove);
+        assert!(!take_profit.is_triggered(Number128::from_decimal(149, 0)));
+        assert!(take_profit.is_triggered(Number128::from_decimal(150, 0)));
+        assert!(take_profit.is_triggered(Number128::from_decimal(151, 0)));
+    }
+
+    #[test]
+    fn order_slippage_is_bounded() {
+        let order = order(TriggerCondition::AtOrBelow);
+        let sold = Number128::from_decimal(1_000, 0);
+
+        assert!(order
+            .verify_slippage(sold, Number128::from_decimal(990, 0))
+            .is_ok());
+        assert!(order
+            .verify_slippage(sold, Number128::from_decimal(989, 0))
+            .is_err());
+        assert_eq!(order.keeper_fee(1_000_000), 1_000);
+    }
+
+    #[test]
+    fn order_size_is_bounded() {
+        let order = TriggerOrder {
+            route: SwapRoute {
+                input_token: Pubkey::new_unique(),
+                output_token: Pubkey::new_unique(),
+                ..Default::default()
+            },
+            ..order(TriggerCondition::AtOrBelow)
+        };
+        order.validate().unwrap();
+
+        assert!(order.verify_size(1_000).is_ok());
+        assert!(order.verify_size(1_001).is_err());
+        assert!(TriggerOrder {
+            max_input_amount: 0,
+            ..order
+        }
+        .validate()
+        .is_err());
+    }
+
+    #[test]
+    fn route_restricts_the_adapter_instruction() {
+        let mut route = SwapRoute::default();
+        assert!(route.is_instruction_allowed(&[]));
+        assert!(route.is_instruction_allowed(&[1; 16]));
+
+        route.instruction = [1; 8];
+        assert!(route.is_instruction_allowed(&[1; 16]));
+        assert!(route.is_instruction_allowed(&[1; 8]));
+        assert!(!route.is_instruction_allowed(&[1; 7]));
+        assert!(!route.is_instruction_allowed(&[2; 16]));
+    }
+
+    #[test]
+    fn deleverage_order_is_triggered_below_the_c_ratio() {
+        let order = DeleverageOrder {
+            trigger_c_ratio_bps: 12_000,
+            max_repay_bps: 2_500,
+            route: SwapRoute {
+                input_token: Pubkey::new_unique(),
+                output_token: Pubkey::new_unique(),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        order.validate().unwrap();
+
+        assert!(order.is_triggered(Number128::from_bps(11_999)));
+        assert!(!order.is_triggered(Number128::from_bps(12_000)));
+        assert!(!order.is_triggered(Number128::MAX));
+
+        // Thresholds above a c-ratio of 6.5535 can be expressed
+        let high_threshold = DeleverageOrder {
+            trigger_c_ratio_bps: 70_000,
+            ..order
+        };
+        high_threshold.validate().unwrap();
+        assert!(high_threshold.is_triggered(Number128::const_from_bps(69_999)));
+        assert!(!high_threshold.is_triggered(Number128::const_from_bps(70_000)));
+
+        let liquidation_level = DeleverageOrder {
+            trigger_c_ratio_bps: 10_000,
+            ..order
+        };
+        assert!(liquidation_level.validate().is_err());
+    }
+}
@@ -0,0 +1,32 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+//
+// Copyright (C) 2024 A1 XYZ, INC.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+mod close_margin_position;
+mod create_lp_config;
+mod deposit_lp_position;
+mod margin_refresh_lp_position;
+mod pricing;
+mod register_margin_position;
+mod withdraw_lp_position;
+
+pub use close_margin_position::*;
+pub use create_lp_config::*;
+pub use deposit_lp_position::*;
+pub use margin_refresh_lp_position::*;
+pub use pricing::*;
+pub use register_margin_position::*;
+pub use withdraw_lp_position::*;
@@ -0,0 +1,95 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+//
+// Copyright (C) 2024 A1 XYZ, INC.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    token_2022::Token2022,
+    token_interface::{self, CloseAccount, TokenAccount},
+};
+
+use glow_margin::{AdapterResult, MarginAccount, PositionChange};
+
+use crate::{
+    seeds,
+    state::{LpConfig, MarginLpPositions},
+    ErrorCode,
+};
+
+#[derive(Accounts)]
+pub struct CloseMarginPosition<'info> {
+    #[account(signer)]
+    pub margin_account: AccountLoader<'info, MarginAccount>,
+
+    /// The config of the whirlpool
+    #[account(has_one = receipt_mint)]
+    pub lp_config: Box<Account<'info, LpConfig>>,
+
+    /// The mint of the receipt token
+    /// CHECK:
+    pub receipt_mint: AccountInfo<'info>,
+
+    /// The token account holding the receipt token
+    #[account(mut,
+        seeds = [margin_account.key().as_ref(),
+                 receipt_mint.key().as_ref()],
+        bump,
+    )]
+    pub receipt_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// The list of positions deposited by the margin account, which must be empty
+    #[account(mut,
+        seeds = [seeds::LP_POSITIONS,
+                 margin_account.key().as_ref(),
+                 lp_config.key().as_ref()],
+        bump,
+        close = beneficiary,
+        constraint = lp_positions.positions.is_empty() @ ErrorCode::LpPositionsNotEmpty,
+    )]
+    pub lp_positions: Box<Account<'info, MarginLpPositions>>,
+
+    #[account(mut)]
+    pub beneficiary: Signer<'info>,
+
+    pub token_program: Program<'info, Token2022>,
+}
+
+pub fn close_margin_position_handler(ctx: Context<CloseMarginPosition>) -> Result<()> {
+    let lp_config = &ctx.accounts.lp_config;
+    token_interface::close_account(
+        CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            CloseAccount {
+                account: ctx.accounts.receipt_account.to_account_info(),
+                authority: lp_config.to_account_info(),
+                destination: ctx.accounts.beneficiary.to_account_info(),
+            },
+        )
+        .with_signer(&[&lp_config.signer_seeds()]),
+    )?;
+
+    glow_margin::write_adapter_result(
+        &*ctx.accounts.margin_account.load()?,
+        &AdapterResult {
+            position_changes: vec![(
+                ctx.accounts.receipt_mint.key(),
+                vec![PositionChange::Close(ctx.accounts.receipt_account.key())],
+            )],
+        },
+    )?;
+
+    Ok(())
+}
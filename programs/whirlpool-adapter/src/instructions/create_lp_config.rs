@@ -0,0 +1,96 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+//
+// Copyright (C) 2024 A1 XYZ, INC.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use anchor_lang::prelude::*;
+use anchor_spl::{token_2022::Token2022, token_interface::Mint};
+use whirlpool::state::Whirlpool;
+
+use glow_airspace::state::Airspace;
+
+use crate::{seeds, state::LpConfig};
+
+#[derive(Accounts)]
+pub struct CreateLpConfig<'info> {
+    /// The authority to create configs, which must be the airspace authority
+    pub authority: Signer<'info>,
+
+    /// The airspace that the positions are used in
+    #[account(
+      constraint = airspace.authority == authority.key(),
+    )]
+    pub airspace: Box<Account<'info, Airspace>>,
+
+    /// The payer of rent for new accounts
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    /// The whirlpool whose positions can be deposited
+    pub whirlpool: Box<Account<'info, Whirlpool>>,
+
+    /// The config to be created
+    #[account(init,
+              seeds = [
+                seeds::LP_CONFIG,
+                airspace.key().as_ref(),
+                whirlpool.key().as_ref()
+              ],
+              bump,
+              space = LpConfig::SPACE,
+              payer = payer
+    )]
+    pub lp_config: Box<Account<'info, LpConfig>>,
+
+    /// The mint of the receipt token
+    #[account(init,
+              seeds = [
+                seeds::LP_RECEIPT_MINT,
+                lp_config.key().as_ref()
+              ],
+              bump,
+              mint::decimals = 0,
+              mint::authority = lp_config,
+              mint::token_program = token_program,
+              payer = payer
+    )]
+    pub receipt_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    /// The mint of the whirlpool's token A
+    #[account(address = whirlpool.token_mint_a)]
+    pub mint_a: Box<InterfaceAccount<'info, Mint>>,
+
+    /// The mint of the whirlpool's token B
+    #[account(address = whirlpool.token_mint_b)]
+    pub mint_b: Box<InterfaceAccount<'info, Mint>>,
+
+    pub token_program: Program<'info, Token2022>,
+    pub system_program: Program<'info, System>,
+}
+
+pub fn create_lp_config_handler(ctx: Context<CreateLpConfig>) -> Result<()> {
+    let lp_config = &mut ctx.accounts.lp_config;
+
+    lp_config.bump = [ctx.bumps.lp_config];
+    lp_config.airspace = ctx.accounts.airspace.key();
+    lp_config.whirlpool = ctx.accounts.whirlpool.key();
+    lp_config.receipt_mint = ctx.accounts.receipt_mint.key();
+    lp_config.mint_a = ctx.accounts.mint_a.key();
+    lp_config.mint_b = ctx.accounts.mint_b.key();
+    lp_config.decimals_a = ctx.accounts.mint_a.decimals;
+    lp_config.decimals_b = ctx.accounts.mint_b.decimals;
+
+    Ok(())
+}
@@ -0,0 +1,155 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+//
+// Copyright (C) 2024 A1 XYZ, INC.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    token_2022::Token2022,
+    token_interface::{self, Mint, MintTo, TokenAccount, TokenInterface, TransferChecked},
+};
+
+use glow_margin::MarginAccount;
+
+use crate::{
+    seeds,
+    state::{LpConfig, MarginLpPositions},
+    ErrorCode,
+};
+
+use super::{load_position, load_positions, LpPricing};
+
+#[derive(Accounts)]
+pub struct DepositLpPosition<'info> {
+    #[account(signer)]
+    pub margin_account: AccountLoader<'info, MarginAccount>,
+
+    /// The config of the whirlpool
+    #[account(has_one = receipt_mint)]
+    pub lp_config: Box<Account<'info, LpConfig>>,
+
+    /// The list of positions deposited by the margin account
+    #[account(mut,
+        seeds = [seeds::LP_POSITIONS,
+                 margin_account.key().as_ref(),
+                 lp_config.key().as_ref()],
+        bump,
+    )]
+    pub lp_positions: Box<Account<'info, MarginLpPositions>>,
+
+    /// The whirlpool position being deposited
+    /// CHECK: We verify this account when loading the position
+    pub position: AccountInfo<'info>,
+
+    /// The mint of the position NFT
+    pub position_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    /// The margin account's token account holding the position NFT
+    #[account(mut,
+        token::mint = position_mint,
+        token::authority = margin_account,
+    )]
+    pub source: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// The token account to hold the position NFT while it's deposited
+    #[account(init,
+        seeds = [seeds::LP_CUSTODY,
+                 position_mint.key().as_ref()],
+        bump,
+        payer = payer,
+        token::mint = position_mint,
+        token::authority = lp_config,
+        token::token_program = position_token_program
+    )]
+    pub custody: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// The mint of the receipt token
+    /// CHECK:
+    #[account(mut)]
+    pub receipt_mint: AccountInfo<'info>,
+
+    /// The token account holding the receipt token
+    #[account(mut,
+        seeds = [margin_account.key().as_ref(),
+                 receipt_mint.key().as_ref()],
+        bump,
+    )]
+    pub receipt_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    pub pricing: LpPricing<'info>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub position_token_program: Interface<'info, TokenInterface>,
+    pub token_program: Program<'info, Token2022>,
+    pub system_program: Program<'info, System>,
+}
+
+pub fn deposit_lp_position_handler(ctx: Context<DepositLpPosition>) -> Result<()> {
+    let lp_config = &ctx.accounts.lp_config;
+
+    let position = load_position(lp_config, &ctx.accounts.position)?;
+    require!(
+        position.position_mint == ctx.accounts.position_mint.key(),
+        ErrorCode::InvalidLpPosition
+    );
+    let mut positions = load_positions(
+        lp_config,
+        &ctx.accounts.lp_positions,
+        ctx.remaining_accounts,
+    )?;
+    positions.push(position);
+    ctx.accounts.lp_positions.add(ctx.accounts.position.key())?;
+
+    token_interface::transfer_checked(
+        CpiContext::new(
+            ctx.accounts.position_token_program.to_account_info(),
+            TransferChecked {
+                from: ctx.accounts.source.to_account_info(),
+                mint: ctx.accounts.position_mint.to_account_info(),
+                to: ctx.accounts.custody.to_account_info(),
+                authority: ctx.accounts.margin_account.to_account_info(),
+            },
+        ),
+        1,
+        0,
+    )?;
+
+    // The receipt balance is 1 for as long as the margin account has positions deposited
+    if ctx.accounts.receipt_account.amount == 0 {
+        token_interface::mint_to(
+            CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                MintTo {
+                    mint: ctx.accounts.receipt_mint.to_account_info(),
+                    to: ctx.accounts.receipt_account.to_account_info(),
+                    authority: lp_config.to_account_info(),
+                },
+            )
+            .with_signer(&[&lp_config.signer_seeds()]),
+            1,
+        )?;
+    }
+
+    let margin_account = ctx.accounts.margin_account.load()?;
+    let result =
+        ctx.accounts
+            .pricing
+            .adapter_result(lp_config, &positions, margin_account.numeraire())?;
+    glow_margin::write_adapter_result(&*margin_account, &result)?;
+
+    Ok(())
+}
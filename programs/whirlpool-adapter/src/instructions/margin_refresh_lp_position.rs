@@ -0,0 +1,66 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+//
+// Copyright (C) 2024 A1 XYZ, INC.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use anchor_lang::prelude::*;
+
+use glow_margin::MarginAccount;
+
+use crate::{
+    seeds,
+    state::{LpConfig, MarginLpPositions},
+};
+
+use super::{load_positions, LpPricing};
+
+#[derive(Accounts)]
+pub struct MarginRefreshLpPosition<'info> {
+    /// The margin account being executed on
+    pub margin_account: AccountLoader<'info, MarginAccount>,
+
+    /// The config of the whirlpool
+    pub lp_config: Box<Account<'info, LpConfig>>,
+
+    /// The list of positions deposited by the margin account
+    #[account(
+        seeds = [seeds::LP_POSITIONS,
+                 margin_account.key().as_ref(),
+                 lp_config.key().as_ref()],
+        bump,
+    )]
+    pub lp_positions: Box<Account<'info, MarginLpPositions>>,
+
+    pub pricing: LpPricing<'info>,
+}
+
+pub fn margin_refresh_lp_position_handler(ctx: Context<MarginRefreshLpPosition>) -> Result<()> {
+    let lp_config = &ctx.accounts.lp_config;
+    let positions = load_positions(
+        lp_config,
+        &ctx.accounts.lp_positions,
+        ctx.remaining_accounts,
+    )?;
+
+    // Tell the margin program what the positions are currently worth
+    let margin_account = ctx.accounts.margin_account.load()?;
+    let result =
+        ctx.accounts
+            .pricing
+            .adapter_result(lp_config, &positions, margin_account.numeraire())?;
+    glow_margin::write_adapter_result(&*margin_account, &result)?;
+
+    Ok(())
+}
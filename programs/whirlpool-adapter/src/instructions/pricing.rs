@@ -0,0 +1,187 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+//
+// Copyright (C) 2024 A1 XYZ, INC.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use anchor_lang::prelude::*;
+use whirlpool::state::Position;
+
+use glow_margin::{AdapterResult, Numeraire, PositionChange, PriceChangeInfo, TokenConfig};
+
+use crate::{
+    state::{LpConfig, MarginLpPositions},
+    valuation::{positions_price, TokenPrice},
+    ErrorCode,
+};
+
+/// The accounts needed to price the positions of a whirlpool, which are the
+/// margin token configs of the whirlpool's tokens and their oracles.
+#[derive(Accounts)]
+pub struct LpPricing<'info> {
+    /// The margin token config of token A, which provides its oracle
+    pub token_config_a: Box<Account<'info, TokenConfig>>,
+
+    /// The oracle of token A, as configured in its token config.
+    /// CHECK: We verify this account when reading the price
+    pub price_oracle_a: AccountInfo<'info>,
+
+    /// The quote oracle of token A, if it's priced with a redemption rate.
+    /// CHECK: We verify this account when reading the price
+    pub redemption_quote_oracle_a: Option<AccountInfo<'info>>,
+
+    /// The margin token config of token B, which provides its oracle
+    pub token_config_b: Box<Account<'info, TokenConfig>>,
+
+    /// The oracle of token B, as configured in its token config.
+    /// CHECK: We verify this account when reading the price
+    pub price_oracle_b: AccountInfo<'info>,
+
+    /// The quote oracle of token B, if it's priced with a redemption rate.
+    /// CHECK: We verify this account when reading the price
+    pub redemption_quote_oracle_b: Option<AccountInfo<'info>>,
+}
+
+impl<'info> LpPricing<'info> {
+    /// Read the oracle prices of the whirlpool's tokens, in the numeraire of the margin account
+    pub fn token_prices(
+        &self,
+        lp_config: &LpConfig,
+        numeraire: Numeraire,
+    ) -> Result<(TokenPrice, TokenPrice)> {
+        let clock = Clock::get()?;
+        let token_a = token_price(
+            numeraire,
+            &self.token_config_a,
+            &self.price_oracle_a,
+            &self.redemption_quote_oracle_a,
+            lp_config,
+            &lp_config.mint_a,
+            lp_config.decimals_a,
+            &clock,
+        )?;
+        let token_b = token_price(
+            numeraire,
+            &self.token_config_b,
+            &self.price_oracle_b,
+            &self.redemption_quote_oracle_b,
+            lp_config,
+            &lp_config.mint_b,
+            lp_config.decimals_b,
+            &clock,
+        )?;
+
+        Ok((token_a, token_b))
+    }
+
+    /// The adapter result reporting the price of the receipt token, which is the value
+    /// of all the deposited positions.
+    ///
+    /// No price is reported without positions, as the receipt balance is then zero.
+    pub fn adapter_result(
+        &self,
+        lp_config: &LpConfig,
+        positions: &[Position],
+        numeraire: Numeraire,
+    ) -> Result<AdapterResult> {
+        if positions.is_empty() {
+            return Ok(AdapterResult {
+                position_changes: vec![],
+            });
+        }
+
+        let (token_a, token_b) = self.token_prices(lp_config, numeraire)?;
+        let price = positions_price(positions, &token_a, &token_b)?;
+
+        Ok(AdapterResult {
+            position_changes: vec![(lp_config.receipt_mint, vec![PositionChange::Price(price)])],
+        })
+    }
+}
+
+fn token_price(
+    numeraire: Numeraire,
+    token_config: &TokenConfig,
+    price_oracle: &AccountInfo,
+    redemption_quote_oracle: &Option<AccountInfo>,
+    lp_config: &LpConfig,
+    mint: &Pubkey,
+    decimals: u8,
+    clock: &Clock,
+) -> Result<TokenPrice> {
+    if token_config.airspace != lp_config.airspace || token_config.mint != *mint {
+        msg!("token config {} is not for {}", token_config.mint, mint);
+        return err!(ErrorCode::InvalidPricingTokenConfig);
+    }
+    let oracle = token_config
+        .oracle()
+        .ok_or(ErrorCode::InvalidPricingTokenConfig)?;
+
+    let info = PriceChangeInfo::try_from_oracle_accounts_in(
+        numeraire,
+        price_oracle,
+        redemption_quote_oracle,
+        &oracle,
+        mint,
+        clock,
+        &token_config.oracle_limits,
+    )?;
+
+    // Apply the same confidence and staleness checks as the margin program does
+    let price = info
+        .to_price_info(clock.unix_timestamp, &token_config.oracle_limits)
+        .to_number128()?;
+
+    Ok(TokenPrice {
+        price,
+        confidence: info.confidence(),
+        publish_time: info.publish_time(),
+        decimals,
+    })
+}
+
+/// Load the deposited positions from the given accounts, which must be the
+/// positions listed in `lp_positions`, in the same order.
+pub fn load_positions(
+    lp_config: &LpConfig,
+    lp_positions: &MarginLpPositions,
+    accounts: &[AccountInfo],
+) -> Result<Vec<Position>> {
+    require!(
+        accounts.len() == lp_positions.positions.len(),
+        ErrorCode::LpPositionsMismatch
+    );
+
+    lp_positions
+        .positions
+        .iter()
+        .zip(accounts)
+        .map(|(address, info)| {
+            require!(info.key == address, ErrorCode::LpPositionsMismatch);
+            load_position(lp_config, info)
+        })
+        .collect()
+}
+
+/// Load a whirlpool position, which has to belong to the configured whirlpool
+pub fn load_position(lp_config: &LpConfig, info: &AccountInfo) -> Result<Position> {
+    require!(info.owner == &whirlpool::ID, ErrorCode::InvalidLpPosition);
+    let position = Position::try_deserialize(&mut &info.try_borrow_data()?[..])?;
+    require!(
+        position.whirlpool == lp_config.whirlpool,
+        ErrorCode::InvalidLpPosition
+    );
+
+    Ok(position)
+}
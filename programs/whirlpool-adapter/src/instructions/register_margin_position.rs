@@ -0,0 +1,97 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+//
+// Copyright (C) 2024 A1 XYZ, INC.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    token_2022::Token2022,
+    token_interface::{Mint, TokenAccount},
+};
+
+use glow_margin::{AdapterResult, MarginAccount, PositionChange};
+
+use crate::{
+    seeds,
+    state::{LpConfig, MarginLpPositions},
+};
+
+#[derive(Accounts)]
+pub struct RegisterMarginPosition<'info> {
+    #[account(signer)]
+    pub margin_account: AccountLoader<'info, MarginAccount>,
+
+    /// The config of the whirlpool
+    #[account(has_one = receipt_mint)]
+    pub lp_config: Box<Account<'info, LpConfig>>,
+
+    /// This will be required for margin to register the position,
+    /// so requiring it here makes it easier for clients to ensure
+    /// that it will be sent.
+    ///
+    /// CHECK:
+    pub receipt_token_config: AccountInfo<'info>,
+
+    /// The mint of the receipt token
+    pub receipt_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    /// The token account holding the receipt token, which represents the
+    /// positions deposited by the margin account
+    #[account(init,
+        seeds = [margin_account.key().as_ref(),
+                 receipt_mint.key().as_ref()],
+        bump,
+        payer = payer,
+        token::mint = receipt_mint,
+        token::authority = lp_config,
+        token::token_program = token_program
+    )]
+    pub receipt_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// The list of positions deposited by the margin account
+    #[account(init,
+        seeds = [seeds::LP_POSITIONS,
+                 margin_account.key().as_ref(),
+                 lp_config.key().as_ref()],
+        bump,
+        space = MarginLpPositions::SPACE,
+        payer = payer,
+    )]
+    pub lp_positions: Box<Account<'info, MarginLpPositions>>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub token_program: Program<'info, Token2022>,
+    pub system_program: Program<'info, System>,
+}
+
+pub fn register_margin_position_handler(ctx: Context<RegisterMarginPosition>) -> Result<()> {
+    let lp_positions = &mut ctx.accounts.lp_positions;
+    lp_positions.margin_account = ctx.accounts.margin_account.key();
+    lp_positions.lp_config = ctx.accounts.lp_config.key();
+
+    glow_margin::write_adapter_result(
+        &*ctx.accounts.margin_account.load()?,
+        &AdapterResult {
+            position_changes: vec![(
+                ctx.accounts.receipt_mint.key(),
+                vec![PositionChange::Register(ctx.accounts.receipt_account.key())],
+            )],
+        },
+    )?;
+
+    Ok(())
+}
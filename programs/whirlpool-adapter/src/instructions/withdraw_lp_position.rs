@@ -0,0 +1,224 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+//
+// Copyright (C) 2024 A1 XYZ, INC.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::get_associated_token_address_with_program_id,
+    token_2022::Token2022,
+    token_interface::{
+        self, Burn, CloseAccount, Mint, TokenAccount, TokenInterface, TransferChecked,
+    },
+};
+
+use glow_margin::{AccountConstraintTicket, AccountConstraints, MarginAccount};
+
+use crate::{
+    seeds,
+    state::{LpConfig, MarginLpPositions},
+    ErrorCode,
+};
+
+use super::{load_position, load_positions, LpPricing};
+
+#[derive(Accounts)]
+pub struct WithdrawLpPosition<'info> {
+    #[account(signer)]
+    pub margin_account: AccountLoader<'info, MarginAccount>,
+
+    /// The config of the whirlpool
+    #[account(has_one = receipt_mint)]
+    pub lp_config: Box<Account<'info, LpConfig>>,
+
+    /// The list of positions deposited by the margin account
+    #[account(mut,
+        seeds = [seeds::LP_POSITIONS,
+                 margin_account.key().as_ref(),
+                 lp_config.key().as_ref()],
+        bump,
+    )]
+    pub lp_positions: Box<Account<'info, MarginLpPositions>>,
+
+    /// The whirlpool position being withdrawn
+    /// CHECK: We verify this account when loading the position
+    pub position: AccountInfo<'info>,
+
+    /// The mint of the position NFT
+    pub position_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    /// The token account holding the position NFT while it's deposited
+    #[account(mut,
+        seeds = [seeds::LP_CUSTODY,
+                 position_mint.key().as_ref()],
+        bump,
+    )]
+    pub custody: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// The token account to receive the position NFT, which is checked like the
+    /// destination of a withdrawal from a margin pool.
+    ///
+    /// If the margin account denies withdrawals or transfers, the destination must be
+    /// the margin account's associated token account for the position mint.
+    ///
+    /// If the margin account has restricted destinations, a destination not owned by
+    /// the margin account must be allowlisted on its constraint ticket.
+    ///
+    /// Otherwise the destination must be owned by the margin account or its owner.
+    #[account(mut, token::mint = position_mint)]
+    pub destination: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// The constraint ticket of the margin account, if its destinations are restricted
+    /// CHECK: We verify this account when loading the ticket
+    pub account_constraint_ticket: Option<AccountInfo<'info>>,
+
+    /// The mint of the receipt token
+    /// CHECK:
+    #[account(mut)]
+    pub receipt_mint: AccountInfo<'info>,
+
+    /// The token account holding the receipt token
+    #[account(mut,
+        seeds = [margin_account.key().as_ref(),
+                 receipt_mint.key().as_ref()],
+        bump,
+    )]
+    pub receipt_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    pub pricing: LpPricing<'info>,
+
+    /// The address to return the rent of the custody account to
+    /// CHECK:
+    #[account(mut)]
+    pub beneficiary: AccountInfo<'info>,
+
+    pub position_token_program: Interface<'info, TokenInterface>,
+    pub token_program: Program<'info, Token2022>,
+}
+
+impl<'info> WithdrawLpPosition<'info> {
+    /// Check that the position NFT may be sent to the destination
+    fn verify_destination(&self) -> Result<()> {
+        let margin_account_key = self.margin_account.key();
+        let margin_account = self.margin_account.load()?;
+        let destination = self.destination.key();
+        let destination_owner = self.destination.owner;
+
+        if margin_account
+            .constraints
+            .intersects(AccountConstraints::DENY_WITHDRAWALS | AccountConstraints::DENY_TRANSFERS)
+        {
+            // Constrained accounts may only withdraw to their own ATA
+            let ata = get_associated_token_address_with_program_id(
+                &margin_account_key,
+                &self.position_mint.key(),
+                &self.position_token_program.key(),
+            );
+            require_keys_eq!(destination, ata, ErrorCode::InvalidDestination);
+        } else if margin_account
+            .constraints
+            .contains(AccountConstraints::RESTRICT_DESTINATIONS)
+            && destination_owner != margin_account_key
+        {
+            // The position leaving the margin account must go to an allowlisted destination
+            let ticket = AccountConstraintTicket::load_checked(
+                self.account_constraint_ticket.as_ref(),
+                &margin_account_key,
+            )?;
+            require!(
+                ticket.allows_destination(&destination),
+                ErrorCode::InvalidDestination
+            );
+        } else {
+            require!(
+                destination_owner == margin_account.owner
+                    || destination_owner == margin_account_key,
+                ErrorCode::InvalidDestination
+            );
+        }
+
+        Ok(())
+    }
+}
+
+pub fn withdraw_lp_position_handler(ctx: Context<WithdrawLpPosition>) -> Result<()> {
+    let lp_config = &ctx.accounts.lp_config;
+    ctx.accounts.verify_destination()?;
+
+    let position = load_position(lp_config, &ctx.accounts.position)?;
+    require!(
+        position.position_mint == ctx.accounts.position_mint.key(),
+        ErrorCode::InvalidLpPosition
+    );
+    ctx.accounts
+        .lp_positions
+        .remove(&ctx.accounts.position.key())?;
+    let positions = load_positions(
+        lp_config,
+        &ctx.accounts.lp_positions,
+        ctx.remaining_accounts,
+    )?;
+
+    let position_token_program = ctx.accounts.position_token_program.to_account_info();
+    token_interface::transfer_checked(
+        CpiContext::new(
+            position_token_program.clone(),
+            TransferChecked {
+                from: ctx.accounts.custody.to_account_info(),
+                mint: ctx.accounts.position_mint.to_account_info(),
+                to: ctx.accounts.destination.to_account_info(),
+                authority: lp_config.to_account_info(),
+            },
+        )
+        .with_signer(&[&lp_config.signer_seeds()]),
+        1,
+        0,
+    )?;
+    token_interface::close_account(
+        CpiContext::new(
+            position_token_program,
+            CloseAccount {
+                account: ctx.accounts.custody.to_account_info(),
+                authority: lp_config.to_account_info(),
+                destination: ctx.accounts.beneficiary.to_account_info(),
+            },
+        )
+        .with_signer(&[&lp_config.signer_seeds()]),
+    )?;
+
+    if positions.is_empty() {
+        token_interface::burn(
+            CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                Burn {
+                    mint: ctx.accounts.receipt_mint.to_account_info(),
+                    from: ctx.accounts.receipt_account.to_account_info(),
+                    authority: lp_config.to_account_info(),
+                },
+            )
+            .with_signer(&[&lp_config.signer_seeds()]),
+            ctx.accounts.receipt_account.amount,
+        )?;
+    }
+
+    let margin_account = ctx.accounts.margin_account.load()?;
+    let result =
+        ctx.accounts
+            .pricing
+            .adapter_result(lp_config, &positions, margin_account.numeraire())?;
+    glow_margin::write_adapter_result(&*margin_account, &result)?;
+
+    Ok(())
+}
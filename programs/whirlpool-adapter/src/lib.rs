@@ -0,0 +1,185 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+//
+// Copyright (C) 2024 A1 XYZ, INC.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+// Allow this until fixed upstream
+#![allow(clippy::result_large_err)]
+
+use anchor_lang::prelude::*;
+
+mod instructions;
+pub mod state;
+pub mod valuation;
+use instructions::*;
+
+pub use state::{LpConfig, MarginLpPositions};
+
+declare_id!("G8VyZJ3DhkD1j8sRo5A8e5ByUUNHwhyygvJUum1qhndB");
+
+/// The maximum number of whirlpool positions of a single whirlpool
+/// that a margin account can deposit as collateral.
+pub const MAX_LP_POSITIONS: usize = 8;
+
+pub mod seeds {
+    use super::constant;
+
+    #[constant]
+    pub const LP_CONFIG: &[u8] = b"lp-config";
+
+    #[constant]
+    pub const LP_RECEIPT_MINT: &[u8] = b"lp-receipt-mint";
+
+    #[constant]
+    pub const LP_POSITIONS: &[u8] = b"lp-positions";
+
+    #[constant]
+    pub const LP_CUSTODY: &[u8] = b"lp-custody";
+}
+
+#[program]
+mod whirlpool_adapter {
+    use super::*;
+
+    /// Create the configuration for using positions of a whirlpool as collateral
+    /// in an airspace.
+    ///
+    /// This creates a receipt mint, which represents all the deposited positions of
+    /// the whirlpool in a margin account. The airspace authority should then configure
+    /// the receipt mint as `AdapterCollateral` administered by this program.
+    ///
+    /// # [Accounts](whirlpool_adapter::accounts::CreateLpConfig)
+    ///
+    /// |     |     |     |
+    /// | --- | --- | --- |
+    /// | **Name** | **Type** | **Description** |
+    /// | `authority` | `Signer` | The airspace authority. |
+    /// | `airspace` | `read_only` | The airspace the positions are used in. |
+    /// | `payer` | `Signer` | The payer of rent for new accounts. |
+    /// | `whirlpool` | `read_only` | The whirlpool whose positions can be deposited. |
+    /// | `lp_config` | `writable` | The config to be created. |
+    /// | `receipt_mint` | `writable` | The mint of the receipt token to be created. |
+    /// | `mint_a` | `read_only` | The mint of the whirlpool's token A. |
+    /// | `mint_b` | `read_only` | The mint of the whirlpool's token B. |
+    /// | `token_program` | `read_only` | The [spl token program](https://spl.solana.com/token). |
+    /// | `system_program` | `read_only` | The [system native program](https://docs.solana.com/developing/runtime-facilities/programs#system-program). |
+    pub fn create_lp_config(ctx: Context<CreateLpConfig>) -> Result<()> {
+        instructions::create_lp_config_handler(ctx)
+    }
+
+    /// Creates the receipt token account and position list of a margin account,
+    /// then requests margin to register the position.
+    ///
+    /// # [Accounts](whirlpool_adapter::accounts::RegisterMarginPosition)
+    ///
+    /// |     |     |     |
+    /// | --- | --- | --- |
+    /// | **Name** | **Type** | **Description** |
+    /// | `margin_account` | `Signer` | The margin account registering the position. |
+    /// | `lp_config` | `read_only` | The config of the whirlpool. |
+    /// | `receipt_token_config` | `read_only` | The margin token config of the receipt mint. |
+    /// | `receipt_mint` | `read_only` | The mint of the receipt token. |
+    /// | `receipt_account` | `writable` | The receipt token account to be created. |
+    /// | `lp_positions` | `writable` | The list of deposited positions to be created. |
+    /// | `payer` | `Signer` | The payer of rent for new accounts. |
+    /// | `token_program` | `read_only` | The [spl token program](https://spl.solana.com/token). |
+    /// | `system_program` | `read_only` | The [system native program](https://docs.solana.com/developing/runtime-facilities/programs#system-program). |
+    pub fn register_margin_position(ctx: Context<RegisterMarginPosition>) -> Result<()> {
+        instructions::register_margin_position_handler(ctx)
+    }
+
+    /// Deposit a whirlpool position NFT held by a margin account into custody,
+    /// and update the value of the margin account's positions.
+    ///
+    /// The positions that were deposited before must be provided as remaining
+    /// accounts, in the order they are stored in `lp_positions`.
+    pub fn deposit_lp_position(ctx: Context<DepositLpPosition>) -> Result<()> {
+        instructions::deposit_lp_position_handler(ctx)
+    }
+
+    /// Withdraw a whirlpool position NFT from custody to a margin account,
+    /// and update the value of the margin account's positions.
+    ///
+    /// The destination is subject to the same checks as withdrawals from margin
+    /// pools, including the destination constraints of the margin account.
+    ///
+    /// The positions that remain deposited must be provided as remaining
+    /// accounts, in the order they are stored in `lp_positions`.
+    pub fn withdraw_lp_position(ctx: Context<WithdrawLpPosition>) -> Result<()> {
+        instructions::withdraw_lp_position_handler(ctx)
+    }
+
+    /// Update the value of the whirlpool positions of a margin account.
+    ///
+    /// The deposited positions must be provided as remaining accounts,
+    /// in the order they are stored in `lp_positions`.
+    pub fn margin_refresh_lp_position(ctx: Context<MarginRefreshLpPosition>) -> Result<()> {
+        instructions::margin_refresh_lp_position_handler(ctx)
+    }
+
+    /// Close the receipt token account and position list of a margin account,
+    /// which must not have any deposited positions left.
+    ///
+    /// # [Accounts](whirlpool_adapter::accounts::CloseMarginPosition)
+    ///
+    /// |     |     |     |
+    /// | --- | --- | --- |
+    /// | **Name** | **Type** | **Description** |
+    /// | `margin_account` | `Signer` | The margin account closing the position. |
+    /// | `lp_config` | `read_only` | The config of the whirlpool. |
+    /// | `receipt_mint` | `read_only` | The mint of the receipt token. |
+    /// | `receipt_account` | `writable` | The receipt token account to be closed. |
+    /// | `lp_positions` | `writable` | The list of deposited positions to be closed. |
+    /// | `beneficiary` | `writable` | The address to return rent to. |
+    /// | `token_program` | `read_only` | The [spl token program](https://spl.solana.com/token). |
+    pub fn close_margin_position(ctx: Context<CloseMarginPosition>) -> Result<()> {
+        instructions::close_margin_position_handler(ctx)
+    }
+}
+
+#[error_code]
+pub enum ErrorCode {
+    /// 141200 - The whirlpool does not match the config
+    #[msg("The whirlpool does not match the config")]
+    InvalidWhirlpool = 135_200,
+
+    /// 141201 - A token config for pricing the whirlpool tokens is invalid
+    #[msg("A token config for pricing the whirlpool tokens is invalid")]
+    InvalidPricingTokenConfig,
+
+    /// 141202 - The whirlpool position is invalid
+    #[msg("The whirlpool position is invalid")]
+    InvalidLpPosition,
+
+    /// 141203 - The deposited positions were not provided as expected
+    #[msg("The deposited positions were not provided as expected")]
+    LpPositionsMismatch,
+
+    /// 141204 - The maximum number of positions has been deposited
+    #[msg("The maximum number of positions has been deposited")]
+    MaxLpPositionsReached,
+
+    /// 141205 - The margin account still has positions deposited
+    #[msg("The margin account still has positions deposited")]
+    LpPositionsNotEmpty,
+
+    /// 141206 - The value of the positions could not be calculated
+    #[msg("The value of the positions could not be calculated")]
+    ValuationFailed,
+
+    /// 141207 - The position can't be withdrawn to the destination
+    #[msg("The position can't be withdrawn to the destination")]
+    InvalidDestination,
+}
@@ -0,0 +1,133 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+//
+// Copyright (C) 2024 A1 XYZ, INC.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use anchor_lang::prelude::*;
+
+use crate::{seeds, ErrorCode, MAX_LP_POSITIONS};
+
+/// The configuration for using the positions of a whirlpool as
+/// collateral in an airspace.
+#[account]
+#[derive(Default, Debug)]
+pub struct LpConfig {
+    /// The bump seed used to create the config address
+    pub bump: [u8; 1],
+
+    /// The airspace the positions are used in
+    pub airspace: Pubkey,
+
+    /// The whirlpool whose positions can be deposited
+    pub whirlpool: Pubkey,
+
+    /// The mint of the receipt token, which represents all the deposited
+    /// positions of the whirlpool in a margin account
+    pub receipt_mint: Pubkey,
+
+    /// The mint of the whirlpool's token A
+    pub mint_a: Pubkey,
+
+    /// The mint of the whirlpool's token B
+    pub mint_b: Pubkey,
+
+    /// The decimals of the whirlpool's token A
+    pub decimals_a: u8,
+
+    /// The decimals of the whirlpool's token B
+    pub decimals_b: u8,
+}
+
+impl LpConfig {
+    pub const SPACE: usize = 8 + std::mem::size_of::<Self>();
+
+    pub fn signer_seeds(&self) -> [&[u8]; 4] {
+        [
+            seeds::LP_CONFIG,
+            self.airspace.as_ref(),
+            self.whirlpool.as_ref(),
+            self.bump.as_ref(),
+        ]
+    }
+}
+
+/// The whirlpool positions deposited by a margin account
+#[account]
+#[derive(Default, Debug)]
+pub struct MarginLpPositions {
+    /// The margin account that deposited the positions
+    pub margin_account: Pubkey,
+
+    /// The config of the whirlpool the positions belong to
+    pub lp_config: Pubkey,
+
+    /// The addresses of the deposited whirlpool position accounts
+    pub positions: Vec<Pubkey>,
+}
+
+impl MarginLpPositions {
+    pub const SPACE: usize = 8 + 32 + 32 + 4 + 32 * MAX_LP_POSITIONS;
+
+    pub fn add(&mut self, position: Pubkey) -> Result<()> {
+        require!(
+            self.positions.len() < MAX_LP_POSITIONS,
+            ErrorCode::MaxLpPositionsReached
+        );
+        require!(
+            !self.positions.contains(&position),
+            ErrorCode::InvalidLpPosition
+        );
+        self.positions.push(position);
+
+        Ok(())
+    }
+
+    pub fn remove(&mut self, position: &Pubkey) -> Result<()> {
+        let index = self
+            .positions
+            .iter()
+            .position(|p| p == position)
+            .ok_or(ErrorCode::InvalidLpPosition)?;
+        self.positions.remove(index);
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn positions_are_added_up_to_the_max() {
+        let mut positions = MarginLpPositions::default();
+        for _ in 0..MAX_LP_POSITIONS {
+            positions.add(Pubkey::new_unique()).unwrap();
+        }
+        assert!(positions.add(Pubkey::new_unique()).is_err());
+    }
+
+    #[test]
+    fn positions_are_not_added_twice() {
+        let mut positions = MarginLpPositions::default();
+        let position = Pubkey::new_unique();
+        positions.add(position).unwrap();
+        assert!(positions.add(position).is_err());
+
+        positions.remove(&position).unwrap();
+        assert!(positions.remove(&position).is_err());
+        assert!(positions.positions.is_empty());
+    }
+}
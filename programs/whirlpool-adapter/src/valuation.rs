@@ -0,0 +1,287 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+//
+// Copyright (C) 2024 A1 XYZ, INC.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Valuation of whirlpool positions.
+//!
+//! Positions are valued from their tick range and liquidity at the price implied by
+//! the oracle prices of the whirlpool's tokens, rather than at the pool's spot price,
+//! so that the value can't be moved by trading against the pool.
+
+use anchor_lang::prelude::*;
+use whirlpool::math::{get_amount_delta_a, get_amount_delta_b, sqrt_price_from_tick_index};
+use whirlpool::state::Position;
+
+use glow_margin::PriceChangeInfo;
+use glow_program_common::Number128;
+
+use crate::ErrorCode;
+
+/// The exponent of the prices reported to the margin program
+pub const PRICE_EXPONENT: i32 = -8;
+
+/// The oracle price of one of the whirlpool's tokens
+#[derive(Debug, Clone, Copy)]
+pub struct TokenPrice {
+    pub price: Number128,
+    pub confidence: Number128,
+    pub publish_time: i64,
+    pub decimals: u8,
+}
+
+impl TokenPrice {
+    /// The value of an amount of base units of the token
+    fn value_of(&self, amount: u64) -> Number128 {
+        Number128::from_decimal(amount, -(self.decimals as i32)) * self.price
+    }
+
+    /// The confidence interval of the value of an amount of base units of the token
+    fn confidence_of(&self, amount: u64) -> Number128 {
+        Number128::from_decimal(amount, -(self.decimals as i32)) * self.confidence
+    }
+}
+
+/// Calculate the price of the receipt token representing all the positions,
+/// which is the total value of the positions including their owed fees.
+///
+/// The confidence of the price is the sum of the confidence intervals of the token
+/// amounts, and its publish time is that of the older token price.
+pub fn positions_price(
+    positions: &[Position],
+    token_a: &TokenPrice,
+    token_b: &TokenPrice,
+) -> Result<PriceChangeInfo> {
+    let sqrt_price = oracle_sqrt_price_x64(token_a, token_b)?;
+
+    let mut value = Number128::ZERO;
+    let mut confidence = Number128::ZERO;
+    for position in positions {
+        let (amount_a, amount_b) = position_token_amounts(
+            position.liquidity,
+            position.tick_lower_index,
+            position.tick_upper_index,
+            sqrt_price,
+        )?;
+        let amount_a = amount_a.saturating_add(position.fee_owed_a);
+        let amount_b = amount_b.saturating_add(position.fee_owed_b);
+        value += token_a.value_of(amount_a) + token_b.value_of(amount_b);
+        confidence += token_a.confidence_of(amount_a) + token_b.confidence_of(amount_b);
+    }
+
+    let value = i64::try_from(value.as_u64(PRICE_EXPONENT))
+        .map_err(|_| error!(ErrorCode::ValuationFailed))?;
+
+    Ok(PriceChangeInfo::new(
+        value,
+        confidence.as_u64(PRICE_EXPONENT),
+        value,
+        token_a.publish_time.min(token_b.publish_time),
+        PRICE_EXPONENT,
+    ))
+}
+
+/// The amounts of tokens A and B that a position consists of at a price, excluding fees.
+///
+/// Below its range a position only holds token A, above its range only token B.
+pub fn position_token_amounts(
+    liquidity: u128,
+    tick_lower_index: i32,
+    tick_upper_index: i32,
+    sqrt_price_x64: u128,
+) -> Result<(u64, u64)> {
+    if liquidity == 0 {
+        return Ok((0, 0));
+    }
+    let sqrt_price_lower = sqrt_price_from_tick_index(tick_lower_index);
+    let sqrt_price_upper = sqrt_price_from_tick_index(tick_upper_index);
+    require!(
+        sqrt_price_lower < sqrt_price_upper,
+        ErrorCode::InvalidLpPosition
+    );
+    let sqrt_price = sqrt_price_x64.clamp(sqrt_price_lower, sqrt_price_upper);
+
+    let amount_a = get_amount_delta_a(sqrt_price, sqrt_price_upper, liquidity, false)?;
+    let amount_b = get_amount_delta_b(sqrt_price_lower, sqrt_price, liquidity, false)?;
+
+    Ok((amount_a, amount_b))
+}
+
+/// The square root of the price of token A in token B as a Q64.64 number, which is
+/// how whirlpools represent prices, derived from the oracle prices of both tokens.
+///
+/// Whirlpool prices are in base units, so the price is adjusted by the token decimals.
+pub fn oracle_sqrt_price_x64(token_a: &TokenPrice, token_b: &TokenPrice) -> Result<u128> {
+    require!(
+        token_a.price > Number128::ZERO && token_b.price > Number128::ZERO,
+        ErrorCode::ValuationFailed
+    );
+    let decimals_adjustment =
+        Number128::from_decimal(1, token_b.decimals as i32 - token_a.decimals as i32);
+    let price = token_a.price / token_b.price * decimals_adjustment;
+    require!(price > Number128::ZERO, ErrorCode::ValuationFailed);
+
+    Ok(sqrt_x64(price.to_i128() as u128))
+}
+
+/// The square root of a [Number128] given by its raw value, as a Q64.64 number.
+///
+/// `sqrt(raw / 10^10) * 2^64 = sqrt(raw) * 2^59 / 5^5`, where the radicand is shifted
+/// left by an even number of bits first, to keep the precision of the root.
+fn sqrt_x64(raw: u128) -> u128 {
+    if raw == 0 {
+        return 0;
+    }
+    let shift = raw.leading_zeros() & !1;
+    let root = isqrt(raw << shift);
+    let half_shift = shift / 2;
+
+    if half_shift <= 59 {
+        (root << (59 - half_shift)) / 3125
+    } else {
+        (root >> (half_shift - 59)) / 3125
+    }
+}
+
+/// The integer square root, rounded down
+fn isqrt(n: u128) -> u128 {
+    if n < 2 {
+        return n;
+    }
+    let mut x = 1u128 << ((128 - n.leading_zeros()) / 2 + 1);
+    loop {
+        let y = (x + n / x) / 2;
+        if y >= x {
+            return x;
+        }
+        x = y;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn token_price(price: i64, decimals: u8) -> TokenPrice {
+        TokenPrice {
+            price: Number128::from_decimal(price, 0),
+            confidence: Number128::ZERO,
+            publish_time: 0,
+            decimals,
+        }
+    }
+
+    fn price_value(price: PriceChangeInfo) -> i64 {
+        let info = price.to_price_info(0, &Default::default());
+        assert_eq!(info.exponent, PRICE_EXPONENT);
+        info.value
+    }
+
+    #[test]
+    fn isqrt_rounds_down() {
+        assert_eq!(isqrt(0), 0);
+        assert_eq!(isqrt(1), 1);
+        assert_eq!(isqrt(15), 3);
+        assert_eq!(isqrt(16), 4);
+        assert_eq!(isqrt(u128::MAX), u64::MAX as u128);
+    }
+
+    #[test]
+    fn sqrt_price_of_equal_prices_is_one() {
+        let token = token_price(2, 6);
+        let sqrt_price = oracle_sqrt_price_x64(&token, &token).unwrap();
+        assert_eq!(sqrt_price, 1 << 64);
+    }
+
+    #[test]
+    fn sqrt_price_accounts_for_decimals() {
+        // 1 SOL (9 decimals) = 100 USDC (6 decimals), so a base unit of
+        // SOL is worth 0.1 base units of USDC
+        let sol = token_price(100, 9);
+        let usdc = token_price(1, 6);
+        let sqrt_price = oracle_sqrt_price_x64(&sol, &usdc).unwrap();
+        let expected = 0.1f64.sqrt() * 2f64.powi(64);
+        assert!((sqrt_price as f64 / expected - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn position_outside_range_holds_one_token() {
+        let liquidity = 1_000_000_000;
+        let below = sqrt_price_from_tick_index(-200);
+        let above = sqrt_price_from_tick_index(200);
+
+        let (a, b) = position_token_amounts(liquidity, -100, 100, below).unwrap();
+        assert!(a > 0);
+        assert_eq!(b, 0);
+
+        let (a, b) = position_token_amounts(liquidity, -100, 100, above).unwrap();
+        assert_eq!(a, 0);
+        assert!(b > 0);
+
+        assert_eq!(position_token_amounts(0, -100, 100, above).unwrap(), (0, 0));
+    }
+
+    #[test]
+    fn position_value_follows_oracle_prices() {
+        let token_a = token_price(1, 6);
+        let token_b = token_price(1, 6);
+        let position = Position {
+            liquidity: 1_000_000_000,
+            tick_lower_index: -100,
+            tick_upper_index: 100,
+            fee_owed_a: 1_000_000,
+            ..Default::default()
+        };
+        let price = positions_price(&[position.clone()], &token_a, &token_b).unwrap();
+        let (a, b) = position_token_amounts(position.liquidity, -100, 100, 1 << 64).unwrap();
+        let expected = (a + b + 1_000_000) as i64 * 100;
+        let value = price_value(price);
+        assert!((value - expected).abs() <= 200);
+
+        // When token A is worth more, the position is worth more
+        let token_a = token_price(2, 6);
+        let higher = positions_price(&[position], &token_a, &token_b).unwrap();
+        assert!(price_value(higher) > value);
+    }
+
+    #[test]
+    fn position_confidence_and_publish_time_follow_oracles() {
+        let position = Position {
+            liquidity: 1_000_000_000,
+            tick_lower_index: -100,
+            tick_upper_index: 100,
+            ..Default::default()
+        };
+        let token_a = TokenPrice {
+            confidence: Number128::from_decimal(1, -2),
+            publish_time: 100,
+            ..token_price(1, 6)
+        };
+        let token_b = TokenPrice {
+            publish_time: 90,
+            ..token_price(1, 6)
+        };
+        let price = positions_price(&[position.clone()], &token_a, &token_b).unwrap();
+        assert_eq!(price.publish_time(), 90);
+
+        // Only the amount of token A contributes to the confidence, at 1% of its value
+        let (a, _) = position_token_amounts(position.liquidity, -100, 100, 1 << 64).unwrap();
+        let expected = Number128::from_decimal(a, -6) * Number128::from_decimal(1, -2);
+        assert_eq!(
+            price.confidence().as_u64(PRICE_EXPONENT),
+            expected.as_u64(PRICE_EXPONENT)
+        );
+    }
+}
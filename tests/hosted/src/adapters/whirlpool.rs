@@ -1,10 +1,17 @@
 //! Set up whirlpool and set liquidity
 
+use std::sync::Arc;
+
 use anchor_lang::{prelude::*, system_program, InstructionData};
 use anchor_spl::associated_token::{
     get_associated_token_address, get_associated_token_address_with_program_id,
 };
-use glow_instructions::MintInfo;
+use glow_instructions::{
+    derive_pyth_price_feed_account,
+    margin::{adapter_invoke, derive_token_config},
+    MintInfo,
+};
+use glow_program_common::oracle::TokenPriceOracle;
 use num_traits::{Pow, ToPrimitive};
 use rust_decimal::{Decimal, MathematicalOps};
 use solana_sdk::{
@@ -19,9 +26,11 @@ use whirlpool::state::{
 };
 
 use glow_simulation::Keygen;
-use glow_simulation::{runtime::TestRuntimeRpcClient, DeterministicKeygen};
+use glow_simulation::{
+    runtime::TestRuntimeRpcClient, solana_rpc_api::SolanaRpcClient, DeterministicKeygen,
+};
 
-use crate::tokens::TokenManager;
+use crate::{send_and_confirm, tokens::TokenManager};
 
 pub struct TestWhirlpool {
     pub address: Pubkey,
@@ -407,3 +416,235 @@ pub fn derive_tick_array(whirlpool: &Pubkey, tick_index: i32, tick_spacing: u16)
     )
     .0
 }
+
+/// The accounts of a whirlpool configured as collateral with the whirlpool adapter
+pub struct TestLpConfig {
+    pub address: Pubkey,
+    pub airspace: Pubkey,
+    pub whirlpool: Pubkey,
+    pub receipt_mint: Pubkey,
+    pub mint_a: MintInfo,
+    pub mint_b: MintInfo,
+    pub oracle_a: TokenPriceOracle,
+    pub oracle_b: TokenPriceOracle,
+}
+
+impl TestLpConfig {
+    /// Create the config of a whirlpool in an airspace, signed by the airspace authority.
+    ///
+    /// The oracles are those configured in the margin token configs of the whirlpool's tokens.
+    pub async fn create(
+        rpc: &Arc<dyn SolanaRpcClient>,
+        airspace_authority: &Keypair,
+        airspace: Pubkey,
+        whirlpool: &TestWhirlpool,
+        [oracle_a, oracle_b]: [TokenPriceOracle; 2],
+    ) -> anyhow::Result<Self> {
+        let address = Pubkey::find_program_address(
+            &[
+                glow_whirlpool_adapter::seeds::LP_CONFIG,
+                airspace.as_ref(),
+                whirlpool.address.as_ref(),
+            ],
+            &glow_whirlpool_adapter::ID,
+        )
+        .0;
+        let receipt_mint = Pubkey::find_program_address(
+            &[
+                glow_whirlpool_adapter::seeds::LP_RECEIPT_MINT,
+                address.as_ref(),
+            ],
+            &glow_whirlpool_adapter::ID,
+        )
+        .0;
+        let payer = rpc.payer();
+        let ix = Instruction {
+            program_id: glow_whirlpool_adapter::ID,
+            accounts: glow_whirlpool_adapter::accounts::CreateLpConfig {
+                authority: airspace_authority.pubkey(),
+                airspace,
+                payer: payer.pubkey(),
+                whirlpool: whirlpool.address,
+                lp_config: address,
+                receipt_mint,
+                mint_a: whirlpool.mint_a.address,
+                mint_b: whirlpool.mint_b.address,
+                token_program: anchor_spl::token_2022::ID,
+                system_program: system_program::ID,
+            }
+            .to_account_metas(None),
+            data: glow_whirlpool_adapter::instruction::CreateLpConfig {}.data(),
+        };
+        send_and_confirm(rpc, &[ix], &[airspace_authority]).await?;
+
+        Ok(Self {
+            address,
+            airspace,
+            whirlpool: whirlpool.address,
+            receipt_mint,
+            mint_a: whirlpool.mint_a,
+            mint_b: whirlpool.mint_b,
+            oracle_a,
+            oracle_b,
+        })
+    }
+
+    /// Register the receipt token of the whirlpool positions in a margin account
+    pub async fn register_margin_position(
+        &self,
+        rpc: &Arc<dyn SolanaRpcClient>,
+        owner: &Keypair,
+        margin_account: Pubkey,
+    ) -> anyhow::Result<()> {
+        let ix = Instruction {
+            program_id: glow_whirlpool_adapter::ID,
+            accounts: glow_whirlpool_adapter::accounts::RegisterMarginPosition {
+                margin_account,
+                lp_config: self.address,
+                receipt_token_config: derive_token_config(&self.airspace, &self.receipt_mint),
+                receipt_mint: self.receipt_mint,
+                receipt_account: self.receipt_account(&margin_account),
+                lp_positions: self.lp_positions(&margin_account),
+                payer: owner.pubkey(),
+                token_program: anchor_spl::token_2022::ID,
+                system_program: system_program::ID,
+            }
+            .to_account_metas(None),
+            data: glow_whirlpool_adapter::instruction::RegisterMarginPosition {}.data(),
+        };
+        let invoke_ix = adapter_invoke(self.airspace, owner.pubkey(), margin_account, ix, &[]);
+        send_and_confirm(rpc, &[invoke_ix], &[owner]).await?;
+
+        Ok(())
+    }
+
+    /// Deposit a position held by the margin account, which has no other positions deposited
+    pub async fn deposit_lp_position(
+        &self,
+        rpc: &Arc<dyn SolanaRpcClient>,
+        owner: &Keypair,
+        margin_account: Pubkey,
+        position_mint: Pubkey,
+    ) -> anyhow::Result<()> {
+        let ix = Instruction {
+            program_id: glow_whirlpool_adapter::ID,
+            accounts: glow_whirlpool_adapter::accounts::DepositLpPosition {
+                margin_account,
+                lp_config: self.address,
+                lp_positions: self.lp_positions(&margin_account),
+                position: derive_position(&position_mint),
+                position_mint,
+                source: get_associated_token_address(&margin_account, &position_mint),
+                custody: derive_lp_custody(&position_mint),
+                receipt_mint: self.receipt_mint,
+                receipt_account: self.receipt_account(&margin_account),
+                pricing: self.pricing(),
+                payer: owner.pubkey(),
+                position_token_program: anchor_spl::token::ID,
+                token_program: anchor_spl::token_2022::ID,
+                system_program: system_program::ID,
+            }
+            .to_account_metas(None),
+            data: glow_whirlpool_adapter::instruction::DepositLpPosition {}.data(),
+        };
+        let invoke_ix = adapter_invoke(self.airspace, owner.pubkey(), margin_account, ix, &[]);
+        send_and_confirm(rpc, &[invoke_ix], &[owner]).await?;
+
+        Ok(())
+    }
+
+    /// Withdraw the only position deposited by the margin account
+    pub async fn withdraw_lp_position(
+        &self,
+        rpc: &Arc<dyn SolanaRpcClient>,
+        owner: &Keypair,
+        margin_account: Pubkey,
+        position_mint: Pubkey,
+        destination: Pubkey,
+    ) -> anyhow::Result<()> {
+        let ix = Instruction {
+            program_id: glow_whirlpool_adapter::ID,
+            accounts: glow_whirlpool_adapter::accounts::WithdrawLpPosition {
+                margin_account,
+                lp_config: self.address,
+                lp_positions: self.lp_positions(&margin_account),
+                position: derive_position(&position_mint),
+                position_mint,
+                custody: derive_lp_custody(&position_mint),
+                destination,
+                account_constraint_ticket: None,
+                receipt_mint: self.receipt_mint,
+                receipt_account: self.receipt_account(&margin_account),
+                pricing: self.pricing(),
+                beneficiary: owner.pubkey(),
+                position_token_program: anchor_spl::token::ID,
+                token_program: anchor_spl::token_2022::ID,
+            }
+            .to_account_metas(None),
+            data: glow_whirlpool_adapter::instruction::WithdrawLpPosition {}.data(),
+        };
+        let invoke_ix = adapter_invoke(self.airspace, owner.pubkey(), margin_account, ix, &[]);
+        send_and_confirm(rpc, &[invoke_ix], &[owner]).await?;
+
+        Ok(())
+    }
+
+    pub fn receipt_account(&self, margin_account: &Pubkey) -> Pubkey {
+        Pubkey::find_program_address(
+            &[margin_account.as_ref(), self.receipt_mint.as_ref()],
+            &glow_whirlpool_adapter::ID,
+        )
+        .0
+    }
+
+    pub fn lp_positions(&self, margin_account: &Pubkey) -> Pubkey {
+        Pubkey::find_program_address(
+            &[
+                glow_whirlpool_adapter::seeds::LP_POSITIONS,
+                margin_account.as_ref(),
+                self.address.as_ref(),
+            ],
+            &glow_whirlpool_adapter::ID,
+        )
+        .0
+    }
+
+    /// The accounts for pricing the whirlpool tokens, which are priced by pyth pull oracles
+    fn pricing(&self) -> glow_whirlpool_adapter::accounts::LpPricing {
+        let oracle = |mint: &MintInfo, oracle: &TokenPriceOracle| {
+            let token_config = derive_token_config(&self.airspace, &mint.address);
+            let price_oracle = derive_pyth_price_feed_account(
+                oracle.pyth_feed_id().unwrap(),
+                None,
+                glow_test_service::ID,
+            );
+            (token_config, price_oracle)
+        };
+        let (token_config_a, price_oracle_a) = oracle(&self.mint_a, &self.oracle_a);
+        let (token_config_b, price_oracle_b) = oracle(&self.mint_b, &self.oracle_b);
+
+        glow_whirlpool_adapter::accounts::LpPricing {
+            token_config_a,
+            price_oracle_a,
+            redemption_quote_oracle_a: None,
+            token_config_b,
+            price_oracle_b,
+            redemption_quote_oracle_b: None,
+        }
+    }
+}
+
+pub fn derive_position(position_mint: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"position", position_mint.as_ref()], &whirlpool::ID).0
+}
+
+pub fn derive_lp_custody(position_mint: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &[
+            glow_whirlpool_adapter::seeds::LP_CUSTODY,
+            position_mint.as_ref(),
+        ],
+        &glow_whirlpool_adapter::ID,
+    )
+    .0
+}
@@ -26,8 +26,8 @@ use anyhow::{bail, Error};
 use glow_client::NetworkKind;
 use glow_instructions::MintInfo;
 use glow_margin::{
-    AccountFeatureFlags, AccountPosition, MarginAccount, TokenConfigUpdate, TokenFeatures,
-    TokenKind,
+    AccountFeatureFlags, AccountPosition, AdapterOutflowLimits, MarginAccount, TokenConfigUpdate,
+    TokenFeatures, TokenKind,
 };
 use glow_margin_sdk::get_state::get_anchor_account;
 use glow_margin_sdk::ix_builder::test_service::if_not_initialized;
@@ -61,8 +61,8 @@ use solana_sdk::{pubkey::Pubkey, transaction::Transaction};
 
 use glow_margin_pool::{Amount, MarginPool, MarginPoolConfig, TokenMetadataParams};
 use glow_margin_sdk::tx_builder::{
-    global_initialize_instructions, AirspaceAdmin, MarginActionAuthority, MarginInvokeContext,
-    MarginTxBuilder, TokenDepositsConfig,
+    global_initialize_instructions, AirspaceAdmin, LiquidatorPermission, MarginActionAuthority,
+    MarginInvokeContext, MarginTxBuilder, TokenDepositsConfig,
 };
 use glow_metadata::TokenMetadata;
 use tokio::sync::Mutex;
@@ -235,6 +235,19 @@ impl MarginClient {
         Ok(())
     }
 
+    pub async fn configure_adapter_outflow_limits(
+        &self,
+        adapter: Pubkey,
+        limits: AdapterOutflowLimits,
+    ) -> Result<(), Error> {
+        self.tx_admin
+            .configure_margin_adapter_outflow_limits(adapter, limits)
+            .with_signer(&self.airspace_authority)
+            .send_and_confirm(&self.rpc)
+            .await?;
+        Ok(())
+    }
+
     /// Configure deposits for a given token (when placed directly into a margin account)
     pub async fn configure_token_deposits(
         &self,
@@ -399,6 +412,12 @@ impl MarginUser {
         }
     }
 
+    /// Pass the configs of the given adapters as writable when invoking them
+    pub fn with_windowed_adapters(mut self, adapters: Vec<Pubkey>) -> Self {
+        self.tx = self.tx.with_windowed_adapters(adapters);
+        self
+    }
+
     pub fn owner(&self) -> &Pubkey {
         self.tx.owner()
     }
@@ -416,6 +435,7 @@ impl MarginUser {
             authority: self.signer.pubkey(),
             airspace: self.tx.airspace(),
             is_liquidator: self.tx.is_liquidator(),
+            windowed_adapters: self.tx.ix.windowed_adapters().to_vec(),
         }
     }
 
@@ -680,16 +700,27 @@ impl MarginUser {
             .collect())
     }
 
-    pub async fn liquidate_begin(&self, refresh_positions: bool) -> Result<(), Error> {
-        self.send_confirm_tx(self.tx.liquidate_begin(refresh_positions).await?)
-            .await
+    pub async fn liquidate_begin(
+        &self,
+        refresh_positions: bool,
+        permission: LiquidatorPermission,
+    ) -> Result<(), Error> {
+        self.send_confirm_tx(
+            self.tx
+                .liquidate_begin(refresh_positions, permission)
+                .await?,
+        )
+        .await
     }
 
     pub async fn liquidate_begin_tx(
         &self,
         refresh_positions: bool,
+        permission: LiquidatorPermission,
     ) -> Result<TransactionBuilder, Error> {
-        self.tx.liquidate_begin_builder(refresh_positions).await
+        self.tx
+            .liquidate_begin_builder(refresh_positions, permission)
+            .await
     }
 
     pub async fn collect_liquidation_fees(&self) -> Result<(), Error> {
@@ -72,6 +72,11 @@ pub fn get_programs() -> Vec<SolanaProgram> {
             program_name: "glow_margin_pool".into(),
             builtin_function: anchor_processor!(glow_margin_pool),
         },
+        SolanaProgram {
+            program_id: glow_backstop::ID,
+            program_name: "glow_backstop".into(),
+            builtin_function: anchor_processor!(glow_backstop),
+        },
         SolanaProgram {
             program_id: lookup_table_registry::ID,
             program_name: "lookup_table_registry".into(),
@@ -87,6 +92,11 @@ pub fn get_programs() -> Vec<SolanaProgram> {
             program_name: "whirlpool".into(),
             builtin_function: anchor_processor!(whirlpool),
         },
+        SolanaProgram {
+            program_id: glow_whirlpool_adapter::ID,
+            program_name: "glow_whirlpool_adapter".into(),
+            builtin_function: anchor_processor!(glow_whirlpool_adapter),
+        },
         SolanaProgram {
             program_id: stable_swap_client::ID,
             program_name: "saber_stable_swap".into(),
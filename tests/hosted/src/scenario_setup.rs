@@ -24,6 +24,8 @@ pub const ONE_BTC: u64 = LAMPORTS_PER_SOL;
 pub struct Scenario1 {
     pub usdc: MintInfo,
     pub usdc_oracle: TokenPriceOracle,
+    pub tsol: MintInfo,
+    pub tsol_oracle: TokenPriceOracle,
     pub user_a: MarginUser,
     pub user_b: MarginUser,
     pub liquidator: TestLiquidator,
@@ -147,6 +149,8 @@ pub async fn scenario1_with_ctx(ctx: &Arc<MarginTestContext>) -> Result<Scenario
         user_b: user_b.user.clone(),
         usdc,
         usdc_oracle,
+        tsol,
+        tsol_oracle,
         liquidator: TestLiquidator::new(ctx).await?,
     })
 }
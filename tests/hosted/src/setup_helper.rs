@@ -305,6 +305,7 @@ pub async fn register_deposit(
                 value_modifier: collateral_weight.unwrap_or(100),
                 max_staleness: 30, // Use the common default
                 token_features: TokenFeatures::empty(),
+                oracle_limits: Default::default(),
             },
         )
         .with_signer(airspace_authority)
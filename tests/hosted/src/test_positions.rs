@@ -57,7 +57,7 @@ pub async fn register_test_adapter_position(
         .to_account_metas(None),
         data: glow_test_service::instruction::RegisterAdapterPosition {}.data(),
     };
-    let invoke_ix = adapter_invoke(airspace, signer.pubkey(), margin_account, instruction);
+    let invoke_ix = adapter_invoke(airspace, signer.pubkey(), margin_account, instruction, &[]);
     send_and_confirm(rpc, &[invoke_ix], &[signer]).await?;
 
     Ok(position_account)
@@ -88,7 +88,7 @@ pub async fn close_test_adapter_position(
         .to_account_metas(None),
         data: glow_test_service::instruction::CloseAdapterPosition {}.data(),
     };
-    let invoke_ix = adapter_invoke(airspace, signer.pubkey(), margin_account, instruction);
+    let invoke_ix = adapter_invoke(airspace, signer.pubkey(), margin_account, instruction, &[]);
     send_and_confirm(rpc, &[invoke_ix], &[signer]).await?;
 
     Ok(position_account)
@@ -17,7 +17,7 @@ use glow_instructions::{derive_pyth_price_feed_account, MintInfo};
 use glow_margin::TokenConfig;
 use glow_margin_sdk::cat;
 use glow_margin_sdk::solana::transaction::{SendTransactionBuilder, TransactionBuilder};
-use glow_margin_sdk::tx_builder::MarginActionAuthority;
+use glow_margin_sdk::tx_builder::{LiquidatorPermission, MarginActionAuthority};
 use glow_margin_sdk::util::asynchronous::{AndAsync, MapAsync};
 use glow_program_common::oracle::TokenPriceOracle;
 use glow_program_common::token_change::TokenChange;
@@ -207,13 +207,21 @@ impl TestUser {
             .await
     }
 
-    pub async fn liquidate_begin(&self, refresh_positions: bool) -> Result<()> {
+    pub async fn liquidate_begin(
+        &self,
+        refresh_positions: bool,
+        permission: LiquidatorPermission,
+    ) -> Result<()> {
         let mut txs = if refresh_positions {
             self.refresh_position_oracles_txs().await?
         } else {
             vec![]
         };
-        txs.push(self.user.liquidate_begin_tx(refresh_positions).await?);
+        txs.push(
+            self.user
+                .liquidate_begin_tx(refresh_positions, permission)
+                .await?,
+        );
         self.ctx.rpc().send_and_confirm_condensed(txs).await?;
 
         Ok(())
@@ -315,10 +323,20 @@ impl TestLiquidator {
     }
 
     pub async fn begin(&self, user: &MarginUser, refresh_positions: bool) -> Result<TestUser> {
+        self.begin_with_permission(user, refresh_positions, LiquidatorPermission::Permit)
+            .await
+    }
+
+    pub async fn begin_with_permission(
+        &self,
+        user: &MarginUser,
+        refresh_positions: bool,
+        permission: LiquidatorPermission,
+    ) -> Result<TestUser> {
         let test_liquidation = self.for_user(user)?;
         test_liquidation
             .user
-            .liquidate_begin(refresh_positions)
+            .liquidate_begin(refresh_positions, permission)
             .await?;
 
         Ok(test_liquidation)
@@ -0,0 +1,95 @@
+use anyhow::Result;
+
+use glow_margin::{AdapterOutflowLimits, ErrorCode};
+use glow_program_common::token_change::TokenChange;
+use glow_simulation::assert_custom_program_error;
+use hosted_tests::{
+    margin_test_context, scenario1,
+    scenario_setup::{scenario1_with_ctx, ONE_USDC},
+};
+
+/// The outflow window of an adapter is recorded in its config, which has to be passed
+/// as writable to every invocation of the adapter.
+#[tokio::test(flavor = "multi_thread")]
+#[cfg_attr(not(feature = "localnet"), serial_test::serial)]
+async fn adapter_invoke_records_window_outflow() -> Result<()> {
+    let (ctx, scen) = scenario1!()?;
+
+    ctx.margin_client()
+        .configure_adapter_outflow_limits(
+            glow_margin_pool::ID,
+            AdapterOutflowLimits {
+                max_invoke_outflow: 0,
+                max_window_outflow: 1_000,
+                window_duration: 3_600,
+            },
+        )
+        .await?;
+
+    let wallet = ctx
+        .tokens()
+        .create_account(scen.usdc, scen.user_a.owner())
+        .await?;
+    let user = scen
+        .user_a
+        .clone()
+        .with_windowed_adapters(vec![glow_margin_pool::ID]);
+    user.refresh_all_pool_positions().await?;
+
+    // The window can't be recorded if the adapter config is read-only
+    let result = scen
+        .user_a
+        .withdraw(scen.usdc, &wallet, TokenChange::shift(ONE_USDC))
+        .await;
+    assert_custom_program_error(anchor_lang::error::ErrorCode::ConstraintMut, result);
+
+    // An outflow within the window limit is recorded in the window
+    user.withdraw(scen.usdc, &wallet, TokenChange::shift(600 * ONE_USDC))
+        .await?;
+
+    // A further outflow in the same window exceeds its limit
+    let result = user
+        .withdraw(scen.usdc, &wallet, TokenChange::shift(600 * ONE_USDC))
+        .await;
+    assert_custom_program_error(ErrorCode::AdapterOutflowLimitExceeded, result);
+
+    Ok(())
+}
+
+/// The outflow is measured from the positions of the margin account, so it is limited
+/// for adapters that don't report their token changes, such as the margin pool.
+#[tokio::test(flavor = "multi_thread")]
+#[cfg_attr(not(feature = "localnet"), serial_test::serial)]
+async fn adapter_invoke_limits_outflow_of_positions() -> Result<()> {
+    let (ctx, scen) = scenario1!()?;
+
+    ctx.margin_client()
+        .configure_adapter_outflow_limits(
+            glow_margin_pool::ID,
+            AdapterOutflowLimits {
+                max_invoke_outflow: 500,
+                max_window_outflow: 0,
+                window_duration: 0,
+            },
+        )
+        .await?;
+
+    let wallet = ctx
+        .tokens()
+        .create_account(scen.usdc, scen.user_a.owner())
+        .await?;
+    scen.user_a.refresh_all_pool_positions().await?;
+
+    // Withdrawing a deposit is an outflow of its value
+    let result = scen
+        .user_a
+        .withdraw(scen.usdc, &wallet, TokenChange::shift(600 * ONE_USDC))
+        .await;
+    assert_custom_program_error(ErrorCode::AdapterOutflowLimitExceeded, result);
+
+    scen.user_a
+        .withdraw(scen.usdc, &wallet, TokenChange::shift(400 * ONE_USDC))
+        .await?;
+
+    Ok(())
+}
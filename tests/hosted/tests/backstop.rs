@@ -0,0 +1,376 @@
+use std::sync::Arc;
+
+use anchor_lang::{InstructionData, ToAccountMetas};
+use anyhow::Result;
+
+use glow_backstop::{seeds, BackstopDeposit, BackstopPool, POOL_MARGIN_ACCOUNT_SEED};
+use glow_instructions::{
+    airspace::{derive_issuer_id, derive_permit},
+    margin::{
+        derive_adapter_config, derive_liquidation, derive_margin_account, derive_margin_permit,
+        derive_position_token_account, derive_token_config,
+    },
+    margin_pool::derive_loan_account,
+    MintInfo,
+};
+use glow_margin_sdk::{
+    get_state::get_anchor_account,
+    ix_builder::MarginPoolIxBuilder,
+    solana::transaction::{TransactionBuilderExt, WithSigner},
+};
+use glow_simulation::assert_custom_program_error;
+use hosted_tests::{
+    context::MarginTestContext,
+    margin_test_context, scenario1,
+    scenario_setup::{scenario1_with_ctx, Scenario1, ONE_USDC},
+};
+use solana_sdk::{
+    instruction::Instruction, pubkey::Pubkey, signature::Keypair, signer::Signer, system_program,
+    sysvar,
+};
+
+/// The USDC deposited into the backstop pool
+const DEPOSIT_AMOUNT: u64 = 100_000 * ONE_USDC;
+
+/// The USDC debt of user B that the backstop repays
+const REPAY_AMOUNT: u64 = 1_000 * ONE_USDC;
+
+/// A backstop pool of USDC, which receives the TSOL deposit notes of liquidated accounts
+struct Backstop {
+    ctx: Arc<MarginTestContext>,
+    usdc: MintInfo,
+    tsol_notes: MintInfo,
+    pool: Pubkey,
+    margin_account: Pubkey,
+}
+
+impl Backstop {
+    async fn create(ctx: &Arc<MarginTestContext>, scen: &Scenario1) -> Result<Self> {
+        let airspace = ctx.airspace_details.address;
+        let authority = &ctx.airspace_authority;
+        let usdc = scen.usdc;
+        let tsol_notes = MarginPoolIxBuilder::new(airspace, scen.tsol).pool_deposit_mint_info();
+        let pool = Pubkey::find_program_address(
+            &[
+                seeds::BACKSTOP_POOL,
+                airspace.as_ref(),
+                usdc.address.as_ref(),
+            ],
+            &glow_backstop::ID,
+        )
+        .0;
+        let margin_account = derive_margin_account(&airspace, &pool, POOL_MARGIN_ACCOUNT_SEED);
+
+        let create_pool = Instruction {
+            program_id: glow_backstop::ID,
+            accounts: glow_backstop::accounts::CreatePool {
+                authority: authority.pubkey(),
+                airspace,
+                payer: ctx.payer().pubkey(),
+                token_mint: usdc.address,
+                token_config: derive_token_config(&airspace, &usdc.address),
+                pool,
+                airspace_permit: derive_permit(&airspace, &pool),
+                issuer_id: derive_issuer_id(&airspace, &authority.pubkey()),
+                margin_account,
+                liquidator_permit: derive_margin_permit(&airspace, &pool),
+                deposit_account: usdc.associated_token_address(&margin_account),
+                token_program: usdc.token_program(),
+                associated_token_program: anchor_spl::associated_token::ID,
+                airspace_program: glow_airspace::ID,
+                margin_program: glow_margin::ID,
+                rent: sysvar::rent::ID,
+                system_program: system_program::ID,
+            }
+            .to_account_metas(None),
+            data: glow_backstop::instruction::CreatePool {}.data(),
+        };
+        let register_collateral = Instruction {
+            program_id: glow_backstop::ID,
+            accounts: glow_backstop::accounts::RegisterCollateral {
+                authority: authority.pubkey(),
+                airspace,
+                payer: ctx.payer().pubkey(),
+                pool,
+                margin_account,
+                collateral_mint: tsol_notes.address,
+                collateral_config: derive_token_config(&airspace, &tsol_notes.address),
+                collateral_account: derive_position_token_account(
+                    &margin_account,
+                    &tsol_notes.address,
+                ),
+                token_program: tsol_notes.token_program(),
+                associated_token_program: anchor_spl::associated_token::ID,
+                margin_program: glow_margin::ID,
+                rent: sysvar::rent::ID,
+                system_program: system_program::ID,
+            }
+            .to_account_metas(None),
+            data: glow_backstop::instruction::RegisterCollateral {}.data(),
+        };
+        for ix in [create_pool, register_collateral] {
+            ix.with_signer(authority)
+                .send_and_confirm(&ctx.rpc())
+                .await?;
+        }
+
+        Ok(Self {
+            ctx: ctx.clone(),
+            usdc,
+            tsol_notes,
+            pool,
+            margin_account,
+        })
+    }
+
+    fn deposit_address(&self, depositor: &Pubkey) -> Pubkey {
+        Pubkey::find_program_address(
+            &[
+                seeds::BACKSTOP_DEPOSIT,
+                self.pool.as_ref(),
+                depositor.as_ref(),
+            ],
+            &glow_backstop::ID,
+        )
+        .0
+    }
+
+    async fn deposit(&self, depositor: &Keypair, source: Pubkey, amount: u64) -> Result<()> {
+        Instruction {
+            program_id: glow_backstop::ID,
+            accounts: glow_backstop::accounts::Deposit {
+                depositor: depositor.pubkey(),
+                payer: self.ctx.payer().pubkey(),
+                pool: self.pool,
+                margin_account: self.margin_account,
+                deposit: self.deposit_address(&depositor.pubkey()),
+                token_mint: self.usdc.address,
+                source,
+                deposit_account: self.usdc.associated_token_address(&self.margin_account),
+                token_program: self.usdc.token_program(),
+                margin_program: glow_margin::ID,
+                system_program: system_program::ID,
+            }
+            .to_account_metas(None),
+            data: glow_backstop::instruction::Deposit { amount }.data(),
+        }
+        .with_signer(depositor)
+        .send_and_confirm(&self.ctx.rpc())
+        .await?;
+
+        Ok(())
+    }
+
+    async fn withdraw(&self, depositor: &Keypair, destination: Pubkey, amount: u64) -> Result<()> {
+        Instruction {
+            program_id: glow_backstop::ID,
+            accounts: glow_backstop::accounts::Withdraw {
+                depositor: depositor.pubkey(),
+                pool: self.pool,
+                margin_account: self.margin_account,
+                deposit: self.deposit_address(&depositor.pubkey()),
+                token_mint: self.usdc.address,
+                deposit_account: self.usdc.associated_token_address(&self.margin_account),
+                destination,
+                token_program: self.usdc.token_program(),
+                margin_program: glow_margin::ID,
+            }
+            .to_account_metas(None),
+            data: glow_backstop::instruction::Withdraw { amount }.data(),
+        }
+        .with_signer(depositor)
+        .send_and_confirm(&self.ctx.rpc())
+        .await?;
+
+        Ok(())
+    }
+
+    async fn claim_gains(&self, depositor: &Keypair, destination: Pubkey) -> Result<()> {
+        Instruction {
+            program_id: glow_backstop::ID,
+            accounts: glow_backstop::accounts::ClaimGains {
+                depositor: depositor.pubkey(),
+                pool: self.pool,
+                margin_account: self.margin_account,
+                deposit: self.deposit_address(&depositor.pubkey()),
+                collateral_mint: self.tsol_notes.address,
+                collateral_account: derive_position_token_account(
+                    &self.margin_account,
+                    &self.tsol_notes.address,
+                ),
+                destination,
+                token_program: self.tsol_notes.token_program(),
+                margin_program: glow_margin::ID,
+            }
+            .to_account_metas(None),
+            data: glow_backstop::instruction::ClaimGains {}.data(),
+        }
+        .with_signer(depositor)
+        .send_and_confirm(&self.ctx.rpc())
+        .await?;
+
+        Ok(())
+    }
+
+    /// Liquidate the USDC loan of a margin account, repaying it from its loan account
+    fn liquidate_ix(
+        &self,
+        margin_account: &Pubkey,
+        loan_account: Pubkey,
+        repay_amount: u64,
+    ) -> Instruction {
+        let airspace = self.ctx.airspace_details.address;
+        let usdc_pool = MarginPoolIxBuilder::new(airspace, self.usdc);
+
+        Instruction {
+            program_id: glow_backstop::ID,
+            accounts: glow_backstop::accounts::Liquidate {
+                payer: self.ctx.payer().pubkey(),
+                pool: self.pool,
+                pool_margin_account: self.margin_account,
+                liquidator_permit: derive_margin_permit(&airspace, &self.pool),
+                airspace,
+                margin_account: *margin_account,
+                liquidation: derive_liquidation(*margin_account, self.pool),
+                repay_mint: self.usdc.address,
+                repay_source: self.usdc.associated_token_address(&self.margin_account),
+                repay_destination: self.usdc.associated_token_address(margin_account),
+                repay_token_program: self.usdc.token_program(),
+                margin_pool: usdc_pool.address,
+                margin_pool_adapter_config: derive_adapter_config(&airspace, &glow_margin_pool::ID),
+                loan_note_mint: usdc_pool.loan_note_mint,
+                vault: usdc_pool.vault,
+                loan_account,
+                loan_token_program: usdc_pool.pool_loan_mint_info().token_program(),
+                seize_mint: self.tsol_notes.address,
+                seize_token_config: derive_token_config(&airspace, &self.tsol_notes.address),
+                seize_source: derive_position_token_account(
+                    margin_account,
+                    &self.tsol_notes.address,
+                ),
+                seize_destination: derive_position_token_account(
+                    &self.margin_account,
+                    &self.tsol_notes.address,
+                ),
+                seize_token_program: self.tsol_notes.token_program(),
+                margin_pool_program: glow_margin_pool::ID,
+                margin_program: glow_margin::ID,
+                system_program: system_program::ID,
+            }
+            .to_account_metas(None),
+            data: glow_backstop::instruction::Liquidate { repay_amount }.data(),
+        }
+    }
+
+    async fn liquidate(&self, scen: &Scenario1, loan_owner: &Pubkey) -> Result<()> {
+        let loan_account = derive_loan_account(
+            loan_owner,
+            &MarginPoolIxBuilder::new(self.ctx.airspace_details.address, self.usdc).loan_note_mint,
+        );
+        self.liquidate_ix(scen.user_b.address(), loan_account, REPAY_AMOUNT)
+            .with_signer(self.ctx.payer())
+            .send_and_confirm(&self.ctx.rpc())
+            .await?;
+
+        Ok(())
+    }
+
+    async fn state(&self) -> Result<BackstopPool> {
+        Ok(get_anchor_account(&self.ctx.rpc(), &self.pool).await?)
+    }
+
+    async fn deposit_state(&self, depositor: &Pubkey) -> Result<BackstopDeposit> {
+        Ok(get_anchor_account(&self.ctx.rpc(), &self.deposit_address(depositor)).await?)
+    }
+}
+
+/// Set up a backstop pool with a single depositor, and user B of scenario 1 ready to
+/// be liquidated by it
+async fn setup(
+    ctx: &Arc<MarginTestContext>,
+    scen: &Scenario1,
+) -> Result<(Backstop, Keypair, Pubkey)> {
+    let backstop = Backstop::create(ctx, scen).await?;
+
+    let depositor = ctx.solana.create_wallet(10).await?;
+    let source = ctx
+        .tokens()
+        .create_account_funded(scen.usdc, &depositor.pubkey(), DEPOSIT_AMOUNT)
+        .await?;
+    backstop.deposit(&depositor, source, DEPOSIT_AMOUNT).await?;
+
+    // The repayment is moved into a deposit of the liquidated account
+    scen.user_b.create_deposit_position(scen.usdc).await?;
+    scen.user_b.refresh_positions().await?;
+
+    Ok((backstop, depositor, source))
+}
+
+/// Depositors fund a liquidation, share the seized collateral and withdraw what remains
+#[tokio::test(flavor = "multi_thread")]
+#[cfg_attr(not(feature = "localnet"), serial_test::serial)]
+async fn backstop_deposit_liquidate_claim_withdraw() -> Result<()> {
+    let (ctx, scen) = scenario1!()?;
+    let (backstop, depositor, source) = setup(&ctx, &scen).await?;
+
+    assert_eq!(DEPOSIT_AMOUNT, backstop.state().await?.total_deposits);
+    assert_eq!(0, ctx.tokens().get_balance(&source).await?);
+
+    // The pool repays the debt of user B, and receives its collateral
+    backstop.liquidate(&scen, scen.user_b.address()).await?;
+
+    let pool = backstop.state().await?;
+    assert_eq!(DEPOSIT_AMOUNT - REPAY_AMOUNT, pool.total_deposits);
+    let seized = ctx
+        .tokens()
+        .get_balance(&derive_position_token_account(
+            &backstop.margin_account,
+            &backstop.tsol_notes.address,
+        ))
+        .await?;
+    assert!(seized > 0);
+
+    // The only depositor receives all of the seized collateral
+    let gains = ctx
+        .tokens()
+        .create_account(backstop.tsol_notes, &depositor.pubkey())
+        .await?;
+    backstop.claim_gains(&depositor, gains).await?;
+    let claimed = ctx.tokens().get_balance(&gains).await?;
+    assert!(claimed <= seized && seized - claimed <= 1);
+
+    // The deposit was reduced by the repaid debt
+    let deposit = backstop.deposit_state(&depositor.pubkey()).await?;
+    assert_eq!(DEPOSIT_AMOUNT - REPAY_AMOUNT, deposit.amount);
+
+    let result = backstop
+        .withdraw(&depositor, source, DEPOSIT_AMOUNT - REPAY_AMOUNT + 1)
+        .await;
+    assert_custom_program_error(glow_backstop::ErrorCode::InsufficientDeposit, result);
+
+    backstop
+        .withdraw(&depositor, source, DEPOSIT_AMOUNT - REPAY_AMOUNT)
+        .await?;
+    assert_eq!(
+        DEPOSIT_AMOUNT - REPAY_AMOUNT,
+        ctx.tokens().get_balance(&source).await?
+    );
+    assert_eq!(0, backstop.state().await?.total_deposits);
+
+    Ok(())
+}
+
+/// The deposits only repay the loan of the liquidated account
+#[tokio::test(flavor = "multi_thread")]
+#[cfg_attr(not(feature = "localnet"), serial_test::serial)]
+async fn backstop_only_repays_the_liquidated_loan() -> Result<()> {
+    let (ctx, scen) = scenario1!()?;
+    let (backstop, _, _) = setup(&ctx, &scen).await?;
+
+    let result = backstop.liquidate(&scen, scen.user_a.address()).await;
+    assert_custom_program_error(anchor_lang::error::ErrorCode::ConstraintSeeds, result);
+
+    assert_eq!(DEPOSIT_AMOUNT, backstop.state().await?.total_deposits);
+
+    Ok(())
+}
@@ -98,6 +98,7 @@ async fn test_configure_token_happy_path() -> Result<(), anyhow::Error> {
         value_modifier: 95,
         max_staleness: 30,
         token_features: TokenFeatures::USD_STABLECOIN,
+        oracle_limits: Default::default(),
     };
 
     config_ix
@@ -139,6 +140,7 @@ async fn test_configure_token_update() -> Result<(), anyhow::Error> {
         value_modifier: 90,
         max_staleness: 20,
         token_features: TokenFeatures::SOL_BASED,
+        oracle_limits: Default::default(),
     };
 
     config_ix
@@ -164,6 +166,7 @@ async fn test_configure_token_update() -> Result<(), anyhow::Error> {
         value_modifier: 85,
         max_staleness: 25,
         token_features: TokenFeatures::SOL_BASED | TokenFeatures::RESTRICTED,
+        oracle_limits: Default::default(),
     };
 
     config_ix
@@ -204,6 +207,7 @@ async fn test_configure_token_validation_errors() -> Result<(), anyhow::Error> {
         value_modifier: 100,
         max_staleness: MAX_TOKEN_STALENESS + 1, // Exceeds max
         token_features: TokenFeatures::empty(),
+        oracle_limits: Default::default(),
     };
 
     let result = config_ix
@@ -225,6 +229,7 @@ async fn test_configure_token_validation_errors() -> Result<(), anyhow::Error> {
         value_modifier: MAX_COLLATERAL_VALUE_MODIFIER + 1, // Exceeds max
         max_staleness: 30,
         token_features: TokenFeatures::empty(),
+        oracle_limits: Default::default(),
     };
 
     let result = config_ix
@@ -247,6 +252,7 @@ async fn test_configure_token_validation_errors() -> Result<(), anyhow::Error> {
         value_modifier: MAX_CLAIM_VALUE_MODIFIER + 1, // Exceeds max
         max_staleness: 30,
         token_features: TokenFeatures::empty(),
+        oracle_limits: Default::default(),
     };
 
     let result = config_ix
@@ -271,6 +277,7 @@ async fn test_configure_token_validation_errors() -> Result<(), anyhow::Error> {
         value_modifier: 100,
         max_staleness: 30,
         token_features: TokenFeatures::RESTRICTED, // Only RESTRICTED, no other features
+        oracle_limits: Default::default(),
     };
 
     let result = config_ix
@@ -292,6 +299,7 @@ async fn test_configure_token_validation_errors() -> Result<(), anyhow::Error> {
         value_modifier: 100,
         max_staleness: 30,
         token_features: TokenFeatures::USD_STABLECOIN | TokenFeatures::SOL_BASED, // Multiple non-RESTRICTED features
+        oracle_limits: Default::default(),
     };
 
     let result = config_ix
@@ -323,6 +331,7 @@ async fn test_configure_token_immutability() -> Result<(), anyhow::Error> {
         value_modifier: 95,
         max_staleness: 30,
         token_features: TokenFeatures::USD_STABLECOIN,
+        oracle_limits: Default::default(),
     };
 
     config_ix
@@ -420,6 +429,7 @@ async fn test_configure_token_feature_immutability() -> Result<(), anyhow::Error
         value_modifier: 100,
         max_staleness: 30,
         token_features: TokenFeatures::USD_STABLECOIN | TokenFeatures::RESTRICTED,
+        oracle_limits: Default::default(),
     };
 
     config_ix
@@ -476,6 +486,7 @@ async fn test_configure_token_boundary_values() -> Result<(), anyhow::Error> {
         value_modifier: MAX_COLLATERAL_VALUE_MODIFIER,
         max_staleness: 30,
         token_features: TokenFeatures::empty(),
+        oracle_limits: Default::default(),
     };
 
     config_ix
@@ -492,6 +503,7 @@ async fn test_configure_token_boundary_values() -> Result<(), anyhow::Error> {
         value_modifier: MAX_CLAIM_VALUE_MODIFIER,
         max_staleness: 30,
         token_features: TokenFeatures::empty(),
+        oracle_limits: Default::default(),
     };
 
     config_ix
@@ -528,6 +540,7 @@ async fn test_configure_token_boundary_values() -> Result<(), anyhow::Error> {
         value_modifier: 100,
         max_staleness: MAX_TOKEN_STALENESS, // Exactly at limit
         token_features: TokenFeatures::empty(),
+        oracle_limits: Default::default(),
     };
 
     config_ix
@@ -0,0 +1,333 @@
+use anyhow::Result;
+
+use glow_instructions::margin::{close_credit_line, derive_credit_line, open_credit_line};
+use glow_margin::{AccountFeatureFlags, CreditLine, ErrorCode, TokenKind, CREDIT_LINE_DECIMALS};
+use glow_margin_sdk::{
+    get_state::get_anchor_account,
+    solana::transaction::{TransactionBuilderExt, WithSigner},
+};
+use glow_simulation::assert_custom_program_error;
+use hosted_tests::{
+    context::MarginTestContext,
+    margin::MarginUser,
+    margin_test_context, scenario1,
+    scenario_setup::{scenario1_with_ctx, Scenario1, ONE_USDC},
+};
+use solana_sdk::{pubkey::Pubkey, signature::Signer};
+
+/// The USDC deposited into the lender's margin account
+const LENDER_DEPOSIT: u64 = 1_000_000 * ONE_USDC;
+
+/// A limit of 10,000 in the numeraire of the accounts
+const LIMIT: u64 = 10_000 * 10u64.pow(CREDIT_LINE_DECIMALS as u32);
+
+/// The USDC seized from the lender, worth 1,000 USD
+const SEIZE_AMOUNT: u64 = 1_000 * ONE_USDC;
+
+/// A new margin account with a USDC deposit, lending to the users of scenario 1
+async fn create_lender(ctx: &MarginTestContext, scen: &Scenario1) -> Result<MarginUser> {
+    let wallet = ctx.solana.create_wallet(10).await?;
+    ctx.issue_permit(wallet.pubkey()).await?;
+    let lender = ctx
+        .margin_client()
+        .user(&wallet, 0, glow_client::NetworkKind::Localnet)
+        .created(AccountFeatureFlags::default())
+        .await?;
+
+    let wallet_usdc = ctx
+        .tokens()
+        .create_account_funded(scen.usdc, &wallet.pubkey(), LENDER_DEPOSIT)
+        .await?;
+    lender
+        .transfer_deposit(
+            scen.usdc,
+            &wallet.pubkey(),
+            &wallet_usdc,
+            &scen.usdc.associated_token_address(lender.address()),
+            LENDER_DEPOSIT,
+        )
+        .await?;
+    lender.refresh_positions().await?;
+
+    Ok(lender)
+}
+
+/// Open a line from the lender to the borrower, with the lender paying the rent
+async fn open(
+    ctx: &MarginTestContext,
+    lender: &MarginUser,
+    borrower: &MarginUser,
+    limit: u64,
+) -> Result<()> {
+    open_credit_line(
+        *lender.owner(),
+        *borrower.owner(),
+        *lender.owner(),
+        *lender.address(),
+        *borrower.address(),
+        limit,
+    )
+    .with_signers([&lender.signer, &borrower.signer])
+    .send_and_confirm(&ctx.rpc())
+    .await?;
+
+    Ok(())
+}
+
+async fn get_line(
+    ctx: &MarginTestContext,
+    lender: &MarginUser,
+    borrower: &MarginUser,
+) -> Result<CreditLine> {
+    get_anchor_account(
+        &ctx.rpc(),
+        &derive_credit_line(lender.address(), borrower.address()),
+    )
+    .await
+}
+
+/// The balance and kind of the position of a line in a margin account
+async fn line_position(
+    user: &MarginUser,
+    credit_line: &Pubkey,
+) -> Result<Option<(u64, TokenKind)>> {
+    Ok(user
+        .tx
+        .get_account_state()
+        .await?
+        .get_position(credit_line)
+        .map(|p| (p.balance, p.kind())))
+}
+
+async fn usdc_balance(user: &MarginUser, scen: &Scenario1) -> Result<u64> {
+    Ok(user
+        .tx
+        .get_account_state()
+        .await?
+        .get_position(&scen.usdc.address)
+        .map(|p| p.balance)
+        .unwrap_or_default())
+}
+
+/// The value of the collateral the borrower lacks to meet its required collateral
+async fn shortfall(ctx: &MarginTestContext, borrower: &MarginUser) -> Result<f64> {
+    let clock = ctx.rpc().get_clock().await?;
+    let valuation = borrower
+        .tx
+        .get_account_state()
+        .await?
+        .valuation(clock.unix_timestamp as u64)?;
+
+    Ok((valuation.required_collateral - valuation.effective_collateral).as_f64())
+}
+
+async fn lamports(ctx: &MarginTestContext, address: &Pubkey) -> Result<u64> {
+    Ok(ctx
+        .rpc()
+        .get_account(address)
+        .await?
+        .map(|account| account.lamports)
+        .unwrap_or_default())
+}
+
+/// Opening a line adds its limit as collateral of the borrower and as a claim of the lender
+#[tokio::test(flavor = "multi_thread")]
+#[cfg_attr(not(feature = "localnet"), serial_test::serial)]
+async fn open_credit_line_backs_the_borrower() -> Result<()> {
+    let (ctx, scen) = scenario1!()?;
+    let lender = create_lender(&ctx, &scen).await?;
+    let borrower = &scen.user_a;
+    let address = derive_credit_line(lender.address(), borrower.address());
+
+    open(&ctx, &lender, borrower, LIMIT).await?;
+
+    let line = get_line(&ctx, &lender, borrower).await?;
+    assert_eq!(*lender.address(), line.lender);
+    assert_eq!(*borrower.address(), line.borrower);
+    assert_eq!(LIMIT, line.limit);
+    assert_eq!(0, line.seized);
+    assert_eq!(*lender.owner(), line.payer);
+
+    assert_eq!(
+        Some((LIMIT, TokenKind::AdapterCollateral)),
+        line_position(borrower, &address).await?
+    );
+    assert_eq!(
+        Some((LIMIT, TokenKind::Claim)),
+        line_position(&lender, &address).await?
+    );
+
+    // A line between the same accounts cannot be opened twice
+    assert!(open(&ctx, &lender, borrower, LIMIT).await.is_err());
+
+    Ok(())
+}
+
+/// The borrower of a line cannot lend through another line, and its lender cannot borrow
+/// through another, so no line is backed by the credit of another line
+#[tokio::test(flavor = "multi_thread")]
+#[cfg_attr(not(feature = "localnet"), serial_test::serial)]
+async fn credit_lines_cannot_be_relent() -> Result<()> {
+    let (ctx, scen) = scenario1!()?;
+    let lender = create_lender(&ctx, &scen).await?;
+    let other = create_lender(&ctx, &scen).await?;
+    let borrower = &scen.user_a;
+
+    open(&ctx, &lender, borrower, LIMIT).await?;
+
+    let result = open(&ctx, borrower, &other, LIMIT).await;
+    assert_custom_program_error(ErrorCode::CreditLineRelending, result);
+    let result = open(&ctx, &other, &lender, LIMIT).await;
+    assert_custom_program_error(ErrorCode::CreditLineRelending, result);
+
+    Ok(())
+}
+
+/// A liquidator of the borrower seizes collateral from the lender, within the limit of the line
+#[tokio::test(flavor = "multi_thread")]
+#[cfg_attr(not(feature = "localnet"), serial_test::serial)]
+async fn liquidator_seizes_credit_from_the_lender() -> Result<()> {
+    let (ctx, scen) = scenario1!()?;
+    let lender = create_lender(&ctx, &scen).await?;
+    let borrower = &scen.user_b;
+    let address = derive_credit_line(lender.address(), borrower.address());
+
+    // The line is too small to keep the borrower from being liquidated
+    open(&ctx, &lender, borrower, LIMIT).await?;
+    borrower.create_deposit_position(scen.usdc).await?;
+    lender.refresh_positions().await?;
+    let liquidation = scen.liquidator.begin(borrower, true).await?;
+
+    liquidation
+        .user
+        .tx
+        .ix
+        .liquidator_seize_credit(*lender.address(), scen.usdc, SEIZE_AMOUNT)
+        .with_signer(&scen.liquidator.wallet)
+        .send_and_confirm(&ctx.rpc())
+        .await?;
+
+    // The collateral moved from the lender to the borrower
+    assert_eq!(
+        LENDER_DEPOSIT - SEIZE_AMOUNT,
+        usdc_balance(&lender, &scen).await?
+    );
+    assert_eq!(SEIZE_AMOUNT, usdc_balance(borrower, &scen).await?);
+
+    // The line was reduced by the value seized, at the USDC price of 1 USD
+    let line = get_line(&ctx, &lender, borrower).await?;
+    assert_eq!(LIMIT, line.limit + line.seized);
+    let seized_value = line.seized as f64 / 10f64.powi(CREDIT_LINE_DECIMALS as i32);
+    assert!(
+        (seized_value - 1_000.0).abs() < 10.0,
+        "seized {seized_value} USD of credit"
+    );
+    assert_eq!(
+        Some((line.limit, TokenKind::AdapterCollateral)),
+        line_position(borrower, &address).await?
+    );
+    assert_eq!(
+        Some((line.limit, TokenKind::Claim)),
+        line_position(&lender, &address).await?
+    );
+
+    // Nothing can be seized beyond the remaining limit
+    let result = liquidation
+        .user
+        .tx
+        .ix
+        .liquidator_seize_credit(*lender.address(), scen.usdc, 2 * LIMIT)
+        .with_signer(&scen.liquidator.wallet)
+        .send_and_confirm(&ctx.rpc())
+        .await;
+    assert_custom_program_error(ErrorCode::CreditLineLimitExceeded, result);
+
+    Ok(())
+}
+
+/// A liquidator cannot seize more from the lender than the collateral the borrower lacks,
+/// even within the limit of the line
+#[tokio::test(flavor = "multi_thread")]
+#[cfg_attr(not(feature = "localnet"), serial_test::serial)]
+async fn liquidator_cannot_seize_beyond_the_shortfall() -> Result<()> {
+    let (ctx, scen) = scenario1!()?;
+    let lender = create_lender(&ctx, &scen).await?;
+    let borrower = &scen.user_b;
+
+    // The line covers most of the shortfall, which leaves less to seize than its limit
+    let limit_value = (shortfall(&ctx, borrower).await? * 0.75) as u64;
+    let limit = limit_value * 10u64.pow(CREDIT_LINE_DECIMALS as u32);
+    open(&ctx, &lender, borrower, limit).await?;
+    borrower.create_deposit_position(scen.usdc).await?;
+    lender.refresh_positions().await?;
+    let remaining = shortfall(&ctx, borrower).await? as u64;
+    assert!(0 < remaining && remaining < limit_value);
+    let liquidation = scen.liquidator.begin(borrower, true).await?;
+
+    let result = liquidation
+        .user
+        .tx
+        .ix
+        .liquidator_seize_credit(*lender.address(), scen.usdc, (remaining + 1) * ONE_USDC)
+        .with_signer(&scen.liquidator.wallet)
+        .send_and_confirm(&ctx.rpc())
+        .await;
+    assert_custom_program_error(ErrorCode::CreditLineSeizureExceedsShortfall, result);
+    assert_eq!(LENDER_DEPOSIT, usdc_balance(&lender, &scen).await?);
+
+    // The shortfall itself can be seized
+    liquidation
+        .user
+        .tx
+        .ix
+        .liquidator_seize_credit(*lender.address(), scen.usdc, remaining * ONE_USDC)
+        .with_signer(&scen.liquidator.wallet)
+        .send_and_confirm(&ctx.rpc())
+        .await?;
+    assert_eq!(remaining * ONE_USDC, usdc_balance(borrower, &scen).await?);
+
+    Ok(())
+}
+
+/// Either owner can close the line, which returns the rent to the account that paid it
+#[tokio::test(flavor = "multi_thread")]
+#[cfg_attr(not(feature = "localnet"), serial_test::serial)]
+async fn close_credit_line_returns_rent_to_the_payer() -> Result<()> {
+    let (ctx, scen) = scenario1!()?;
+    let lender = create_lender(&ctx, &scen).await?;
+    let borrower = &scen.user_a;
+    let address = derive_credit_line(lender.address(), borrower.address());
+
+    open(&ctx, &lender, borrower, LIMIT).await?;
+    let rent = lamports(&ctx, &address).await?;
+    let payer_before = lamports(&ctx, lender.owner()).await?;
+
+    // The borrower cannot take the rent paid by the lender
+    let result = close_credit_line(
+        *borrower.owner(),
+        *borrower.owner(),
+        *lender.address(),
+        *borrower.address(),
+    )
+    .with_signer(&borrower.signer)
+    .send_and_confirm(&ctx.rpc())
+    .await;
+    assert_custom_program_error(ErrorCode::InvalidCreditLine, result);
+
+    close_credit_line(
+        *borrower.owner(),
+        *lender.owner(),
+        *lender.address(),
+        *borrower.address(),
+    )
+    .with_signer(&borrower.signer)
+    .send_and_confirm(&ctx.rpc())
+    .await?;
+
+    assert!(ctx.rpc().get_account(&address).await?.is_none());
+    assert_eq!(payer_before + rent, lamports(&ctx, lender.owner()).await?);
+    assert_eq!(None, line_position(borrower, &address).await?);
+    assert_eq!(None, line_position(&lender, &address).await?);
+
+    Ok(())
+}
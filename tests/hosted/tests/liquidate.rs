@@ -1,6 +1,10 @@
 use anyhow::Result;
 
 use glow_margin::{ErrorCode, TokenKind};
+use glow_margin_sdk::{
+    solana::transaction::{TransactionBuilderExt, WithSigner},
+    tx_builder::LiquidatorPermission,
+};
 use glow_program_common::{oracle::pyth_feed_ids::*, token_change::TokenChange};
 use glow_simulation::assert_custom_program_error;
 use hosted_tests::{
@@ -260,7 +264,9 @@ async fn no_one_else_can_liquidate_after_liquidate_begin() -> Result<()> {
     // Should fail to begin liquidation
     assert_custom_program_error(
         ErrorCode::Liquidating,
-        user_b_rliq.liquidate_begin(true).await,
+        user_b_rliq
+            .liquidate_begin(true, LiquidatorPermission::Permit)
+            .await,
     );
 
     let (ctx, scen2) = scenario2!().unwrap();
@@ -281,7 +287,9 @@ async fn no_one_else_can_liquidate_after_liquidate_begin() -> Result<()> {
     // Should fail to begin liquidation
     assert_custom_program_error(
         ErrorCode::Liquidating,
-        user_b_rliq2.liquidate_begin(true).await,
+        user_b_rliq2
+            .liquidate_begin(true, LiquidatorPermission::Permit)
+            .await,
     );
 
     let (ctx, scen3) = scenario3!().unwrap();
@@ -302,7 +310,9 @@ async fn no_one_else_can_liquidate_after_liquidate_begin() -> Result<()> {
     // Should fail to begin liquidation
     assert_custom_program_error(
         ErrorCode::Liquidating,
-        user_b_rliq3.liquidate_begin(true).await,
+        user_b_rliq3
+            .liquidate_begin(true, LiquidatorPermission::Permit)
+            .await,
     );
 
     Ok(())
@@ -603,3 +613,42 @@ async fn liquidator_permission_is_removable() -> Result<()> {
 
     Ok(())
 }
+
+#[tokio::test(flavor = "multi_thread")]
+#[cfg_attr(not(feature = "localnet"), serial_test::serial)]
+async fn any_signer_can_liquidate_when_airspace_is_permissionless() -> Result<()> {
+    let (ctx, scen1) = scenario1!().unwrap();
+
+    ctx.margin_client()
+        .set_liquidator_metadata(scen1.liquidator.wallet.pubkey(), false)
+        .await
+        .unwrap();
+
+    // Without a permit, the liquidator is rejected while the airspace requires permits
+    let result = scen1.liquidator.begin(&scen1.user_b, false).await;
+
+    assert_custom_program_error(anchor_lang::error::ErrorCode::AccountNotInitialized, result);
+
+    let result = scen1
+        .liquidator
+        .begin_with_permission(&scen1.user_b, false, LiquidatorPermission::Permissionless)
+        .await;
+
+    assert_custom_program_error(ErrorCode::UnauthorizedLiquidator, result);
+
+    ctx.airspace_ix()
+        .set_liquidation_mode(true)
+        .with_signer(&ctx.airspace_authority)
+        .send_and_confirm(&ctx.rpc())
+        .await?;
+
+    let liq = scen1
+        .liquidator
+        .begin_with_permission(&scen1.user_b, false, LiquidatorPermission::Permissionless)
+        .await
+        .unwrap();
+    liq.verify_unhealthy().await.unwrap();
+    liq.liquidate_end(None).await.unwrap();
+
+    Ok(())
+}
@@ -10,7 +10,7 @@ use glow_margin_sdk::{
     ix_builder::MarginPoolIxBuilder,
     solana::transaction::{TransactionBuilderExt, WithSigner},
     tokens::TokenPrice,
-    tx_builder::{MarginActionAuthority, TokenDepositsConfig},
+    tx_builder::{LiquidatorPermission, MarginActionAuthority, TokenDepositsConfig},
 };
 use glow_program_common::{oracle::TokenPriceOracle, token_change::TokenChange};
 use glow_simulation::assert_custom_program_error;
@@ -465,7 +465,9 @@ async fn test_swap_low_slippage_with_fees() -> Result<(), anyhow::Error> {
         glow_client::NetworkKind::Localnet,
     )?;
 
-    user_a_liquidation.liquidate_begin(true).await?;
+    user_a_liquidation
+        .liquidate_begin(true, LiquidatorPermission::Permit)
+        .await?;
 
     // At this point the valuation of the account is:
     // - Equity                   4'000
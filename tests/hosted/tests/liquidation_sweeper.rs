@@ -0,0 +1,125 @@
+use std::sync::Arc;
+
+use anyhow::Result;
+
+use glow_instructions::margin::{derive_liquidation, end_timed_out_liquidations};
+use glow_margin::{LIQUIDATION_TIMEOUT, LIQUIDATION_TIMEOUT_KEEPER_BOUNTY};
+use glow_margin_sdk::{
+    liquidation_sweeper::LiquidationSweeper,
+    solana::transaction::{TransactionBuilderExt, WithSigner},
+};
+use glow_simulation::solana_rpc_api::SolanaRpcClient;
+use hosted_tests::{
+    context::MarginTestContext,
+    margin_test_context, scenario1,
+    scenario_setup::{scenario1_with_ctx, Scenario1},
+};
+use solana_sdk::{pubkey::Pubkey, signature::Signer};
+
+async fn lamports(ctx: &MarginTestContext, address: &Pubkey) -> Result<u64> {
+    Ok(ctx
+        .rpc()
+        .get_account(address)
+        .await?
+        .map(|account| account.lamports)
+        .unwrap_or_default())
+}
+
+async fn pass_timeout(ctx: &MarginTestContext) -> Result<()> {
+    let mut clock = ctx.rpc().get_clock().await?;
+    clock.unix_timestamp += LIQUIDATION_TIMEOUT + 1;
+    ctx.rpc().set_clock(clock).await?;
+
+    Ok(())
+}
+
+/// The liquidation of user B by the liquidator of scenario 1
+fn liquidation_of_user_b(scen: &Scenario1) -> Pubkey {
+    derive_liquidation(*scen.user_b.address(), scen.liquidator.wallet.pubkey())
+}
+
+/// A keeper ends the timed out liquidation, receiving the bounty while the rest of
+/// the rent is returned to the liquidator
+#[tokio::test(flavor = "multi_thread")]
+#[cfg_attr(not(feature = "localnet"), serial_test::serial)]
+async fn sweeper_ends_timed_out_liquidations_for_a_bounty() -> Result<()> {
+    let (ctx, scen) = scenario1!()?;
+    let keeper = ctx.solana.create_wallet(10).await?;
+    let keeper_address = keeper.pubkey();
+    let sweeper = LiquidationSweeper::new(Arc::from(ctx.rpc().clone_with_payer(keeper)));
+    assert_eq!(keeper_address, sweeper.keeper());
+
+    scen.liquidator.begin(&scen.user_b, false).await?;
+    let liquidation = liquidation_of_user_b(&scen);
+
+    // The liquidator can still act before the timeout
+    assert!(sweeper.find_stale_liquidations().await?.is_empty());
+
+    pass_timeout(&ctx).await?;
+
+    let stale = sweeper.find_stale_liquidations().await?;
+    assert_eq!(1, stale.len());
+    assert_eq!(liquidation, stale[0].address);
+    assert_eq!(*scen.user_b.address(), stale[0].margin_account);
+    assert_eq!(scen.liquidator.wallet.pubkey(), stale[0].liquidator);
+
+    let rent = lamports(&ctx, &liquidation).await?;
+    let liquidator_before = lamports(&ctx, &scen.liquidator.wallet.pubkey()).await?;
+    let keeper_before = lamports(&ctx, &keeper_address).await?;
+
+    assert_eq!(1, sweeper.sweep().await?.len());
+
+    assert!(ctx.rpc().get_account(&liquidation).await?.is_none());
+    assert_eq!(
+        Pubkey::default(),
+        scen.user_b.tx.get_account_state().await?.liquidator
+    );
+    assert_eq!(
+        liquidator_before + rent - LIQUIDATION_TIMEOUT_KEEPER_BOUNTY,
+        lamports(&ctx, &scen.liquidator.wallet.pubkey()).await?
+    );
+    // The bounty is worth more than the fee of the transaction
+    assert!(lamports(&ctx, &keeper_address).await? > keeper_before);
+
+    // Nothing is left to sweep
+    assert!(sweeper.find_stale_liquidations().await?.is_empty());
+
+    Ok(())
+}
+
+/// Liquidations that ended or have not timed out are skipped without failing the batch
+#[tokio::test(flavor = "multi_thread")]
+#[cfg_attr(not(feature = "localnet"), serial_test::serial)]
+async fn sweeping_skips_liquidations_that_are_not_timed_out() -> Result<()> {
+    let (ctx, scen) = scenario1!()?;
+    let keeper = ctx.solana.create_wallet(10).await?;
+    let liquidator = scen.liquidator.wallet.pubkey();
+    let liquidation = liquidation_of_user_b(&scen);
+    let crank = || {
+        end_timed_out_liquidations(keeper.pubkey(), &[(*scen.user_b.address(), liquidator)])
+            .with_signer(&keeper)
+    };
+
+    scen.liquidator.begin(&scen.user_b, false).await?;
+
+    // The liquidator is still active
+    crank().send_and_confirm(&ctx.rpc()).await?;
+    assert!(ctx.rpc().get_account(&liquidation).await?.is_some());
+    assert_eq!(
+        liquidator,
+        scen.user_b.tx.get_account_state().await?.liquidator
+    );
+
+    pass_timeout(&ctx).await?;
+    crank().send_and_confirm(&ctx.rpc()).await?;
+    assert!(ctx.rpc().get_account(&liquidation).await?.is_none());
+
+    // The liquidation has already ended
+    crank().send_and_confirm(&ctx.rpc()).await?;
+    assert_eq!(
+        Pubkey::default(),
+        scen.user_b.tx.get_account_state().await?.liquidator
+    );
+
+    Ok(())
+}
@@ -0,0 +1,276 @@
+use std::{collections::HashMap, sync::Arc};
+
+use anyhow::Result;
+
+use glow_instructions::{
+    margin::derive_position_token_account, margin_pool::derive_loan_account, MintInfo,
+};
+use glow_margin::{AccountFeatureFlags, ErrorCode, TokenRiskUpdate};
+use glow_margin_sdk::{
+    ix_builder::MarginPoolIxBuilder,
+    solana::transaction::{TransactionBuilderExt, WithSigner},
+    tokens::TokenPrice,
+};
+use glow_program_common::token_change::TokenChange;
+use glow_simulation::assert_custom_program_error;
+use hosted_tests::{
+    context::MarginTestContext,
+    margin_test_context, scenario1,
+    scenario_setup::{scenario1_with_ctx, Scenario1, ONE_TSOL, ONE_USDC},
+    setup_helper::borrow_and_dispatch,
+    test_user::TestUser,
+};
+use solana_sdk::{instruction::Instruction, signature::Signer};
+
+/// The USDC deposited into the liquidator's own margin account
+const LIQUIDATOR_DEPOSIT: u64 = 100_000 * ONE_USDC;
+
+/// The USDC debt of user B that the liquidator repays
+const REPAY_AMOUNT: u64 = 1_000 * ONE_USDC;
+
+/// A liquidator with its own margin account, liquidating user B of scenario 1
+struct SeizeSetup {
+    ctx: Arc<MarginTestContext>,
+    liquidator_account: TestUser,
+    liquidation: TestUser,
+    tsol_notes: MintInfo,
+}
+
+impl SeizeSetup {
+    async fn new(ctx: &Arc<MarginTestContext>, scen: &Scenario1) -> Result<Self> {
+        let wallet = &scen.liquidator.wallet;
+        ctx.issue_permit(wallet.pubkey()).await?;
+        let user = ctx
+            .margin_client()
+            .user(wallet, 0, glow_client::NetworkKind::Localnet)
+            .created(AccountFeatureFlags::default())
+            .await?;
+
+        // Fund the liquidator's account with a deposit of the token being repaid
+        let wallet_usdc = ctx
+            .tokens()
+            .create_account_funded(scen.usdc, &wallet.pubkey(), LIQUIDATOR_DEPOSIT)
+            .await?;
+        user.transfer_deposit(
+            scen.usdc,
+            &wallet.pubkey(),
+            &wallet_usdc,
+            &scen.usdc.associated_token_address(user.address()),
+            LIQUIDATOR_DEPOSIT,
+        )
+        .await?;
+
+        // The seized deposit notes need a position in the liquidator's account
+        let tsol_notes = MarginPoolIxBuilder::new(ctx.airspace_details.address, scen.tsol)
+            .pool_deposit_mint_info();
+        user.tx
+            .ix
+            .register_position(tsol_notes)
+            .with_signer(wallet)
+            .send_and_confirm(&ctx.rpc())
+            .await?;
+
+        // The repayment is moved into a deposit of the liquidated account
+        scen.user_b.create_deposit_position(scen.usdc).await?;
+
+        let liquidation = scen.liquidator.begin(&scen.user_b, true).await?;
+        let liquidator_account = TestUser {
+            ctx: ctx.clone(),
+            user,
+            mint_to_token_account: HashMap::from([(scen.usdc, wallet_usdc)]),
+        };
+        liquidator_account.user.refresh_positions().await?;
+
+        Ok(Self {
+            ctx: ctx.clone(),
+            liquidator_account,
+            liquidation,
+            tsol_notes,
+        })
+    }
+
+    /// Repay the USDC debt of user B from the liquidator's account, seizing its TSOL notes
+    fn seize_ix(&self, scen: &Scenario1, repay_amount: u64) -> Instruction {
+        let user_b = scen.user_b.address();
+        let usdc_pool = MarginPoolIxBuilder::new(self.ctx.airspace_details.address, scen.usdc);
+        let repay_ix = usdc_pool.repay(
+            *user_b,
+            scen.usdc.associated_token_address(user_b),
+            derive_loan_account(user_b, &usdc_pool.loan_note_mint),
+            TokenChange::shift(repay_amount),
+        );
+
+        self.liquidation.user.tx.ix.liquidator_seize(
+            *self.liquidator_account.user.address(),
+            scen.usdc,
+            repay_amount,
+            self.tsol_notes,
+            derive_position_token_account(user_b, &self.tsol_notes.address),
+            derive_position_token_account(
+                self.liquidator_account.user.address(),
+                &self.tsol_notes.address,
+            ),
+            &[repay_ix],
+        )
+    }
+
+    async fn seize(&self, scen: &Scenario1, repay_amount: u64) -> Result<()> {
+        self.seize_ix(scen, repay_amount)
+            .with_signer(&scen.liquidator.wallet)
+            .send_and_confirm(&self.ctx.rpc())
+            .await?;
+
+        Ok(())
+    }
+
+    async fn configure_bonus(&self, mint: MintInfo, liquidation_bonus_bps: u16) -> Result<()> {
+        self.ctx
+            .margin_config_ix()
+            .configure_token_risk(
+                mint.address,
+                TokenRiskUpdate {
+                    liquidation_bonus_bps: Some(liquidation_bonus_bps),
+                    ..Default::default()
+                },
+            )
+            .with_signer(&self.ctx.airspace_authority)
+            .send_and_confirm(&self.ctx.rpc())
+            .await?;
+
+        Ok(())
+    }
+}
+
+/// The balance of a position of a margin account, or zero if it has no such position
+async fn balance(user: &TestUser, mint: &MintInfo) -> Result<u64> {
+    Ok(user
+        .user
+        .tx
+        .get_account_state()
+        .await?
+        .get_position(&mint.address)
+        .map(|p| p.balance)
+        .unwrap_or_default())
+}
+
+/// Check that the seized notes are worth the repaid USDC plus the bonus, at the TSOL price
+/// of 80 USD. The notes are worth slightly more than a token, as the pool accrued interest.
+fn assert_seized_with_bonus(seized_notes: u64, bonus_bps: u16) {
+    let seized_value = seized_notes as f64 / ONE_TSOL as f64 * 80.0;
+    let repaid_value = (REPAY_AMOUNT / ONE_USDC) as f64;
+    let expected = repaid_value * (1.0 + bonus_bps as f64 / 10_000.0);
+
+    assert!(
+        (seized_value - expected).abs() < expected * 0.005,
+        "seized {seized_value} USD for a repayment of {repaid_value} USD"
+    );
+}
+
+/// A liquidator repays a debt from its own margin account, and receives the collateral of
+/// the liquidated account with the default bonus
+#[tokio::test(flavor = "multi_thread")]
+#[cfg_attr(not(feature = "localnet"), serial_test::serial)]
+async fn liquidator_repays_debt_and_seizes_collateral() -> Result<()> {
+    let (ctx, scen) = scenario1!()?;
+    let setup = SeizeSetup::new(&ctx, &scen).await?;
+    let usdc_loans =
+        MarginPoolIxBuilder::new(ctx.airspace_details.address, scen.usdc).pool_loan_mint_info();
+
+    let loans_before = balance(&setup.liquidation, &usdc_loans).await?;
+    let notes_before = balance(&setup.liquidation, &setup.tsol_notes).await?;
+
+    setup.seize(&scen, REPAY_AMOUNT).await?;
+
+    // The liquidator paid for the repayment of the debt
+    assert_eq!(
+        LIQUIDATOR_DEPOSIT - REPAY_AMOUNT,
+        balance(&setup.liquidator_account, &scen.usdc).await?
+    );
+    assert!(balance(&setup.liquidation, &usdc_loans).await? < loans_before);
+    assert_eq!(0, balance(&setup.liquidation, &scen.usdc).await?);
+
+    // The collateral moved into the liquidator's account, with the default bonus
+    let seized = balance(&setup.liquidator_account, &setup.tsol_notes).await?;
+    assert_eq!(
+        notes_before - seized,
+        balance(&setup.liquidation, &setup.tsol_notes).await?
+    );
+    assert_seized_with_bonus(seized, glow_margin::LIQUIDATION_SEIZURE_BONUS_BPS);
+
+    Ok(())
+}
+
+/// The liquidation bonus is configured per token, within the max equity loss of a liquidation
+#[tokio::test(flavor = "multi_thread")]
+#[cfg_attr(not(feature = "localnet"), serial_test::serial)]
+async fn liquidation_bonus_is_bounded_and_configured_per_token() -> Result<()> {
+    let (ctx, scen) = scenario1!()?;
+    let setup = SeizeSetup::new(&ctx, &scen).await?;
+
+    // The bonus is lost equity of the liquidated account
+    let result = setup
+        .configure_bonus(
+            setup.tsol_notes,
+            glow_margin::LIQUIDATION_MAX_EQUITY_LOSS_PROPORTION_BPS + 1,
+        )
+        .await;
+    assert_custom_program_error(ErrorCode::InvalidConfigLiquidationBonus, result);
+
+    // Only collateral is seized, so other tokens cannot have a bonus
+    let usdc_loans =
+        MarginPoolIxBuilder::new(ctx.airspace_details.address, scen.usdc).pool_loan_mint_info();
+    let result = setup.configure_bonus(usdc_loans, 100).await;
+    assert_custom_program_error(ErrorCode::InvalidConfigLiquidationBonus, result);
+
+    setup.configure_bonus(setup.tsol_notes, 200).await?;
+    setup.seize(&scen, REPAY_AMOUNT).await?;
+
+    let seized = balance(&setup.liquidator_account, &setup.tsol_notes).await?;
+    assert_seized_with_bonus(seized, 200);
+
+    Ok(())
+}
+
+/// The liquidator's account takes on the seized collateral, and has to remain healthy
+#[tokio::test(flavor = "multi_thread")]
+#[cfg_attr(not(feature = "localnet"), serial_test::serial)]
+async fn liquidator_account_must_remain_healthy() -> Result<()> {
+    let (ctx, scen) = scenario1!()?;
+    let setup = SeizeSetup::new(&ctx, &scen).await?;
+
+    // The liquidator's account borrows TSOL and moves it out of the account
+    borrow_and_dispatch(
+        &ctx,
+        &setup.liquidator_account,
+        scen.tsol,
+        scen.tsol_oracle,
+        1_500 * ONE_TSOL,
+    )
+    .await;
+    setup
+        .liquidator_account
+        .withdraw(scen.tsol, 1_500 * ONE_TSOL)
+        .await?;
+
+    // A rise of the TSOL price leaves its USDC deposit short of the debt
+    ctx.tokens()
+        .set_price(
+            &scen.tsol.address,
+            &TokenPrice {
+                exponent: -8,
+                price: 40_000_000_000,
+                confidence: 100_000_000,
+                twap: 40_000_000_000,
+                feed_id: *scen.tsol_oracle.pyth_feed_id().unwrap(),
+            },
+        )
+        .await?;
+    setup.liquidator_account.user.refresh_positions().await?;
+    scen.user_b.refresh_positions().await?;
+    setup.liquidator_account.verify_unhealthy().await?;
+
+    let result = setup.seize(&scen, REPAY_AMOUNT).await;
+    assert_custom_program_error(ErrorCode::Unhealthy, result);
+
+    Ok(())
+}
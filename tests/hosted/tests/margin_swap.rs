@@ -10,7 +10,7 @@ use glow_margin_sdk::{
     ix_builder::MarginPoolIxBuilder,
     solana::transaction::{TransactionBuilderExt, WithSigner},
     tokens::TokenPrice,
-    tx_builder::{MarginActionAuthority, TokenDepositsConfig},
+    tx_builder::{LiquidatorPermission, MarginActionAuthority, TokenDepositsConfig},
 };
 use glow_program_common::{oracle::TokenPriceOracle, token_change::TokenChange};
 use hosted_tests::{
@@ -1027,7 +1027,9 @@ async fn liquidator_swap() -> Result<(), anyhow::Error> {
         glow_client::NetworkKind::Localnet,
     )?;
 
-    user_a_liquidation.liquidate_begin(true).await?;
+    user_a_liquidation
+        .liquidate_begin(true, LiquidatorPermission::Permit)
+        .await?;
 
     // The liquidator will reverse the USDC:USDT swap, and take a fee for it.
     let usdt_src = env.usdt.associated_token_address(user_a.address());
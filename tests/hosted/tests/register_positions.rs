@@ -200,6 +200,7 @@ async fn test_register_positions() -> Result<(), anyhow::Error> {
             value_modifier: 100,
             max_staleness: 0,
             token_features: Default::default(),
+            oracle_limits: Default::default(),
         },
     );
     send_and_confirm(&ctx.rpc(), &[ix], &[&ctx.airspace_authority]).await?;
@@ -215,6 +216,7 @@ async fn test_register_positions() -> Result<(), anyhow::Error> {
             value_modifier: 100,
             max_staleness: 0,
             token_features: Default::default(),
+            oracle_limits: Default::default(),
         },
     );
     send_and_confirm(&ctx.rpc(), &[ix], &[&ctx.airspace_authority]).await?;
@@ -236,6 +238,7 @@ async fn test_register_positions() -> Result<(), anyhow::Error> {
                 value_modifier: 100,
                 max_staleness: 0,
                 token_features: Default::default(),
+                oracle_limits: Default::default(),
             },
         );
         let result = send_and_confirm(&ctx.rpc(), &[ix], &[&ctx.airspace_authority]).await;
@@ -294,6 +297,7 @@ async fn test_register_positions() -> Result<(), anyhow::Error> {
             value_modifier: 100,
             max_staleness: 0,
             token_features: Default::default(),
+            oracle_limits: Default::default(),
         },
     );
     send_and_confirm(&ctx.rpc(), &[ix], &[&ctx.airspace_authority]).await?;
@@ -338,6 +342,7 @@ async fn test_register_positions() -> Result<(), anyhow::Error> {
             value_modifier: 100,
             max_staleness: 0,
             token_features: Default::default(),
+            oracle_limits: Default::default(),
         },
     );
     send_and_confirm(&ctx.rpc(), &[ix], &[&ctx.airspace_authority]).await?;
@@ -0,0 +1,173 @@
+use anchor_spl::{
+    associated_token::{
+        get_associated_token_address,
+        spl_associated_token_account::instruction::create_associated_token_account,
+    },
+    token::spl_token,
+};
+use anyhow::Result;
+use rand::Rng;
+use solana_sdk::signature::Signer;
+
+use glow_margin::{TokenAdmin, TokenConfigUpdate, TokenFeatures, TokenKind};
+use glow_margin_sdk::solana::transaction::{TransactionBuilderExt, WithSigner};
+use glow_simulation::assert_custom_program_error;
+use hosted_tests::{
+    adapters::whirlpool::{TestLpConfig, TestWhirlpool},
+    margin_test_context, send_and_confirm,
+    setup_helper::setup_token,
+};
+
+/// A whirlpool position deposited through the adapter can only be withdrawn to
+/// an account of the margin account or its owner.
+#[tokio::test(flavor = "multi_thread")]
+#[cfg_attr(not(feature = "localnet"), serial_test::serial)]
+async fn withdraw_lp_position_checks_destination() -> Result<()> {
+    let ctx = margin_test_context!();
+    let rpc = ctx.rpc();
+    let (feed_a, feed_b) = {
+        let mut rng = rand::thread_rng();
+        (rng.gen(), rng.gen())
+    };
+
+    // The whirlpool requires its mints to be ordered
+    let mut tokens = [
+        setup_token(
+            &ctx,
+            6,
+            100,
+            400,
+            148.0,
+            false,
+            feed_a,
+            TokenFeatures::default(),
+        )
+        .await?,
+        setup_token(
+            &ctx,
+            6,
+            100,
+            400,
+            1.0,
+            false,
+            feed_b,
+            TokenFeatures::default(),
+        )
+        .await?,
+    ];
+    tokens.sort_by_key(|(mint, _)| mint.address);
+    let [(mint_a, oracle_a), (mint_b, oracle_b)] = tokens;
+
+    let whirlpool =
+        TestWhirlpool::create(&ctx.solana, mint_a, mint_b, 6, 6, Default::default()).await?;
+    let lp_config = TestLpConfig::create(
+        &rpc,
+        &ctx.airspace_authority,
+        ctx.airspace_details.address,
+        &whirlpool,
+        [oracle_a, oracle_b],
+    )
+    .await?;
+
+    ctx.margin_client()
+        .register_adapter(&glow_whirlpool_adapter::ID)
+        .await?;
+    ctx.margin_config_ix()
+        .configure_token(
+            lp_config.receipt_mint,
+            TokenConfigUpdate {
+                underlying_mint: lp_config.receipt_mint,
+                underlying_mint_token_program: anchor_spl::token_2022::ID,
+                admin: TokenAdmin::Adapter(glow_whirlpool_adapter::ID),
+                token_kind: TokenKind::AdapterCollateral,
+                value_modifier: 90,
+                max_staleness: 30,
+                token_features: TokenFeatures::empty(),
+                oracle_limits: Default::default(),
+            },
+        )
+        .with_signer(&ctx.airspace_authority)
+        .send_and_confirm(&rpc)
+        .await?;
+
+    let wallet = ctx.create_wallet(10).await?;
+    ctx.issue_permit(wallet.pubkey()).await?;
+    let user = ctx
+        .margin_client()
+        .user(&wallet, 0, glow_client::NetworkKind::Localnet)
+        .created(Default::default())
+        .await?;
+    let margin_account = *user.address();
+
+    // Move the position opened by the payer into the margin account
+    let payer = ctx.payer().pubkey();
+    let position_mint = whirlpool.position_mint.pubkey();
+    send_and_confirm(
+        &rpc,
+        &[
+            create_associated_token_account(
+                &payer,
+                &margin_account,
+                &position_mint,
+                &spl_token::ID,
+            ),
+            spl_token::instruction::transfer(
+                &spl_token::ID,
+                &get_associated_token_address(&payer, &position_mint),
+                &get_associated_token_address(&margin_account, &position_mint),
+                &payer,
+                &[],
+                1,
+            )?,
+        ],
+        &[],
+    )
+    .await?;
+
+    lp_config
+        .register_margin_position(&rpc, &wallet, margin_account)
+        .await?;
+    lp_config
+        .deposit_lp_position(&rpc, &wallet, margin_account, position_mint)
+        .await?;
+
+    // Someone else's account is not a valid destination
+    let stranger = ctx.generate_key();
+    let foreign = get_associated_token_address(&stranger.pubkey(), &position_mint);
+    let owned = get_associated_token_address(&wallet.pubkey(), &position_mint);
+    send_and_confirm(
+        &rpc,
+        &[
+            create_associated_token_account(
+                &payer,
+                &stranger.pubkey(),
+                &position_mint,
+                &spl_token::ID,
+            ),
+            create_associated_token_account(
+                &payer,
+                &wallet.pubkey(),
+                &position_mint,
+                &spl_token::ID,
+            ),
+        ],
+        &[],
+    )
+    .await?;
+
+    let result = lp_config
+        .withdraw_lp_position(&rpc, &wallet, margin_account, position_mint, foreign)
+        .await;
+    assert_custom_program_error(
+        glow_whirlpool_adapter::ErrorCode::InvalidDestination,
+        result,
+    );
+
+    // The owner of the margin account can receive the position
+    lp_config
+        .withdraw_lp_position(&rpc, &wallet, margin_account, position_mint, owned)
+        .await?;
+    assert_eq!(1, ctx.tokens().get_balance(&owned).await?);
+
+    Ok(())
+}